@@ -4,12 +4,14 @@ use alloy_primitives::{Address, Bytes, FixedBytes, U256, keccak256};
 use alloy_sol_types::{SolCall, SolType, SolValue, sol};
 use blueprint_client_tangle::{ExecutionProfile, inject_execution_profile};
 use color_eyre::eyre::{Context, Result, eyre};
+use dialoguer::console::style;
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value};
 use std::collections::HashSet;
 use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use url::Url;
 
 sol! {
@@ -142,6 +144,48 @@ pub fn decode_blueprint_definition(bytes: &[u8]) -> Result<BlueprintDefinition>
     })
 }
 
+/// Regenerate the `jobs_file` of the blueprint definition at `path`, if it sets one.
+///
+/// The file is written by the blueprint's `build.rs` with
+/// `blueprint_sdk::tangle::schema::JobDefinitions`, so this runs `cargo build` in the
+/// definition's directory. The build also fails if a routed job has no definition.
+pub fn generate_jobs_file(path: &Path) -> Result<()> {
+    let bytes = fs::read(path).with_context(|| {
+        format!(
+            "failed to read blueprint definition file {}",
+            path.display()
+        )
+    })?;
+    let spec = parse_definition_spec(&bytes, path)?;
+    let Some(jobs_file) = spec.jobs_file else {
+        return Ok(());
+    };
+
+    let base_dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    println!(
+        "  {} cargo build (generating {})",
+        style("> Building:").cyan().bold(),
+        jobs_file.display()
+    );
+    let status = Command::new("cargo")
+        .current_dir(base_dir)
+        .arg("build")
+        .status()
+        .with_context(|| "spawning cargo build".to_string())?;
+    if !status.success() {
+        return Err(eyre!(
+            "cargo build failed (exit {:?}) while generating {}",
+            status.code(),
+            jobs_file.display()
+        ));
+    }
+
+    Ok(())
+}
+
 /// Load and encode a blueprint definition from disk.
 pub fn load_blueprint_definition(
     path: &Path,
@@ -158,6 +202,7 @@ pub fn load_blueprint_definition(
     if let Some(extra) = overrides {
         spec.apply_overrides(extra)?;
     }
+    spec.resolve_jobs(path)?;
     let metadata_hash = spec.resolve_metadata_hash(path)?;
     let summary = DefinitionSummary {
         metadata_uri: spec.metadata_uri.clone(),
@@ -258,8 +303,11 @@ struct BlueprintDefinitionSpec {
     config: Option<BlueprintConfigSpec>,
     #[serde(default)]
     metadata: MetadataSpec,
+    #[serde(default)]
     jobs: Vec<JobSpec>,
     #[serde(default)]
+    jobs_file: Option<PathBuf>,
+    #[serde(default)]
     registration_schema: Option<String>,
     #[serde(default)]
     request_schema: Option<String>,
@@ -338,6 +386,40 @@ impl BlueprintDefinitionSpec {
         })
     }
 
+    /// Load the job list from `jobs_file`, if set
+    ///
+    /// The file holds the `jobs` array generated by `blueprint_sdk::tangle::schema::JobDefinitions`.
+    fn resolve_jobs(&mut self, definition_path: &Path) -> Result<()> {
+        let Some(jobs_file) = self.jobs_file.take() else {
+            return Ok(());
+        };
+
+        if !self.jobs.is_empty() {
+            return Err(eyre!(
+                "definition must set either jobs or jobs_file, not both"
+            ));
+        }
+
+        let resolved_path = match definition_path.parent() {
+            Some(base_dir) if !jobs_file.is_absolute() => base_dir.join(jobs_file),
+            _ => jobs_file,
+        };
+        if !resolved_path.exists() {
+            return Err(eyre!(
+                "jobs file {} does not exist. It is generated by `JobDefinitions::write()` from \
+                 the blueprint's build.rs, see `blueprint_sdk::tangle::schema`",
+                resolved_path.display()
+            ));
+        }
+        let bytes = fs::read(&resolved_path)
+            .with_context(|| format!("failed to read jobs file {}", resolved_path.display()))?;
+        self.jobs = serde_json::from_slice(&bytes).with_context(|| {
+            format!("failed to parse jobs JSON from {}", resolved_path.display())
+        })?;
+
+        Ok(())
+    }
+
     fn resolve_metadata_hash(&self, definition_path: &Path) -> Result<FixedBytes<32>> {
         self.resolve_metadata_hash_inner(definition_path.parent())
     }
@@ -1669,6 +1751,66 @@ mod tests {
         assert_eq!(loaded.definition.metadata_hash, expected);
     }
 
+    #[test]
+    fn loads_jobs_from_jobs_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let jobs = serde_json::json!([
+            {
+                "name": "square",
+                "description": "Squares a number",
+                "params_schema": r#"[{"name":"x","type":"uint64","internalType":"uint64","components":[]}]"#,
+                "result_schema": r#"[{"name":"","type":"uint64","internalType":"uint64","components":[]}]"#
+            }
+        ]);
+        fs::write(
+            temp_dir.path().join("jobs.json"),
+            serde_json::to_vec_pretty(&jobs).unwrap(),
+        )
+        .unwrap();
+
+        let path = temp_dir.path().join("definition.json");
+        let mut manifest = serde_json::json!({
+            "metadata_uri": "ipfs://cid",
+            "metadata_hash": TEST_METADATA_HASH,
+            "manager": "0x0000000000000000000000000000000000000001",
+            "jobs_file": "jobs.json",
+            "sources": [
+                {
+                    "kind": "container",
+                    "registry": "ghcr.io",
+                    "image": "org/blueprint",
+                    "tag": "v0.1.0",
+                    "binaries": [
+                        {
+                            "name": "blueprint",
+                            "arch": "x86_64",
+                            "os": "linux",
+                            "sha256": "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+                        }
+                    ]
+                }
+            ]
+        });
+        fs::write(&path, serde_json::to_vec_pretty(&manifest).unwrap()).unwrap();
+
+        let loaded = load_blueprint_definition(&path, None).unwrap();
+        let decoded = decode_blueprint_definition(loaded.definition.encoded.as_ref()).unwrap();
+        assert_eq!(decoded.jobs.len(), 1);
+        assert_eq!(decoded.jobs[0].name, "square");
+        assert_eq!(decoded.jobs[0].description, "Squares a number");
+        assert!(!decoded.jobs[0].paramsSchema.is_empty());
+
+        manifest["jobs"] = serde_json::json!([{ "name": "square" }]);
+        fs::write(&path, serde_json::to_vec_pretty(&manifest).unwrap()).unwrap();
+        assert!(load_blueprint_definition(&path, None).is_err());
+
+        manifest.as_object_mut().unwrap().remove("jobs");
+        manifest["jobs_file"] = serde_json::json!("missing.json");
+        fs::write(&path, serde_json::to_vec_pretty(&manifest).unwrap()).unwrap();
+        let err = load_blueprint_definition(&path, None).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{err}");
+    }
+
     #[test]
     fn round_trip_github_source_matches_metadata() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -1,7 +1,7 @@
 use crate::command::deploy::definition::{
     BinaryArtifactSpec, BlueprintDefinitionInput, DefinitionOverrides, FetcherKind,
     GithubArtifactSpec, NativeSourceOverride, RemoteArtifactSpec, SourceSummary,
-    SourceSummaryDetails, load_blueprint_definition,
+    SourceSummaryDetails, generate_jobs_file, load_blueprint_definition,
 };
 use crate::command::run::tangle::run_blueprint;
 use crate::command::signer::KEYSTORE_PATH_ENV;
@@ -217,6 +217,7 @@ pub async fn execute(args: TangleDeployArgs) -> Result<()> {
                 .as_ref()
                 .ok_or_else(|| eyre!("--definition is required for testnet/mainnet deployments"))?;
             let overrides = args.definition_overrides()?;
+            generate_jobs_file(definition_path)?;
             let loaded = load_blueprint_definition(definition_path, overrides.as_ref())?;
             print_source_summaries(&loaded.summaries);
             let remote = NetworkDeploymentConfig::from_args(&args, &tangle_settings)?;
@@ -10,6 +10,7 @@ pub mod jobs;
 pub mod keys;
 pub mod list;
 pub mod operator;
+pub mod replay;
 pub mod run;
 pub mod service;
 pub mod ship;
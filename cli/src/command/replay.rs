@@ -0,0 +1,140 @@
+//! `cargo tangle blueprint replay` — re-run recorded job calls locally.
+//!
+//! Takes a log written by `blueprint_runner::record::Recorder`, and feeds
+//! every recorded `JobCall` through the blueprint's `Router`, with no chain:
+//!
+//! 1. Build the blueprint (or use `--binary`).
+//! 2. Run it in test + dry-run mode with `BLUEPRINT_REPLAY_LOG` set, which
+//!    makes the runner replay the log and exit instead of starting up.
+//! 3. Diff the replayed `JobResult`s against the recorded ones.
+//!
+//! The blueprint's `main` still runs up to `BlueprintRunner::run()`, so it
+//! must be able to construct its producers and consumers without reaching
+//! the chain (for example, by pointing `--http-rpc-url` at a local node).
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use blueprint_runner::record::{REPLAY_LOG_ENV, REPLAY_OUTPUT_ENV, Recording};
+use color_eyre::eyre::{Context, Result, bail, eyre};
+use dialoguer::console::style;
+
+use crate::command::ship::guess_default_binary_name;
+
+#[derive(Debug, Clone)]
+pub struct ReplayArgs {
+    /// The recorded log to replay.
+    pub log: PathBuf,
+    /// Cargo package to build (`-p <pkg>`).
+    pub package: Option<String>,
+    /// Path to a pre-built blueprint binary. Skips the build step.
+    pub binary: Option<PathBuf>,
+    /// Extra arguments passed to the blueprint's `run` subcommand.
+    pub args: Vec<String>,
+}
+
+/// Replay the log, returning an error if any outcome differs.
+pub fn run(args: ReplayArgs) -> Result<()> {
+    let recording = Recording::open(&args.log)
+        .with_context(|| format!("reading recording {}", args.log.display()))?;
+    if recording.is_empty() {
+        println!(
+            "{} no job calls recorded",
+            style("Nothing to replay:").yellow()
+        );
+        return Ok(());
+    }
+
+    let binary = resolve_binary(&args)?;
+    let data_dir = tempfile::tempdir().context("creating a temporary data dir")?;
+    let output = data_dir.path().join("replayed.log");
+
+    println!(
+        "  {} {} job call(s) from {}",
+        style("> Replaying:").cyan().bold(),
+        recording.len(),
+        args.log.display()
+    );
+    let status = Command::new(&binary)
+        .arg("run")
+        .arg("--test-mode")
+        .arg("--dry-run")
+        .arg("--data-dir")
+        .arg(data_dir.path())
+        .args(&args.args)
+        .env(REPLAY_LOG_ENV, &args.log)
+        .env(REPLAY_OUTPUT_ENV, &output)
+        .status()
+        .with_context(|| format!("spawning {}", binary.display()))?;
+    if !status.success() {
+        bail!("blueprint exited with {:?} while replaying", status.code());
+    }
+
+    let replayed = Recording::open(&output).context("reading replayed outcomes")?;
+    let diffs = recording.diff(&replayed);
+    if diffs.is_empty() {
+        println!(
+            "  {} every recorded outcome was reproduced",
+            style("Match:").green().bold()
+        );
+        return Ok(());
+    }
+
+    for diff in &diffs {
+        println!("{diff}");
+    }
+    bail!(
+        "{} of {} replayed job call(s) differ from the recording",
+        diffs.len(),
+        recording.len()
+    )
+}
+
+fn resolve_binary(args: &ReplayArgs) -> Result<PathBuf> {
+    if let Some(path) = &args.binary {
+        if !path.exists() {
+            bail!("--binary {} does not exist", path.display());
+        }
+        return Ok(path.clone());
+    }
+
+    let cwd = std::env::current_dir().context("reading cwd")?;
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&cwd).arg("build");
+    if let Some(pkg) = &args.package {
+        cmd.arg("-p").arg(pkg);
+    }
+    println!(
+        "  {} cargo build{}",
+        style("> Building:").cyan().bold(),
+        args.package
+            .as_ref()
+            .map(|p| format!(" -p {p}"))
+            .unwrap_or_default()
+    );
+    let status = cmd
+        .status()
+        .with_context(|| "spawning cargo build".to_string())?;
+    if !status.success() {
+        bail!("cargo build failed (exit {:?})", status.code());
+    }
+
+    debug_binary(&cwd, args.package.as_deref())
+}
+
+fn debug_binary(cwd: &Path, package: Option<&str>) -> Result<PathBuf> {
+    let binary_name = package
+        .map(str::to_string)
+        .or_else(|| guess_default_binary_name(cwd))
+        .ok_or_else(|| {
+            eyre!("could not determine binary name; pass --binary <path-to-target/debug/foo>")
+        })?;
+    let path = cwd.join("target").join("debug").join(&binary_name);
+    if !path.exists() {
+        bail!(
+            "expected built binary at {} but the file is missing — pass --binary <path>",
+            path.display()
+        );
+    }
+    Ok(path)
+}
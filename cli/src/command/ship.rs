@@ -343,7 +343,7 @@ fn resolve_binary(workspace: &DetectedWorkspace, args: &ShipArgs) -> Result<Path
 /// Cheap heuristic: read `Cargo.toml` and pick the first `[[bin]] name = "..."`
 /// or fall back to the workspace's `package.name`. Anything more elaborate
 /// is `--binary <path>` territory.
-fn guess_default_binary_name(cwd: &Path) -> Option<String> {
+pub(crate) fn guess_default_binary_name(cwd: &Path) -> Option<String> {
     let manifest = cwd.join("Cargo.toml");
     let text = std::fs::read_to_string(&manifest).ok()?;
     // Naive scan — avoid pulling in a full toml dependency on the hot path.
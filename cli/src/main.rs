@@ -320,6 +320,26 @@ enum BlueprintCommands {
         command: DebugCommands,
     },
 
+    /// Replay recorded job calls through a local build of the blueprint.
+    ///
+    /// Feeds every call in a log written by `blueprint_runner::record::Recorder`
+    /// through the blueprint's router, with no chain, and diffs the produced
+    /// results against the recorded ones. Exits with an error on any mismatch.
+    Replay {
+        /// The recorded log to replay.
+        #[arg(value_name = "LOG")]
+        log: PathBuf,
+        /// Cargo package to build (`-p <pkg>`).
+        #[arg(long, short = 'p', value_name = "NAME")]
+        package: Option<String>,
+        /// Path to a pre-built blueprint binary. Skips the build step.
+        #[arg(long, value_name = "PATH")]
+        binary: Option<PathBuf>,
+        /// Extra arguments for the blueprint's `run` subcommand.
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
     /// Submit, watch, and inspect job invocations.
     ///
     /// Manage job calls to blueprint services.
@@ -1675,6 +1695,19 @@ async fn main() -> Result<()> {
                     debug::spawn::execute(args).await?;
                 }
             },
+            BlueprintCommands::Replay {
+                log,
+                package,
+                binary,
+                args,
+            } => {
+                cargo_tangle::command::replay::run(cargo_tangle::command::replay::ReplayArgs {
+                    log,
+                    package,
+                    binary,
+                    args,
+                })?;
+            }
             BlueprintCommands::Preregister {
                 protocol,
                 http_rpc_url,
@@ -48,6 +48,9 @@ pub enum InstrumentedClientError {
 
     #[error(transparent)]
     Rpc(#[from] alloy_json_rpc::RpcError<alloy_transport::TransportErrorKind>),
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    MultiRpc(#[from] crate::multi_rpc::MultiRpcError),
 }
 
 impl Provider for InstrumentedClient {
@@ -207,6 +210,37 @@ impl InstrumentedClient {
         })
     }
 
+    /// Creates a new instance of the `InstrumentedClient` backed by several RPC endpoints.
+    ///
+    /// Requests fail over between the endpoints, and critical reads can require a quorum of them
+    /// to agree. See [`MultiRpcTransport`](crate::multi_rpc::MultiRpcTransport).
+    ///
+    /// # Arguments
+    ///
+    /// * `urls` - The HTTP or ws URLs of the RPC servers, in order of preference.
+    /// * `config` - The failover and quorum configuration.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the `InstrumentedClient`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the endpoints can't be connected to, the quorum can't be reached
+    /// with the given endpoints, or there is an error getting the version.
+    #[cfg(feature = "std")]
+    pub async fn new_with_endpoints<I, S>(
+        urls: I,
+        config: crate::multi_rpc::MultiRpcConfig,
+    ) -> Result<Self, InstrumentedClientError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let transport = crate::multi_rpc::MultiRpcTransport::connect(urls, config).await?;
+        Self::new_from_client(RootProvider::new(transport.into_client())).await
+    }
+
     /// Returns the chain ID.
     ///
     /// # Returns
@@ -3,3 +3,5 @@
 pub mod client;
 pub mod error;
 pub mod instrumented_client;
+#[cfg(feature = "std")]
+pub mod multi_rpc;
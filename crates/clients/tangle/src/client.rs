@@ -7,6 +7,7 @@ extern crate alloc;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
+use alloy::rpc::client::{ClientBuilder, RpcClient};
 use alloy_network::Ethereum;
 use alloy_primitives::{Address, B256, Bytes, TxKind, U256, keccak256};
 use alloy_provider::{DynProvider, Provider, ProviderBuilder};
@@ -16,6 +17,7 @@ use alloy_rpc_types::{
 };
 use alloy_sol_types::{SolCall, SolEvent};
 use blueprint_client_core::{BlueprintServicesClient, OperatorSet};
+use blueprint_client_evm::multi_rpc::{MultiRpcConfig, MultiRpcTransport};
 use blueprint_crypto::k256::K256Ecdsa;
 use blueprint_keystore::Keystore;
 use blueprint_keystore::backends::Backend;
@@ -456,11 +458,35 @@ pub struct TangleEvent {
     pub logs: Vec<Log>,
 }
 
+/// Connect to the HTTP RPC endpoints of `config`
+///
+/// With fallback endpoints or a quorum, requests go through a [`MultiRpcTransport`], failing over
+/// between the endpoints, and reading critical data from a quorum of them.
+async fn connect_rpc(config: &TangleClientConfig) -> Result<RpcClient> {
+    if config.fallback_http_rpc_endpoints.is_empty() && config.rpc_quorum.is_none_or(|q| q <= 1) {
+        return ClientBuilder::default()
+            .connect(config.http_rpc_endpoint.as_str())
+            .await
+            .map_err(|e| Error::Config(e.to_string()));
+    }
+
+    let mut multi_rpc = MultiRpcConfig::default();
+    if let Some(quorum) = config.rpc_quorum {
+        multi_rpc = multi_rpc.with_quorum(quorum);
+    }
+    let transport = MultiRpcTransport::connect(config.http_rpc_endpoints(), multi_rpc)
+        .await
+        .map_err(|e| Error::Config(e.to_string()))?;
+    Ok(transport.into_client())
+}
+
 /// Tangle Client for interacting with Tangle v2 contracts
 #[derive(Clone)]
 pub struct TangleClient {
     /// RPC provider
     provider: Arc<TangleProvider>,
+    /// RPC client shared by the provider and the signing providers
+    rpc_client: RpcClient,
     /// Tangle contract address
     tangle_address: Address,
     /// MultiAssetDelegation contract address
@@ -515,13 +541,10 @@ impl TangleClient {
     /// # Errors
     /// Returns error if RPC connection fails
     pub async fn with_keystore(config: TangleClientConfig, keystore: Keystore) -> Result<Self> {
-        let rpc_url = config.http_rpc_endpoint.as_str();
+        let rpc_client = connect_rpc(&config).await?;
 
         // Create provider and wrap in DynProvider for type erasure
-        let provider = ProviderBuilder::new()
-            .connect(rpc_url)
-            .await
-            .map_err(|e| Error::Config(e.to_string()))?;
+        let provider = ProviderBuilder::new().connect_client(rpc_client.clone());
 
         let dyn_provider = DynProvider::new(provider);
 
@@ -537,6 +560,7 @@ impl TangleClient {
 
         Ok(Self {
             provider: Arc::new(dyn_provider),
+            rpc_client,
             tangle_address: config.settings.tangle_contract,
             restaking_address: config.settings.staking_contract,
             status_registry_address: config.settings.status_registry_contract,
@@ -909,9 +933,7 @@ impl TangleClient {
         let from_address = wallet.default_signer().address();
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let tx_request = TransactionRequest::default()
             .to(self.tangle_address)
             .input(Bytes::from(calldata).into());
@@ -966,6 +988,23 @@ impl TangleClient {
             .map_err(|e| Error::Contract(e.to_string()))
     }
 
+    /// Get the BLS public key an operator registered for a service
+    ///
+    /// The G2 point is returned in contract format; all zeros if the operator
+    /// registered none.
+    pub async fn get_operator_bls_pubkey(
+        &self,
+        service_id: u64,
+        operator: Address,
+    ) -> Result<[U256; 4]> {
+        let contract = self.tangle_contract();
+        contract
+            .getOperatorBlsPubkey(service_id, operator)
+            .call()
+            .await
+            .map_err(|e| Error::Contract(e.to_string()))
+    }
+
     /// Check if address is a service operator
     pub async fn is_service_operator(&self, service_id: u64, operator: Address) -> Result<bool> {
         let contract = self.tangle_contract();
@@ -1041,9 +1080,7 @@ impl TangleClient {
         let from_address = wallet.default_signer().address();
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
 
         let signing_key = self.ecdsa_signing_key()?;
         let verifying = signing_key.verifying_key();
@@ -1098,9 +1135,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = ITangle::new(self.tangle_address, &provider);
 
         let receipt = contract
@@ -1321,9 +1356,7 @@ impl TangleClient {
         let from_address = wallet.default_signer().address();
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = ITangle::new(self.tangle_address, &provider);
 
         let ServiceRequestParams {
@@ -1510,9 +1543,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = ITangle::new(self.tangle_address, &provider);
 
         let receipt = contract
@@ -1539,9 +1570,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = ITangle::new(self.tangle_address, &provider);
 
         let receipt = contract
@@ -1566,9 +1595,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = ITangle::new(self.tangle_address, &provider);
 
         let receipt = contract
@@ -1593,9 +1620,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = ITangle::new(self.tangle_address, &provider);
 
         let receipt = contract
@@ -1617,9 +1642,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = ITangle::new(self.tangle_address, &provider);
 
         let receipt = contract
@@ -1641,9 +1664,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = ITangle::new(self.tangle_address, &provider);
 
         let receipt = contract
@@ -1674,9 +1695,7 @@ impl TangleClient {
         let from_address = wallet.default_signer().address();
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
 
         let calldata = approveServiceCall { params }.abi_encode();
         let tx_request = TransactionRequest::default()
@@ -1731,9 +1750,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = ITangle::new(self.tangle_address, &provider);
 
         let receipt = contract
@@ -1963,9 +1980,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, provider);
 
         let mode_value: u8 = match mode {
@@ -2003,9 +2018,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, provider);
 
         let receipt = contract
@@ -2058,9 +2071,7 @@ impl TangleClient {
         let from_address = wallet.default_signer().address();
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let tx_request = TransactionRequest::default()
             .to(token)
             .input(approveCall { spender, amount }.abi_encode().into());
@@ -2221,9 +2232,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let mut call = contract.depositAndDelegateWithOptions(
@@ -2259,9 +2268,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2291,9 +2298,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2312,9 +2317,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2340,9 +2343,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2365,9 +2366,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2386,9 +2385,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2407,9 +2404,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2428,9 +2423,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2449,9 +2442,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2470,9 +2461,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2527,9 +2516,7 @@ impl TangleClient {
         let from_address = wallet.default_signer().address();
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
 
         let receipt = if bond_token == Address::ZERO {
             let tx_request = TransactionRequest::default()
@@ -2582,9 +2569,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = if bond_token == Address::ZERO {
@@ -2618,9 +2603,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2642,9 +2625,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
         let contract = IMultiAssetDelegation::new(self.restaking_address, &provider);
 
         let receipt = contract
@@ -2774,9 +2755,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
 
         let call = submitJobCall {
             serviceId: service_id,
@@ -2825,9 +2804,7 @@ impl TangleClient {
         let wallet = self.wallet()?;
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
 
         let call = submitJobFromQuoteCall {
             serviceId: service_id,
@@ -2927,9 +2904,7 @@ impl TangleClient {
         let from_address = wallet.default_signer().address();
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
 
         let tx_request = TransactionRequest::default().to(self.tangle_address).input(
             submitResultCall {
@@ -2987,9 +2962,7 @@ impl TangleClient {
         let from_address = wallet.default_signer().address();
         let provider = ProviderBuilder::new()
             .wallet(wallet)
-            .connect(self.config.http_rpc_endpoint.as_str())
-            .await
-            .map_err(Error::Transport)?;
+            .connect_client(self.rpc_client.clone());
 
         let call = submitAggregatedResultCall {
             serviceId: service_id,
@@ -6,6 +6,7 @@
 extern crate alloc;
 
 use alloc::string::String;
+use alloc::vec::Vec;
 use alloy_primitives::Address;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -61,6 +62,15 @@ pub struct TangleClientConfig {
     /// When true, avoid on-chain submissions from this client.
     #[serde(default)]
     pub dry_run: bool,
+    /// Additional HTTP RPC endpoints, failed over to when `http_rpc_endpoint` is unavailable
+    #[serde(default)]
+    pub fallback_http_rpc_endpoints: Vec<Url>,
+    /// Number of HTTP RPC endpoints that must agree on critical reads, like `eth_getLogs`,
+    /// `eth_blockNumber` and contract calls
+    ///
+    /// `None` reads from a single endpoint.
+    #[serde(default)]
+    pub rpc_quorum: Option<usize>,
 }
 
 impl TangleClientConfig {
@@ -79,6 +89,8 @@ impl TangleClientConfig {
             settings,
             test_mode: false,
             dry_run: false,
+            fallback_http_rpc_endpoints: Vec::new(),
+            rpc_quorum: None,
         }
     }
 
@@ -100,6 +112,23 @@ impl TangleClientConfig {
         self
     }
 
+    /// Set the HTTP RPC endpoints to fail over to
+    pub fn fallback_http_rpc_endpoints(mut self, endpoints: impl IntoIterator<Item = Url>) -> Self {
+        self.fallback_http_rpc_endpoints = endpoints.into_iter().collect();
+        self
+    }
+
+    /// Require `quorum` HTTP RPC endpoints to agree on critical reads
+    pub fn rpc_quorum(mut self, quorum: usize) -> Self {
+        self.rpc_quorum = Some(quorum);
+        self
+    }
+
+    /// Every HTTP RPC endpoint, in order of preference
+    pub fn http_rpc_endpoints(&self) -> impl Iterator<Item = &Url> {
+        core::iter::once(&self.http_rpc_endpoint).chain(&self.fallback_http_rpc_endpoints)
+    }
+
     /// Get keystore configuration
     pub fn keystore_config(&self) -> blueprint_keystore::KeystoreConfig {
         blueprint_keystore::KeystoreConfig::new().fs_root(self.keystore_uri.replace("file://", ""))
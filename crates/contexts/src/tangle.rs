@@ -42,6 +42,8 @@ impl TangleClientContext for BlueprintEnvironment {
             },
             test_mode: self.test_mode,
             dry_run: self.dry_run,
+            fallback_http_rpc_endpoints: self.fallback_http_rpc_endpoints.clone(),
+            rpc_quorum: self.rpc_quorum,
         };
 
         TangleClient::with_keystore(config, keystore).await
@@ -8,16 +8,56 @@ pub type CloneableError = alloc::sync::Arc<dyn core::error::Error + Send + Sync>
 #[derive(Debug, Clone)]
 pub struct Error {
     inner: CloneableError,
+    kind: ErrorKind,
+}
+
+/// The kind of an [`Error`], for telling failures apart without inspecting the underlying error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The job did not finish within its time limit.
+    TimedOut,
+    /// The job was disabled, and the call was rejected without running it.
+    Disabled,
+    /// Any other error.
+    Other,
+}
+
+impl ErrorKind {
+    /// A short, stable name for this kind, suitable for metric labels.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::TimedOut => "timed_out",
+            ErrorKind::Disabled => "disabled",
+            ErrorKind::Other => "other",
+        }
+    }
 }
 
 impl Error {
     /// Create a new `Error` from a boxable error.
     pub fn new(error: impl Into<BoxError>) -> Self {
+        Self::with_kind(ErrorKind::Other, error)
+    }
+
+    /// Create a new `Error` of the given [`ErrorKind`] from a boxable error.
+    pub fn with_kind(kind: ErrorKind, error: impl Into<BoxError>) -> Self {
         Self {
             inner: CloneableError::from(error.into()),
+            kind,
         }
     }
 
+    /// The kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Whether this error is the result of a job timing out.
+    pub fn is_timeout(&self) -> bool {
+        self.kind == ErrorKind::TimedOut
+    }
+
     /// Convert an `Error` back into the underlying trait object.
     pub fn into_inner(self) -> CloneableError {
         self.inner
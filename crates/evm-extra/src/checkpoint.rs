@@ -0,0 +1,114 @@
+//! Producer Checkpoints
+//!
+//! The polling producers only keep their position in memory, so without a [`CheckpointStore`]
+//! every restart starts again from the configured start block, and events emitted while the
+//! operator was down are missed, or replayed.
+//!
+//! With a store set through [`MultiChainProducerBuilder::with_checkpoint_store()`], each chain
+//! resumes right after the last block it committed, independently of the others. A block is
+//! committed once every job call produced from the range it was fetched in has been yielded, so
+//! a crash in the middle of a range replays the job calls of that range that were already
+//! yielded.
+//!
+//! Blocks are committed by a background task, so that a slow store never holds up polling. A
+//! crash right after a range was yielded can therefore replay it too.
+//!
+//! Blocks can be kept in a [`LocalDatabase`] of `u64`s. Opened with
+//! [`LocalDatabase::open_strict()`], a corrupted file fails to load instead of replaying every
+//! chain from its start block.
+//!
+//! ```rust,ignore
+//! use blueprint_store_local_database::LocalDatabase;
+//!
+//! let store = LocalDatabase::<u64>::open_strict(env.data_dir.join("evm-checkpoints.json"))?;
+//! let producer = MultiChainProducer::builder()
+//!     .chain(mainnet)
+//!     .chain(arbitrum)
+//!     .with_checkpoint_store(store)
+//!     .build()
+//!     .await?;
+//! ```
+//!
+//! [`MultiChainProducerBuilder::with_checkpoint_store()`]: crate::producer::MultiChainProducerBuilder::with_checkpoint_store
+
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use blueprint_store_local_database::{BackgroundWriter, LocalDatabase, StoreError};
+
+/// Error type for checkpoint stores
+pub type CheckpointError = StoreError;
+
+/// Persistent storage for the last block a producer processed
+///
+/// Checkpoints are keyed by chain ID, so a single store can be shared by the chains of a
+/// [`MultiChainProducer`].
+///
+/// [`MultiChainProducer`]: crate::producer::MultiChainProducer
+pub trait CheckpointStore: Send + Sync {
+    /// Load the last block committed for `chain_id`
+    ///
+    /// # Errors
+    ///
+    /// If the store cannot be read.
+    fn load(&self, chain_id: u64) -> Result<Option<u64>, CheckpointError>;
+
+    /// Persist `block` as the last block processed on `chain_id`
+    ///
+    /// # Errors
+    ///
+    /// If the store cannot be written to.
+    fn commit(&self, chain_id: u64, block: u64) -> Result<(), CheckpointError>;
+}
+
+impl CheckpointStore for LocalDatabase<u64> {
+    fn load(&self, chain_id: u64) -> Result<Option<u64>, CheckpointError> {
+        Ok(self.get(&chain_id.to_string())?)
+    }
+
+    fn commit(&self, chain_id: u64, block: u64) -> Result<(), CheckpointError> {
+        Ok(self.set(&chain_id.to_string(), block)?)
+    }
+}
+
+/// Commit the blocks processed on `chain_id` to `store` in the background
+///
+/// Failures are only logged. The producer then resumes from an older checkpoint after a restart,
+/// and replays the blocks since.
+pub(crate) fn checkpoint_writer(
+    store: Arc<dyn CheckpointStore>,
+    chain_id: u64,
+) -> BackgroundWriter<u64> {
+    BackgroundWriter::spawn(move |block| {
+        if let Err(e) = store.commit(chain_id, block) {
+            blueprint_core::warn!(
+                target: "evm-polling-producer",
+                chain_id,
+                block,
+                "Failed to commit checkpoint: {e}"
+            );
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_database_store_keeps_chains_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoints").join("evm.json");
+
+        let store = LocalDatabase::<u64>::open_strict(&path).unwrap();
+        assert_eq!(store.load(1).unwrap(), None);
+
+        store.commit(1, 120).unwrap();
+        store.commit(42161, 80).unwrap();
+        store.commit(1, 121).unwrap();
+
+        let reopened = LocalDatabase::<u64>::open_strict(&path).unwrap();
+        assert_eq!(reopened.load(1).unwrap(), Some(121));
+        assert_eq!(reopened.load(42161).unwrap(), Some(80));
+        assert_eq!(reopened.load(10).unwrap(), None);
+    }
+}
@@ -0,0 +1,72 @@
+//! Chain extractor for EVM
+//!
+//! Extracts the ID of the chain a job call was produced from, for producers following several
+//! chains, like the [`MultiChainProducer`].
+//!
+//! [`MultiChainProducer`]: crate::producer::MultiChainProducer
+
+use blueprint_core::{
+    __composite_rejection as composite_rejection, __define_rejection as define_rejection,
+    __impl_deref as impl_deref, __impl_from as impl_from, FromJobCallParts,
+    job::call::Parts as JobCallParts,
+};
+
+/// Chain ID extractor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainId(pub u64);
+
+impl ChainId {
+    /// Metadata key for chain ID
+    pub const METADATA_KEY: &'static str = "X-EVM-CHAIN-ID";
+}
+
+impl_deref!(ChainId: u64);
+impl_from!(u64, ChainId);
+
+define_rejection! {
+    #[body = "No chain ID found in metadata"]
+    /// This rejection is used to indicate that a chain ID was not found in the metadata.
+    pub struct MissingChainId;
+}
+
+define_rejection! {
+    #[body = "Chain ID must be a valid number"]
+    /// This rejection is used to indicate that the chain ID is not a valid number.
+    pub struct InvalidChainId;
+}
+
+composite_rejection! {
+    /// Rejection for chain ID extractor
+    pub enum ChainIdRejection {
+        MissingChainId,
+        InvalidChainId,
+    }
+}
+
+impl TryFrom<&mut JobCallParts> for ChainId {
+    type Error = ChainIdRejection;
+
+    fn try_from(parts: &mut JobCallParts) -> Result<Self, Self::Error> {
+        let chain_id = parts
+            .metadata
+            .get(Self::METADATA_KEY)
+            .ok_or(MissingChainId)?
+            .try_into()
+            .map_err(|_| InvalidChainId)?;
+        Ok(ChainId(chain_id))
+    }
+}
+
+impl<Ctx> FromJobCallParts<Ctx> for ChainId
+where
+    Ctx: Send + Sync,
+{
+    type Rejection = ChainIdRejection;
+
+    async fn from_job_call_parts(
+        parts: &mut JobCallParts,
+        _: &Ctx,
+    ) -> Result<Self, Self::Rejection> {
+        Self::try_from(parts)
+    }
+}
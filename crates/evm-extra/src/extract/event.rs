@@ -109,6 +109,56 @@ where
     }
 }
 
+/// Extracts the event that triggered the current job call
+///
+/// This is only available for job calls from an [`EventProducer`], which produces one job call
+/// per log.
+///
+/// [`EventProducer`]: crate::producer::EventProducer
+#[derive(Debug, Clone)]
+pub struct Event<T>(pub T);
+
+impl_deref!(Event);
+
+define_rejection! {
+    #[body = "No event log found in the extensions"]
+    /// This rejection is used to indicate that the job call wasn't produced for a single log.
+    pub struct MissingEventLog;
+}
+
+composite_rejection! {
+    /// Rejection for the [`Event`] extractor
+    pub enum EventLogRejection {
+        MissingEventLog,
+        EventDecodingError,
+    }
+}
+
+impl<T: SolEvent> TryFrom<&mut JobCallParts> for Event<T> {
+    type Error = EventLogRejection;
+
+    fn try_from(parts: &mut JobCallParts) -> Result<Self, Self::Error> {
+        let log = parts.extensions.get::<Log>().ok_or(MissingEventLog)?;
+        let event = T::decode_log(&log.inner).map_err(|_| EventDecodingError)?;
+        Ok(Event(event.data))
+    }
+}
+
+impl<Ctx, T> FromJobCallParts<Ctx> for Event<T>
+where
+    Ctx: Send + Sync,
+    T: SolEvent + Send + Sync,
+{
+    type Rejection = EventLogRejection;
+
+    async fn from_job_call_parts(
+        parts: &mut JobCallParts,
+        _: &Ctx,
+    ) -> Result<Self, Self::Rejection> {
+        Self::try_from(parts)
+    }
+}
+
 /// Extracts the first event of type T from the current block
 #[derive(Debug, Clone)]
 pub struct FirstEvent<T>(pub T);
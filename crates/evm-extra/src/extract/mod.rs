@@ -4,14 +4,16 @@
 //! pattern as Tangle for consistency and reusability.
 
 pub mod block;
+pub mod chain;
 pub mod contract;
 pub mod event;
 pub mod job;
 pub mod tx;
 
 pub use block::{BlockHash, BlockNumber, BlockTimestamp};
+pub use chain::ChainId;
 pub use contract::ContractAddress;
-pub use event::{BlockEvents, Events, FirstEvent, LastEvent};
+pub use event::{BlockEvents, Event, Events, FirstEvent, LastEvent};
 pub use job::{
     CallId, CallIdRejection, Caller, CallerRejection, InvalidCallId, InvalidCaller,
     InvalidJobIndex, InvalidServiceId, JobIndex, JobIndexRejection, JobInputs, MissingCallId,
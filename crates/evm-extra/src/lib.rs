@@ -23,6 +23,7 @@
 
 extern crate alloc;
 
+pub mod checkpoint;
 pub mod consumer;
 pub mod extract;
 pub mod filters;
@@ -0,0 +1,340 @@
+//! Typed event producer for EVM chains
+//!
+//! A [`PollingProducer`] hands every job the raw logs of a block, leaving it to the job to find
+//! and decode the events it cares about. An [`EventProducer`] instead only fetches the events
+//! registered with its [builder](EventProducerBuilder), as (contract address, [`SolEvent`] type,
+//! job ID) triples, and turns each matching log into its own [`JobCall`] for that job.
+//!
+//! The decoded event is available through the [`Event`] extractor, and the log's block and
+//! contract through the usual extractors, like [`BlockNumber`] and [`ContractAddress`].
+//!
+//! ```rust,ignore
+//! use blueprint_evm_extra::extract::Event;
+//! use blueprint_evm_extra::producer::{EventProducer, PollingConfig};
+//!
+//! let config = PollingConfig::from_current().confirmations(2).step(10);
+//! let producer = EventProducer::builder(provider, config)
+//!     .event::<IERC20::Transfer>(token_a, TRANSFER_JOB)
+//!     .event::<IERC20::Transfer>(token_b, TRANSFER_JOB)
+//!     .event::<IVault::Deposit>(vault, DEPOSIT_JOB)
+//!     .build()
+//!     .await?;
+//!
+//! async fn on_transfer(Event(transfer): Event<IERC20::Transfer>) { /* ... */ }
+//! ```
+//!
+//! The [`PollingConfig`] start block, confirmation depth and step apply the same way as with a
+//! [`PollingProducer`].
+//!
+//! [`BlockNumber`]: crate::extract::BlockNumber
+//! [`ContractAddress`]: crate::extract::ContractAddress
+//! [`Event`]: crate::extract::Event
+
+use alloc::collections::BTreeSet;
+use alloy_primitives::{Address, B256};
+use alloy_provider::Provider;
+use alloy_rpc_types::{Filter, Log};
+use alloy_sol_types::SolEvent;
+use alloy_transport::TransportError;
+use blueprint_core::{
+    JobCall, JobId, extensions::Extensions, job::call::Parts, metadata::MetadataMap,
+};
+use blueprint_std::sync::Arc;
+use bytes::Bytes;
+use core::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures::Stream;
+
+use super::{PollingConfig, PollingProducer};
+use crate::extract::{BlockHash, BlockNumber, BlockTimestamp, ContractAddress};
+
+/// A producer of one [`JobCall`] per registered event log
+///
+/// See the [module docs](self) for details.
+#[derive(Debug)]
+pub struct EventProducer<P: Provider> {
+    inner: PollingProducer<P>,
+}
+
+impl<P: Provider> EventProducer<P> {
+    /// Start registering the events to produce job calls for
+    pub fn builder(provider: Arc<P>, config: PollingConfig) -> EventProducerBuilder<P> {
+        EventProducerBuilder {
+            provider,
+            config,
+            events: EventRegistry::default(),
+        }
+    }
+
+    /// Wait for log notifications from the websocket endpoint `ws_endpoint`, instead of polling
+    ///
+    /// Only notifications for the registered events wake the producer.
+    ///
+    /// See [`PollingProducer::with_log_subscription()`].
+    #[must_use]
+    pub fn with_log_subscription(mut self, ws_endpoint: impl Into<String>) -> Self {
+        self.inner = self.inner.with_log_subscription(ws_endpoint);
+        self
+    }
+}
+
+impl<P: Provider + 'static> Stream for EventProducer<P> {
+    type Item = Result<JobCall, TransportError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().inner).poll_next(cx)
+    }
+}
+
+/// Builder for an [`EventProducer`]
+#[must_use]
+#[derive(Debug)]
+pub struct EventProducerBuilder<P: Provider> {
+    provider: Arc<P>,
+    config: PollingConfig,
+    events: EventRegistry,
+}
+
+impl<P: Provider> EventProducerBuilder<P> {
+    /// Call `job_id` for every `E` event emitted by the contract at `address`
+    ///
+    /// The same event can be registered for several contracts, and several jobs. A log matching
+    /// more than one registration produces a job call for each of them.
+    pub fn event<E: SolEvent>(mut self, address: Address, job_id: impl Into<JobId>) -> Self {
+        self.events.register::<E>(address, job_id.into());
+        self
+    }
+
+    /// Create the producer
+    ///
+    /// # Errors
+    ///
+    /// * If no event was registered, as the producer would fetch every log of the chain.
+    /// * If using [`PollingConfig::from_current()`], transport errors may occur when fetching the current block number.
+    pub async fn build(self) -> Result<EventProducer<P>, TransportError> {
+        if self.events.is_empty() {
+            return Err(TransportError::local_usage_str(
+                "no events registered with the EventProducer",
+            ));
+        }
+        let inner = PollingProducer::new(self.provider, self.config)
+            .await?
+            .with_events(self.events);
+        Ok(EventProducer { inner })
+    }
+}
+
+/// The events registered with an [`EventProducer`]
+#[derive(Debug, Default)]
+pub(crate) struct EventRegistry {
+    registrations: Vec<Registration>,
+}
+
+#[derive(Debug)]
+struct Registration {
+    address: Address,
+    signature: B256,
+    /// The Solidity signature, for logging
+    event: &'static str,
+    job_id: JobId,
+    /// Whether a log with the right address and signature decodes as the event
+    decodes: fn(&alloy_primitives::Log) -> bool,
+}
+
+fn decodes<E: SolEvent>(log: &alloy_primitives::Log) -> bool {
+    E::decode_log(log).is_ok()
+}
+
+impl EventRegistry {
+    fn register<E: SolEvent>(&mut self, address: Address, job_id: JobId) {
+        self.registrations.push(Registration {
+            address,
+            signature: E::SIGNATURE_HASH,
+            event: E::SIGNATURE,
+            job_id,
+            decodes: decodes::<E>,
+        });
+    }
+
+    fn is_empty(&self) -> bool {
+        self.registrations.is_empty()
+    }
+
+    /// A filter for the logs of every registered event, without a block range
+    pub(crate) fn filter(&self) -> Filter {
+        let addresses: BTreeSet<Address> = self.registrations.iter().map(|r| r.address).collect();
+        let signatures: BTreeSet<B256> = self.registrations.iter().map(|r| r.signature).collect();
+        Filter::new()
+            .address(addresses.into_iter().collect::<Vec<_>>())
+            .event_signature(signatures.into_iter().collect::<Vec<_>>())
+    }
+
+    /// Converts each log matching a registration to a [`JobCall`] for its job
+    ///
+    /// The filter may return logs of an event registered for another contract, which are skipped.
+    pub(crate) fn to_job_calls(&self, logs: Vec<Log>) -> Vec<JobCall> {
+        let mut job_calls = Vec::new();
+        for log in logs {
+            let Some(block_number) = log.block_number else {
+                blueprint_core::warn!(?log, "Missing block number");
+                continue;
+            };
+
+            let matches: Vec<&Registration> = self
+                .registrations
+                .iter()
+                .filter(|r| r.address == log.address() && Some(&r.signature) == log.topic0())
+                .collect();
+            let Some(registration) = matches.first() else {
+                continue;
+            };
+            if !(registration.decodes)(&log.inner) {
+                blueprint_core::warn!(
+                    target: "evm-polling-producer",
+                    block_number,
+                    address = %log.address(),
+                    event = registration.event,
+                    "Skipping log that doesn't decode as its event"
+                );
+                continue;
+            }
+
+            let mut metadata = MetadataMap::new();
+            metadata.insert(BlockNumber::METADATA_KEY, block_number);
+            if let Some(block_hash) = log.block_hash {
+                metadata.insert(BlockHash::METADATA_KEY, *block_hash);
+            } else {
+                blueprint_core::warn!(?log, "Missing block hash");
+            }
+            if let Some(block_timestamp) = log.block_timestamp {
+                metadata.insert(BlockTimestamp::METADATA_KEY, block_timestamp);
+            }
+            metadata.insert(ContractAddress::METADATA_KEY, **log.address());
+
+            // The log on its own, for the `Event` extractor, and as the block's events, so the
+            // extractors and filters for `PollingProducer` job calls work as well
+            let mut extensions = Extensions::new();
+            extensions.insert(vec![log.clone()]);
+            extensions.insert(log);
+
+            for registration in matches {
+                blueprint_core::trace!(
+                    target: "evm-polling-producer",
+                    block_number,
+                    address = %registration.address,
+                    event = registration.event,
+                    job_id = %registration.job_id,
+                    "Producing job call for event"
+                );
+                let parts = Parts::new(registration.job_id)
+                    .with_metadata(metadata.clone())
+                    .with_extensions(extensions.clone());
+                job_calls.push(JobCall::from_parts(parts, Bytes::default()));
+            }
+        }
+        job_calls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::Event;
+    use alloy_primitives::{U256, address};
+    use alloy_sol_types::sol;
+
+    sol! {
+        event Transfer(address indexed from, address indexed to, uint256 value);
+        event Deposit(address indexed account, uint256 amount);
+    }
+
+    const TOKEN_A: Address = address!("0x00000000000000000000000000000000000000aa");
+    const TOKEN_B: Address = address!("0x00000000000000000000000000000000000000bb");
+    const VAULT: Address = address!("0x00000000000000000000000000000000000000cc");
+
+    fn log<E: SolEvent>(address: Address, block_number: u64, event: &E) -> Log {
+        Log {
+            inner: alloy_primitives::Log {
+                address,
+                data: event.encode_log_data(),
+            },
+            block_number: Some(block_number),
+            block_hash: Some(B256::from(U256::from(block_number))),
+            ..Default::default()
+        }
+    }
+
+    fn transfer(value: u64) -> Transfer {
+        Transfer {
+            from: TOKEN_A,
+            to: VAULT,
+            value: U256::from(value),
+        }
+    }
+
+    fn registry() -> EventRegistry {
+        let mut events = EventRegistry::default();
+        events.register::<Transfer>(TOKEN_A, JobId::from(1u8));
+        events.register::<Transfer>(TOKEN_B, JobId::from(1u8));
+        events.register::<Deposit>(VAULT, JobId::from(2u8));
+        events
+    }
+
+    #[test]
+    fn one_job_call_per_matching_log() {
+        let deposit = Deposit {
+            account: TOKEN_A,
+            amount: U256::from(7),
+        };
+        let logs = vec![
+            log(TOKEN_A, 10, &transfer(1)),
+            log(VAULT, 10, &deposit),
+            // Registered event, but not for this contract
+            log(VAULT, 11, &transfer(2)),
+            log(TOKEN_B, 12, &transfer(3)),
+        ];
+
+        let calls = registry().to_job_calls(logs);
+        let ids: Vec<JobId> = calls.iter().map(|call| call.job_id()).collect();
+        assert_eq!(ids, [JobId::from(1u8), JobId::from(2u8), JobId::from(1u8)]);
+
+        let (mut parts, _) = calls[2].clone().into_parts();
+        let Event(event) = Event::<Transfer>::try_from(&mut parts).unwrap();
+        assert_eq!(event.value, U256::from(3));
+        let address = ContractAddress::try_from(&mut parts).unwrap();
+        assert_eq!(*address, TOKEN_B);
+        let block = BlockNumber::try_from(&mut parts).unwrap();
+        assert_eq!(*block, 12);
+
+        let (mut parts, _) = calls[1].clone().into_parts();
+        assert!(Event::<Transfer>::try_from(&mut parts).is_err());
+        let Event(event) = Event::<Deposit>::try_from(&mut parts).unwrap();
+        assert_eq!(event.amount, deposit.amount);
+    }
+
+    #[tokio::test]
+    async fn building_without_events_fails() {
+        use alloy_provider::ProviderBuilder;
+        use alloy_transport::mock::Asserter;
+
+        let provider = Arc::new(ProviderBuilder::new().connect_mocked_client(Asserter::new()));
+        let producer = EventProducer::builder(provider, PollingConfig::from_genesis())
+            .build()
+            .await;
+        assert!(producer.is_err());
+    }
+
+    #[test]
+    fn filter_covers_every_registration() {
+        let filter = registry().filter();
+        for address in [TOKEN_A, TOKEN_B, VAULT] {
+            assert!(filter.address.matches(&address));
+        }
+        let signatures = &filter.topics[0];
+        assert!(signatures.matches(&Transfer::SIGNATURE_HASH));
+        assert!(signatures.matches(&Deposit::SIGNATURE_HASH));
+        assert!(!signatures.matches(&B256::ZERO));
+    }
+}
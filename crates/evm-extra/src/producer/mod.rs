@@ -1,9 +1,14 @@
 //! Event producers for EVM
 //!
-//! Provides both polling and subscription-based producers for EVM events.
+//! Provides polling producers for EVM events, which can be woken up by log subscriptions.
+//! The `EventProducer` turns each log of a set of registered contract events into its own job call,
+//! and the `MultiChainProducer` follows several chains at once.
 //! Includes a specialized `TangleProducer` for listening to Tangle `JobSubmitted` events.
 
+mod event;
+mod multi_chain;
 mod polling;
+mod subscription;
 mod tangle;
 
 use alloc::collections::BTreeMap;
@@ -12,7 +17,12 @@ use alloy_primitives::FixedBytes;
 use alloy_rpc_types::Log;
 use blueprint_core::{JobCall, extensions::Extensions, job::call::Parts, metadata::MetadataMap};
 use bytes::Bytes;
+pub use event::{EventProducer, EventProducerBuilder};
+pub use multi_chain::{
+    ChainSource, MultiChainError, MultiChainProducer, MultiChainProducerBuilder,
+};
 pub use polling::{PollingConfig, PollingProducer};
+pub use subscription::LogSubscriber;
 pub use tangle::{JobSubmitted, TangleProducer, TangleProducerConfig};
 
 use crate::extract::{BlockHash, BlockNumber, BlockTimestamp, ContractAddress};
@@ -0,0 +1,274 @@
+//! Multi-chain producer for EVM chains
+//!
+//! A [`MultiChainProducer`] follows several EVM chains at once, each with its own provider, log
+//! filter and [`PollingConfig`], and merges their job calls into a single stream. Every job call
+//! is tagged with the ID of the chain it was produced from, available through the [`ChainId`]
+//! extractor.
+//!
+//! Chains are polled independently, so a slow or failing chain never holds back the others. With
+//! a [`CheckpointStore`], each chain also resumes from its own checkpoint.
+//!
+//! ```rust,ignore
+//! use blueprint_evm_extra::producer::{ChainSource, MultiChainProducer, PollingConfig};
+//!
+//! let bridge = Filter::new().address(bridge_address).event_signature(Locked::SIGNATURE_HASH);
+//! let producer = MultiChainProducer::builder()
+//!     .chain(ChainSource::new(1, mainnet, PollingConfig::from_current().confirmations(12)).with_filter(bridge.clone()))
+//!     .chain(ChainSource::new(42161, arbitrum, PollingConfig::from_current().confirmations(1)).with_filter(bridge))
+//!     .with_checkpoint_store(LocalDatabase::<u64>::open_strict(env.data_dir.join("evm-checkpoints.json"))?)
+//!     .build()
+//!     .await?;
+//!
+//! async fn on_locked(ChainId(chain_id): ChainId, BlockEvents(logs): BlockEvents) { /* ... */ }
+//! ```
+//!
+//! [`ChainId`]: crate::extract::ChainId
+//! [`CheckpointStore`]: crate::checkpoint::CheckpointStore
+
+use alloy_provider::{DynProvider, Provider};
+use alloy_rpc_types::Filter;
+use alloy_transport::TransportError;
+use blueprint_core::JobCall;
+use blueprint_std::sync::Arc;
+use core::{
+    fmt::{self, Debug},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures::stream::{BoxStream, SelectAll};
+use futures::{Stream, StreamExt};
+
+use super::{PollingConfig, PollingProducer};
+use crate::checkpoint::{CheckpointError, CheckpointStore};
+use crate::extract::ChainId;
+
+/// Error type for building a [`MultiChainProducer`]
+#[derive(Debug, thiserror::Error)]
+pub enum MultiChainError {
+    /// A chain's provider couldn't be reached
+    #[error("Transport error on chain {chain_id}: {source}")]
+    Transport {
+        /// The chain the provider is for
+        chain_id: u64,
+        /// The underlying error
+        source: TransportError,
+    },
+    /// A chain's provider is connected to another chain
+    #[error("Provider for chain {expected} is connected to chain {actual}")]
+    ChainIdMismatch {
+        /// The chain ID the source was registered with
+        expected: u64,
+        /// The chain ID reported by the provider
+        actual: u64,
+    },
+    /// The same chain was registered twice
+    #[error("Chain {0} was registered more than once")]
+    DuplicateChain(u64),
+    /// The checkpoint store couldn't be read
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(#[from] CheckpointError),
+}
+
+/// A chain to follow with a [`MultiChainProducer`]
+#[must_use]
+#[derive(Clone)]
+pub struct ChainSource {
+    chain_id: u64,
+    provider: DynProvider,
+    config: PollingConfig,
+    filter: Filter,
+    ws_endpoint: Option<String>,
+}
+
+impl Debug for ChainSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainSource")
+            .field("chain_id", &self.chain_id)
+            .field("config", &self.config)
+            .field("filter", &self.filter)
+            .field("ws_endpoint", &self.ws_endpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ChainSource {
+    /// Follow `chain_id` through `provider`
+    ///
+    /// `config` sets where to start, and the confirmation depth and step for this chain. Every log
+    /// is fetched, unless a filter is set through [`ChainSource::with_filter()`].
+    pub fn new(chain_id: u64, provider: impl Provider + 'static, config: PollingConfig) -> Self {
+        Self {
+            chain_id,
+            provider: DynProvider::new(provider),
+            config,
+            filter: Filter::new(),
+            ws_endpoint: None,
+        }
+    }
+
+    /// Only fetch the logs matching `filter`, like the logs of some events from some contracts
+    ///
+    /// The block range of `filter` is ignored.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Wait for log notifications from the websocket endpoint `ws_endpoint`, instead of polling
+    ///
+    /// See [`PollingProducer::with_log_subscription()`].
+    pub fn with_log_subscription(mut self, ws_endpoint: impl Into<String>) -> Self {
+        self.ws_endpoint = Some(ws_endpoint.into());
+        self
+    }
+
+    /// The ID of the chain
+    #[must_use]
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+}
+
+/// A producer merging the job calls of several EVM chains
+///
+/// See the [module docs](self) for details.
+pub struct MultiChainProducer {
+    chain_ids: Vec<u64>,
+    streams: SelectAll<BoxStream<'static, Result<JobCall, TransportError>>>,
+}
+
+impl Debug for MultiChainProducer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiChainProducer")
+            .field("chain_ids", &self.chain_ids)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MultiChainProducer {
+    /// Start registering the chains to follow
+    pub fn builder() -> MultiChainProducerBuilder {
+        MultiChainProducerBuilder::default()
+    }
+
+    /// The IDs of the chains followed
+    #[must_use]
+    pub fn chain_ids(&self) -> &[u64] {
+        &self.chain_ids
+    }
+}
+
+impl Stream for MultiChainProducer {
+    type Item = Result<JobCall, TransportError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().streams.poll_next_unpin(cx)
+    }
+}
+
+/// Builder for a [`MultiChainProducer`]
+#[must_use]
+#[derive(Default)]
+pub struct MultiChainProducerBuilder {
+    chains: Vec<ChainSource>,
+    checkpoints: Option<Arc<dyn CheckpointStore>>,
+}
+
+impl Debug for MultiChainProducerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiChainProducerBuilder")
+            .field("chains", &self.chains)
+            .field("checkpoints", &self.checkpoints.is_some())
+            .finish()
+    }
+}
+
+impl MultiChainProducerBuilder {
+    /// Follow `chain`
+    pub fn chain(mut self, chain: ChainSource) -> Self {
+        self.chains.push(chain);
+        self
+    }
+
+    /// Resume each chain from its last block committed to `store`, and commit processed blocks
+    /// to it
+    ///
+    /// See the [checkpoint module](crate::checkpoint) for details.
+    pub fn with_checkpoint_store(mut self, store: impl CheckpointStore + 'static) -> Self {
+        self.checkpoints = Some(Arc::new(store));
+        self
+    }
+
+    /// Create the producer
+    ///
+    /// The chain ID of every provider is checked against the one it was registered with.
+    ///
+    /// # Errors
+    ///
+    /// * A chain was registered twice, or its provider is connected to another chain
+    /// * Transport errors when checking the chain ID, or when fetching the current block number of
+    ///   a chain using [`PollingConfig::from_current()`]
+    /// * The checkpoint store couldn't be read
+    pub async fn build(self) -> Result<MultiChainProducer, MultiChainError> {
+        let mut chain_ids = Vec::with_capacity(self.chains.len());
+        let mut streams = SelectAll::new();
+        for chain in self.chains {
+            let chain_id = chain.chain_id;
+            if chain_ids.contains(&chain_id) {
+                return Err(MultiChainError::DuplicateChain(chain_id));
+            }
+
+            let transport = |source| MultiChainError::Transport { chain_id, source };
+            let actual = chain.provider.get_chain_id().await.map_err(transport)?;
+            if actual != chain_id {
+                return Err(MultiChainError::ChainIdMismatch {
+                    expected: chain_id,
+                    actual,
+                });
+            }
+
+            let mut producer = PollingProducer::new(Arc::new(chain.provider), chain.config)
+                .await
+                .map_err(transport)?
+                .with_filter(chain.filter);
+            if let Some(store) = &self.checkpoints {
+                producer = producer.with_checkpoint_store(chain_id, store.clone())?;
+            }
+            if let Some(ws_endpoint) = chain.ws_endpoint {
+                producer = producer.with_log_subscription(ws_endpoint);
+            }
+
+            blueprint_core::debug!(
+                target: "evm-polling-producer",
+                chain_id,
+                "Following chain"
+            );
+            chain_ids.push(chain_id);
+            streams.push(
+                producer
+                    .map(move |result| result.map(|call| stamp_chain_id(call, chain_id)))
+                    .boxed(),
+            );
+        }
+
+        Ok(MultiChainProducer { chain_ids, streams })
+    }
+}
+
+fn stamp_chain_id(mut call: JobCall, chain_id: u64) -> JobCall {
+    call.metadata_mut().insert(ChainId::METADATA_KEY, chain_id);
+    call
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blueprint_core::job::call::Parts;
+
+    #[test]
+    fn job_calls_are_stamped_with_their_chain() {
+        let call = stamp_chain_id(JobCall::new(0, bytes::Bytes::new()), 42161);
+        let (mut parts, _): (Parts, _) = call.into_parts();
+        assert_eq!(ChainId::try_from(&mut parts).unwrap(), ChainId(42161));
+    }
+}
@@ -18,6 +18,13 @@
 //! 3. Respects confirmation depth for finality
 //! 4. Converts logs to job calls
 //! 5. Maintains a buffer of pending jobs
+//!
+//! # Subscriptions
+//!
+//! With [`PollingProducer::with_log_subscription()`], the producer waits for `eth_subscribe`
+//! log notifications from a websocket endpoint instead of the polling interval, and still polls once
+//! the subscription is silent for a polling interval. If the subscription can't be established or
+//! drops, it falls back to polling until it can resubscribe, and then reads every block it missed.
 
 use alloc::collections::VecDeque;
 use alloy_provider::Provider;
@@ -32,7 +39,11 @@ use core::{
     time::Duration,
 };
 use futures::Stream;
-use tokio::time::Sleep;
+
+use super::event::EventRegistry;
+use super::subscription::LogSubscriber;
+use crate::checkpoint::{CheckpointError, CheckpointStore, checkpoint_writer};
+use blueprint_store_local_database::BackgroundWriter;
 
 #[derive(Debug, Clone, Copy)]
 enum StartBlockSource {
@@ -133,6 +144,14 @@ pub struct PollingProducer<P: Provider> {
     config: PollingConfig,
     state: Arc<Mutex<PollingState>>,
     buffer: VecDeque<JobCall>,
+    subscriber: Option<Arc<tokio::sync::Mutex<LogSubscriber>>>,
+    /// The filter for the logs to fetch, without a block range
+    logs: Filter,
+    /// Only fetch these events, with one job call per log. See [`EventProducer`](super::EventProducer).
+    events: Option<Arc<EventRegistry>>,
+    checkpoints: Option<BackgroundWriter<u64>>,
+    /// The last block of the range being yielded, committed once the buffer is drained
+    uncommitted: Option<u64>,
 }
 
 /// Producer state for managing the polling lifecycle
@@ -141,8 +160,8 @@ enum PollingState {
     FetchingBlockNumber(Pin<Box<dyn Future<Output = Result<u64, TransportError>> + Send>>),
     /// Fetching logs for a specific block range
     FetchingLogs(Pin<Box<dyn Future<Output = Result<Vec<Log>, TransportError>> + Send>>),
-    /// Waiting for next polling interval
-    Idle(Pin<Box<Sleep>>),
+    /// Waiting for next polling interval, or log notification
+    Idle(Pin<Box<dyn Future<Output = ()> + Send>>),
 }
 
 impl Debug for PollingState {
@@ -196,8 +215,93 @@ impl<P: Provider> PollingProducer<P> {
                 tokio::time::sleep(Duration::from_micros(1)),
             )))),
             buffer: VecDeque::with_capacity(config.step as usize),
+            subscriber: None,
+            logs: Filter::new(),
+            events: None,
+            checkpoints: None,
+            uncommitted: None,
         })
     }
+
+    /// Wait for log notifications from the websocket endpoint `ws_endpoint`, instead of polling
+    ///
+    /// This is usually the `ws_rpc_endpoint` of the `BlueprintEnvironment`. The polling interval
+    /// is only used while the subscription is down or silent, or while waiting for confirmations.
+    /// See the [module docs](self) for details.
+    #[must_use]
+    pub fn with_log_subscription(mut self, ws_endpoint: impl Into<String>) -> Self {
+        let subscriber = LogSubscriber::new(ws_endpoint.into(), self.log_filter());
+        self.subscriber = Some(Arc::new(tokio::sync::Mutex::new(subscriber)));
+        self
+    }
+
+    /// Only fetch the logs of `events`, and turn each of them into its own job call
+    pub(crate) fn with_events(mut self, events: EventRegistry) -> Self {
+        self.logs = events.filter();
+        self.events = Some(Arc::new(events));
+        self
+    }
+
+    /// Only fetch the logs matching `filter`, ignoring its block range
+    pub(crate) fn with_filter(mut self, filter: Filter) -> Self {
+        self.logs = filter;
+        self
+    }
+
+    /// Resume from the last block committed to `store` for `chain_id`, and commit processed
+    /// blocks to it
+    ///
+    /// See the [checkpoint module](crate::checkpoint) for details.
+    pub(crate) fn with_checkpoint_store(
+        mut self,
+        chain_id: u64,
+        store: Arc<dyn CheckpointStore>,
+    ) -> Result<Self, CheckpointError> {
+        if let Some(block) = store.load(chain_id)? {
+            blueprint_core::info!(
+                target: "evm-polling-producer",
+                chain_id,
+                block,
+                "Resuming from checkpoint"
+            );
+            self.filter = Filter::new().from_block(block).to_block(block);
+        }
+        self.checkpoints = Some(checkpoint_writer(store, chain_id));
+        Ok(self)
+    }
+
+    /// The filter for the logs to fetch, without a block range
+    fn log_filter(&self) -> Filter {
+        self.logs.clone()
+    }
+
+    /// Persist `block` as the last block processed in the background, if there's a
+    /// [`CheckpointStore`]
+    fn commit(&self, block: u64) {
+        if let Some(checkpoints) = &self.checkpoints {
+            checkpoints.write(block);
+        }
+    }
+
+    /// The state to wait in until the next poll
+    fn idle(&self) -> PollingState {
+        let poll_interval = self.config.poll_interval;
+        let Some(subscriber) = self.subscriber.clone() else {
+            return PollingState::Idle(Box::pin(tokio::time::sleep(poll_interval)));
+        };
+
+        let last_queried = self
+            .filter
+            .get_to_block()
+            .unwrap_or(self.config.start_block.number());
+        PollingState::Idle(Box::pin(async move {
+            subscriber
+                .lock()
+                .await
+                .wait(poll_interval, last_queried)
+                .await;
+        }))
+    }
 }
 
 impl<P: Provider + 'static> Stream for PollingProducer<P> {
@@ -211,6 +315,8 @@ impl<P: Provider + 'static> Stream for PollingProducer<P> {
             if let Some(job) = this.buffer.pop_front() {
                 if !this.buffer.is_empty() {
                     cx.waker().wake_by_ref();
+                } else if let Some(block) = this.uncommitted.take() {
+                    this.commit(block);
                 }
                 return Poll::Ready(Some(Ok(job)));
             }
@@ -256,14 +362,13 @@ impl<P: Provider + 'static> Stream for PollingProducer<P> {
                             blueprint_core::trace!(
                                 target: "evm-polling-producer", "No new blocks to process yet, waiting for next interval"
                             );
-                            *state = PollingState::Idle(Box::pin(tokio::time::sleep(
-                                this.config.poll_interval,
-                            )));
+                            *state = this.idle();
                             continue;
                         }
 
                         // Update filter for next range
-                        this.filter = Filter::new()
+                        this.filter = this
+                            .log_filter()
                             .from_block(next_from_block)
                             .to_block(next_to_block);
 
@@ -302,13 +407,22 @@ impl<P: Provider + 'static> Stream for PollingProducer<P> {
                         );
 
                         // Convert logs to job calls and buffer them
-                        let job_calls = super::logs_to_job_calls(logs);
+                        let job_calls = match &this.events {
+                            Some(events) => events.to_job_calls(logs),
+                            None => super::logs_to_job_calls(logs),
+                        };
+                        let to_block = this.filter.get_to_block();
+                        if job_calls.is_empty() {
+                            if let Some(block) = to_block {
+                                this.commit(block);
+                            }
+                        } else {
+                            this.uncommitted = to_block;
+                        }
                         this.buffer.extend(job_calls);
 
                         // Transition back to idle state
-                        *state = PollingState::Idle(Box::pin(tokio::time::sleep(
-                            this.config.poll_interval,
-                        )));
+                        *state = this.idle();
                     }
                     Poll::Ready(Err(e)) => {
                         blueprint_core::error!(
@@ -0,0 +1,197 @@
+//! Log subscriptions for polling producers
+//!
+//! The subscription is only used to know *when* to poll. Every notification makes the producer
+//! read the new blocks with `eth_getLogs` right away, so confirmations and checkpoints work the
+//! same way with or without it. While the subscription is down or silent, the producer polls on
+//! its interval instead, and reads every block it missed once it's back.
+//!
+//! Used by the [`PollingProducer`], and the producers of other crates following EVM contracts.
+//!
+//! [`PollingProducer`]: super::PollingProducer
+
+use alloy_provider::{DynProvider, Provider, ProviderBuilder, WsConnect};
+use alloy_rpc_types::{Filter, Log};
+use alloy_transport::TransportError;
+use core::fmt::{self, Debug, Display};
+use core::pin::Pin;
+use core::time::Duration;
+use futures::{FutureExt, Stream, StreamExt};
+use tokio::time::{Instant, sleep, timeout};
+
+/// How long to wait for the websocket to connect and the subscription to be accepted
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The delay before resubscribing grows by this much on every failure
+const RESUBSCRIBE_BASE_DELAY: Duration = Duration::from_secs(1);
+const RESUBSCRIBE_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// An `eth_subscribe("logs")` subscription, with automatic fallback to polling
+pub struct LogSubscriber {
+    endpoint: String,
+    filter: Filter,
+    active: Option<ActiveSubscription>,
+    /// The highest block a notification was received for
+    notified: u64,
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+impl Debug for LogSubscriber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogSubscriber")
+            .field("endpoint", &self.endpoint)
+            .field("subscribed", &self.active.is_some())
+            .field("notified", &self.notified)
+            .finish_non_exhaustive()
+    }
+}
+
+struct ActiveSubscription {
+    // The subscription ends when its provider is dropped
+    _provider: DynProvider,
+    logs: Pin<Box<dyn Stream<Item = Log> + Send>>,
+}
+
+impl LogSubscriber {
+    /// Subscribe to the logs matching `filter` through the websocket `endpoint`
+    ///
+    /// Nothing is connected until the first [`Self::wait()`].
+    #[must_use]
+    pub fn new(endpoint: String, filter: Filter) -> Self {
+        Self {
+            endpoint,
+            filter,
+            active: None,
+            notified: 0,
+            failures: 0,
+            retry_at: None,
+        }
+    }
+
+    /// Wait until the next poll is due
+    ///
+    /// `cursor` is the last block the producer has read. If a notification was received for a
+    /// later block, which happens when it doesn't have enough confirmations yet, the producer
+    /// keeps polling on `poll_interval` until it gets there. A poll is also due once the
+    /// subscription is silent for `poll_interval`, in case it stalled without closing.
+    pub async fn wait(&mut self, poll_interval: Duration, cursor: u64) {
+        if poll_interval.is_zero() || self.notified > cursor {
+            sleep(poll_interval).await;
+            return;
+        }
+
+        if self.active.is_none() && self.retry_at.is_none_or(|at| at <= Instant::now()) {
+            // Poll right away, to read whatever was missed while unsubscribed
+            if self.subscribe().await {
+                return;
+            }
+        }
+
+        let Some(active) = &mut self.active else {
+            sleep(poll_interval).await;
+            return;
+        };
+
+        let Ok(next) = timeout(poll_interval, active.logs.next()).await else {
+            return;
+        };
+        match next {
+            Some(log) => {
+                let mut notified = log.block_number;
+                // Notifications for the same blocks are covered by a single poll
+                while let Some(Some(log)) = active.logs.next().now_or_never() {
+                    notified = notified.max(log.block_number);
+                }
+                if let Some(block) = notified {
+                    self.notified = self.notified.max(block);
+                }
+            }
+            None => {
+                blueprint_core::warn!(
+                    target: "evm-log-subscriber",
+                    endpoint = %self.endpoint,
+                    "Log subscription closed, falling back to polling"
+                );
+                self.active = None;
+                self.schedule_retry();
+            }
+        }
+    }
+
+    async fn subscribe(&mut self) -> bool {
+        let result = timeout(CONNECT_TIMEOUT, async {
+            let provider = ProviderBuilder::new()
+                .connect_ws(WsConnect::new(self.endpoint.clone()))
+                .await?;
+            let subscription = provider.subscribe_logs(&self.filter).await?;
+            Ok::<_, TransportError>((DynProvider::new(provider), subscription.into_stream()))
+        })
+        .await;
+
+        match result {
+            Ok(Ok((provider, logs))) => {
+                blueprint_core::info!(
+                    target: "evm-log-subscriber",
+                    endpoint = %self.endpoint,
+                    "Subscribed to logs"
+                );
+                self.active = Some(ActiveSubscription {
+                    _provider: provider,
+                    logs: Box::pin(logs),
+                });
+                self.failures = 0;
+                self.retry_at = None;
+                true
+            }
+            Ok(Err(e)) => {
+                self.subscribe_failed(&e);
+                false
+            }
+            Err(_) => {
+                self.subscribe_failed(&"timed out");
+                false
+            }
+        }
+    }
+
+    fn subscribe_failed(&mut self, err: &dyn Display) {
+        self.schedule_retry();
+        blueprint_core::warn!(
+            target: "evm-log-subscriber",
+            endpoint = %self.endpoint,
+            attempts = self.failures,
+            "Failed to subscribe to logs, polling in the meantime: {err}"
+        );
+    }
+
+    fn schedule_retry(&mut self) {
+        self.failures += 1;
+        let delay = RESUBSCRIBE_BASE_DELAY
+            .saturating_mul(self.failures)
+            .min(RESUBSCRIBE_MAX_DELAY);
+        self.retry_at = Some(Instant::now() + delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn silent_subscription_still_polls() {
+        let provider =
+            ProviderBuilder::new().connect_http("http://127.0.0.1:8545".parse().unwrap());
+        let mut subscriber = LogSubscriber::new("ws://127.0.0.1:8545".into(), Filter::new());
+        subscriber.active = Some(ActiveSubscription {
+            _provider: DynProvider::new(provider),
+            logs: Box::pin(futures::stream::pending()),
+        });
+
+        let poll_interval = Duration::from_secs(5);
+        let start = Instant::now();
+        timeout(poll_interval * 2, subscriber.wait(poll_interval, 0))
+            .await
+            .expect("a poll should be due after a silent interval");
+        assert_eq!(start.elapsed(), poll_interval);
+        assert!(subscriber.active.is_some());
+    }
+}
@@ -0,0 +1,266 @@
+use crate::attr_parsing::{parse_assignment_attribute, second};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{
+    Attribute, Expr, ExprLit, FnArg, GenericArgument, ItemFn, Lit, LitStr, Meta, Pat,
+    PathArguments, ReturnType, Token, Type, parse::Parse, spanned::Spanned,
+};
+
+pub(crate) fn expand(attr: Attrs, item_fn: &ItemFn) -> syn::Result<TokenStream> {
+    let Attrs { name } = attr;
+
+    let fn_name = &item_fn.sig.ident;
+    let name = name
+        .map(second)
+        .map_or_else(|| fn_name.unraw().to_string(), |name| name.value());
+    let description = description(&item_fn.attrs);
+    let schema_fn = format_ident!("{}_schema", fn_name.unraw());
+    let vis = &item_fn.vis;
+
+    let params = match tangle_arg(item_fn)? {
+        Some((ty, names)) => quote! {
+            __private::params((&&__private::SchemaOf::<#ty>::new()).schema()?, &[#(#names),*])
+        },
+        None => quote! { ::std::vec::Vec::new() },
+    };
+    let result = match tangle_result(&item_fn.sig.output)? {
+        Some(ty) => quote! {
+            __private::result((&&__private::SchemaOf::<#ty>::new()).schema()?)
+        },
+        None => quote! { ::std::vec::Vec::new() },
+    };
+
+    let doc = format!("The job schema of `{fn_name}`");
+
+    Ok(quote! {
+        #item_fn
+
+        #[doc = #doc]
+        #vis fn #schema_fn() -> ::core::result::Result<
+            ::blueprint_sdk::tangle::schema::JobSchema,
+            ::blueprint_sdk::tangle::schema::SchemaError,
+        > {
+            #[allow(unused_imports)]
+            use ::blueprint_sdk::tangle::schema::__private::{
+                self, StructSchema as _, ValueSchema as _,
+            };
+
+            ::core::result::Result::Ok(::blueprint_sdk::tangle::schema::JobSchema {
+                name: ::std::string::String::from(#name),
+                description: ::std::string::String::from(#description),
+                params: #params,
+                result: #result,
+            })
+        }
+    })
+}
+
+mod kw {
+    syn::custom_keyword!(name);
+}
+
+pub(crate) struct Attrs {
+    name: Option<(kw::name, LitStr)>,
+}
+
+impl Parse for Attrs {
+    fn parse(input: syn::parse::ParseStream<'_>) -> syn::Result<Self> {
+        let mut name = None;
+
+        while !input.is_empty() {
+            let lh = input.lookahead1();
+            if lh.peek(kw::name) {
+                parse_assignment_attribute(input, &mut name)?;
+            } else {
+                return Err(lh.error());
+            }
+
+            let _ = input.parse::<Token![,]>();
+        }
+
+        Ok(Self { name })
+    }
+}
+
+/// The first paragraph of the function's doc comment, as a single line
+fn description(attrs: &[Attribute]) -> String {
+    let lines = attrs.iter().filter_map(|attr| match &attr.meta {
+        Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(doc), ..
+            }) => Some(doc.value()),
+            _ => None,
+        },
+        _ => None,
+    });
+
+    let mut description = String::new();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() {
+            if description.is_empty() {
+                continue;
+            }
+            break;
+        }
+
+        if !description.is_empty() {
+            description.push(' ');
+        }
+        description.push_str(line);
+    }
+
+    description
+}
+
+/// The type inside the job's `TangleArg`, and the names bound by its pattern
+fn tangle_arg(item_fn: &ItemFn) -> syn::Result<Option<(Type, Vec<String>)>> {
+    let mut found = None;
+    for arg in &item_fn.sig.inputs {
+        let FnArg::Typed(pat_type) = arg else {
+            continue;
+        };
+        let Some(ty) = wrapped_type(&pat_type.ty, "TangleArg") else {
+            continue;
+        };
+
+        if found.is_some() {
+            return Err(syn::Error::new_spanned(
+                &pat_type.ty,
+                "`#[job_schema]` only supports a single `TangleArg`, use a tuple for multiple params",
+            ));
+        }
+
+        found = Some((ty.clone(), binding_names(&pat_type.pat)));
+    }
+
+    Ok(found)
+}
+
+/// The type inside the job's `TangleResult`, if it returns one
+///
+/// `Result<TangleResult<T>, E>` and `Option<TangleResult<T>>` are also accepted.
+fn tangle_result(output: &ReturnType) -> syn::Result<Option<Type>> {
+    let ReturnType::Type(_, ty) = output else {
+        return Ok(None);
+    };
+    if matches!(&**ty, Type::Tuple(tuple) if tuple.elems.is_empty()) {
+        return Ok(None);
+    }
+
+    let inner = wrapped_type(ty, "Result")
+        .or_else(|| wrapped_type(ty, "Option"))
+        .unwrap_or(ty);
+    wrapped_type(inner, "TangleResult")
+        .cloned()
+        .map(Some)
+        .ok_or_else(|| {
+            syn::Error::new(
+                ty.span(),
+                "`#[job_schema]` requires jobs to return a `TangleResult`, or nothing",
+            )
+        })
+}
+
+/// The first generic argument of `ty`, if its last path segment is `wrapper`
+fn wrapped_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// The param names bound by an argument's pattern
+///
+/// `TangleArg((a, b))` names two params, `TangleArg(x)` and `x: TangleArg<_>` name one, and
+/// anything else (such as `_`) is left unnamed.
+fn binding_names(pat: &Pat) -> Vec<String> {
+    let inner = match pat {
+        Pat::TupleStruct(tuple_struct) if tuple_struct.elems.len() == 1 => &tuple_struct.elems[0],
+        pat => pat,
+    };
+
+    match inner {
+        Pat::Tuple(tuple) => tuple.elems.iter().map(binding_name).collect(),
+        pat => vec![binding_name(pat)],
+    }
+}
+
+fn binding_name(pat: &Pat) -> String {
+    match pat {
+        Pat::Ident(ident) => ident
+            .ident
+            .unraw()
+            .to_string()
+            .trim_start_matches('_')
+            .to_owned(),
+        _ => String::new(),
+    }
+}
+
+#[test]
+fn binding_names_follow_the_pattern() {
+    let names = |arg: syn::FnArg| match arg {
+        FnArg::Typed(pat_type) => binding_names(&pat_type.pat),
+        FnArg::Receiver(_) => unreachable!(),
+    };
+
+    assert_eq!(
+        names(syn::parse_quote!(TangleArg((a, _b, _)): TangleArg<(u64, u64, u64)>)),
+        ["a", "b", ""]
+    );
+    assert_eq!(
+        names(syn::parse_quote!(TangleArg(x): TangleArg<u64>)),
+        ["x"]
+    );
+    assert_eq!(names(syn::parse_quote!(r#type: TangleArg<u64>)), ["type"]);
+    assert_eq!(names(syn::parse_quote!(_: TangleArg<u64>)), [""]);
+}
+
+#[test]
+fn result_type_is_unwrapped() {
+    let result = |output: ReturnType| {
+        tangle_result(&output)
+            .map(|ty| ty.map(|ty| quote!(#ty).to_string()))
+            .map_err(|e| e.to_string())
+    };
+
+    assert_eq!(result(syn::parse_quote!()), Ok(None));
+    assert_eq!(result(syn::parse_quote!(-> ())), Ok(None));
+    assert_eq!(
+        result(syn::parse_quote!(-> TangleResult<u64>)),
+        Ok(Some("u64".into()))
+    );
+    assert_eq!(
+        result(syn::parse_quote!(-> Result<TangleResult<(u64, bool)>, Error>)),
+        Ok(Some("(u64 , bool)".into()))
+    );
+    assert!(result(syn::parse_quote!(-> String)).is_err());
+}
+
+#[test]
+fn description_is_the_first_paragraph() {
+    let item_fn: ItemFn = syn::parse_quote! {
+        /// Squares a number,
+        /// and returns it.
+        ///
+        /// More details.
+        async fn square() {}
+    };
+    assert_eq!(
+        description(&item_fn.attrs),
+        "Squares a number, and returns it."
+    );
+}
@@ -17,6 +17,7 @@ mod debug_job;
 #[cfg(feature = "evm")]
 mod evm;
 mod from_ref;
+mod job_schema;
 mod with_position;
 
 #[cfg(feature = "evm")]
@@ -178,6 +179,51 @@ pub fn debug_job(_attr: TokenStream, input: TokenStream) -> TokenStream {
     });
 }
 
+/// Generates the job schema of a Tangle job from its signature.
+///
+/// Alongside the job, this generates a `<job>_schema()` function with the same visibility, which
+/// returns the job's `JobSchema`: its name, the first paragraph of its doc comment as the
+/// description, and its params and result, derived from the types in its `TangleArg` and
+/// `TangleResult`.
+///
+/// ```rust
+/// use blueprint_sdk::macros::job_schema;
+/// use blueprint_sdk::tangle::extract::{TangleArg, TangleResult};
+///
+/// /// Multiplies two numbers
+/// #[job_schema]
+/// pub async fn multiply(TangleArg((x, y)): TangleArg<(u64, u64)>) -> TangleResult<u64> {
+///     TangleResult(x * y)
+/// }
+///
+/// let schema = multiply_schema().unwrap();
+/// assert_eq!(schema.name, "multiply");
+/// assert_eq!(schema.description, "Multiplies two numbers");
+/// // Tuple elements are named after the pattern they're bound to
+/// assert_eq!(schema.params[0].name, "x");
+/// assert_eq!(schema.params[1].ty, "uint64");
+/// ```
+///
+/// The name can be overridden with `#[job_schema(name = "...")]`.
+///
+/// The generated schemas are collected into the blueprint's job list with `JobDefinitions`, which
+/// also checks that every job in the `Router` has a definition. See the
+/// `blueprint_sdk::tangle::schema` module for details.
+///
+/// # Limitations
+///
+/// * Jobs can have at most one `TangleArg`. Use a tuple or struct for multiple params.
+/// * Jobs must return a `TangleResult` (optionally wrapped in a `Result` or `Option`), or nothing.
+/// * `sol!` structs nested inside tuples or arrays aren't supported, and produce an error from the
+///   `<job>_schema()` function.
+#[proc_macro_attribute]
+pub fn job_schema(attr: TokenStream, input: TokenStream) -> TokenStream {
+    expand(syn::parse(attr).and_then(|attrs| {
+        let item_fn = syn::parse(input)?;
+        job_schema::expand(attrs, &item_fn)
+    }))
+}
+
 /// Derive an implementation of [`FromRef`] for each field in a struct.
 ///
 /// # Example
@@ -2,7 +2,7 @@ use crate::VSOCK_PORT;
 use crate::blueprint_manager_bridge_client::BlueprintManagerBridgeClient;
 use crate::{
     AddOwnerToServiceRequest, Error, PortRequest, RegisterBlueprintServiceProxyRequest,
-    RemoveOwnerFromServiceRequest, ServiceOwner, TlsProfileConfig,
+    RemoveOwnerFromServiceRequest, RunnerCommand, ServiceOwner, TlsProfileConfig,
     UnregisterBlueprintServiceProxyRequest, UpdateBlueprintServiceTlsProfileRequest,
 };
 use blueprint_auth::models::{ServiceOwnerModel, TlsProfile};
@@ -11,6 +11,7 @@ use hyper_util::rt::TokioIo;
 use std::path::Path;
 use tokio::net::UnixStream;
 use tokio_vsock::{VsockAddr, VsockStream};
+use tonic::Streaming;
 use tonic::transport::Channel;
 
 #[derive(Debug)]
@@ -103,6 +104,25 @@ impl Bridge {
         Ok(reply.port as u16)
     }
 
+    /// Subscribes to the commands the blueprint manager pushes to the runner.
+    ///
+    /// The returned stream stays open for as long as the bridge is alive. See [`RunnerCommand`]
+    /// for the commands that may be sent.
+    ///
+    /// # Errors
+    /// - Returns an error if the subscription fails, e.g., if the manager predates runner commands
+    ///   (the status code will be `Unimplemented`).
+    pub async fn watch_runner_commands(&self) -> Result<Streaming<RunnerCommand>, Error> {
+        let stream = self
+            .client
+            .clone()
+            .watch_runner_commands(())
+            .await?
+            .into_inner();
+
+        Ok(stream)
+    }
+
     /// Registers a blueprint service proxy with the blueprint manager bridge.
     ///
     /// This method allows a blueprint to make a service accessible via the proxy.
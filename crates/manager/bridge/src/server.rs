@@ -3,9 +3,10 @@ use crate::blueprint_manager_bridge_server::{
     BlueprintManagerBridge, BlueprintManagerBridgeServer,
 };
 use crate::{
-    AddOwnerToServiceRequest, Error, PortRequest, PortResponse,
-    RegisterBlueprintServiceProxyRequest, RemoveOwnerFromServiceRequest,
-    UnregisterBlueprintServiceProxyRequest, UpdateBlueprintServiceTlsProfileRequest,
+    AddOwnerToServiceRequest, DrainCommand, Error, PortRequest, PortResponse,
+    RegisterBlueprintServiceProxyRequest, RemoveOwnerFromServiceRequest, RunnerCommand,
+    SetJobEnabledCommand, UnregisterBlueprintServiceProxyRequest,
+    UpdateBlueprintServiceTlsProfileRequest, runner_command,
 };
 use blueprint_auth::{
     db::RocksDb,
@@ -15,10 +16,11 @@ use blueprint_auth::{
 use blueprint_core::{error, info, warn};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::UnixListener;
-use tokio::sync::{Mutex, oneshot};
+use tokio::sync::{Mutex, broadcast, mpsc, oneshot};
 use tokio::task::JoinHandle;
-use tonic::codegen::tokio_stream::wrappers::UnixListenerStream;
+use tonic::codegen::tokio_stream::wrappers::{ReceiverStream, UnixListenerStream};
 use tonic::{Request, Response, transport::Server};
 
 /// Handle to a running bridge
@@ -29,10 +31,48 @@ pub struct BridgeHandle {
     handle: JoinHandle<Result<(), Error>>,
     db: RocksDb,
     registered_service_id: Arc<std::sync::Mutex<Option<u64>>>,
+    runner_commands: broadcast::Sender<RunnerCommand>,
 }
 
 impl BridgeHandle {
     pub fn shutdown(self) {}
+
+    /// Ask the service's runner to drain
+    ///
+    /// The runner stops accepting new job calls and waits up to `timeout` (or its own configured
+    /// deadline, if `None`) for in-flight jobs before exiting.
+    ///
+    /// Returns `false` if no runner is currently watching for commands, in which case the request
+    /// is dropped.
+    pub fn request_drain(&self, timeout: Option<Duration>) -> bool {
+        let command = runner_command::Command::Drain(DrainCommand {
+            timeout_secs: timeout.map_or(0, |timeout| timeout.as_secs().max(1)),
+        });
+
+        self.send_runner_command(command)
+    }
+
+    /// Ask the service's runner to start or stop accepting calls for `job_id`
+    ///
+    /// Calls for a disabled job are rejected without running it. Jobs that are already running are
+    /// not affected.
+    ///
+    /// Returns `false` if no runner is currently watching for commands, in which case the request
+    /// is dropped.
+    pub fn set_job_enabled(&self, job_id: u32, enabled: bool) -> bool {
+        let command =
+            runner_command::Command::SetJobEnabled(SetJobEnabledCommand { job_id, enabled });
+
+        self.send_runner_command(command)
+    }
+
+    fn send_runner_command(&self, command: runner_command::Command) -> bool {
+        self.runner_commands
+            .send(RunnerCommand {
+                command: Some(command),
+            })
+            .is_ok()
+    }
 }
 
 impl Drop for BridgeHandle {
@@ -137,6 +177,8 @@ impl Bridge {
         let registered_service_id = Arc::new(std::sync::Mutex::new(None));
         let registered_service_id_clone = Arc::clone(&registered_service_id);
         let db_clone = self.db.clone();
+        let (runner_commands, _) = broadcast::channel(RUNNER_COMMAND_BUFFER);
+        let runner_commands_clone = runner_commands.clone();
 
         let handle = tokio::task::spawn(async move {
             Server::builder()
@@ -146,6 +188,7 @@ impl Bridge {
                         self.db,
                         self.expected_service_id,
                         registered_service_id_clone,
+                        runner_commands_clone,
                     ),
                 ))
                 .serve_with_incoming(UnixListenerStream::new(listener))
@@ -159,17 +202,22 @@ impl Bridge {
                 handle,
                 db: db_clone,
                 registered_service_id,
+                runner_commands,
             },
             rx,
         ))
     }
 }
 
+/// The number of runner commands buffered per watcher before the oldest are dropped
+const RUNNER_COMMAND_BUFFER: usize = 16;
+
 struct BridgeService {
     ready_tx: Arc<Mutex<Option<oneshot::Sender<()>>>>,
     db: RocksDb,
     expected_service_id: Option<u64>,
     registered_service_id: Arc<std::sync::Mutex<Option<u64>>>,
+    runner_commands: broadcast::Sender<RunnerCommand>,
 }
 
 impl BridgeService {
@@ -180,6 +228,7 @@ impl BridgeService {
             db,
             expected_service_id: None,
             registered_service_id: Arc::new(std::sync::Mutex::new(None)),
+            runner_commands: broadcast::channel(RUNNER_COMMAND_BUFFER).0,
         }
     }
 
@@ -188,12 +237,14 @@ impl BridgeService {
         db: RocksDb,
         expected_service_id: Option<u64>,
         registered_service_id: Arc<std::sync::Mutex<Option<u64>>>,
+        runner_commands: broadcast::Sender<RunnerCommand>,
     ) -> Self {
         Self {
             ready_tx: Arc::new(Mutex::new(Some(tx))),
             db,
             expected_service_id,
             registered_service_id,
+            runner_commands,
         }
     }
 
@@ -291,6 +342,36 @@ impl BlueprintManagerBridge for BridgeService {
         }))
     }
 
+    type WatchRunnerCommandsStream = ReceiverStream<Result<RunnerCommand, tonic::Status>>;
+
+    async fn watch_runner_commands(
+        &self,
+        _req: Request<()>,
+    ) -> Result<Response<Self::WatchRunnerCommandsStream>, tonic::Status> {
+        let mut commands = self.runner_commands.subscribe();
+        let (tx, rx) = mpsc::channel(RUNNER_COMMAND_BUFFER);
+
+        tokio::spawn(async move {
+            loop {
+                let command = match commands.recv().await {
+                    Ok(command) => command,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Runner command watcher lagged, dropped {skipped} commands");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                if tx.send(Ok(command)).await.is_err() {
+                    // The runner hung up
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
     async fn register_blueprint_service_proxy(
         &self,
         req: Request<RegisterBlueprintServiceProxyRequest>,
@@ -998,4 +1079,28 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().code(), tonic::Code::FailedPrecondition);
     }
+
+    #[tokio::test]
+    async fn test_runner_commands_reach_watchers() {
+        use tonic::codegen::tokio_stream::StreamExt;
+
+        let tmp_dir = tempdir().unwrap();
+        let db = RocksDb::open(tmp_dir.path(), &RocksDbConfig::default()).unwrap();
+        let service = BridgeService::new(tokio::sync::oneshot::channel().0, db);
+
+        let mut commands = service
+            .watch_runner_commands(tonic::Request::new(()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let drain = RunnerCommand {
+            command: Some(runner_command::Command::Drain(DrainCommand {
+                timeout_secs: 5,
+            })),
+        };
+        service.runner_commands.send(drain.clone()).unwrap();
+
+        assert_eq!(commands.next().await.unwrap().unwrap(), drain);
+    }
 }
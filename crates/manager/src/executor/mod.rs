@@ -377,6 +377,8 @@ async fn build_upgrade_pipeline(
         },
         test_mode: env.test_mode,
         dry_run: env.dry_run,
+        fallback_http_rpc_endpoints: env.fallback_http_rpc_endpoints.clone(),
+        rpc_quorum: env.rpc_quorum,
     };
     // Build a dedicated keystore handle for the upgrade pipeline; we don't
     // share the executor's `Keystore` because the lifetime requirements
@@ -37,6 +37,8 @@ impl TangleProtocolClient {
             },
             test_mode: env.test_mode,
             dry_run: env.dry_run,
+            fallback_http_rpc_endpoints: env.fallback_http_rpc_endpoints.clone(),
+            rpc_quorum: env.rpc_quorum,
         };
 
         let keystore = Keystore::new(KeystoreConfig::new().fs_root(&env.keystore_uri))?;
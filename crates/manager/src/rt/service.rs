@@ -424,6 +424,28 @@ impl Service {
         }))
     }
 
+    /// Ask the service's runner to drain its in-flight jobs and exit
+    ///
+    /// The runner stops accepting new job calls and waits up to `timeout` (or its own configured
+    /// deadline, if `None`) for in-flight jobs to finish. This returns immediately, use
+    /// [`Self::status()`] to follow the service until it exits, and [`Self::shutdown()`] to
+    /// force it down if it does not exit in time.
+    ///
+    /// Returns `false` if the service's runner is not listening on the bridge.
+    pub fn request_drain(&self, timeout: Option<Duration>) -> bool {
+        self.bridge.request_drain(timeout)
+    }
+
+    /// Ask the service's runner to start or stop accepting calls for `job_id`
+    ///
+    /// This lets a misbehaving job be turned off without restarting the service. Calls for a
+    /// disabled job are rejected by the runner without being run.
+    ///
+    /// Returns `false` if the service's runner is not listening on the bridge.
+    pub fn set_job_enabled(&self, job_id: u32, enabled: bool) -> bool {
+        self.bridge.set_job_enabled(job_id, enabled)
+    }
+
     /// Gracefully shutdown the service (VM+bridge)
     ///
     /// # Errors
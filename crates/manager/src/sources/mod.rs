@@ -163,6 +163,8 @@ impl BlueprintArgs {
 pub struct BlueprintEnvVars {
     pub http_rpc_endpoint: Url,
     pub ws_rpc_endpoint: Url,
+    pub fallback_http_rpc_endpoints: Vec<Url>,
+    pub rpc_quorum: Option<usize>,
     #[cfg(feature = "tee")]
     pub kms_endpoint: Url,
     pub keystore_uri: String,
@@ -215,6 +217,8 @@ impl BlueprintEnvVars {
         BlueprintEnvVars {
             http_rpc_endpoint: env.http_rpc_endpoint.clone(),
             ws_rpc_endpoint: env.ws_rpc_endpoint.clone(),
+            fallback_http_rpc_endpoints: env.fallback_http_rpc_endpoints.clone(),
+            rpc_quorum: env.rpc_quorum,
             #[cfg(feature = "tee")]
             kms_endpoint: env.kms_url.clone(),
             keystore_uri: env.keystore_uri.to_string(),
@@ -238,6 +242,8 @@ impl BlueprintEnvVars {
         let BlueprintEnvVars {
             http_rpc_endpoint,
             ws_rpc_endpoint,
+            fallback_http_rpc_endpoints,
+            rpc_quorum,
             #[cfg(feature = "tee")]
             kms_endpoint,
             keystore_uri,
@@ -277,6 +283,17 @@ impl BlueprintEnvVars {
             ("BOOTNODES".to_string(), bootnodes.clone()),
         ];
 
+        if !fallback_http_rpc_endpoints.is_empty() {
+            let urls: Vec<&str> = fallback_http_rpc_endpoints
+                .iter()
+                .map(Url::as_str)
+                .collect();
+            env_vars.push(("FALLBACK_HTTP_RPC_URLS".to_string(), urls.join(",")));
+        }
+        if let Some(quorum) = rpc_quorum {
+            env_vars.push(("RPC_QUORUM".to_string(), quorum.to_string()));
+        }
+
         // Add Tangle contract addresses if present
         if let Some(addr) = tangle_contract {
             env_vars.push(("TANGLE_CONTRACT".to_string(), format!("{addr}")));
@@ -1,18 +1,93 @@
 use blueprint_core::error::BoxError;
-use blueprint_core::{Bytes, JobCall, JobId};
-use chrono::{TimeZone, Utc};
+use blueprint_core::{
+    __composite_rejection as composite_rejection, __define_rejection as define_rejection,
+    __impl_deref as impl_deref, Bytes, FromJobCallParts, JobCall, JobId,
+    job::call::Parts as JobCallParts,
+};
+use blueprint_store_local_database::{BackgroundWriter, LocalDatabase};
+use chrono::{DateTime, FixedOffset, Offset, TimeDelta, TimeZone, Utc};
 use core::pin::Pin;
 use core::task::Poll;
+use core::time::Duration;
+use croner::Cron;
 use futures::Stream;
-use tokio::sync::mpsc::UnboundedReceiver;
-use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::time::Sleep;
+
+/// Error type for [`CronJob`]s
+#[derive(Debug, thiserror::Error)]
+pub enum CronError {
+    /// The cron schedule couldn't be parsed
+    #[error("Invalid cron schedule: {0}")]
+    Schedule(#[from] croner::errors::CronError),
+    /// The [`LastFiredStore`] couldn't be read or written to
+    #[error("Last fired store error: {0}")]
+    Store(#[from] StoreError),
+}
+
+/// What a [`CronJob`] does with the fires it missed
+///
+/// Fires are missed while the operator is down, or when the job's stream isn't polled in time.
+/// Missing fires while the operator is down can only be detected with a [`LastFiredStore`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissedFires {
+    /// Drop every missed fire
+    ///
+    /// NOTE: This is the default
+    #[default]
+    Skip,
+    /// Fire once for a run of missed fires, at the time of the latest one
+    ///
+    /// When the stream wasn't polled in time, the fire that is due covers the missed ones.
+    FireOnce,
+    /// Fire every missed fire, in order
+    ///
+    /// At most [`CronJob::with_max_catch_up()`] missed fires are caught up on. Older ones are
+    /// dropped.
+    FireAll,
+}
+
+/// The default for [`CronJob::with_max_catch_up()`]
+pub const DEFAULT_MAX_CATCH_UP: usize = 1000;
+
+/// The body of the [`JobCall`]s of a [`CronJob`]
+enum Body {
+    Static(Bytes),
+    Template(Arc<dyn Fn(DateTime<Utc>) -> Bytes + Send + Sync>),
+}
+
+impl Body {
+    fn render(&self, scheduled_at: DateTime<Utc>) -> Bytes {
+        match self {
+            Body::Static(body) => body.clone(),
+            Body::Template(template) => template(scheduled_at),
+        }
+    }
+}
 
 /// A producer that generates [`JobCall`]s on a [cron] schedule
 ///
 /// Note that the schedule **must** include seconds, which is *non-standard*.
 ///
-/// This will produce [`JobCall`]s with an empty body, meaning in order for a [`Job`] to receive any
-/// calls, it is *at most* allowed to take a [`Context`].
+/// By default, this will produce [`JobCall`]s with an empty body, meaning in order for a [`Job`] to
+/// receive any calls, it is *at most* allowed to take a [`Context`]. A body can be set with
+/// [`CronJob::with_body()`], or rendered for every fire from its scheduled time with
+/// [`CronJob::with_body_fn()`].
+///
+/// Every [`JobCall`] carries the time it was scheduled for, available through the [`ScheduledAt`]
+/// extractor.
+///
+/// # Missed fires
+///
+/// Fires that were missed, because the operator was down or the stream wasn't polled in time, are
+/// handled according to [`CronJob::with_missed_fires()`]. To know what was missed while the
+/// operator was down, the time of the last fire is kept in a [`LastFiredStore`], set with
+/// [`CronJob::with_last_fired_store()`]. The number of missed fires that are caught up on is
+/// capped by [`CronJob::with_max_catch_up()`].
+///
+/// The stored time is that of the last fire that was either yielded, or skipped on purpose, so
+/// that skipped fires aren't caught up on after a restart.
 ///
 /// # Usage
 ///
@@ -24,11 +99,11 @@ use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
 /// use tokio::time::Instant;
 ///
 /// # #[tokio::main]
-/// # async fn main() -> Result<(), tokio_cron_scheduler::JobSchedulerError> {
+/// # async fn main() -> Result<(), blueprint_producers_extra::cron::CronError> {
 /// const MY_JOB_ID: u8 = 0;
 ///
 /// // I want to run my job every 5 seconds
-/// let mut cron = CronJob::new(MY_JOB_ID, "5 * * * * *").await?;
+/// let mut cron = CronJob::new(MY_JOB_ID, "5 * * * * *")?;
 ///
 /// // Verify that we're producing something
 /// assert!(cron.next().await.is_some());
@@ -42,54 +117,342 @@ use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
 /// # Ok(()) }
 /// ```
 ///
+/// Catching up on every hour missed while the operator was down:
+///
+/// ```rust,ignore
+/// use blueprint_producers_extra::cron::{CronJob, MissedFires, ScheduledAt};
+/// use blueprint_store_local_database::LocalDatabase;
+///
+/// let store = LocalDatabase::<i64>::open_strict(env.data_dir.join("cron.json"))?;
+/// let cron = CronJob::new(HOURLY_REPORT_JOB, "0 0 * * * *")?
+///     .with_body_fn(|scheduled_at| scheduled_at.timestamp().to_be_bytes().to_vec().into())
+///     .with_missed_fires(MissedFires::FireAll)
+///     .with_last_fired_store(store);
+///
+/// async fn hourly_report(ScheduledAt(hour): ScheduledAt) { /* ... */ }
+/// ```
+///
 /// [`Context`]: https://docs.rs/blueprint_sdk/latest/blueprint_sdk/extract/struct.Context.html
+/// [`Job`]: blueprint_core::Job
 /// [cron]: https://en.wikipedia.org/wiki/Cron
 pub struct CronJob {
     job_id: JobId,
-    _scheduler: JobScheduler,
-    rx: UnboundedReceiver<()>,
+    /// The first fire strictly after the given time
+    next_after: Box<dyn Fn(DateTime<Utc>) -> Option<DateTime<Utc>> + Send + Sync>,
+    body: Body,
+    missed_fires: MissedFires,
+    max_catch_up: usize,
+    store: Option<Arc<dyn LastFiredStore>>,
+    /// Commits the last fire to `store` off the polling task, spawned on the first poll
+    writer: Option<BackgroundWriter<DateTime<Utc>>>,
+    /// The scheduled time of the last fire that was handled, `None` until the first poll
+    cursor: Option<DateTime<Utc>>,
+    /// Fires waiting to be yielded
+    due: VecDeque<DateTime<Utc>>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl core::fmt::Debug for CronJob {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CronJob")
+            .field("job_id", &self.job_id)
+            .field("missed_fires", &self.missed_fires)
+            .field("cursor", &self.cursor)
+            .field("due", &self.due)
+            .finish_non_exhaustive()
+    }
 }
 
 impl CronJob {
     /// Create a new [`CronJob`], using the [`Utc`] timezone
-    pub async fn new<I, S>(job_id: I, schedule: S) -> Result<Self, JobSchedulerError>
+    ///
+    /// # Errors
+    ///
+    /// If `schedule` can't be parsed.
+    pub fn new<I, S>(job_id: I, schedule: S) -> Result<Self, CronError>
     where
         I: Into<JobId>,
         S: AsRef<str>,
     {
-        Self::new_tz(job_id, schedule, Utc).await
+        Self::new_tz(job_id, schedule, Utc)
     }
 
     /// Create a new [`CronJob`] with the specified `timezone`
-    pub async fn new_tz<I, S, Tz>(
-        job_id: I,
-        schedule: S,
-        timezone: Tz,
-    ) -> Result<Self, JobSchedulerError>
+    ///
+    /// The current offset of `timezone` is used for the whole life of the job.
+    ///
+    /// # Errors
+    ///
+    /// If `schedule` can't be parsed.
+    pub fn new_tz<I, S, Tz>(job_id: I, schedule: S, timezone: Tz) -> Result<Self, CronError>
     where
         I: Into<JobId>,
         S: AsRef<str>,
         Tz: TimeZone,
     {
-        let scheduler = JobScheduler::new().await?;
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let cron = Cron::new(schedule.as_ref())
+            .with_seconds_required()
+            .parse()?;
 
-        scheduler
-            .add(Job::new_tz(schedule.as_ref(), timezone, move |_, _| {
-                if let Err(e) = tx.send(()) {
-                    blueprint_core::error!("Failed to send cron job message: {e}");
-                }
-            })?)
-            .await?;
-
-        scheduler.start().await?;
+        let offset: FixedOffset = timezone
+            .offset_from_utc_datetime(&Utc::now().naive_utc())
+            .fix();
+        let next_after = move |after: DateTime<Utc>| {
+            cron.find_next_occurrence(&after.with_timezone(&offset), false)
+                .ok()
+                .map(|next| next.with_timezone(&Utc))
+        };
 
         Ok(Self {
             job_id: job_id.into(),
-            _scheduler: scheduler,
-            rx,
+            next_after: Box::new(next_after),
+            body: Body::Static(Bytes::new()),
+            missed_fires: MissedFires::default(),
+            max_catch_up: DEFAULT_MAX_CATCH_UP,
+            store: None,
+            writer: None,
+            cursor: None,
+            due: VecDeque::new(),
+            sleep: None,
         })
     }
+
+    /// Use `body` as the body of every [`JobCall`]
+    pub fn with_body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = Body::Static(body.into());
+        self
+    }
+
+    /// Render the body of every [`JobCall`] from the time it was scheduled for
+    pub fn with_body_fn<F>(mut self, template: F) -> Self
+    where
+        F: Fn(DateTime<Utc>) -> Bytes + Send + Sync + 'static,
+    {
+        self.body = Body::Template(Arc::new(template));
+        self
+    }
+
+    /// Set what to do with missed fires
+    ///
+    /// See [`MissedFires`].
+    pub fn with_missed_fires(mut self, policy: MissedFires) -> Self {
+        self.missed_fires = policy;
+        self
+    }
+
+    /// Catch up on at most `max` missed fires with [`MissedFires::FireAll`]
+    ///
+    /// The latest `max` missed fires are yielded, and any older ones are dropped with a warning.
+    /// Defaults to [`DEFAULT_MAX_CATCH_UP`].
+    ///
+    /// # Panics
+    ///
+    /// If `max` is zero.
+    pub fn with_max_catch_up(mut self, max: usize) -> Self {
+        assert!(max > 0, "the catch-up limit must be non-zero");
+        self.max_catch_up = max;
+        self
+    }
+
+    /// Keep the time of the last fire in `store`, to catch up on the fires missed while the
+    /// operator was down
+    ///
+    /// A single store can be shared by several jobs, as long as their job IDs differ.
+    pub fn with_last_fired_store(mut self, store: impl LastFiredStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Every fire in `(after, until]`, keeping only as many of the latest as the policy needs
+    ///
+    /// When the range holds far more fires than are kept, such as after a long outage, only its
+    /// end is walked, and the older fires are counted as if they were as frequent.
+    fn fires_between(&self, after: DateTime<Utc>, until: DateTime<Utc>) -> Fires {
+        // Other policies only need the latest fire, and the one before it
+        let limit = match self.missed_fires {
+            MissedFires::FireAll => self.max_catch_up,
+            MissedFires::Skip | MissedFires::FireOnce => 2,
+        };
+
+        let Some(first) = (self.next_after)(after).filter(|first| *first <= until) else {
+            return Fires::default();
+        };
+
+        // Start with a window holding about twice the fires to keep, at the pace of the first
+        // ones, and double it until it holds enough
+        let pace =
+            (self.next_after)(first).map_or(1, |second| (second - first).num_seconds().max(1));
+        let range = (until - after).num_seconds();
+        let mut window =
+            pace.saturating_mul(i64::try_from(limit).unwrap_or(i64::MAX).saturating_mul(2));
+        while window < range {
+            let start = until - TimeDelta::seconds(window);
+            if first > start {
+                break;
+            }
+
+            let mut fires = self.walk_fires(start, until, limit);
+            if fires.kept.len() == limit {
+                let walked = i128::try_from(fires.len()).unwrap_or(i128::MAX);
+                let skipped =
+                    i128::from((start - after).num_seconds()) * walked / i128::from(window);
+                fires.dropped = fires
+                    .dropped
+                    .saturating_add(usize::try_from(skipped).unwrap_or(usize::MAX).max(1));
+                fires.first_dropped = Some(first);
+                return fires;
+            }
+            window = window.saturating_mul(2);
+        }
+
+        self.walk_fires(after, until, limit)
+    }
+
+    /// Every fire in `(after, until]`, found one by one, keeping the latest `limit` of them
+    fn walk_fires(&self, after: DateTime<Utc>, until: DateTime<Utc>, limit: usize) -> Fires {
+        let mut fires = Fires::default();
+        let mut kept = VecDeque::new();
+        let mut cursor = after;
+        while let Some(next) = (self.next_after)(cursor) {
+            if next > until {
+                break;
+            }
+            if kept.len() == limit
+                && let Some(oldest) = kept.pop_front()
+            {
+                fires.dropped += 1;
+                fires.first_dropped.get_or_insert(oldest);
+            }
+            kept.push_back(next);
+            cursor = next;
+        }
+        fires.kept = kept.into();
+        fires
+    }
+
+    /// Log the fires that were dropped for exceeding the catch-up limit
+    fn log_dropped(&self, fires: &Fires) {
+        if self.missed_fires != MissedFires::FireAll {
+            return;
+        }
+        if let (Some(first), Some(&caught_up_from)) = (fires.first_dropped, fires.kept.first()) {
+            blueprint_core::warn!(
+                target: "cron-producer",
+                job_id = %self.job_id,
+                dropped = fires.dropped,
+                max_catch_up = self.max_catch_up,
+                %first,
+                %caught_up_from,
+                "Dropped missed fires beyond the catch-up limit"
+            );
+        }
+    }
+
+    /// Start from the last fire in the store, queueing the fires missed since
+    fn resume(&mut self, now: DateTime<Utc>) -> Result<(), StoreError> {
+        let last_fired = match &self.store {
+            Some(store) => store.last_fired(self.job_id)?,
+            None => None,
+        };
+
+        let Some(last_fired) = last_fired else {
+            self.cursor = Some(now);
+            return Ok(());
+        };
+
+        let missed = self.fires_between(last_fired, now);
+        if !missed.kept.is_empty() {
+            blueprint_core::info!(
+                target: "cron-producer",
+                job_id = %self.job_id,
+                missed = missed.len(),
+                policy = ?self.missed_fires,
+                %last_fired,
+                "Fires were missed while down"
+            );
+        }
+        self.log_dropped(&missed);
+        let (due, skipped) = select_fires(missed.kept, self.missed_fires, false);
+        if let Some(skipped) = skipped {
+            self.commit(skipped);
+        }
+        self.due.extend(due);
+        self.cursor = Some(now);
+        Ok(())
+    }
+
+    /// Persist `fired` as the last fire in the background, if there's a [`LastFiredStore`]
+    ///
+    /// Failures are only logged. The job then catches up from an older fire after a restart.
+    fn commit(&mut self, fired: DateTime<Utc>) {
+        let Some(store) = &self.store else {
+            return;
+        };
+
+        let writer = self.writer.get_or_insert_with(|| {
+            let store = store.clone();
+            let job_id = self.job_id;
+            BackgroundWriter::spawn(move |fired| {
+                if let Err(e) = store.commit(job_id, fired) {
+                    blueprint_core::warn!(
+                        target: "cron-producer",
+                        job_id = %job_id,
+                        %fired,
+                        "Failed to commit last fire: {e}"
+                    );
+                }
+            })
+        });
+        writer.write(fired);
+    }
+
+    fn job_call(&self, scheduled_at: DateTime<Utc>) -> JobCall {
+        let mut call = JobCall::new(self.job_id, self.body.render(scheduled_at));
+        call.metadata_mut()
+            .insert(ScheduledAt::METADATA_KEY, scheduled_at.timestamp());
+        call
+    }
+}
+
+/// The fires in a time range, see [`CronJob::fires_between()`]
+#[derive(Debug, Default)]
+struct Fires {
+    /// The latest fires, oldest first
+    kept: Vec<DateTime<Utc>>,
+    /// How many older fires were dropped, estimated when the range was too long to walk
+    dropped: usize,
+    first_dropped: Option<DateTime<Utc>>,
+}
+
+impl Fires {
+    /// Every fire in the range, including the dropped ones
+    fn len(&self) -> usize {
+        self.kept.len().saturating_add(self.dropped)
+    }
+}
+
+/// Pick the fires to yield out of `fires`, the fires due since the last poll, along with the
+/// latest one that was skipped
+///
+/// With `live`, the last of `fires` is the one the job was waiting for, and only the others were
+/// missed. Otherwise, they were all missed while the operator was down.
+fn select_fires(
+    mut fires: Vec<DateTime<Utc>>,
+    policy: MissedFires,
+    live: bool,
+) -> (Vec<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    let Some(&latest) = fires.last() else {
+        return (fires, None);
+    };
+
+    match (policy, live) {
+        (MissedFires::FireAll, _) => (fires, None),
+        (MissedFires::FireOnce, _) | (MissedFires::Skip, true) => {
+            fires.truncate(fires.len() - 1);
+            (vec![latest], fires.last().copied())
+        }
+        (MissedFires::Skip, false) => (Vec::new(), Some(latest)),
+    }
 }
 
 impl Stream for CronJob {
@@ -99,17 +462,169 @@ impl Stream for CronJob {
         mut self: Pin<&mut Self>,
         cx: &mut core::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        match self.as_mut().rx.poll_recv(cx) {
-            Poll::Ready(Some(())) => Poll::Ready(Some(Ok(JobCall::new(self.job_id, Bytes::new())))),
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => Poll::Pending,
+        let this = &mut *self;
+
+        if this.cursor.is_none()
+            && let Err(e) = this.resume(Utc::now())
+        {
+            return Poll::Ready(Some(Err(CronError::from(e).into())));
+        }
+
+        loop {
+            if let Some(scheduled_at) = this.due.pop_front() {
+                this.commit(scheduled_at);
+                return Poll::Ready(Some(Ok(this.job_call(scheduled_at))));
+            }
+
+            let cursor = this.cursor.unwrap_or_else(Utc::now);
+            if this.sleep.is_none() {
+                let Some(next) = (this.next_after)(cursor) else {
+                    // The schedule has no fires left
+                    return Poll::Ready(None);
+                };
+                let delay = (next - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+                this.sleep = Some(Box::pin(tokio::time::sleep(delay)));
+            }
+
+            if let Some(sleep) = &mut this.sleep
+                && sleep.as_mut().poll(cx).is_pending()
+            {
+                return Poll::Pending;
+            }
+            this.sleep = None;
+
+            // The timer may fire slightly before the system clock reaches the scheduled time, in
+            // which case nothing is due yet, and the same fire is waited for again
+            let now = Utc::now();
+            let fires = this.fires_between(cursor, now);
+            let Some(&latest) = fires.kept.last() else {
+                continue;
+            };
+            if fires.len() > 1 {
+                blueprint_core::warn!(
+                    target: "cron-producer",
+                    job_id = %this.job_id,
+                    missed = fires.len() - 1,
+                    policy = ?this.missed_fires,
+                    "Fires were missed, the job isn't polled often enough"
+                );
+            }
+
+            this.log_dropped(&fires);
+            let (due, skipped) = select_fires(fires.kept, this.missed_fires, true);
+            if let Some(skipped) = skipped {
+                this.commit(skipped);
+            }
+            this.due.extend(due);
+            this.cursor = Some(latest);
         }
     }
 }
 
+/// Extracts the time a [`CronJob`] call was scheduled for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledAt(pub DateTime<Utc>);
+
+impl ScheduledAt {
+    /// Metadata key for the scheduled time, in seconds since the Unix epoch
+    pub const METADATA_KEY: &'static str = "X-CRON-SCHEDULED-AT";
+}
+
+impl_deref!(ScheduledAt: DateTime<Utc>);
+
+define_rejection! {
+    #[body = "No scheduled time found in metadata"]
+    /// This rejection is used to indicate that the job call wasn't produced by a [`CronJob`].
+    pub struct MissingScheduledAt;
+}
+
+define_rejection! {
+    #[body = "Scheduled time must be a valid timestamp"]
+    /// This rejection is used to indicate that the scheduled time is not a valid timestamp.
+    pub struct InvalidScheduledAt;
+}
+
+composite_rejection! {
+    /// Rejection for the [`ScheduledAt`] extractor
+    pub enum ScheduledAtRejection {
+        MissingScheduledAt,
+        InvalidScheduledAt,
+    }
+}
+
+impl TryFrom<&mut JobCallParts> for ScheduledAt {
+    type Error = ScheduledAtRejection;
+
+    fn try_from(parts: &mut JobCallParts) -> Result<Self, Self::Error> {
+        let timestamp: i64 = parts
+            .metadata
+            .get(Self::METADATA_KEY)
+            .ok_or(MissingScheduledAt)?
+            .try_into()
+            .map_err(|_| InvalidScheduledAt)?;
+        let scheduled_at = DateTime::from_timestamp(timestamp, 0).ok_or(InvalidScheduledAt)?;
+        Ok(ScheduledAt(scheduled_at))
+    }
+}
+
+impl<Ctx> FromJobCallParts<Ctx> for ScheduledAt
+where
+    Ctx: Send + Sync,
+{
+    type Rejection = ScheduledAtRejection;
+
+    async fn from_job_call_parts(
+        parts: &mut JobCallParts,
+        _: &Ctx,
+    ) -> Result<Self, Self::Rejection> {
+        Self::try_from(parts)
+    }
+}
+
+/// Error type for [`LastFiredStore`]s
+pub use blueprint_store_local_database::StoreError;
+
+/// Persistent storage for the time of the last fire of [`CronJob`]s
+///
+/// Fires are keyed by job ID, so a single store can be shared by several jobs.
+///
+/// The last fires can be kept in a [`LocalDatabase`], in seconds since the Unix epoch. Open it with
+/// [`LocalDatabase::open_strict()`], so that a corrupted file isn't mistaken for jobs that never
+/// fired.
+pub trait LastFiredStore: Send + Sync {
+    /// Load the scheduled time of the last fire of `job_id`
+    ///
+    /// # Errors
+    ///
+    /// If the store cannot be read.
+    fn last_fired(&self, job_id: JobId) -> Result<Option<DateTime<Utc>>, StoreError>;
+
+    /// Persist `fired` as the scheduled time of the last fire of `job_id`
+    ///
+    /// # Errors
+    ///
+    /// If the store cannot be written to.
+    fn commit(&self, job_id: JobId, fired: DateTime<Utc>) -> Result<(), StoreError>;
+}
+
+impl LastFiredStore for LocalDatabase<i64> {
+    fn last_fired(&self, job_id: JobId) -> Result<Option<DateTime<Utc>>, StoreError> {
+        let Some(timestamp) = self.get(&job_id.to_string())? else {
+            return Ok(None);
+        };
+        DateTime::from_timestamp(timestamp, 0)
+            .map(Some)
+            .ok_or_else(|| StoreError::Other(format!("Invalid timestamp {timestamp}")))
+    }
+
+    fn commit(&self, job_id: JobId, fired: DateTime<Utc>) -> Result<(), StoreError> {
+        Ok(self.set(&job_id.to_string(), fired.timestamp())?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::cron::CronJob;
+    use super::*;
     use blueprint_core::JobId;
     use futures::StreamExt;
     use std::time::Duration;
@@ -117,7 +632,7 @@ mod tests {
 
     #[tokio::test]
     async fn it_works() {
-        let mut cron = CronJob::new(0, "* * * * * *").await.unwrap();
+        let mut cron = CronJob::new(0, "* * * * * *").unwrap();
 
         // Should continuously return
         for _ in 0..10 {
@@ -132,4 +647,168 @@ mod tests {
             assert_eq!(j.unwrap().job_id(), JobId::from(0));
         }
     }
+
+    /// Wait for the background writer to commit a last fire of `job_id` matching `done`
+    async fn wait_for_commit(
+        path: &std::path::Path,
+        job_id: u8,
+        done: impl Fn(DateTime<Utc>) -> bool,
+    ) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let store = LocalDatabase::<i64>::open_strict(path).unwrap();
+            if store
+                .last_fired(JobId::from(job_id))
+                .unwrap()
+                .is_some_and(&done)
+            {
+                return;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "the last fire was never committed"
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn missed_fire_policies() {
+        let fires = vec![at(1), at(2), at(3)];
+
+        // On boot, every fire was missed
+        assert_eq!(
+            select_fires(fires.clone(), MissedFires::Skip, false),
+            (vec![], Some(at(3)))
+        );
+        assert_eq!(
+            select_fires(fires.clone(), MissedFires::FireOnce, false),
+            (vec![at(3)], Some(at(2)))
+        );
+        assert_eq!(
+            select_fires(fires.clone(), MissedFires::FireAll, false),
+            (fires.clone(), None)
+        );
+
+        // While running, the latest fire is always yielded
+        assert_eq!(
+            select_fires(fires.clone(), MissedFires::Skip, true),
+            (vec![at(3)], Some(at(2)))
+        );
+        assert_eq!(
+            select_fires(fires.clone(), MissedFires::FireAll, true),
+            (fires, None)
+        );
+        assert_eq!(
+            select_fires(vec![at(1)], MissedFires::Skip, true),
+            (vec![at(1)], None)
+        );
+    }
+
+    #[tokio::test]
+    async fn catches_up_on_fires_missed_while_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cron.json");
+        let now = Utc::now();
+
+        let store = LocalDatabase::<i64>::open_strict(&path).unwrap();
+        store
+            .commit(JobId::from(7), now - chrono::Duration::hours(3))
+            .unwrap();
+
+        let mut cron = CronJob::new(7, "0 0 * * * *")
+            .unwrap()
+            .with_body_fn(|scheduled_at| {
+                Bytes::from(scheduled_at.timestamp().to_be_bytes().to_vec())
+            })
+            .with_missed_fires(MissedFires::FireAll)
+            .with_last_fired_store(store);
+
+        let mut fired = Vec::new();
+        for _ in 0..3 {
+            let call = cron.next().await.unwrap().unwrap();
+            let (mut parts, body) = call.into_parts();
+            let ScheduledAt(scheduled_at) = ScheduledAt::try_from(&mut parts).unwrap();
+            assert_eq!(
+                body,
+                Bytes::from(scheduled_at.timestamp().to_be_bytes().to_vec())
+            );
+            fired.push(scheduled_at);
+        }
+
+        assert!(fired.is_sorted());
+        assert!(fired.iter().all(|fire| *fire <= now));
+        assert_eq!(fired[2] - fired[0], chrono::Duration::hours(2));
+
+        // The last fire was persisted
+        wait_for_commit(&path, 7, |last_fired| last_fired == fired[2]).await;
+    }
+
+    #[tokio::test]
+    async fn catch_up_is_capped() {
+        let cron = CronJob::new(0, "0 0 * * * *")
+            .unwrap()
+            .with_missed_fires(MissedFires::FireAll)
+            .with_max_catch_up(2);
+
+        let fires = cron.fires_between(at(0), at(5));
+        assert_eq!(fires.kept, [at(4), at(5)]);
+        assert_eq!(fires.len(), 5);
+        assert_eq!(fires.first_dropped, Some(at(1)));
+
+        // Other policies only keep what they need, whatever the limit
+        let cron = cron.with_missed_fires(MissedFires::FireOnce);
+        let fires = cron.fires_between(at(0), at(5));
+        assert_eq!(fires.kept, [at(4), at(5)]);
+        assert_eq!(fires.len(), 5);
+    }
+
+    #[test]
+    fn long_outages_are_not_walked_fire_by_fire() {
+        let cron = CronJob::new(0, "* * * * * *")
+            .unwrap()
+            .with_missed_fires(MissedFires::FireAll)
+            .with_max_catch_up(3);
+
+        // A month of fires every second
+        let until = at(0) + chrono::Duration::days(30);
+        let before = Instant::now();
+        let fires = cron.fires_between(at(0), until);
+        assert!(before.elapsed() < Duration::from_secs(1));
+
+        let second = chrono::Duration::seconds(1);
+        assert_eq!(fires.kept, [until - second * 2, until - second, until]);
+        assert_eq!(fires.len(), 30 * 24 * 60 * 60);
+        assert_eq!(fires.first_dropped, Some(at(0) + second));
+    }
+
+    #[tokio::test]
+    async fn skipped_fires_are_not_caught_up_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cron.json");
+        let now = Utc::now();
+
+        let store = LocalDatabase::<i64>::open_strict(&path).unwrap();
+        store
+            .commit(JobId::from(1), now - chrono::Duration::hours(3))
+            .unwrap();
+
+        let mut cron = CronJob::new(1, "0 0 * * * *")
+            .unwrap()
+            .with_last_fired_store(store);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), cron.next())
+                .await
+                .is_err()
+        );
+
+        wait_for_commit(&path, 1, |last_fired| {
+            now - last_fired < chrono::Duration::hours(1)
+        })
+        .await;
+    }
 }
@@ -29,6 +29,7 @@ pub struct PrometheusServer {
     registry: Arc<Registry>,
     enhanced_metrics_provider: Arc<EnhancedMetricsProvider>,
     bind_address: String,
+    routes: Option<Router>,
     shutdown_tx: Option<oneshot::Sender<()>>,
 }
 
@@ -44,10 +45,18 @@ impl PrometheusServer {
             registry,
             enhanced_metrics_provider,
             bind_address,
+            routes: None,
             shutdown_tx: None,
         }
     }
 
+    /// Serve `routes` alongside the metrics endpoints
+    #[must_use]
+    pub fn with_routes(mut self, routes: Router) -> Self {
+        self.routes = Some(routes);
+        self
+    }
+
     /// Start the Prometheus metrics server
     ///
     /// # Errors
@@ -76,6 +85,10 @@ impl PrometheusServer {
             .route("/api/v1/series", get(api_v1_series_handler))
             .route("/api/v1/query_range", get(api_v1_query_range_handler))
             .with_state(state);
+        let app = match self.routes.clone() {
+            Some(routes) => app.merge(routes),
+            None => app,
+        };
 
         let (tx, rx) = oneshot::channel();
         self.shutdown_tx = Some(tx);
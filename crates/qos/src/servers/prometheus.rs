@@ -70,7 +70,7 @@ pub struct PrometheusServer {
     config: PrometheusServerConfig,
 
     /// The Docker manager for the Prometheus server (if using Docker)
-    docker_manager: DockerManager,
+    docker_manager: Option<DockerManager>,
 
     /// The container ID for the Prometheus server (if using Docker)
     container_id: Arc<Mutex<Option<String>>>,
@@ -83,6 +83,9 @@ pub struct PrometheusServer {
 
     /// The enhanced metrics provider, used to force flush OTEL metrics on scrape
     enhanced_metrics_provider: Arc<EnhancedMetricsProvider>,
+
+    /// Extra routes served by the embedded server, see [`Self::mount`]
+    routes: Arc<Mutex<Option<axum::Router>>>,
 }
 
 impl PrometheusServer {
@@ -97,20 +100,69 @@ impl PrometheusServer {
     /// * `enhanced_metrics_provider` - Provider that generates metrics data
     ///
     /// # Errors
-    /// Returns an error if the Docker manager connection fails to initialize
+    /// Returns an error if the Docker manager connection fails to initialize. Docker is only
+    /// connected to in Docker mode.
     pub fn new(
         config: PrometheusServerConfig,
         metrics_registry: Option<Arc<prometheus::Registry>>,
         enhanced_metrics_provider: Arc<EnhancedMetricsProvider>,
     ) -> Result<Self> {
+        let docker_manager = if config.use_docker {
+            Some(
+                DockerManager::new()
+                    .map_err(|e| crate::error::Error::DockerConnection(e.to_string()))?,
+            )
+        } else {
+            None
+        };
+
         Ok(Self {
             config,
-            docker_manager: DockerManager::new()
-                .map_err(|e| crate::error::Error::DockerConnection(e.to_string()))?,
+            docker_manager,
             container_id: Arc::new(Mutex::new(None)),
             embedded_server: Arc::new(Mutex::new(None)),
             metrics_registry,
             enhanced_metrics_provider,
+            routes: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Serve `routes` from the embedded server, alongside `/metrics`
+    ///
+    /// This lets other components, such as the runner's health endpoints, share the metrics
+    /// server instead of binding their own. Routes mounted more than once are merged.
+    ///
+    /// # Errors
+    /// Returns an error if the server runs in Docker mode, or if the embedded server was already
+    /// started, since it can't pick up new routes.
+    ///
+    /// # Panics
+    /// Panics if mutex locks cannot be acquired
+    pub fn mount(&self, routes: axum::Router) -> Result<()> {
+        if self.config.use_docker {
+            return Err(crate::error::Error::Other(
+                "Routes can only be mounted on an embedded Prometheus server".to_string(),
+            ));
+        }
+        if self.embedded_server.lock().unwrap().is_some() {
+            return Err(crate::error::Error::Other(
+                "Routes must be mounted before the Prometheus server starts".to_string(),
+            ));
+        }
+
+        let mut mounted = self.routes.lock().unwrap();
+        *mounted = Some(match mounted.take() {
+            Some(existing) => existing.merge(routes),
+            None => routes,
+        });
+        Ok(())
+    }
+
+    fn docker_manager(&self) -> Result<&DockerManager> {
+        self.docker_manager.as_ref().ok_or_else(|| {
+            crate::error::Error::Other(
+                "Docker is not used by an embedded Prometheus server".to_string(),
+            )
         })
     }
 
@@ -146,7 +198,7 @@ impl PrometheusServer {
         }
 
         let new_container_id_result = self
-            .docker_manager
+            .docker_manager()?
             .run_container(
                 &self.config.docker_image,
                 &self.config.docker_container_name,
@@ -285,7 +337,7 @@ impl ServerManager for PrometheusServer {
                     existing_id
                 );
                 let is_running = self
-                    .docker_manager
+                    .docker_manager()?
                     .is_container_running(&existing_id)
                     .await?;
                 if is_running {
@@ -301,7 +353,7 @@ impl ServerManager for PrometheusServer {
                         existing_id
                     );
                     if let Err(e) = self
-                        .docker_manager
+                        .docker_manager()?
                         .stop_and_remove_container(&existing_id, &self.config.docker_container_name)
                         .await
                     {
@@ -337,7 +389,7 @@ impl ServerManager for PrometheusServer {
                 let extra_hosts = vec!["host.docker.internal:host-gateway".to_string()];
 
                 let new_id_result = self
-                    .docker_manager
+                    .docker_manager()?
                     .run_container(
                         &self.config.docker_image,
                         &self.config.docker_container_name,
@@ -393,7 +445,7 @@ impl ServerManager for PrometheusServer {
                     final_id_for_connection_and_health_check,
                     net
                 );
-                self.docker_manager
+                self.docker_manager()?
                     .connect_to_network(&final_id_for_connection_and_health_check, net)
                     .await?;
             }
@@ -404,7 +456,7 @@ impl ServerManager for PrometheusServer {
                     &self.config.docker_container_name, final_id_for_connection_and_health_check
                 );
                 if self
-                    .docker_manager
+                    .docker_manager()?
                     .wait_for_container_health(
                         &final_id_for_connection_and_health_check,
                         PROMETHEUS_DOCKER_HEALTH_TIMEOUT_SECS,
@@ -495,6 +547,9 @@ impl ServerManager for PrometheusServer {
                 self.enhanced_metrics_provider.clone(),
                 bind_address_for_new_server.clone(),
             );
+            if let Some(routes) = self.routes.lock().unwrap().clone() {
+                server_instance = server_instance.with_routes(routes);
+            }
 
             server_instance.start().await?;
 
@@ -556,7 +611,7 @@ impl ServerManager for PrometheusServer {
                 "Stopping Prometheus server in Docker container: {}",
                 &self.config.docker_container_name
             );
-            self.docker_manager
+            self.docker_manager()?
                 .stop_and_remove_container(&container_id, &self.config.docker_container_name)
                 .await?;
 
@@ -620,7 +675,7 @@ impl ServerManager for PrometheusServer {
             };
 
             return self
-                .docker_manager
+                .docker_manager()?
                 .is_container_running(&container_id)
                 .await;
         }
@@ -652,7 +707,7 @@ impl ServerManager for PrometheusServer {
 
             info!("Waiting for Prometheus container to be healthy...");
             if let Err(e) = self
-                .docker_manager
+                .docker_manager()?
                 .wait_for_container_health(&container_id, timeout_secs)
                 .await
             {
@@ -97,6 +97,25 @@ where
             .push(Handler::Boxed(BoxedIntoRoute::from_job(job)));
     }
 
+    #[cfg(feature = "tokio")]
+    #[track_caller]
+    pub(super) fn route_policy<I>(&mut self, job_id: I, policy: crate::policy::RoutePolicy)
+    where
+        I: Into<JobId>,
+    {
+        let job_id = job_id.into();
+        let Some(id) = self.job_to_route_id.get(&job_id) else {
+            panic!("Invalid route policy: no route exists for job {job_id}");
+        };
+
+        let handler = self
+            .routes
+            .remove(id)
+            .expect("no route for id. This is a bug in blueprint-sdk. Please file an issue");
+        let layer = crate::policy::PolicyLayer::new(job_id, policy);
+        self.routes.insert(*id, handler.layer(layer));
+    }
+
     pub(super) fn layer<L>(self, layer: L) -> JobIdRouter<Ctx>
     where
         L: Layer<Route> + Clone + Send + Sync + 'static,
@@ -135,6 +154,10 @@ where
         !self.routes.is_empty()
     }
 
+    pub(super) fn job_ids(&self) -> impl Iterator<Item = JobId> + '_ {
+        self.job_to_route_id.keys().copied()
+    }
+
     pub(super) fn with_context<Ctx2>(self, context: Ctx) -> JobIdRouter<Ctx2> {
         let routes = self
             .routes
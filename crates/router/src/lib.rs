@@ -49,6 +49,8 @@ pub mod __private {
 mod boxed;
 pub mod future;
 mod job_id_router;
+#[cfg(feature = "tokio")]
+pub mod policy;
 mod routing;
 mod util;
 
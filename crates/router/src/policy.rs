@@ -0,0 +1,283 @@
+//! Per-route timeout and retry policies
+//!
+//! A [`RoutePolicy`] is attached to a single route with [`Router::route_policy()`], and applies to
+//! every call of that route:
+//!
+//! * A timeout, after which the job is cancelled and produces a [`JobResult::Err`] whose
+//!   [`kind`](blueprint_core::Error::kind) is [`ErrorKind::TimedOut`]
+//! * A [`RetryPolicy`], which re-runs the job with exponential backoff when it produces a
+//!   retryable [`JobResult::Err`]
+//!
+//! # Examples
+//!
+//! ```rust
+//! use blueprint_sdk::Router;
+//! use blueprint_sdk::router::policy::{RetryPolicy, RoutePolicy};
+//! use std::time::Duration;
+//!
+//! const SLOW_JOB: u32 = 3;
+//!
+//! async fn slow_job() -> Result<String, std::io::Error> {
+//!     Ok(String::from("done"))
+//! }
+//!
+//! let router = Router::new().route(SLOW_JOB, slow_job).route_policy(
+//!     SLOW_JOB,
+//!     RoutePolicy::new()
+//!         // Each attempt must finish within 30 seconds...
+//!         .timeout(Duration::from_secs(30))
+//!         // ...and only timeouts are worth retrying, at most twice
+//!         .retry(RetryPolicy::new(2).retry_if(|e| e.is_timeout())),
+//! );
+//! # let _: Router = router;
+//! ```
+//!
+//! [`Router::route_policy()`]: crate::Router::route_policy
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use blueprint_core::error::ErrorKind;
+use blueprint_core::{Error, JobCall, JobId, JobResult};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use tower::{BoxError, Layer, Service, ServiceExt};
+
+/// Timeout and retry behavior for a single route
+///
+/// See the [module docs](self) for details.
+#[must_use]
+#[derive(Debug, Clone, Default)]
+pub struct RoutePolicy {
+    timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+}
+
+impl RoutePolicy {
+    /// Create a new, empty `RoutePolicy`
+    ///
+    /// By default, there is no timeout and jobs are never retried.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel each attempt of the job after `timeout`
+    ///
+    /// With a [`RetryPolicy`], every retry gets the full `timeout` again.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retry the job when it fails
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+}
+
+/// How a route retries failed jobs
+///
+/// Only jobs that produce a [`JobResult::Err`] (including timeouts) are retried. Panics and errors
+/// from the route's [`Service`] itself are passed through untouched.
+#[must_use]
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    retry_if: Arc<dyn Fn(&Error) -> bool + Send + Sync>,
+}
+
+impl RetryPolicy {
+    /// Retry a failed job up to `max_retries` times
+    ///
+    /// By default, every error is retried, starting with a 100ms backoff that doubles on each
+    /// attempt up to 30 seconds.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            retry_if: Arc::new(|_| true),
+        }
+    }
+
+    /// The delay before the first retry
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// The maximum delay between retries
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Only retry errors for which `f` returns `true`
+    ///
+    /// Timeouts can be identified with [`Error::is_timeout()`].
+    pub fn retry_if<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Error) -> bool + Send + Sync + 'static,
+    {
+        self.retry_if = Arc::new(f);
+        self
+    }
+
+    fn should_retry(&self, retries: u32, error: &Error) -> bool {
+        retries < self.max_retries && (self.retry_if)(error)
+    }
+
+    fn backoff(&self, retries: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retries))
+            .min(self.max_backoff)
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_retries", &self.max_retries)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The error inside a [`JobResult::Err`] produced by a job that exceeded its timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut {
+    job_id: JobId,
+    timeout: Duration,
+}
+
+impl TimedOut {
+    /// The job that timed out
+    pub fn job_id(&self) -> JobId {
+        self.job_id
+    }
+
+    /// The timeout that was exceeded
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "job {} timed out after {:?}", self.job_id, self.timeout)
+    }
+}
+
+impl core::error::Error for TimedOut {}
+
+#[derive(Clone)]
+pub(crate) struct PolicyLayer {
+    job_id: JobId,
+    policy: Arc<RoutePolicy>,
+}
+
+impl PolicyLayer {
+    pub(crate) fn new(job_id: JobId, policy: RoutePolicy) -> Self {
+        Self {
+            job_id,
+            policy: Arc::new(policy),
+        }
+    }
+}
+
+impl<S> Layer<S> for PolicyLayer {
+    type Service = PolicyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PolicyService {
+            inner,
+            job_id: self.job_id,
+            policy: Arc::clone(&self.policy),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct PolicyService<S> {
+    inner: S,
+    job_id: JobId,
+    policy: Arc<RoutePolicy>,
+}
+
+impl<S> Service<JobCall> for PolicyService<S>
+where
+    S: Service<JobCall, Response = Option<JobResult>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Option<JobResult>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, call: JobCall) -> Self::Future {
+        let inner = self.inner.clone();
+        let job_id = self.job_id;
+        let policy = Arc::clone(&self.policy);
+
+        Box::pin(async move {
+            let mut retries = 0;
+            loop {
+                let attempt = inner.clone().oneshot(call.clone());
+                let result = match policy.timeout {
+                    Some(timeout) => with_timeout(job_id, timeout, attempt).await,
+                    None => attempt.await,
+                };
+
+                let Some(retry) = &policy.retry else {
+                    return result;
+                };
+
+                match &result {
+                    Ok(Some(JobResult::Err(e))) if retry.should_retry(retries, e) => {
+                        let backoff = retry.backoff(retries);
+                        blueprint_core::debug!(
+                            target: "blueprint-router",
+                            %job_id,
+                            retries,
+                            ?backoff,
+                            "Retrying failed job: {e}"
+                        );
+                        tokio::time::sleep(backoff).await;
+                        retries += 1;
+                    }
+                    _ => return result,
+                }
+            }
+        })
+    }
+}
+
+/// Run a single attempt of a job, turning a timeout into a [`TimedOut`] [`JobResult::Err`]
+async fn with_timeout<F>(job_id: JobId, timeout: Duration, attempt: F) -> F::Output
+where
+    F: Future<Output = Result<Option<JobResult>, BoxError>>,
+{
+    match tokio::time::timeout(timeout, attempt).await {
+        Ok(result) => result,
+        Err(_) => {
+            blueprint_core::warn!(
+                target: "blueprint-router",
+                %job_id,
+                ?timeout,
+                "Job timed out"
+            );
+            let error = Error::with_kind(ErrorKind::TimedOut, TimedOut { job_id, timeout });
+            Ok(Some(JobResult::Err(error)))
+        }
+    }
+}
@@ -116,6 +116,31 @@ where
         }
     }
 
+    /// Apply a [`RoutePolicy`] to the route for `job_id`
+    ///
+    /// Calling this more than once for the same job ID nests the policies, with the latest one
+    /// outermost. Adding a new route for `job_id` afterward discards them.
+    ///
+    /// See the [policy module](crate::policy) for more details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no route has been added for `job_id`.
+    ///
+    /// [`RoutePolicy`]: crate::policy::RoutePolicy
+    #[cfg(feature = "tokio")]
+    #[track_caller]
+    pub fn route_policy<I>(self, job_id: I, policy: crate::policy::RoutePolicy) -> Self
+    where
+        I: Into<JobId>,
+    {
+        let mut inner = self.into_inner();
+        inner.route_policy(job_id, policy);
+        Router {
+            inner: Arc::new(inner),
+        }
+    }
+
     /// Add a [`Job`] that *always* gets called, regardless of the job ID
     ///
     /// This is useful for jobs that want to watch for certain events. Any [`JobCall`] received by
@@ -201,6 +226,19 @@ where
         self.inner.has_routes()
     }
 
+    /// The job IDs that have a route in this `Router`, in ascending order
+    ///
+    /// [`always`] and [`fallback`] jobs are not tied to a job ID, and are not included.
+    ///
+    /// [`always`]: Router::always
+    /// [`fallback`]: Router::fallback
+    #[must_use]
+    pub fn job_ids(&self) -> Vec<JobId> {
+        let mut job_ids = self.inner.job_ids().collect::<Vec<_>>();
+        job_ids.sort_unstable_by_key(|job_id| job_id.0);
+        job_ids
+    }
+
     #[doc = include_str!("../docs/with_context.md")]
     pub fn with_context<Ctx2>(self, context: Ctx) -> Router<Ctx2> {
         let inner = self.into_inner().with_context(context);
@@ -433,6 +433,23 @@ async fn has_routes_false_with_only_fallback() {
     assert!(!router_with_fallback.has_routes());
 }
 
+#[tokio::test]
+async fn job_ids_are_sorted_and_skip_fallback() {
+    use blueprint_core::JobId;
+
+    let router: Router = Router::new()
+        .route(7u32, || async { "seven" })
+        .route(0u32, || async { "zero" })
+        .route(300u32, || async { "three hundred" })
+        .always(|| async { "always" })
+        .fallback(|| async { "fallback" });
+
+    assert_eq!(
+        router.job_ids(),
+        vec![JobId::from(0u32), JobId::from(7u32), JobId::from(300u32)]
+    );
+}
+
 #[tokio::test]
 async fn router_clone_works_independently() {
     setup_log();
@@ -727,3 +744,114 @@ async fn router_debug_impl_works() {
     let debug_str = std::format!("{:?}", router);
     assert!(debug_str.contains("Router"));
 }
+
+// =============================================================================
+// ROUTE POLICY TESTS
+// =============================================================================
+
+#[cfg(feature = "tokio")]
+mod policy {
+    use super::*;
+    use crate::policy::{RetryPolicy, RoutePolicy, TimedOut};
+    use blueprint_core::JobResult;
+    use core::time::Duration;
+
+    /// Fails until it has been called `succeed_on` times
+    fn flaky_job(
+        attempts: Arc<AtomicU32>,
+        succeed_on: u32,
+    ) -> impl Fn() -> core::future::Ready<Result<&'static str, BoxError>> + Clone + Send + Sync
+    {
+        move || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            core::future::ready(if attempt >= succeed_on {
+                Ok("recovered")
+            } else {
+                Err("transient".into())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn timeout_produces_timed_out_error() {
+        setup_log();
+
+        let mut router: Router = Router::new()
+            .route(3u32, || async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                "too slow"
+            })
+            .route_policy(3u32, RoutePolicy::new().timeout(Duration::from_millis(10)));
+
+        let results = router
+            .call(JobCall::new(3u32, Bytes::new()))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let [JobResult::Err(e)] = results.as_slice() else {
+            panic!("expected a single error, got {results:?}");
+        };
+        assert!(e.is_timeout());
+        let timed_out = e.clone().into_inner();
+        let timed_out = timed_out.downcast_ref::<TimedOut>().unwrap();
+        assert_eq!(timed_out.job_id(), 3u32.into());
+        assert_eq!(timed_out.timeout(), Duration::from_millis(10));
+    }
+
+    #[tokio::test]
+    async fn retry_recovers_from_transient_errors() {
+        setup_log();
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let mut router: Router = Router::new()
+            .route(3u32, flaky_job(attempts.clone(), 3))
+            .route_policy(
+                3u32,
+                RoutePolicy::new()
+                    .retry(RetryPolicy::new(2).initial_backoff(Duration::from_millis(1))),
+            );
+
+        let results = router
+            .call(JobCall::new(3u32, Bytes::new()))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(results[0].body().unwrap().as_ref(), b"recovered");
+    }
+
+    #[tokio::test]
+    async fn retry_skips_unclassified_errors() {
+        setup_log();
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let mut router: Router = Router::new()
+            .route(3u32, flaky_job(attempts.clone(), 3))
+            .route_policy(
+                3u32,
+                RoutePolicy::new().retry(
+                    RetryPolicy::new(2)
+                        .initial_backoff(Duration::from_millis(1))
+                        .retry_if(blueprint_core::Error::is_timeout),
+                ),
+            );
+
+        let results = router
+            .call(JobCall::new(3u32, Bytes::new()))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        assert!(results[0].is_err());
+        assert!(!results[0].body().unwrap_err().is_timeout());
+    }
+
+    #[test]
+    #[should_panic(expected = "no route exists for job")]
+    fn route_policy_requires_an_existing_route() {
+        let _: Router = Router::new().route_policy(3u32, RoutePolicy::new());
+    }
+}
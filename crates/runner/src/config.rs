@@ -158,6 +158,12 @@ pub struct BlueprintEnvironment {
     pub http_rpc_endpoint: Url,
     /// WS RPC endpoint for host restaking network (Tangle / Ethereum (Eigenlayer or Symbiotic)).
     pub ws_rpc_endpoint: Url,
+    /// HTTP RPC endpoints used when [`Self::http_rpc_endpoint`] fails, in order of preference.
+    #[serde(default)]
+    pub fallback_http_rpc_endpoints: Vec<Url>,
+    /// Number of HTTP RPC endpoints that must agree on reads, `None` trusting a single endpoint.
+    #[serde(default)]
+    pub rpc_quorum: Option<usize>,
     /// The keystore URI for the blueprint
     pub keystore_uri: String,
     /// Data directory exclusively for this blueprint
@@ -206,6 +212,8 @@ impl Default for BlueprintEnvironment {
         Self {
             http_rpc_endpoint: default_http_rpc_url(),
             ws_rpc_endpoint: default_ws_rpc_url(),
+            fallback_http_rpc_endpoints: Vec::new(),
+            rpc_quorum: None,
             keystore_uri: String::default(),
             data_dir: PathBuf::default(),
             protocol_settings: ProtocolSettings::default(),
@@ -297,6 +305,8 @@ fn load_inner(config: ContextConfig) -> Result<BlueprintEnvironment, ConfigError
     let dry_run = settings.dry_run;
     let http_rpc_url = settings.http_rpc_url.clone();
     let ws_rpc_url = settings.ws_rpc_url.clone();
+    let fallback_http_rpc_endpoints = settings.fallback_http_rpc_urls.clone();
+    let rpc_quorum = settings.rpc_quorum;
     let keystore_uri = settings.keystore_uri.clone();
     let bridge_socket_path = settings.bridge_socket_path.clone();
     let registration_mode = env_flag("REGISTRATION_MODE_ON");
@@ -331,6 +341,8 @@ fn load_inner(config: ContextConfig) -> Result<BlueprintEnvironment, ConfigError
         dry_run,
         http_rpc_endpoint: http_rpc_url,
         ws_rpc_endpoint: ws_rpc_url,
+        fallback_http_rpc_endpoints,
+        rpc_quorum,
         keystore_uri,
         data_dir,
         protocol_settings,
@@ -636,6 +648,8 @@ impl ContextConfig {
                 test_mode: false,
                 dry_run: false,
                 http_rpc_url,
+                fallback_http_rpc_urls: Vec::new(),
+                rpc_quorum: None,
                 #[cfg(feature = "networking")]
                 bootnodes: None,
                 #[cfg(feature = "networking")]
@@ -744,6 +758,14 @@ pub struct BlueprintSettings {
     #[arg(long, env, default_value_t = default_ws_rpc_url())]
     #[serde(default = "default_ws_rpc_url")]
     pub ws_rpc_url: Url,
+    /// HTTP RPC URLs to fall back to when `http_rpc_url` fails, in order of preference
+    #[arg(long, env, value_delimiter = ',')]
+    #[serde(default)]
+    pub fallback_http_rpc_urls: Vec<Url>,
+    /// Number of HTTP RPC endpoints that must agree on reads
+    #[arg(long, env)]
+    #[serde(default)]
+    pub rpc_quorum: Option<usize>,
     #[arg(long, short = 'k', env, default_value_t = String::from("./keystore"))]
     pub keystore_uri: String,
     #[arg(long, short, env)]
@@ -872,6 +894,8 @@ impl Default for BlueprintSettings {
             dry_run: false,
             http_rpc_url: default_http_rpc_url(),
             ws_rpc_url: default_ws_rpc_url(),
+            fallback_http_rpc_urls: Vec::new(),
+            rpc_quorum: None,
             keystore_uri: String::new(),
             data_dir: PathBuf::new(),
             chain: SupportedChains::default(),
@@ -0,0 +1,503 @@
+//! Result routing and failure isolation for consumers
+//!
+//! By default, every [`JobResult`] is sent to every consumer, and a consumer that fails takes the
+//! whole runner down with it. A [`ConsumerConfig`], passed to
+//! [`BlueprintRunnerBuilder::consumer_with()`], changes both:
+//!
+//! * Selectors limit a consumer to the results of specific jobs, or to results carrying specific
+//!   metadata. A consumer without selectors receives every result.
+//! * An [isolated](ConsumerConfig::isolated()) consumer runs in its own task, behind a bounded
+//!   queue. Failed sends are retried according to its [`RetryPolicy`], and results that still
+//!   can't be delivered (or don't fit in the queue) are logged and dropped, without affecting the
+//!   runner or any other consumer.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use blueprint_router::Router;
+//! use blueprint_runner::BlueprintRunner;
+//! use blueprint_runner::config::BlueprintEnvironment;
+//! use blueprint_runner::consumer::ConsumerConfig;
+//! use blueprint_runner::failure::RetryPolicy;
+//! use futures::channel::mpsc;
+//! use std::time::Duration;
+//!
+//! const PAID_JOB: u32 = 0;
+//!
+//! let (on_chain, _) = mpsc::unbounded();
+//! let (webhook, _) = mpsc::unbounded();
+//! let (x402_store, _) = mpsc::unbounded();
+//!
+//! let builder = BlueprintRunner::builder((), BlueprintEnvironment::default())
+//!     .router(Router::new().route(PAID_JOB, async || "paid"))
+//!     // On-chain submission gets every result, and a failure is still fatal
+//!     .consumer(on_chain)
+//!     // A flaky webhook can never take the runner down
+//!     .consumer_with(
+//!         webhook,
+//!         ConsumerConfig::new("webhook")
+//!             .isolated(RetryPolicy::new(5).initial_backoff(Duration::from_millis(200))),
+//!     )
+//!     // Only results of x402 calls are stored
+//!     .consumer_with(
+//!         x402_store,
+//!         ConsumerConfig::new("x402-store")
+//!             .metadata("X-X402-ORIGIN", "x402")
+//!             .isolated(RetryPolicy::new(3)),
+//!     );
+//! ```
+//!
+//! [`BlueprintRunnerBuilder::consumer_with()`]: crate::BlueprintRunnerBuilder::consumer_with
+
+use crate::failure::RetryPolicy;
+use crate::{Consumer, metrics};
+use blueprint_core::error::BoxError;
+use blueprint_core::metadata::MetadataValue;
+use blueprint_core::{JobId, JobResult};
+use futures::{SinkExt, stream};
+use std::borrow::Cow;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+/// The default capacity of an [isolated](ConsumerConfig::isolated()) consumer's queue
+pub const DEFAULT_CONSUMER_QUEUE_CAPACITY: usize = 1024;
+
+/// Which results a consumer receives, and how its failures are handled
+///
+/// A consumer receives a result if *any* of its selectors match. A consumer without selectors
+/// receives every result.
+///
+/// See the [module docs](self) for details.
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct ConsumerConfig {
+    name: Option<Cow<'static, str>>,
+    job_ids: Vec<JobId>,
+    metadata: Vec<(Cow<'static, str>, Option<MetadataValue>)>,
+    isolation: Option<RetryPolicy>,
+    queue_capacity: usize,
+}
+
+impl Default for ConsumerConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            job_ids: Vec::new(),
+            metadata: Vec::new(),
+            isolation: None,
+            queue_capacity: DEFAULT_CONSUMER_QUEUE_CAPACITY,
+        }
+    }
+}
+
+impl ConsumerConfig {
+    /// Create a new config, for a consumer that receives every result
+    ///
+    /// The `name` is used for logging and as the `consumer` label of the consumer metrics.
+    /// Consumers added without a name are named after the order they were added in, e.g.
+    /// `consumer-0`.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Receive the results of `job_id`
+    pub fn job<I: Into<JobId>>(mut self, job_id: I) -> Self {
+        self.job_ids.push(job_id.into());
+        self
+    }
+
+    /// Receive results with a metadata entry for `key`, with any value
+    pub fn metadata_key(mut self, key: impl Into<Cow<'static, str>>) -> Self {
+        self.metadata.push((key.into(), None));
+        self
+    }
+
+    /// Receive results with a metadata entry for `key` equal to `value`
+    pub fn metadata(
+        mut self,
+        key: impl Into<Cow<'static, str>>,
+        value: impl Into<MetadataValue>,
+    ) -> Self {
+        self.metadata.push((key.into(), Some(value.into())));
+        self
+    }
+
+    /// Run the consumer in its own task, so that its failures never reach the runner
+    ///
+    /// Failed sends are retried with `retry`'s backoff, up to its maximum number of retries.
+    /// After that, the result is logged and dropped. [`RetryPolicy::on_exhausted()`] does not
+    /// apply to consumers.
+    pub fn isolated(mut self, retry: RetryPolicy) -> Self {
+        self.isolation = Some(retry);
+        self
+    }
+
+    /// The maximum number of results waiting for an [isolated](Self::isolated()) consumer
+    ///
+    /// Results that arrive while the queue is full are dropped. Defaults to
+    /// [`DEFAULT_CONSUMER_QUEUE_CAPACITY`].
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is 0
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "queue capacity must be non-zero");
+        self.queue_capacity = capacity;
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or_default()
+    }
+
+    fn matches(&self, job_id: Option<JobId>, result: &JobResult) -> bool {
+        if self.job_ids.is_empty() && self.metadata.is_empty() {
+            return true;
+        }
+
+        let metadata = result.metadata();
+        job_id.is_some_and(|job_id| self.job_ids.contains(&job_id))
+            || self.metadata.iter().any(|(key, expected)| {
+                match (metadata.and_then(|m| m.get(key.as_ref())), expected) {
+                    (Some(_), None) => true,
+                    (Some(value), Some(expected)) => value.as_bytes() == expected.as_bytes(),
+                    (None, _) => false,
+                }
+            })
+    }
+}
+
+/// Every consumer of a runner, along with its [`ConsumerConfig`]
+pub(crate) struct Consumers {
+    inline: Vec<(ConsumerConfig, Consumer)>,
+    isolated: Vec<IsolatedConsumer>,
+}
+
+impl Consumers {
+    /// Start the worker tasks of every isolated consumer
+    pub(crate) fn start(consumers: Vec<(Consumer, ConsumerConfig)>) -> Self {
+        let mut inline = Vec::new();
+        let mut isolated = Vec::new();
+        for (index, (consumer, mut config)) in consumers.into_iter().enumerate() {
+            if config.name.is_none() {
+                config.name = Some(format!("consumer-{index}").into());
+            }
+
+            match config.isolation.clone() {
+                Some(retry) => isolated.push(IsolatedConsumer::spawn(consumer, config, retry)),
+                None => inline.push((config, consumer)),
+            }
+        }
+
+        Self { inline, isolated }
+    }
+
+    /// Send the results of a call to `job_id` to every consumer that selects them
+    ///
+    /// # Errors
+    ///
+    /// If a consumer that isn't isolated fails.
+    pub(crate) async fn send(
+        &self,
+        job_id: Option<JobId>,
+        results: &[JobResult],
+    ) -> Result<(), BoxError> {
+        for consumer in &self.isolated {
+            for result in results {
+                if consumer.config.matches(job_id, result) {
+                    consumer.enqueue(result.clone());
+                }
+            }
+        }
+
+        let send_futures = self.inline.iter().filter_map(|(config, consumer)| {
+            let selected = results
+                .iter()
+                .filter(|result| config.matches(job_id, result))
+                .cloned()
+                .map(Ok)
+                .collect::<Vec<_>>();
+            if selected.is_empty() {
+                return None;
+            }
+
+            Some(async move {
+                let mut guard = consumer.lock().await;
+                guard.send_all(&mut stream::iter(selected)).await
+            })
+        });
+
+        futures::future::try_join_all(send_futures)
+            .await
+            .map(|_| ())
+    }
+
+    /// The number of results waiting for each isolated consumer, by consumer name
+    pub(crate) fn backlog(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.isolated
+            .iter()
+            .map(|consumer| (consumer.config.name(), consumer.backlog()))
+    }
+
+    /// Flush every consumer, waiting for isolated consumers to work through their queues
+    ///
+    /// # Errors
+    ///
+    /// If a consumer that isn't isolated fails.
+    pub(crate) async fn flush(&self) -> Result<(), BoxError> {
+        let isolated = futures::future::join_all(self.isolated.iter().map(IsolatedConsumer::flush));
+        let inline = futures::future::try_join_all(self.inline.iter().map(|(_, consumer)| async {
+            let mut guard = consumer.lock().await;
+            guard.flush().await
+        }));
+
+        let (_, result) = futures::join!(isolated, inline);
+        result.map(|_| ())
+    }
+}
+
+enum Message {
+    Result(JobResult),
+    Flush(oneshot::Sender<()>),
+}
+
+/// A consumer running in its own task, see [`ConsumerConfig::isolated()`]
+struct IsolatedConsumer {
+    config: ConsumerConfig,
+    queue: mpsc::Sender<Message>,
+    task: JoinHandle<()>,
+}
+
+impl IsolatedConsumer {
+    fn spawn(consumer: Consumer, config: ConsumerConfig, retry: RetryPolicy) -> Self {
+        let (queue, rx) = mpsc::channel(config.queue_capacity);
+        let name = config.name();
+        let task = tokio::spawn(run_isolated(name.to_owned(), consumer, retry, rx));
+        Self {
+            config,
+            queue,
+            task,
+        }
+    }
+
+    fn enqueue(&self, result: JobResult) {
+        let name = self.config.name();
+        let sent = self.queue.try_send(Message::Result(result));
+        if let Err(mpsc::error::TrySendError::Full(_)) = sent {
+            blueprint_core::warn!(
+                target: "blueprint-runner",
+                consumer = name,
+                "Consumer queue is full, dropping job result"
+            );
+            metrics::CONSUMER_DROPPED_RESULTS
+                .with_label_values(&[name, "queue_full"])
+                .inc();
+        }
+
+        metrics::CONSUMER_BACKLOG
+            .with_label_values(&[name])
+            .set(self.backlog() as i64);
+    }
+
+    fn backlog(&self) -> usize {
+        self.queue.max_capacity() - self.queue.capacity()
+    }
+
+    async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.queue.send(Message::Flush(done)).await.is_ok() {
+            let _ = wait.await;
+        }
+    }
+}
+
+impl Drop for IsolatedConsumer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn run_isolated(
+    name: String,
+    consumer: Consumer,
+    retry: RetryPolicy,
+    mut queue: mpsc::Receiver<Message>,
+) {
+    while let Some(message) = queue.recv().await {
+        metrics::CONSUMER_BACKLOG
+            .with_label_values(&[name.as_str()])
+            .set(queue.len() as i64);
+
+        match message {
+            Message::Result(result) => deliver(&name, &consumer, &retry, result).await,
+            Message::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// Send a single result to an isolated consumer, retrying until it succeeds or `retry` is
+/// exhausted
+async fn deliver(name: &str, consumer: &Consumer, retry: &RetryPolicy, result: JobResult) {
+    let mut retries = 0;
+    loop {
+        let outcome = consumer.lock().await.send(result.clone()).await;
+        let Err(e) = outcome else {
+            return;
+        };
+
+        if retries >= retry.max_retries() {
+            blueprint_core::error!(
+                target: "blueprint-runner",
+                consumer = name,
+                retries,
+                "Consumer failed, dropping job result: {e}"
+            );
+            metrics::CONSUMER_DROPPED_RESULTS
+                .with_label_values(&[name, "retries_exhausted"])
+                .inc();
+            return;
+        }
+
+        let backoff = retry.backoff(retries);
+        blueprint_core::warn!(
+            target: "blueprint-runner",
+            consumer = name,
+            retries,
+            ?backoff,
+            "Consumer failed, retrying: {e}"
+        );
+        sleep(backoff).await;
+        retries += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use futures::Sink;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    /// A sink that fails the first `failures` sends, counting every attempt
+    struct FlakySink {
+        failures: u32,
+        attempts: Arc<AtomicU32>,
+        delivered: futures::channel::mpsc::UnboundedSender<JobResult>,
+    }
+
+    impl Sink<JobResult> for FlakySink {
+        type Error = BoxError;
+
+        fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: JobResult) -> Result<(), BoxError> {
+            let this = self.get_mut();
+            let attempt = this.attempts.fetch_add(1, Ordering::SeqCst);
+            if attempt < this.failures {
+                return Err("webhook unavailable".into());
+            }
+            this.delivered.unbounded_send(item).map_err(Into::into)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn consumer(
+        failures: u32,
+    ) -> (
+        Consumer,
+        Arc<AtomicU32>,
+        futures::channel::mpsc::UnboundedReceiver<JobResult>,
+    ) {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let (delivered, rx) = futures::channel::mpsc::unbounded();
+        let sink = FlakySink {
+            failures,
+            attempts: Arc::clone(&attempts),
+            delivered,
+        };
+        (Arc::new(Mutex::new(Box::new(sink))), attempts, rx)
+    }
+
+    fn result(origin: Option<&'static str>) -> JobResult {
+        let mut result = JobResult::new(Bytes::from_static(b"done"));
+        if let Some(origin) = origin {
+            result
+                .metadata_mut()
+                .unwrap()
+                .insert("X-X402-ORIGIN", origin);
+        }
+        result
+    }
+
+    #[test]
+    fn selectors_match_job_ids_or_metadata() {
+        let everything = ConsumerConfig::new("all");
+        assert!(everything.matches(None, &result(None)));
+
+        let selective = ConsumerConfig::new("x402")
+            .job(1u32)
+            .metadata("X-X402-ORIGIN", "x402");
+        assert!(selective.matches(Some(1u32.into()), &result(None)));
+        assert!(selective.matches(Some(0u32.into()), &result(Some("x402"))));
+        assert!(!selective.matches(Some(0u32.into()), &result(Some("other"))));
+        assert!(!selective.matches(None, &result(None)));
+    }
+
+    #[tokio::test]
+    async fn isolated_consumer_retries_without_failing_the_runner() {
+        let (flaky, attempts, mut delivered) = consumer(2);
+        let (broken, _, _) = consumer(u32::MAX);
+        let retry = RetryPolicy::new(2).initial_backoff(Duration::from_millis(1));
+
+        let consumers = Consumers::start(vec![
+            (flaky, ConsumerConfig::new("flaky").isolated(retry.clone())),
+            (broken, ConsumerConfig::new("broken").isolated(retry)),
+        ]);
+
+        consumers.send(None, &[result(None)]).await.unwrap();
+        consumers.flush().await.unwrap();
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert!(delivered.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn inline_consumer_failures_are_returned() {
+        let (broken, _, _) = consumer(u32::MAX);
+        let (skipped, attempts, _) = consumer(u32::MAX);
+
+        let consumers = Consumers::start(vec![
+            (broken, ConsumerConfig::default()),
+            (skipped, ConsumerConfig::new("job-1").job(1u32)),
+        ]);
+
+        assert!(
+            consumers
+                .send(Some(0u32.into()), &[result(None)])
+                .await
+                .is_err()
+        );
+        // The job 1 consumer never saw the job 0 result
+        assert_eq!(attempts.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,575 @@
+//! Job call dispatch
+//!
+//! The [`JobDispatcher`] owns everything needed to run a [`JobCall`] once a producer has yielded
+//! it: the [`Scheduler`] queues, the [`Router`], the `FaaS` executors, the failure policies, the
+//! [`Consumers`] that receive the results, and the optional [`Journal`] that tracks every call.
+//!
+//! Calls for jobs disabled through a [`RunnerHandle`](crate::handle::RunnerHandle) are rejected
+//! here, instead of being run.
+//!
+//! Calls yielded by a [`RecordingProducer`](crate::record::RecordingProducer) have their outcome
+//! recorded here once they finish.
+
+use crate::consumer::Consumers;
+use crate::error::{JobCallError, RunnerError as Error};
+use crate::failure::{DeadLetter, FailureAction, FailurePolicies};
+use crate::handle::{ControlCommand, DisabledJobs};
+use crate::journal::{JobState, Journal, JournalKey, Recovery};
+use crate::record::RecordedCall;
+use crate::scheduler::{Scheduler, SchedulerConfig};
+use crate::{DeadLetterSink, faas, metrics};
+use blueprint_core::error::{BoxError, ErrorKind};
+use blueprint_core::{JobCall, JobId, JobResult};
+use blueprint_router::Router;
+use core::future::{Future, poll_fn};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::{SinkExt, StreamExt};
+use futures_util::stream::FuturesUnordered;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::sleep;
+use tower::Service;
+
+type JobCallOutput = Result<Option<Vec<JobResult>>, BoxError>;
+
+pub(crate) struct JobDispatcher {
+    scheduler: Scheduler,
+    router: Router,
+    faas_registry: faas::FaasRegistry,
+    failure_policies: FailurePolicies,
+    dead_letter_sink: Option<DeadLetterSink>,
+    consumers: Consumers,
+    journal: Option<Journal>,
+    disabled: DisabledJobs,
+    in_flight: FuturesUnordered<PendingJob>,
+}
+
+impl JobDispatcher {
+    pub(crate) fn new(
+        scheduler: SchedulerConfig,
+        router: Router,
+        faas_registry: faas::FaasRegistry,
+        failure_policies: FailurePolicies,
+        dead_letter_sink: Option<DeadLetterSink>,
+        consumers: Consumers,
+        journal: Option<Journal>,
+    ) -> Self {
+        Self {
+            scheduler: Scheduler::new(scheduler),
+            router,
+            faas_registry,
+            failure_policies,
+            dead_letter_sink,
+            consumers,
+            journal,
+            disabled: DisabledJobs::default(),
+            in_flight: FuturesUnordered::new(),
+        }
+    }
+
+    /// Share the set of disabled jobs with a [`RunnerHandle`](crate::handle::RunnerHandle)
+    pub(crate) fn with_disabled_jobs(mut self, disabled: DisabledJobs) -> Self {
+        self.disabled = disabled;
+        self
+    }
+
+    /// Apply a [`ControlCommand`] from a [`RunnerHandle`](crate::handle::RunnerHandle) or the
+    /// manager
+    pub(crate) async fn control(&mut self, command: ControlCommand) {
+        match command {
+            ControlCommand::ReplaceRouter(router) => {
+                blueprint_core::info!(
+                    target: "blueprint-runner",
+                    in_flight = self.in_flight.len(),
+                    "Replacing router"
+                );
+                self.router = router;
+                self.ready().await;
+            }
+            ControlCommand::SetJobEnabled(job_id, enabled) => self.disabled.set(job_id, !enabled),
+        }
+    }
+
+    /// Wait for the [`Router`] to be ready to accept job calls
+    pub(crate) async fn ready(&mut self) {
+        poll_fn(|ctx| Service::<JobCall>::poll_ready(&mut self.router, ctx))
+            .await
+            .unwrap_or(());
+    }
+
+    /// The number of job calls that have been accepted, but have not finished
+    ///
+    /// This includes job calls that are still queued.
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.len() + self.scheduler.queued()
+    }
+
+    /// [`Self::in_flight()`], by job ID
+    pub(crate) fn in_flight_per_job(&self) -> HashMap<JobId, usize> {
+        let mut jobs = HashMap::new();
+        let running = self.in_flight.iter().map(|pending| pending.job_id);
+        for job_id in running.chain(self.scheduler.queued_jobs()) {
+            *jobs.entry(job_id).or_default() += 1;
+        }
+        jobs
+    }
+
+    /// The number of results waiting for each isolated consumer, by consumer name
+    pub(crate) fn consumer_backlog(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.consumers.backlog()
+    }
+
+    /// Whether another job call can be accepted without exceeding a queue's capacity
+    pub(crate) fn has_capacity(&self) -> bool {
+        self.scheduler.has_capacity()
+    }
+
+    /// Accept a new job call
+    ///
+    /// The call is queued, and spawned as soon as the concurrency limits allow. If the call is
+    /// already in the journal, it is skipped unless it failed, and if its job is disabled, it is
+    /// rejected.
+    ///
+    /// # Errors
+    ///
+    /// If the journal cannot be written to.
+    pub(crate) async fn dispatch(&mut self, job_call: JobCall) -> Result<(), Error> {
+        if self.disabled.contains(job_call.job_id()) {
+            return self.reject(job_call).await;
+        }
+
+        let key = self.journal_key(&job_call);
+        if let Some((journal, key)) = self.journal_entry(key) {
+            if let Some(state) = journal.state(key)
+                && state != JobState::Failed
+            {
+                blueprint_core::debug!(
+                    target: "blueprint-runner",
+                    service_id = key.service_id,
+                    call_id = key.call_id,
+                    ?state,
+                    "Skipping job call that is already in the journal"
+                );
+                return Ok(());
+            }
+
+            journal.received(key, &job_call).await?;
+        }
+
+        self.scheduler.enqueue(job_call);
+        self.start_runnable().await
+    }
+
+    /// Resume the job calls left unfinished in the journal by a previous run
+    ///
+    /// Calls that never completed are queued again, and the results of calls that completed but
+    /// were never submitted are sent to the consumers.
+    ///
+    /// # Errors
+    ///
+    /// If a consumer fails, or the journal cannot be written to.
+    pub(crate) async fn recover(&mut self) -> Result<(), Error> {
+        let Some(journal) = &self.journal else {
+            return Ok(());
+        };
+
+        let recovered = journal.recover();
+        if recovered.is_empty() {
+            return Ok(());
+        }
+
+        blueprint_core::info!(
+            target: "blueprint-runner",
+            count = recovered.len(),
+            "Recovering unfinished job calls from the journal"
+        );
+        for recovery in recovered {
+            match recovery {
+                Recovery::Run(job_call) => self.scheduler.enqueue(job_call),
+                Recovery::Submit(key, job_id, results) => {
+                    self.broadcast(job_id, results).await?;
+                    if let Some((journal, key)) = self.journal_entry(Some(key)) {
+                        journal.submitted(key).await?;
+                    }
+                }
+            }
+        }
+
+        self.start_runnable().await
+    }
+
+    /// Spawn every queued job call that fits within the concurrency limits
+    async fn start_runnable(&mut self) -> Result<(), Error> {
+        while let Some(job_call) = self.scheduler.next_runnable() {
+            self.start(job_call).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn start(&mut self, job_call: JobCall) -> Result<(), Error> {
+        let job_id = job_call.job_id();
+        let journal_key = self.journal_key(&job_call);
+
+        // The job may have been disabled while the call was queued
+        if self.disabled.contains(job_id) {
+            self.scheduler.finished(job_id);
+            if let Some((journal, key)) = self.journal_entry(journal_key) {
+                journal.failed(key).await?;
+            }
+            return self.reject(job_call).await;
+        }
+
+        if let Some((journal, key)) = self.journal_entry(journal_key) {
+            journal.started(key).await?;
+        }
+        let recording = job_call.extensions().get::<RecordedCall>().cloned();
+
+        let retained_call = self
+            .failure_policies
+            .for_job(job_id)
+            .retains_call()
+            .then(|| job_call.clone());
+
+        let handle = self.spawn(job_call, None);
+        self.in_flight.push(PendingJob {
+            job_id,
+            journal_key,
+            recording,
+            call: retained_call,
+            retries: 0,
+            handle,
+        });
+
+        Ok(())
+    }
+
+    /// Wait for the next in-flight job call to finish
+    ///
+    /// Returns `None` if there are no job calls in flight.
+    pub(crate) async fn next_finished(&mut self) -> Option<FinishedJob> {
+        self.in_flight.next().await
+    }
+
+    /// Handle a finished job call
+    ///
+    /// Successful results are sent to every consumer that selects them, and failures are handled
+    /// according to the job's [`FailurePolicy`](crate::failure::FailurePolicy).
+    ///
+    /// # Errors
+    ///
+    /// If a consumer fails, or the job call failed and its policy is to abort.
+    pub(crate) async fn finish(&mut self, finished: FinishedJob) -> Result<(), Error> {
+        let FinishedJob {
+            job_id,
+            journal_key,
+            recording,
+            call,
+            retries,
+            result,
+        } = finished;
+
+        let job_result = match result {
+            Ok(Ok(results)) => Ok(results),
+            Ok(Err(e)) => Err(JobCallError::JobFailed(e)),
+            Err(e) => Err(JobCallError::JobDidntFinish(e)),
+        };
+
+        let outcome = match job_result {
+            Ok(Some(results)) => {
+                blueprint_core::trace!(
+                    target: "blueprint-runner",
+                    count = %results.len(),
+                    "Job call(s) processed by router"
+                );
+                for result in &results {
+                    if let JobResult::Err(e) = result {
+                        metrics::JOB_RESULT_ERRORS
+                            .with_label_values(&[job_id.to_string().as_str(), e.kind().as_str()])
+                            .inc();
+                    }
+                }
+                if let Some(recording) = &recording {
+                    recording.results(&results);
+                }
+                self.submit(job_id, journal_key, results)
+                    .await
+                    .map(|()| false)
+            }
+            Ok(None) => {
+                blueprint_core::debug!(target: "blueprint-runner", "Job call was ignored by router");
+                if let Some(recording) = &recording {
+                    recording.ignored();
+                }
+                if let Some((journal, key)) = self.journal_entry(journal_key) {
+                    journal.submitted(key).await?;
+                }
+                Ok(false)
+            }
+            Err(e) => {
+                self.fail(job_id, journal_key, recording, call, retries, e)
+                    .await
+            }
+        };
+
+        // A retried call keeps its concurrency slot
+        if !matches!(outcome, Ok(true)) {
+            self.scheduler.finished(job_id);
+            if outcome.is_ok() {
+                self.start_runnable().await?;
+            }
+        }
+
+        outcome.map(|_| ())
+    }
+
+    /// Flush every consumer, so that all results sent so far are delivered
+    pub(crate) async fn flush_consumers(&mut self) -> Result<(), Error> {
+        self.consumers.flush().await.map_err(Error::Consumer)
+    }
+
+    /// Abort every in-flight job call and drop every queued one, returning how many there were
+    ///
+    /// Abandoned calls are left unfinished in the journal, so they will be run again on restart.
+    pub(crate) fn abandon(&mut self) -> usize {
+        let abandoned = self.in_flight();
+        for pending in &self.in_flight {
+            pending.handle.abort();
+        }
+        self.in_flight.clear();
+        self.scheduler.clear();
+        abandoned
+    }
+
+    /// Send the results of a job call to the consumers, journaling them before and after
+    async fn submit(
+        &mut self,
+        job_id: JobId,
+        journal_key: Option<JournalKey>,
+        results: Vec<JobResult>,
+    ) -> Result<(), Error> {
+        if let Some((journal, key)) = self.journal_entry(journal_key) {
+            journal.completed(key, &results).await?;
+        }
+
+        self.broadcast(Some(job_id), results).await?;
+
+        if let Some((journal, key)) = self.journal_entry(journal_key) {
+            journal.submitted(key).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reject a call to a disabled job, sending a [`ErrorKind::Disabled`] result to the consumers
+    async fn reject(&mut self, job_call: JobCall) -> Result<(), Error> {
+        let job_id = job_call.job_id();
+        blueprint_core::warn!(
+            target: "blueprint-runner",
+            %job_id,
+            "Rejecting job call, the job is disabled"
+        );
+        metrics::JOB_RESULT_ERRORS
+            .with_label_values(&[job_id.to_string().as_str(), ErrorKind::Disabled.as_str()])
+            .inc();
+
+        let results = vec![JobResult::Err(blueprint_core::Error::with_kind(
+            ErrorKind::Disabled,
+            JobCallError::JobDisabled(job_id),
+        ))];
+        if let Some(recording) = job_call.extensions().get::<RecordedCall>() {
+            recording.results(&results);
+        }
+
+        self.broadcast(Some(job_id), results).await
+    }
+
+    /// Send results to every consumer that selects them
+    async fn broadcast(
+        &mut self,
+        job_id: Option<JobId>,
+        results: Vec<JobResult>,
+    ) -> Result<(), Error> {
+        let result = self.consumers.send(job_id, &results).await;
+        blueprint_core::trace!(
+            target: "blueprint-runner",
+            results = ?result.as_ref().map(|_| "success"),
+            "Job call results were sent to consumers"
+        );
+
+        result.map_err(Error::Consumer)
+    }
+
+    /// Apply the failure policy for a failed job call, returning whether it was retried
+    async fn fail(
+        &mut self,
+        job_id: JobId,
+        journal_key: Option<JournalKey>,
+        recording: Option<RecordedCall>,
+        call: Option<JobCall>,
+        retries: u32,
+        e: JobCallError,
+    ) -> Result<bool, Error> {
+        let action = self.failure_policies.for_job(job_id).action(retries);
+        blueprint_core::error!(
+            target: "blueprint-runner",
+            %job_id,
+            retries,
+            outcome = action.as_str(),
+            "Job call failed: {:?}",
+            e
+        );
+        metrics::JOB_FAILURES
+            .with_label_values(&[job_id.to_string().as_str(), action.as_str()])
+            .inc();
+
+        let retried = matches!(action, FailureAction::Retry(_)) && call.is_some();
+        if !retried {
+            if let Some(recording) = &recording {
+                recording.failed(&e);
+            }
+            if let Some((journal, key)) = self.journal_entry(journal_key) {
+                journal.failed(key).await?;
+            }
+        }
+
+        match (action, call) {
+            (FailureAction::Abort, _) => return Err(e.into()),
+            (FailureAction::Retry(backoff), Some(call)) => {
+                let handle = self.spawn(call.clone(), Some(backoff));
+                self.in_flight.push(PendingJob {
+                    job_id,
+                    journal_key,
+                    recording,
+                    call: Some(call),
+                    retries: retries + 1,
+                    handle,
+                });
+                return Ok(true);
+            }
+            (FailureAction::DeadLetter, Some(call)) => {
+                self.send_dead_letter(DeadLetter {
+                    call,
+                    error: blueprint_core::Error::new(e),
+                    retries,
+                })
+                .await;
+            }
+            (FailureAction::Continue | FailureAction::Retry(_) | FailureAction::DeadLetter, _) => {}
+        }
+
+        Ok(false)
+    }
+
+    /// Spawn a job call, either on the local [`Router`] or on its registered `FaaS` executor
+    ///
+    /// If `delay` is set, the call will only start after it has elapsed.
+    fn spawn(&mut self, job_call: JobCall, delay: Option<Duration>) -> JoinHandle<JobCallOutput> {
+        // Check if this job should be delegated to FaaS
+        let job_id: u32 = job_call.job_id().into();
+        let job: Pin<Box<dyn Future<Output = JobCallOutput> + Send>> =
+            if let Some(executor) = self.faas_registry.get(job_id).cloned() {
+                blueprint_core::info!(
+                    target: "blueprint-runner",
+                    job_id = %job_call.job_id(),
+                    provider = executor.provider_name(),
+                    "Delegating job to FaaS executor"
+                );
+
+                Box::pin(async move {
+                    match executor.invoke(job_call).await {
+                        Ok(result) => Ok(Some(vec![result])),
+                        Err(e) => {
+                            blueprint_core::error!(
+                                target: "blueprint-runner",
+                                error = %e,
+                                "FaaS invocation failed"
+                            );
+                            Err(Box::new(e) as BoxError)
+                        }
+                    }
+                })
+            } else {
+                // Normal local execution via router
+                self.router.call(job_call)
+            };
+
+        match delay {
+            Some(delay) => tokio::task::spawn(async move {
+                sleep(delay).await;
+                job.await
+            }),
+            None => tokio::task::spawn(job),
+        }
+    }
+
+    /// The journal key of `job_call`, if there is a journal
+    fn journal_key(&self, job_call: &JobCall) -> Option<JournalKey> {
+        self.journal.as_ref()?;
+        JournalKey::from_metadata(job_call.metadata())
+    }
+
+    fn journal_entry(&mut self, key: Option<JournalKey>) -> Option<(&mut Journal, JournalKey)> {
+        Some((self.journal.as_mut()?, key?))
+    }
+
+    async fn send_dead_letter(&self, letter: DeadLetter) {
+        let Some(sink) = &self.dead_letter_sink else {
+            blueprint_core::warn!(
+                target: "blueprint-runner",
+                job_id = %letter.call.job_id(),
+                "Job call was dead-lettered, but no dead-letter sink is configured; dropping it"
+            );
+            return;
+        };
+
+        let job_id = letter.call.job_id();
+        if let Err(e) = sink.lock().await.send(letter).await {
+            blueprint_core::error!(
+                target: "blueprint-runner",
+                %job_id,
+                "Failed to send job call to the dead-letter sink: {:?}",
+                e
+            );
+        }
+    }
+}
+
+/// A job call that has been dispatched, along with what is needed to apply its failure policy
+struct PendingJob {
+    job_id: JobId,
+    journal_key: Option<JournalKey>,
+    recording: Option<RecordedCall>,
+    /// Only retained when the failure policy may need to re-dispatch or dead-letter the call
+    call: Option<JobCall>,
+    retries: u32,
+    handle: JoinHandle<JobCallOutput>,
+}
+
+impl Future for PendingJob {
+    type Output = FinishedJob;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = core::task::ready!(Pin::new(&mut self.handle).poll(cx));
+        Poll::Ready(FinishedJob {
+            job_id: self.job_id,
+            journal_key: self.journal_key,
+            recording: self.recording.take(),
+            call: self.call.take(),
+            retries: self.retries,
+            result,
+        })
+    }
+}
+
+/// The output of a [`PendingJob`], to be handed back to [`JobDispatcher::finish()`]
+pub(crate) struct FinishedJob {
+    job_id: JobId,
+    journal_key: Option<JournalKey>,
+    recording: Option<RecordedCall>,
+    call: Option<JobCall>,
+    retries: u32,
+    result: Result<JobCallOutput, JoinError>,
+}
@@ -0,0 +1,368 @@
+//! Graceful drain on shutdown
+//!
+//! Without draining, a runner that is told to stop simply drops its in-flight jobs, and any
+//! results they would have produced never reach the consumers. When a drain is requested, the
+//! runner instead:
+//!
+//! 1. Stops polling its producers, so no new job calls are accepted
+//! 2. Waits for the in-flight job calls to finish, up to a deadline
+//! 3. Flushes every consumer
+//! 4. Stops the [background services](crate::BackgroundService), in the reverse order that they
+//!    were added
+//!
+//! A drain can be requested by the blueprint manager over the bridge at any time. With a
+//! [`DrainConfig`] set through [`BlueprintRunnerBuilder::drain()`], the runner will also drain on
+//! `SIGTERM`, and whenever it shuts down because of an error (e.g. a failed producer). In every
+//! case, the [shutdown handler] is called once the drain is over.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use blueprint_router::Router;
+//! use blueprint_runner::BlueprintRunner;
+//! use blueprint_runner::config::BlueprintEnvironment;
+//! use blueprint_runner::drain::DrainConfig;
+//! use std::time::Duration;
+//!
+//! let builder = BlueprintRunner::builder((), BlueprintEnvironment::default())
+//!     .router(Router::new().route(0, async || "Hello, world!"))
+//!     // Give in-flight jobs up to 2 minutes to finish when we receive SIGTERM
+//!     .drain(DrainConfig::new().timeout(Duration::from_secs(120)));
+//! ```
+//!
+//! [`BlueprintRunnerBuilder::drain()`]: crate::BlueprintRunnerBuilder::drain
+//! [shutdown handler]: crate::BlueprintRunnerBuilder::with_shutdown_handler
+
+use crate::dispatch::JobDispatcher;
+use crate::error::RunnerError as Error;
+use crate::{BackgroundService, DynBackgroundService, metrics};
+use blueprint_manager_bridge::client::Bridge;
+use blueprint_manager_bridge::runner_command;
+use core::pin::Pin;
+use futures::{Stream, StreamExt, stream};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Configuration for draining the runner
+///
+/// See the [module docs](self) for details.
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct DrainConfig {
+    timeout: Duration,
+    on_sigterm: bool,
+}
+
+impl Default for DrainConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            on_sigterm: true,
+        }
+    }
+}
+
+impl DrainConfig {
+    /// Create a new `DrainConfig`
+    ///
+    /// By default, in-flight jobs are given 30 seconds to finish, and `SIGTERM` triggers a drain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long to wait for in-flight jobs before abandoning them
+    ///
+    /// The blueprint manager may override this on a per-request basis.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether to drain when the process receives `SIGTERM`
+    ///
+    /// This has no effect on non-Unix platforms.
+    pub fn on_sigterm(mut self, on_sigterm: bool) -> Self {
+        self.on_sigterm = on_sigterm;
+        self
+    }
+
+    pub(crate) fn drain_timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+/// A request to drain the runner
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DrainRequest {
+    /// What asked for the drain, for logging
+    pub(crate) source: &'static str,
+    /// Overrides [`DrainConfig::timeout()`]
+    pub(crate) timeout: Option<Duration>,
+}
+
+/// Every source of [`DrainRequest`]s for this runner
+///
+/// The stream never ends, even if there are no sources.
+pub(crate) async fn requests(
+    config: Option<&DrainConfig>,
+    bridge: Option<&Bridge>,
+) -> Pin<Box<dyn Stream<Item = DrainRequest> + Send>> {
+    let mut sources: Vec<Pin<Box<dyn Stream<Item = DrainRequest> + Send>>> =
+        vec![stream::pending().boxed()];
+
+    #[cfg(unix)]
+    if config.is_some_and(|config| config.on_sigterm) {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(sigterm) => sources.push(
+                stream::unfold(sigterm, |mut sigterm| async move {
+                    sigterm.recv().await?;
+                    let request = DrainRequest {
+                        source: "SIGTERM",
+                        timeout: None,
+                    };
+                    Some((request, sigterm))
+                })
+                .boxed(),
+            ),
+            Err(e) => {
+                blueprint_core::warn!(
+                    target: "blueprint-runner",
+                    "Unable to listen for SIGTERM, the runner will not drain on termination: {e}"
+                );
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = config;
+
+    if let Some(bridge) = bridge {
+        match bridge.watch_runner_commands().await {
+            Ok(commands) => sources.push(
+                commands
+                    .filter_map(|command| async move {
+                        match command.ok()?.command? {
+                            runner_command::Command::Drain(drain) => Some(DrainRequest {
+                                source: "manager",
+                                timeout: (drain.timeout_secs > 0)
+                                    .then(|| Duration::from_secs(drain.timeout_secs)),
+                            }),
+                            runner_command::Command::SetJobEnabled(_) => None,
+                        }
+                    })
+                    .boxed(),
+            ),
+            Err(e) => {
+                blueprint_core::debug!(
+                    target: "blueprint-runner",
+                    "Not watching for runner commands from the manager: {e}"
+                );
+            }
+        }
+    }
+
+    stream::select_all(sources).boxed()
+}
+
+/// Drain the runner
+///
+/// The caller is expected to have stopped polling producers.
+///
+/// If a job call fails with an [`Abort`](crate::failure::FailurePolicy::Abort) policy, or the
+/// consumers can't be flushed, the jobs still in flight are abandoned and the background services
+/// are still stopped before the first error is returned.
+pub(crate) async fn drain(
+    dispatcher: &mut JobDispatcher,
+    background_services: &[Box<DynBackgroundService<'static>>],
+    timeout: Duration,
+) -> Result<(), Error> {
+    let in_flight = dispatcher.in_flight();
+    blueprint_core::info!(
+        target: "blueprint-runner",
+        in_flight,
+        ?timeout,
+        "Draining runner"
+    );
+
+    let mut result = Ok(());
+    let deadline = Instant::now() + timeout;
+    while dispatcher.in_flight() > 0 {
+        match tokio::time::timeout_at(deadline, dispatcher.next_finished()).await {
+            Ok(Some(finished)) => {
+                if let Err(e) = dispatcher.finish(finished).await {
+                    blueprint_core::error!(
+                        target: "blueprint-runner",
+                        remaining = dispatcher.in_flight(),
+                        "Job call failed while draining, abandoning remaining jobs: {e}"
+                    );
+                    result = Err(e);
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(_) => {
+                blueprint_core::warn!(
+                    target: "blueprint-runner",
+                    remaining = dispatcher.in_flight(),
+                    "Drain deadline reached, abandoning remaining jobs"
+                );
+                break;
+            }
+        }
+    }
+
+    let abandoned = dispatcher.abandon();
+    // Retries stay in flight, so every job is either drained or abandoned exactly once
+    let drained = in_flight.saturating_sub(abandoned);
+
+    if let Err(e) = dispatcher.flush_consumers().await {
+        blueprint_core::error!(
+            target: "blueprint-runner",
+            "Failed to flush consumers while draining: {e}"
+        );
+        result = result.and(Err(e));
+    }
+
+    for service in background_services.iter().rev() {
+        if let Err(e) = service.stop().await {
+            blueprint_core::warn!(
+                target: "blueprint-runner",
+                "Failed to stop background service: {e}"
+            );
+        }
+    }
+
+    metrics::DRAINED_JOBS
+        .with_label_values(&["drained"])
+        .inc_by(drained as u64);
+    metrics::DRAINED_JOBS
+        .with_label_values(&["abandoned"])
+        .inc_by(abandoned as u64);
+    blueprint_core::info!(
+        target: "blueprint-runner",
+        drained,
+        abandoned,
+        "Runner drained"
+    );
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Consumer;
+    use crate::consumer::{ConsumerConfig, Consumers};
+    use crate::faas::FaasRegistry;
+    use crate::failure::FailurePolicies;
+    use crate::scheduler::SchedulerConfig;
+    use blueprint_core::{JobCall, JobResult};
+    use blueprint_router::Router;
+    use bytes::Bytes;
+    use futures::SinkExt;
+    use futures::channel::mpsc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::{Mutex, oneshot};
+
+    const FAST_JOB: u32 = 0;
+    const SLOW_JOB: u32 = 1;
+
+    #[tokio::test]
+    async fn drain_waits_for_jobs_until_the_deadline() {
+        let router = Router::new()
+            .route(FAST_JOB, async || "fast")
+            .route(SLOW_JOB, async || {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                "slow"
+            });
+
+        let (tx, mut rx) = mpsc::unbounded::<JobResult>();
+        let consumer: Consumer = Arc::new(Mutex::new(Box::new(tx.sink_map_err(Into::into))));
+
+        let mut dispatcher = JobDispatcher::new(
+            SchedulerConfig::default(),
+            router,
+            FaasRegistry::new(),
+            FailurePolicies::default(),
+            None,
+            Consumers::start(vec![(consumer, ConsumerConfig::default())]),
+            None,
+        );
+        dispatcher.ready().await;
+        for job_id in [FAST_JOB, FAST_JOB, SLOW_JOB] {
+            dispatcher
+                .dispatch(JobCall::new(job_id, Bytes::new()))
+                .await
+                .unwrap();
+        }
+
+        drain(&mut dispatcher, &[], Duration::from_millis(200))
+            .await
+            .unwrap();
+
+        // The slow job was abandoned at the deadline
+        assert_eq!(dispatcher.in_flight(), 0);
+        for _ in 0..2 {
+            let result = rx.try_recv().unwrap();
+            assert_eq!(result.body().unwrap(), &Bytes::from_static(b"fast"));
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    struct StopCounter(Arc<AtomicUsize>);
+
+    impl BackgroundService for StopCounter {
+        async fn start(&self) -> Result<oneshot::Receiver<Result<(), Error>>, Error> {
+            let (_tx, rx) = oneshot::channel();
+            Ok(rx)
+        }
+
+        async fn stop(&self) -> Result<(), Error> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn aborting_job_still_stops_background_services() {
+        let router = Router::new()
+            .route(FAST_JOB, async || -> &'static str { panic!("job failed") })
+            .route(SLOW_JOB, async || {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                "slow"
+            });
+
+        let (tx, _rx) = mpsc::unbounded::<JobResult>();
+        let consumer: Consumer = Arc::new(Mutex::new(Box::new(tx.sink_map_err(Into::into))));
+
+        // The default failure policy aborts
+        let mut dispatcher = JobDispatcher::new(
+            SchedulerConfig::default(),
+            router,
+            FaasRegistry::new(),
+            FailurePolicies::default(),
+            None,
+            Consumers::start(vec![(consumer, ConsumerConfig::default())]),
+            None,
+        );
+        dispatcher.ready().await;
+        for job_id in [SLOW_JOB, FAST_JOB] {
+            dispatcher
+                .dispatch(JobCall::new(job_id, Bytes::new()))
+                .await
+                .unwrap();
+        }
+
+        let stops = Arc::new(AtomicUsize::new(0));
+        let services = vec![DynBackgroundService::boxed(StopCounter(stops.clone()))];
+        let result = drain(&mut dispatcher, &services, Duration::from_secs(30)).await;
+
+        assert!(result.is_err());
+        // The slow job was abandoned rather than waited for
+        assert_eq!(dispatcher.in_flight(), 0);
+        assert_eq!(stops.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,3 +1,4 @@
+use blueprint_core::JobId;
 use blueprint_core::error::BoxError;
 use thiserror::Error;
 use tokio::task::JoinError;
@@ -156,6 +157,12 @@ pub enum JobCallError {
     /// The job call did not complete (canceled or panicked)
     #[error("Job failed to finish: {0}")]
     JobDidntFinish(JoinError),
+
+    /// The job was disabled through a [`RunnerHandle`], so the call was rejected
+    ///
+    /// [`RunnerHandle`]: crate::handle::RunnerHandle
+    #[error("Job {0} is disabled")]
+    JobDisabled(JobId),
 }
 
 /// Errors that come from [producers]
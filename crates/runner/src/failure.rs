@@ -0,0 +1,261 @@
+//! Failure handling for job calls
+//!
+//! By default, a job call that returns an error (or panics) aborts the entire [`BlueprintRunner`],
+//! taking every other job and background service down with it. A [`FailurePolicy`] changes that
+//! behavior, and can be set globally with [`BlueprintRunnerBuilder::failure_policy()`] or per job
+//! with [`BlueprintRunnerBuilder::job_failure_policy()`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use blueprint_router::Router;
+//! use blueprint_runner::BlueprintRunner;
+//! use blueprint_runner::config::BlueprintEnvironment;
+//! use blueprint_runner::failure::{FailurePolicy, RetryPolicy};
+//! use std::time::Duration;
+//!
+//! const CHEAP_JOB: u32 = 0;
+//! const FLAKY_JOB: u32 = 1;
+//!
+//! let router = Router::new()
+//!     .route(CHEAP_JOB, async || "cheap")
+//!     .route(FLAKY_JOB, async || "flaky");
+//!
+//! let builder = BlueprintRunner::builder((), BlueprintEnvironment::default())
+//!     .router(router)
+//!     // Never let a single bad input take down the operator
+//!     .failure_policy(FailurePolicy::LogAndContinue)
+//!     // ...but give the flaky job a few more chances first
+//!     .job_failure_policy(
+//!         FLAKY_JOB,
+//!         FailurePolicy::Retry(
+//!             RetryPolicy::new(3)
+//!                 .initial_backoff(Duration::from_millis(500))
+//!                 .on_exhausted(FailurePolicy::DeadLetter),
+//!         ),
+//!     );
+//! ```
+//!
+//! [`BlueprintRunner`]: crate::BlueprintRunner
+//! [`BlueprintRunnerBuilder::failure_policy()`]: crate::BlueprintRunnerBuilder::failure_policy
+//! [`BlueprintRunnerBuilder::job_failure_policy()`]: crate::BlueprintRunnerBuilder::job_failure_policy
+
+use blueprint_core::{JobCall, JobId};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// What the runner does when a job call fails
+///
+/// A job call fails when its job returns an error, or when the task running it panics.
+#[derive(Debug, Clone, Default)]
+pub enum FailurePolicy {
+    /// Log the failure and keep processing other job calls
+    LogAndContinue,
+    /// Retry the job call with exponential backoff
+    ///
+    /// Once the retries are exhausted, the [`RetryPolicy::on_exhausted()`] policy applies.
+    Retry(RetryPolicy),
+    /// Hand the failed job call to the dead-letter sink
+    ///
+    /// See [`BlueprintRunnerBuilder::dead_letter_sink()`]. If no sink is configured, the failure is
+    /// logged and the runner continues.
+    ///
+    /// [`BlueprintRunnerBuilder::dead_letter_sink()`]: crate::BlueprintRunnerBuilder::dead_letter_sink
+    DeadLetter,
+    /// Shut down the runner and return the error
+    ///
+    /// NOTE: This is the default
+    #[default]
+    Abort,
+}
+
+impl FailurePolicy {
+    /// Whether this policy ever needs the original [`JobCall`] after it has been dispatched
+    pub(crate) fn retains_call(&self) -> bool {
+        match self {
+            FailurePolicy::Retry(_) | FailurePolicy::DeadLetter => true,
+            FailurePolicy::LogAndContinue | FailurePolicy::Abort => false,
+        }
+    }
+
+    /// Resolve the action to take for a job call that has already been retried `retries` times
+    pub(crate) fn action(&self, retries: u32) -> FailureAction {
+        match self {
+            FailurePolicy::LogAndContinue => FailureAction::Continue,
+            FailurePolicy::Retry(policy) if retries < policy.max_retries => {
+                FailureAction::Retry(policy.backoff(retries))
+            }
+            FailurePolicy::Retry(policy) => policy
+                .on_exhausted
+                .action(retries.saturating_sub(policy.max_retries)),
+            FailurePolicy::DeadLetter => FailureAction::DeadLetter,
+            FailurePolicy::Abort => FailureAction::Abort,
+        }
+    }
+}
+
+/// Retry settings for [`FailurePolicy::Retry`]
+///
+/// The delay before retry `n` (starting at 0) is `initial_backoff * 2^n`, capped at `max_backoff`.
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    on_exhausted: Box<FailurePolicy>,
+}
+
+impl RetryPolicy {
+    /// Retry a failed job call up to `max_retries` times
+    ///
+    /// By default, the backoff starts at 1 second and is capped at 60 seconds, and exhausted job
+    /// calls are [logged and dropped](FailurePolicy::LogAndContinue).
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            on_exhausted: Box::new(FailurePolicy::LogAndContinue),
+        }
+    }
+
+    /// Delay before the first retry
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Upper bound on the delay between retries
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// The policy to apply once all retries have failed
+    pub fn on_exhausted(mut self, policy: FailurePolicy) -> Self {
+        self.on_exhausted = Box::new(policy);
+        self
+    }
+
+    pub(crate) fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    pub(crate) fn backoff(&self, retries: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retries))
+            .min(self.max_backoff)
+    }
+}
+
+/// A job call that failed and was routed to the dead-letter sink
+///
+/// See [`FailurePolicy::DeadLetter`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct DeadLetter {
+    /// The job call that failed
+    pub call: JobCall,
+    /// The error produced by the final attempt
+    pub error: blueprint_core::Error,
+    /// The number of times the call was retried before giving up
+    pub retries: u32,
+}
+
+/// The concrete action for a single failure, resolved from a [`FailurePolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FailureAction {
+    Continue,
+    Retry(Duration),
+    DeadLetter,
+    Abort,
+}
+
+impl FailureAction {
+    /// The `outcome` label used for the failure metrics
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            FailureAction::Continue => "continued",
+            FailureAction::Retry(_) => "retried",
+            FailureAction::DeadLetter => "dead_lettered",
+            FailureAction::Abort => "aborted",
+        }
+    }
+}
+
+/// The global and per-job failure policies of a runner
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FailurePolicies {
+    default: FailurePolicy,
+    per_job: HashMap<JobId, FailurePolicy>,
+}
+
+impl FailurePolicies {
+    pub(crate) fn set_default(&mut self, policy: FailurePolicy) {
+        self.default = policy;
+    }
+
+    pub(crate) fn set_for_job(&mut self, job_id: JobId, policy: FailurePolicy) {
+        self.per_job.insert(job_id, policy);
+    }
+
+    pub(crate) fn for_job(&self, job_id: JobId) -> &FailurePolicy {
+        self.per_job.get(&job_id).unwrap_or(&self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_aborts() {
+        let policies = FailurePolicies::default();
+        assert_eq!(
+            policies.for_job(JobId::from(0u32)).action(0),
+            FailureAction::Abort
+        );
+    }
+
+    #[test]
+    fn per_job_policy_overrides_default() {
+        let mut policies = FailurePolicies::default();
+        policies.set_default(FailurePolicy::LogAndContinue);
+        policies.set_for_job(JobId::from(7u32), FailurePolicy::DeadLetter);
+
+        assert_eq!(
+            policies.for_job(JobId::from(0u32)).action(0),
+            FailureAction::Continue
+        );
+        assert_eq!(
+            policies.for_job(JobId::from(7u32)).action(0),
+            FailureAction::DeadLetter
+        );
+    }
+
+    #[test]
+    fn retry_backs_off_then_falls_through() {
+        let policy = FailurePolicy::Retry(
+            RetryPolicy::new(3)
+                .initial_backoff(Duration::from_millis(100))
+                .max_backoff(Duration::from_millis(300))
+                .on_exhausted(FailurePolicy::Abort),
+        );
+
+        assert_eq!(
+            policy.action(0),
+            FailureAction::Retry(Duration::from_millis(100))
+        );
+        assert_eq!(
+            policy.action(1),
+            FailureAction::Retry(Duration::from_millis(200))
+        );
+        assert_eq!(
+            policy.action(2),
+            FailureAction::Retry(Duration::from_millis(300))
+        );
+        assert_eq!(policy.action(3), FailureAction::Abort);
+        assert!(policy.retains_call());
+    }
+}
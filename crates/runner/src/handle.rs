@@ -0,0 +1,227 @@
+//! Runtime control of a running blueprint
+//!
+//! A [`RunnerHandle`] is taken from the builder with [`BlueprintRunnerBuilder::handle()`], and
+//! stays connected to the runner once it starts. It can be used to change what the runner does
+//! without restarting it, and missing heartbeats in the meantime:
+//!
+//! * [`RunnerHandle::replace_router()`] swaps the active [`Router`]. Job calls that are already
+//!   running finish on the old router, and every call started afterwards uses the new one.
+//! * [`RunnerHandle::disable_job()`] and [`RunnerHandle::enable_job()`] toggle individual jobs.
+//!   Calls for a disabled job, including ones that are already queued, are not run. They instead
+//!   produce a [`JobResult::Err`] of kind [`ErrorKind::Disabled`], which is sent to the consumers
+//!   like any other result.
+//!
+//! The blueprint manager can also enable and disable jobs over the bridge.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use blueprint_router::Router;
+//! use blueprint_runner::BlueprintRunner;
+//! use blueprint_runner::config::BlueprintEnvironment;
+//!
+//! let builder = BlueprintRunner::builder((), BlueprintEnvironment::default()).router(
+//!     Router::new()
+//!         .route(0, async || "Hello, world!")
+//!         .route(1, async || "Goodbye, world!"),
+//! );
+//! let handle = builder.handle();
+//!
+//! // Stop taking calls for job 1, without touching job 0
+//! handle.disable_job(1u32);
+//! assert!(!handle.is_job_enabled(1u32));
+//!
+//! // Later, swap in a router with a fixed job 1, and turn it back on
+//! handle.replace_router(
+//!     Router::new()
+//!         .route(0, async || "Hello, world!")
+//!         .route(1, async || "Goodbye again, world!"),
+//! );
+//! handle.enable_job(1u32);
+//! ```
+//!
+//! [`BlueprintRunnerBuilder::handle()`]: crate::BlueprintRunnerBuilder::handle
+//! [`JobResult::Err`]: blueprint_core::JobResult::Err
+//! [`ErrorKind::Disabled`]: blueprint_core::error::ErrorKind::Disabled
+
+use blueprint_core::JobId;
+use blueprint_manager_bridge::client::Bridge;
+use blueprint_manager_bridge::runner_command;
+use blueprint_router::Router;
+use core::pin::Pin;
+use futures::{Stream, StreamExt, stream};
+use std::collections::HashSet;
+use std::sync::{Arc, PoisonError, RwLock};
+use tokio::sync::mpsc;
+
+/// A handle to control a [`BlueprintRunner`](crate::BlueprintRunner) while it runs
+///
+/// See the [module docs](self) for details.
+#[derive(Debug, Clone)]
+pub struct RunnerHandle {
+    disabled: DisabledJobs,
+    routers: mpsc::UnboundedSender<Router>,
+}
+
+impl RunnerHandle {
+    pub(crate) fn new() -> (Self, mpsc::UnboundedReceiver<Router>) {
+        let (routers, rx) = mpsc::unbounded_channel();
+        let handle = Self {
+            disabled: DisabledJobs::default(),
+            routers,
+        };
+
+        (handle, rx)
+    }
+
+    /// Replace the active [`Router`]
+    ///
+    /// Job calls that are already running finish on the old router. If the runner hasn't started
+    /// yet, the router replaces the one given to the builder once it does.
+    ///
+    /// Returns `false` if the runner has exited, in which case the router is dropped.
+    pub fn replace_router(&self, router: Router) -> bool {
+        self.routers.send(router).is_ok()
+    }
+
+    /// Reject calls for `job_id` until it is [enabled](Self::enable_job()) again
+    ///
+    /// Calls that are already running are not affected.
+    pub fn disable_job<I: Into<JobId>>(&self, job_id: I) {
+        self.set_job_enabled(job_id, false);
+    }
+
+    /// Accept calls for `job_id` again, after it was [disabled](Self::disable_job())
+    pub fn enable_job<I: Into<JobId>>(&self, job_id: I) {
+        self.set_job_enabled(job_id, true);
+    }
+
+    /// Enable or disable `job_id`
+    pub fn set_job_enabled<I: Into<JobId>>(&self, job_id: I, enabled: bool) {
+        self.disabled.set(job_id.into(), !enabled);
+    }
+
+    /// Whether calls for `job_id` are currently accepted
+    ///
+    /// Every job is enabled unless it was explicitly disabled.
+    #[must_use]
+    pub fn is_job_enabled<I: Into<JobId>>(&self, job_id: I) -> bool {
+        !self.disabled.contains(job_id.into())
+    }
+
+    pub(crate) fn disabled_jobs(&self) -> DisabledJobs {
+        self.disabled.clone()
+    }
+}
+
+/// The set of disabled jobs, shared between every [`RunnerHandle`] and the dispatcher
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DisabledJobs(Arc<RwLock<HashSet<JobId>>>);
+
+impl DisabledJobs {
+    pub(crate) fn contains(&self, job_id: JobId) -> bool {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(&job_id)
+    }
+
+    pub(crate) fn set(&self, job_id: JobId, disabled: bool) {
+        let mut jobs = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        let changed = if disabled {
+            jobs.insert(job_id)
+        } else {
+            jobs.remove(&job_id)
+        };
+
+        if changed {
+            blueprint_core::info!(
+                target: "blueprint-runner",
+                %job_id,
+                enabled = !disabled,
+                "Job toggled"
+            );
+        }
+    }
+}
+
+/// A change to apply to a running runner
+pub(crate) enum ControlCommand {
+    /// Swap in a new router
+    ReplaceRouter(Router),
+    /// Enable (`true`) or disable (`false`) a job
+    SetJobEnabled(JobId, bool),
+}
+
+/// Every source of [`ControlCommand`]s for this runner
+///
+/// `layer` is applied to every replacement router, so that they are wrapped the same way as the
+/// router the runner started with. The stream never ends, even if there are no sources.
+pub(crate) async fn commands(
+    routers: mpsc::UnboundedReceiver<Router>,
+    layer: fn(Router) -> Router,
+    bridge: Option<&Bridge>,
+) -> Pin<Box<dyn Stream<Item = ControlCommand> + Send>> {
+    let mut sources: Vec<Pin<Box<dyn Stream<Item = ControlCommand> + Send>>> = vec![
+        stream::pending().boxed(),
+        stream::unfold(routers, move |mut routers| async move {
+            let router = routers.recv().await?;
+            Some((ControlCommand::ReplaceRouter(layer(router)), routers))
+        })
+        .boxed(),
+    ];
+
+    if let Some(bridge) = bridge {
+        match bridge.watch_runner_commands().await {
+            Ok(commands) => sources.push(
+                commands
+                    .filter_map(|command| async move {
+                        match command.ok()?.command? {
+                            runner_command::Command::SetJobEnabled(toggle) => Some(
+                                ControlCommand::SetJobEnabled(toggle.job_id.into(), toggle.enabled),
+                            ),
+                            runner_command::Command::Drain(_) => None,
+                        }
+                    })
+                    .boxed(),
+            ),
+            Err(e) => {
+                blueprint_core::debug!(
+                    target: "blueprint-runner",
+                    "Not watching for job toggles from the manager: {e}"
+                );
+            }
+        }
+    }
+
+    stream::select_all(sources).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_are_enabled_until_disabled() {
+        let (handle, _routers) = RunnerHandle::new();
+        let other = handle.clone();
+
+        assert!(handle.is_job_enabled(3u32));
+        other.disable_job(3u32);
+        assert!(!handle.is_job_enabled(3u32));
+        assert!(handle.disabled_jobs().contains(JobId::from(3u32)));
+        assert!(handle.is_job_enabled(4u32));
+
+        handle.enable_job(3u32);
+        assert!(other.is_job_enabled(3u32));
+    }
+
+    #[test]
+    fn replace_router_fails_once_the_runner_is_gone() {
+        let (handle, routers) = RunnerHandle::new();
+        assert!(handle.replace_router(Router::new()));
+
+        drop(routers);
+        assert!(!handle.replace_router(Router::new()));
+    }
+}
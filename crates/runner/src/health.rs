@@ -0,0 +1,440 @@
+//! Health, readiness and status endpoints
+//!
+//! With a [`HealthConfig`] set through [`BlueprintRunnerBuilder::health_server()`], the runner
+//! mounts the following endpoints on its [metrics server], next to `/metrics`:
+//!
+//! * `GET /healthz`: `200 OK` for as long as the runner is running
+//! * `GET /readyz`: `200 OK` once the runner is accepting job calls, and `503 Service
+//!   Unavailable` while it is registering, starting its background services, draining, or after
+//!   a background service failed
+//! * `GET /status`: a JSON [`StatusReport`] with the runner's internals
+//!
+//! The runner [metrics](crate::metrics) are registered on the metrics server too. The metrics
+//! server must be embedded (not Docker-based), and is started before registration, so that probes
+//! get an answer for the whole lifetime of the runner.
+//!
+//! # Producer lag
+//!
+//! The last processed block is the highest block number seen in the metadata of a job call. To
+//! also report the chain head, and so the lag between the two, give the config a way to fetch it
+//! with [`HealthConfig::chain_head()`]. It is only queried when `/status` is requested.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use blueprint_qos::servers::prometheus::PrometheusServer;
+//! use blueprint_router::Router;
+//! use blueprint_runner::BlueprintRunner;
+//! use blueprint_runner::config::BlueprintEnvironment;
+//! use blueprint_runner::health::HealthConfig;
+//! use std::sync::Arc;
+//!
+//! # async fn latest_block() -> Result<u64, blueprint_core::error::BoxError> { Ok(0) }
+//! # fn example(metrics_server: Arc<PrometheusServer>) {
+//! let builder = BlueprintRunner::builder((), BlueprintEnvironment::default())
+//!     .router(Router::new().route(0, async || "Hello, world!"))
+//!     .metrics_server(metrics_server)
+//!     .health_server(HealthConfig::new().chain_head(latest_block));
+//! # }
+//! ```
+//!
+//! [`BlueprintRunnerBuilder::health_server()`]: crate::BlueprintRunnerBuilder::health_server
+//! [metrics server]: crate::BlueprintRunnerBuilder::metrics_server
+
+use crate::dispatch::JobDispatcher;
+use crate::error::{ConfigError, RunnerError as Error};
+use crate::metrics;
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use blueprint_core::JobId;
+use blueprint_core::error::BoxError;
+use blueprint_qos::servers::ServerManager;
+use blueprint_qos::servers::prometheus::PrometheusServer;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// How long `/status` waits for [`HealthConfig::chain_head()`] before leaving it out
+const CHAIN_HEAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+type ChainHead =
+    Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<u64, BoxError>> + Send>> + Send + Sync>;
+
+/// Configuration for the health server
+///
+/// See the [module docs](self) for details.
+#[must_use]
+#[derive(Clone, Default)]
+pub struct HealthConfig {
+    chain_head: Option<ChainHead>,
+}
+
+impl fmt::Debug for HealthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HealthConfig")
+            .field("chain_head", &self.chain_head.is_some())
+            .finish()
+    }
+}
+
+impl HealthConfig {
+    /// Create a new `HealthConfig`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch the latest block number of the chain, to report the producer lag
+    pub fn chain_head<F, Fut>(mut self, chain_head: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<u64, BoxError>> + Send + 'static,
+    {
+        self.chain_head = Some(Arc::new(move || Box::pin(chain_head())));
+        self
+    }
+}
+
+/// The state of the runner, as reported by `/status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusReport {
+    /// Whether `/readyz` currently succeeds
+    pub ready: bool,
+    /// Whether the runner is draining, and no longer accepting job calls
+    pub draining: bool,
+    /// Where the runner is in the registration procedure
+    pub registration: RegistrationState,
+    /// How far behind the chain the producers are
+    pub producer: ProducerStatus,
+    /// Accepted job calls that haven't finished, including queued ones, by job ID
+    ///
+    /// Job IDs that fit in a `u64` are written in decimal, and others in hex.
+    pub in_flight: BTreeMap<String, usize>,
+    /// Job results waiting for an [isolated](crate::consumer::ConsumerConfig::isolated())
+    /// consumer, by consumer name
+    pub consumer_backlog: BTreeMap<String, usize>,
+    /// Every [`BackgroundService`](crate::BackgroundService), in the order they were added
+    pub background_services: Vec<BackgroundServiceStatus>,
+}
+
+/// Where the runner is in the registration procedure
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationState {
+    /// The runner hasn't finished registering yet
+    #[default]
+    Pending,
+    /// The protocol doesn't require registration
+    NotRequired,
+    /// The operator was registered
+    Registered,
+    /// Registration was skipped, because this is a dry run
+    Skipped,
+}
+
+/// The progress of the producers, see the [module docs](self#producer-lag)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProducerStatus {
+    /// The highest block number seen in a job call
+    pub last_block: Option<u64>,
+    /// The latest block of the chain, if [`HealthConfig::chain_head()`] is set
+    pub chain_head: Option<u64>,
+    /// The number of blocks between the two
+    pub lag: Option<u64>,
+}
+
+/// The liveness of a [`BackgroundService`](crate::BackgroundService)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackgroundServiceStatus {
+    /// The name of the service
+    pub name: String,
+    /// Whether it is still running
+    pub state: ServiceState,
+    /// The error it failed with, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The lifecycle of a [`BackgroundService`](crate::BackgroundService)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceState {
+    /// The service hasn't been started yet
+    Starting,
+    /// The service is running
+    Running,
+    /// The service finished without an error
+    Finished,
+    /// The service failed
+    Failed,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    accepting: bool,
+    draining: bool,
+    registration: RegistrationState,
+    last_block: Option<u64>,
+    in_flight: BTreeMap<String, usize>,
+    consumer_backlog: BTreeMap<String, usize>,
+    background_services: Vec<BackgroundServiceStatus>,
+}
+
+impl Inner {
+    fn ready(&self) -> bool {
+        self.accepting
+            && !self.draining
+            && self.registration != RegistrationState::Pending
+            && self.background_services.iter().all(|service| {
+                matches!(
+                    service.state,
+                    ServiceState::Running | ServiceState::Finished
+                )
+            })
+    }
+}
+
+/// The live state of a runner, shared between the run loop and the health server
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RunnerStatus(Arc<Mutex<Inner>>);
+
+impl RunnerStatus {
+    fn update<R>(&self, f: impl FnOnce(&mut Inner) -> R) -> R {
+        f(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    pub(crate) fn registration(&self, state: RegistrationState) {
+        self.update(|inner| inner.registration = state);
+    }
+
+    /// Register the background services, before they are started
+    pub(crate) fn background_services<'a>(&self, names: impl IntoIterator<Item = &'a str>) {
+        self.update(|inner| {
+            inner.background_services = names
+                .into_iter()
+                .map(|name| BackgroundServiceStatus {
+                    name: name.to_owned(),
+                    state: ServiceState::Starting,
+                    error: None,
+                })
+                .collect();
+        });
+    }
+
+    pub(crate) fn service_running(&self, index: usize) {
+        self.set_service_state(index, ServiceState::Running, None);
+    }
+
+    pub(crate) fn service_ended(&self, index: usize, result: &Result<(), Error>) {
+        match result {
+            Ok(()) => self.set_service_state(index, ServiceState::Finished, None),
+            Err(e) => self.set_service_state(index, ServiceState::Failed, Some(e.to_string())),
+        }
+    }
+
+    fn set_service_state(&self, index: usize, state: ServiceState, error: Option<String>) {
+        self.update(|inner| {
+            if let Some(service) = inner.background_services.get_mut(index) {
+                service.state = state;
+                service.error = error;
+            }
+        });
+    }
+
+    pub(crate) fn accepting(&self) {
+        self.update(|inner| inner.accepting = true);
+    }
+
+    pub(crate) fn draining(&self) {
+        self.update(|inner| inner.draining = true);
+    }
+
+    /// Record a job call from `block_number`
+    pub(crate) fn received(&self, block_number: Option<u64>) {
+        let Some(block_number) = block_number else {
+            return;
+        };
+
+        self.update(|inner| {
+            inner.last_block = inner.last_block.max(Some(block_number));
+        });
+    }
+
+    /// Take a snapshot of the in-flight job calls and consumer backlogs
+    pub(crate) fn observe(&self, dispatcher: &JobDispatcher) {
+        let in_flight = dispatcher
+            .in_flight_per_job()
+            .into_iter()
+            .map(|(job_id, count)| (job_label(job_id), count))
+            .collect();
+        let consumer_backlog = dispatcher
+            .consumer_backlog()
+            .map(|(name, backlog)| (name.to_owned(), backlog))
+            .collect();
+
+        self.update(|inner| {
+            inner.in_flight = in_flight;
+            inner.consumer_backlog = consumer_backlog;
+        });
+    }
+
+    fn ready(&self) -> bool {
+        self.update(|inner| inner.ready())
+    }
+
+    fn report(&self, chain_head: Option<u64>) -> StatusReport {
+        self.update(|inner| StatusReport {
+            ready: inner.ready(),
+            draining: inner.draining,
+            registration: inner.registration,
+            producer: ProducerStatus {
+                last_block: inner.last_block,
+                chain_head,
+                lag: chain_head
+                    .zip(inner.last_block)
+                    .map(|(head, last)| head.saturating_sub(last)),
+            },
+            in_flight: inner.in_flight.clone(),
+            consumer_backlog: inner.consumer_backlog.clone(),
+            background_services: inner.background_services.clone(),
+        })
+    }
+}
+
+fn job_label(job_id: JobId) -> String {
+    match job_id.0 {
+        [0, 0, 0, id] => id.to_string(),
+        _ => job_id.to_string(),
+    }
+}
+
+#[derive(Clone)]
+struct ServerState {
+    status: RunnerStatus,
+    chain_head: Option<ChainHead>,
+}
+
+/// Mount the health endpoints for `status` on `server`, and start it
+///
+/// # Errors
+///
+/// If there is no metrics server, or it can't serve extra routes or fails to start.
+pub(crate) async fn serve(
+    config: HealthConfig,
+    status: RunnerStatus,
+    server: Option<&PrometheusServer>,
+) -> Result<(), Error> {
+    let Some(server) = server else {
+        return Err(ConfigError::InvalidArgument(
+            "the health endpoints are served by the metrics server, but none is set".into(),
+        )
+        .into());
+    };
+
+    let HealthConfig { chain_head } = config;
+    let routes = axum::Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/status", get(status_report))
+        .with_state(ServerState { status, chain_head });
+
+    if let Some(registry) = server.registry() {
+        metrics::register(&registry);
+    }
+    server.mount(routes)?;
+    server.start(None, None).await?;
+    blueprint_core::info!(
+        target: "blueprint-runner",
+        url = %server.url(),
+        "Serving health endpoints"
+    );
+
+    Ok(())
+}
+
+async fn healthz() -> &'static str {
+    "OK"
+}
+
+async fn readyz(State(state): State<ServerState>) -> Response {
+    if state.status.ready() {
+        (StatusCode::OK, "ready").into_response()
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not ready").into_response()
+    }
+}
+
+async fn status_report(State(state): State<ServerState>) -> Json<StatusReport> {
+    let chain_head = match &state.chain_head {
+        Some(chain_head) => match tokio::time::timeout(CHAIN_HEAD_TIMEOUT, chain_head()).await {
+            Ok(Ok(head)) => Some(head),
+            Ok(Err(e)) => {
+                blueprint_core::warn!(target: "blueprint-runner", "Failed to fetch chain head: {e}");
+                None
+            }
+            Err(_) => {
+                blueprint_core::warn!(target: "blueprint-runner", "Timed out fetching chain head");
+                None
+            }
+        },
+        None => None,
+    };
+
+    Json(state.status.report(chain_head))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ready_once_registered_accepting_and_services_are_up() {
+        let status = RunnerStatus::default();
+        status.background_services(["metrics", "heartbeat"]);
+        assert!(!status.ready());
+
+        status.registration(RegistrationState::Registered);
+        status.accepting();
+        assert!(!status.ready(), "background services are still starting");
+
+        status.service_running(0);
+        status.service_ended(1, &Ok(()));
+        assert!(status.ready());
+
+        status.service_ended(0, &Err(Error::BackgroundService("boom".into())));
+        let report = status.report(None);
+        assert!(!report.ready);
+        assert_eq!(report.background_services[0].state, ServiceState::Failed);
+        assert!(report.background_services[0].error.is_some());
+    }
+
+    #[test]
+    fn draining_runners_are_not_ready() {
+        let status = RunnerStatus::default();
+        status.registration(RegistrationState::NotRequired);
+        status.accepting();
+        assert!(status.ready());
+
+        status.draining();
+        assert!(!status.ready());
+    }
+
+    #[test]
+    fn producer_lag_is_relative_to_the_highest_block() {
+        let status = RunnerStatus::default();
+        status.received(Some(10));
+        status.received(None);
+        status.received(Some(7));
+
+        let producer = status.report(Some(15)).producer;
+        assert_eq!(producer.last_block, Some(10));
+        assert_eq!(producer.lag, Some(5));
+        assert_eq!(status.report(None).producer.lag, None);
+    }
+}
@@ -0,0 +1,548 @@
+//! Durable job journal
+//!
+//! Job calls normally only live in memory, so a crash between a producer yielding a call and a
+//! consumer submitting its result either loses the job, or (if the producer yields it again after a
+//! restart) runs it twice. The journal is a write-ahead log that records every state a job call goes
+//! through:
+//!
+//! 1. [`Received`](JobState::Received) from a producer
+//! 2. [`Started`](JobState::Started) on the router or a `FaaS` executor
+//! 3. [`Completed`](JobState::Completed), with the results it produced
+//! 4. [`Submitted`](JobState::Submitted) to every consumer (or [`Failed`](JobState::Failed))
+//!
+//! When the runner starts with an existing journal, it resumes every job call that never
+//! completed, re-submits the results of every call that completed but was never submitted, and
+//! skips any call that a producer yields again once it is already in the journal. Calls that
+//! [`Failed`](JobState::Failed) are the exception, and run again if a producer yields them again.
+//!
+//! Records that can't be read, such as a torn final write from a crash, are skipped with a
+//! warning.
+//!
+//! Job calls are keyed by their `(service_id, call_id)`, read from their metadata (see
+//! [`JournalKey::from_metadata()`]). Calls without both are not journaled.
+//!
+//! NOTE: A crash after a consumer has submitted a result, but before the submission is recorded,
+//! will cause that result to be submitted again on restart.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use blueprint_router::Router;
+//! use blueprint_runner::BlueprintRunner;
+//! use blueprint_runner::config::BlueprintEnvironment;
+//! use blueprint_runner::journal::JournalConfig;
+//!
+//! let env = BlueprintEnvironment::default();
+//! let journal = JournalConfig::new(env.data_dir.join("jobs.journal"));
+//!
+//! let builder = BlueprintRunner::builder((), env)
+//!     .router(Router::new().route(0, async || "Hello, world!"))
+//!     .journal(journal);
+//! ```
+
+use crate::stored::{StoredCall, StoredResult};
+use crate::{CALL_ID_METADATA_KEYS, SERVICE_ID_METADATA_KEYS, read_metadata_u64};
+use blueprint_core::metadata::{MetadataMap, MetadataValue};
+use blueprint_core::{JobCall, JobId, JobResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::path::PathBuf;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// Configuration for the job [`Journal`]
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct JournalConfig {
+    path: PathBuf,
+    sync: bool,
+    retain_finished: usize,
+}
+
+impl JournalConfig {
+    /// Journal job calls to the file at `path`
+    ///
+    /// The file is created if it does not exist. By default, every write is synced to disk, and the
+    /// keys of the last 10,000 finished job calls are kept to detect duplicates.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            sync: true,
+            retain_finished: 10_000,
+        }
+    }
+
+    /// Whether to sync every write to disk before continuing
+    ///
+    /// Disabling this is faster, but an OS crash or power loss may lose the latest records.
+    pub fn sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// The number of finished job calls to remember across restarts
+    ///
+    /// A producer yielding a job call that has already been finished will only be detected if the
+    /// call is still remembered.
+    pub fn retain_finished(mut self, retain_finished: usize) -> Self {
+        self.retain_finished = retain_finished;
+        self
+    }
+}
+
+/// The key of a journaled job call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct JournalKey {
+    /// The service the job call was made to
+    pub service_id: u64,
+    /// The ID of the call within the service
+    pub call_id: u64,
+}
+
+impl JournalKey {
+    /// Read the key of a job call from its metadata
+    ///
+    /// The service ID is read from `tangle.service_id` (or `X-TANGLE-SERVICE-ID`), and the call ID
+    /// from `tangle.call_id` (or `X-TANGLE-CALL-ID`).
+    pub fn from_metadata(metadata: &MetadataMap<MetadataValue>) -> Option<Self> {
+        Some(Self {
+            service_id: read_metadata_u64(metadata, SERVICE_ID_METADATA_KEYS)?,
+            call_id: read_metadata_u64(metadata, CALL_ID_METADATA_KEYS)?,
+        })
+    }
+}
+
+/// The state of a journaled job call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// The job call was received from a producer, but has not started
+    Received,
+    /// The job call was started
+    Started,
+    /// The job call finished, and its results have not yet been submitted
+    Completed,
+    /// The job call's results were submitted to every consumer, or it had none
+    Submitted,
+    /// The job call failed, and will not be retried unless a producer yields it again
+    Failed,
+}
+
+impl JobState {
+    fn is_finished(self) -> bool {
+        matches!(self, JobState::Submitted | JobState::Failed)
+    }
+}
+
+/// Work left over from a previous run, see [`Journal::recover()`]
+pub(crate) enum Recovery {
+    /// The job call never completed, and needs to be run (again)
+    Run(JobCall),
+    /// The job call completed, but its results were never submitted
+    Submit(JournalKey, Option<JobId>, Vec<JobResult>),
+}
+
+/// A write-ahead log of job call states
+///
+/// See the [module docs](self) for details.
+pub struct Journal {
+    config: JournalConfig,
+    file: File,
+    entries: BTreeMap<JournalKey, Entry>,
+    /// Finished job calls, oldest first
+    finished: VecDeque<JournalKey>,
+}
+
+struct Entry {
+    state: JobState,
+    call: Option<StoredCall>,
+    results: Option<Vec<StoredResult>>,
+}
+
+impl Journal {
+    /// Open the journal, replaying any existing records
+    ///
+    /// The journal file is compacted on open, dropping the records of finished job calls that are
+    /// no longer retained.
+    ///
+    /// # Errors
+    ///
+    /// If the journal file cannot be read or rewritten.
+    pub async fn open(config: JournalConfig) -> io::Result<Self> {
+        let mut entries = BTreeMap::new();
+        let mut finished = VecDeque::new();
+
+        match File::open(&config.path).await {
+            Ok(file) => {
+                let mut lines = BufReader::new(file).lines();
+                let mut line_number = 0usize;
+                while let Some(line) = lines.next_line().await? {
+                    line_number += 1;
+                    match serde_json::from_str(&line) {
+                        Ok(record) => apply(&mut entries, &mut finished, record),
+                        // A torn write from a crash, or a corrupted record. The records around it
+                        // are intact, so only the job call it belongs to may be off by a state.
+                        Err(e) => blueprint_core::warn!(
+                            target: "blueprint-runner",
+                            path = %config.path.display(),
+                            line = line_number,
+                            error = %e,
+                            "Skipping unreadable journal record"
+                        ),
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        while finished.len() > config.retain_finished {
+            if let Some(key) = finished.pop_front() {
+                entries.remove(&key);
+            }
+        }
+
+        let file = compact(&config, &entries, &finished).await?;
+        Ok(Self {
+            config,
+            file,
+            entries,
+            finished,
+        })
+    }
+
+    /// The state of the job call with `key`, if it is journaled
+    pub fn state(&self, key: JournalKey) -> Option<JobState> {
+        self.entries.get(&key).map(|entry| entry.state)
+    }
+
+    /// Take the work left over from a previous run
+    pub(crate) fn recover(&self) -> Vec<Recovery> {
+        self.entries
+            .iter()
+            .filter_map(|(key, entry)| match entry.state {
+                JobState::Received | JobState::Started => {
+                    entry.call.clone().map(|call| Recovery::Run(call.into()))
+                }
+                JobState::Completed => entry.results.clone().map(|results| {
+                    let job_id = entry.call.as_ref().map(StoredCall::job_id);
+                    Recovery::Submit(*key, job_id, results.into_iter().map(Into::into).collect())
+                }),
+                JobState::Submitted | JobState::Failed => None,
+            })
+            .collect()
+    }
+
+    pub(crate) async fn received(&mut self, key: JournalKey, call: &JobCall) -> io::Result<()> {
+        self.write(Record {
+            key,
+            state: JobState::Received,
+            call: Some(StoredCall::from(call)),
+            results: None,
+        })
+        .await
+    }
+
+    pub(crate) async fn started(&mut self, key: JournalKey) -> io::Result<()> {
+        self.write(Record::new(key, JobState::Started)).await
+    }
+
+    pub(crate) async fn completed(
+        &mut self,
+        key: JournalKey,
+        results: &[JobResult],
+    ) -> io::Result<()> {
+        self.write(Record {
+            key,
+            state: JobState::Completed,
+            call: None,
+            results: Some(results.iter().map(StoredResult::from).collect()),
+        })
+        .await
+    }
+
+    pub(crate) async fn submitted(&mut self, key: JournalKey) -> io::Result<()> {
+        self.write(Record::new(key, JobState::Submitted)).await
+    }
+
+    pub(crate) async fn failed(&mut self, key: JournalKey) -> io::Result<()> {
+        self.write(Record::new(key, JobState::Failed)).await
+    }
+
+    async fn write(&mut self, record: Record) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+
+        self.file.write_all(&line).await?;
+        if self.config.sync {
+            self.file.sync_data().await?;
+        }
+
+        apply(&mut self.entries, &mut self.finished, record);
+        if self.finished.len() > self.config.retain_finished {
+            if let Some(key) = self.finished.pop_front() {
+                self.entries.remove(&key);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn apply(
+    entries: &mut BTreeMap<JournalKey, Entry>,
+    finished: &mut VecDeque<JournalKey>,
+    record: Record,
+) {
+    let Record {
+        key,
+        state,
+        call,
+        results,
+    } = record;
+
+    let entry = entries.entry(key).or_insert(Entry {
+        state,
+        call: None,
+        results: None,
+    });
+    if entry.state.is_finished() && !state.is_finished() {
+        // A failed call that a producer yielded again
+        finished.retain(|finished| *finished != key);
+    }
+    entry.state = state;
+    if call.is_some() {
+        entry.call = call;
+    }
+    if results.is_some() {
+        entry.results = results;
+    }
+
+    if state.is_finished() {
+        // Finished calls are only kept around for deduplication
+        entry.call = None;
+        entry.results = None;
+        finished.push_back(key);
+    }
+}
+
+/// Rewrite the journal with only the live entries, returning the file opened for appending
+async fn compact(
+    config: &JournalConfig,
+    entries: &BTreeMap<JournalKey, Entry>,
+    finished: &VecDeque<JournalKey>,
+) -> io::Result<File> {
+    let mut contents = Vec::new();
+    let mut push = |record: &Record| -> io::Result<()> {
+        serde_json::to_writer(&mut contents, record)?;
+        contents.push(b'\n');
+        Ok(())
+    };
+
+    // Finished calls first, in the order they finished, so that retention is preserved
+    for key in finished {
+        if let Some(entry) = entries.get(key) {
+            push(&Record::new(*key, entry.state))?;
+        }
+    }
+
+    for (key, entry) in entries
+        .iter()
+        .filter(|(_, entry)| !entry.state.is_finished())
+    {
+        push(&Record {
+            key: *key,
+            state: entry.state,
+            call: entry.call.clone(),
+            results: entry.results.clone(),
+        })?;
+    }
+
+    if let Some(parent) = config.path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    let tmp_path = config.path.with_extension("compact");
+    let mut tmp = File::create(&tmp_path).await?;
+    tmp.write_all(&contents).await?;
+    tmp.sync_all().await?;
+    drop(tmp);
+    fs::rename(&tmp_path, &config.path).await?;
+
+    OpenOptions::new().append(true).open(&config.path).await
+}
+
+/// A single line of the journal
+#[derive(Serialize, Deserialize)]
+struct Record {
+    key: JournalKey,
+    state: JobState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    call: Option<StoredCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    results: Option<Vec<StoredResult>>,
+}
+
+impl Record {
+    fn new(key: JournalKey, state: JobState) -> Self {
+        Self {
+            key,
+            state,
+            call: None,
+            results: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blueprint_core::Bytes;
+
+    fn tangle_call(call_id: u64) -> JobCall {
+        let mut call = JobCall::new(7u32, Bytes::from_static(b"input"));
+        call.metadata_mut().insert("tangle.service_id", 1u64);
+        call.metadata_mut().insert("tangle.call_id", call_id);
+        call
+    }
+
+    #[tokio::test]
+    async fn recovers_incomplete_calls_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = JournalConfig::new(dir.path().join("jobs.journal"));
+
+        let key = |call_id| JournalKey {
+            service_id: 1,
+            call_id,
+        };
+
+        let mut journal = Journal::open(config.clone()).await.unwrap();
+        for call_id in 0..4 {
+            journal
+                .received(key(call_id), &tangle_call(call_id))
+                .await
+                .unwrap();
+        }
+        // 0: never started, 1: started, 2: completed, 3: submitted
+        journal.started(key(1)).await.unwrap();
+        journal.started(key(2)).await.unwrap();
+        journal.started(key(3)).await.unwrap();
+        let results = [JobResult::new(Bytes::from_static(b"output"))];
+        journal.completed(key(2), &results).await.unwrap();
+        journal.completed(key(3), &results).await.unwrap();
+        journal.submitted(key(3)).await.unwrap();
+        drop(journal);
+
+        let journal = Journal::open(config).await.unwrap();
+        assert_eq!(journal.state(key(3)), Some(JobState::Submitted));
+
+        let mut run = Vec::new();
+        let mut submit = Vec::new();
+        for recovery in journal.recover() {
+            match recovery {
+                Recovery::Run(call) => {
+                    assert_eq!(call.body(), &Bytes::from_static(b"input"));
+                    run.push(JournalKey::from_metadata(call.metadata()).unwrap());
+                }
+                Recovery::Submit(key, job_id, results) => {
+                    assert_eq!(job_id, Some(JobId::from(7u32)));
+                    assert_eq!(results[0].body().unwrap(), &Bytes::from_static(b"output"));
+                    submit.push(key);
+                }
+            }
+        }
+
+        assert_eq!(run, [key(0), key(1)]);
+        assert_eq!(submit, [key(2)]);
+    }
+
+    #[tokio::test]
+    async fn forgets_finished_calls_beyond_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = JournalConfig::new(dir.path().join("jobs.journal")).retain_finished(2);
+
+        let mut journal = Journal::open(config.clone()).await.unwrap();
+        for call_id in 0..3 {
+            let key = JournalKey {
+                service_id: 1,
+                call_id,
+            };
+            journal.received(key, &tangle_call(call_id)).await.unwrap();
+            journal.failed(key).await.unwrap();
+        }
+        drop(journal);
+
+        let journal = Journal::open(config).await.unwrap();
+        let state = |call_id| {
+            journal.state(JournalKey {
+                service_id: 1,
+                call_id,
+            })
+        };
+        assert_eq!(state(0), None);
+        assert_eq!(state(1), Some(JobState::Failed));
+        assert_eq!(state(2), Some(JobState::Failed));
+    }
+
+    #[tokio::test]
+    async fn skips_corrupted_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = JournalConfig::new(dir.path().join("jobs.journal"));
+
+        let key = |call_id| JournalKey {
+            service_id: 1,
+            call_id,
+        };
+
+        let mut journal = Journal::open(config.clone()).await.unwrap();
+        journal.received(key(0), &tangle_call(0)).await.unwrap();
+        drop(journal);
+
+        let mut contents = tokio::fs::read(&config.path).await.unwrap();
+        contents.extend_from_slice(b"{\"key\":garbage}\n");
+        tokio::fs::write(&config.path, contents).await.unwrap();
+
+        let mut journal = Journal::open(config.clone()).await.unwrap();
+        journal.received(key(1), &tangle_call(1)).await.unwrap();
+        drop(journal);
+
+        let mut contents = tokio::fs::read(&config.path).await.unwrap();
+        contents.extend_from_slice(b"{\"key\":{\"service_id\":1,");
+        tokio::fs::write(&config.path, contents).await.unwrap();
+
+        let journal = Journal::open(config).await.unwrap();
+        assert_eq!(journal.state(key(0)), Some(JobState::Received));
+        assert_eq!(journal.state(key(1)), Some(JobState::Received));
+    }
+
+    #[tokio::test]
+    async fn failed_calls_can_be_received_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = JournalConfig::new(dir.path().join("jobs.journal")).retain_finished(1);
+
+        let key = |call_id| JournalKey {
+            service_id: 1,
+            call_id,
+        };
+
+        let mut journal = Journal::open(config.clone()).await.unwrap();
+        journal.received(key(0), &tangle_call(0)).await.unwrap();
+        journal.failed(key(0)).await.unwrap();
+        journal.received(key(0), &tangle_call(0)).await.unwrap();
+        assert_eq!(journal.state(key(0)), Some(JobState::Received));
+
+        // The call is no longer finished, so it isn't forgotten to make room for others
+        journal.received(key(1), &tangle_call(1)).await.unwrap();
+        journal.failed(key(1)).await.unwrap();
+        journal.received(key(2), &tangle_call(2)).await.unwrap();
+        journal.failed(key(2)).await.unwrap();
+        drop(journal);
+
+        let journal = Journal::open(config).await.unwrap();
+        assert_eq!(journal.state(key(0)), Some(JobState::Received));
+        assert_eq!(journal.state(key(1)), None);
+        assert_eq!(journal.state(key(2)), Some(JobState::Failed));
+        assert!(matches!(&journal.recover()[..], [Recovery::Run(_)]));
+    }
+}
@@ -8,38 +8,56 @@
 extern crate alloc;
 
 pub mod config;
+pub mod consumer;
+mod dispatch;
+pub mod drain;
 pub mod error;
 pub mod faas;
+pub mod failure;
+pub mod handle;
+pub mod health;
+pub mod journal;
+pub mod metrics;
 pub mod metrics_server;
+pub mod record;
+pub mod scheduler;
+mod stored;
 
 #[cfg(feature = "symbiotic")]
 mod symbiotic;
 #[cfg(feature = "tangle")]
 pub mod tangle;
 
+use crate::error::ProducerError;
 use crate::error::RunnerError;
-use crate::error::{JobCallError, ProducerError};
 use blueprint_core::error::BoxError;
 use blueprint_core::metadata::{MetadataMap, MetadataValue};
-use blueprint_core::{JobCall, JobResult};
+use blueprint_core::{JobCall, JobId, JobResult};
 use blueprint_qos::heartbeat::HeartbeatConsumer;
 use blueprint_router::Router;
 use config::BlueprintEnvironment;
+use consumer::{ConsumerConfig, Consumers};
 use core::convert::TryFrom;
-use core::future::{self, poll_fn};
+use core::future;
 use core::pin::Pin;
+use dispatch::JobDispatcher;
+use drain::DrainConfig;
 use error::RunnerError as Error;
+use failure::{DeadLetter, FailurePolicies, FailurePolicy};
 use futures::{Future, Sink};
 use futures_core::Stream;
-use futures_util::stream::FuturesUnordered;
-use futures_util::{SinkExt, StreamExt, TryStreamExt, stream};
+use futures_util::{SinkExt, StreamExt, TryStreamExt};
+use handle::RunnerHandle;
+use health::{HealthConfig, RegistrationState, RunnerStatus};
+use journal::{Journal, JournalConfig};
+use scheduler::SchedulerConfig;
+use std::borrow::Cow;
 use std::io;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::fs;
-use tokio::sync::{Mutex, oneshot};
+use tokio::sync::{Mutex, mpsc, oneshot};
 use tokio::time::sleep;
-use tower::Service;
 
 /// Configuration for the blueprint registration procedure
 #[dynosaur::dynosaur(DynBlueprintConfig)]
@@ -132,6 +150,16 @@ pub trait BackgroundService: Send + Sync {
     fn start(
         &self,
     ) -> impl Future<Output = Result<oneshot::Receiver<Result<(), Error>>, Error>> + Send;
+
+    /// Stop this background service
+    ///
+    /// This is called when the runner [drains](crate::drain), after all in-flight jobs have
+    /// finished or been abandoned.
+    ///
+    /// By default, this does nothing.
+    fn stop(&self) -> impl Future<Output = Result<(), Error>> + Send {
+        async { Ok(()) }
+    }
 }
 
 // SAFETY: DynBackgroundService wraps a dynosaur-erased trait object whose trait bound
@@ -143,6 +171,8 @@ unsafe impl Sync for DynBackgroundService<'_> {}
 type Producer =
     Arc<Mutex<Box<dyn Stream<Item = Result<JobCall, BoxError>> + Send + Unpin + 'static>>>;
 type Consumer = Arc<Mutex<Box<dyn Sink<JobResult, Error = BoxError> + Send + Unpin + 'static>>>;
+type DeadLetterSink =
+    Arc<Mutex<Box<dyn Sink<DeadLetter, Error = BoxError> + Send + Unpin + 'static>>>;
 
 /// A builder for a [`BlueprintRunner`]
 ///
@@ -151,11 +181,21 @@ pub struct BlueprintRunnerBuilder<F> {
     config: Box<DynBlueprintConfig<'static>>,
     env: BlueprintEnvironment,
     producers: Vec<Producer>,
-    consumers: Vec<Consumer>,
+    consumers: Vec<(Consumer, ConsumerConfig)>,
     router: Option<Router>,
     background_services: Vec<Box<DynBackgroundService<'static>>>,
+    background_service_names: Vec<Cow<'static, str>>,
     shutdown_handler: F,
     faas_registry: faas::FaasRegistry,
+    failure_policies: FailurePolicies,
+    dead_letter_sink: Option<DeadLetterSink>,
+    drain: Option<DrainConfig>,
+    scheduler: SchedulerConfig,
+    journal: Option<JournalConfig>,
+    health: Option<HealthConfig>,
+    metrics_server: Option<Arc<blueprint_qos::servers::prometheus::PrometheusServer>>,
+    handle: RunnerHandle,
+    router_updates: mpsc::UnboundedReceiver<Router>,
     /// Whether `.tee()` was called explicitly, suppressing auto-detection.
     #[cfg(feature = "tee")]
     tee_explicitly_configured: bool,
@@ -222,20 +262,132 @@ where
 
     /// Append a [consumer] to the list
     ///
+    /// The consumer receives every job result, and any error it returns stops the runner. Use
+    /// [`Self::consumer_with()`] to change either.
+    ///
     /// [consumer]: https://docs.rs/blueprint_sdk/latest/blueprint_sdk/consumers/index.html
     #[must_use]
     pub fn consumer<E>(
+        self,
+        consumer: impl Sink<JobResult, Error = E> + Send + Unpin + 'static,
+    ) -> Self
+    where
+        E: Into<BoxError> + 'static,
+    {
+        self.consumer_with(consumer, ConsumerConfig::default())
+    }
+
+    /// Append a [consumer] to the list, with selectors and failure isolation
+    ///
+    /// See the [consumer module](crate::consumer) for more details.
+    ///
+    /// [consumer]: https://docs.rs/blueprint_sdk/latest/blueprint_sdk/consumers/index.html
+    #[must_use]
+    pub fn consumer_with<E>(
         mut self,
         consumer: impl Sink<JobResult, Error = E> + Send + Unpin + 'static,
+        config: ConsumerConfig,
     ) -> Self
     where
         E: Into<BoxError> + 'static,
     {
         let consumer: Consumer = Arc::new(Mutex::new(Box::new(consumer.sink_map_err(Into::into))));
-        self.consumers.push(consumer);
+        self.consumers.push((consumer, config));
+        self
+    }
+
+    /// Set the default [`FailurePolicy`] for job calls
+    ///
+    /// This applies to every job without a policy set through [`Self::job_failure_policy()`].
+    /// By default, any failing job call will [abort](FailurePolicy::Abort) the runner.
+    ///
+    /// See the [failure module](crate::failure) for more details.
+    #[must_use]
+    pub fn failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policies.set_default(policy);
+        self
+    }
+
+    /// Set the [`FailurePolicy`] for a specific job
+    ///
+    /// This overrides the default set with [`Self::failure_policy()`].
+    #[must_use]
+    pub fn job_failure_policy<I: Into<JobId>>(mut self, job_id: I, policy: FailurePolicy) -> Self {
+        self.failure_policies.set_for_job(job_id.into(), policy);
+        self
+    }
+
+    /// Set the sink that receives [dead-lettered](FailurePolicy::DeadLetter) job calls
+    ///
+    /// A dead-letter sink can be anything that accepts [`DeadLetter`]s, such as a channel, a file
+    /// writer, or a queue for later inspection. Errors from the sink are logged, but never stop the
+    /// runner.
+    #[must_use]
+    pub fn dead_letter_sink<E>(
+        mut self,
+        sink: impl Sink<DeadLetter, Error = E> + Send + Unpin + 'static,
+    ) -> Self
+    where
+        E: Into<BoxError> + 'static,
+    {
+        let sink: DeadLetterSink = Arc::new(Mutex::new(Box::new(sink.sink_map_err(Into::into))));
+        self.dead_letter_sink = Some(sink);
+        self
+    }
+
+    /// Drain in-flight jobs on `SIGTERM` and when the shutdown handler fires
+    ///
+    /// Drain requests from the blueprint manager are always honored, using the default
+    /// [`DrainConfig`] if none is set here.
+    ///
+    /// See the [drain module](crate::drain) for more details.
+    #[must_use]
+    pub fn drain(mut self, config: DrainConfig) -> Self {
+        self.drain = Some(config);
         self
     }
 
+    /// Set the concurrency limits and priority classes for job calls
+    ///
+    /// By default, up to [`DEFAULT_MAX_CONCURRENCY`](scheduler::DEFAULT_MAX_CONCURRENCY) job calls
+    /// run at once, and job calls are dispatched in the order they are received.
+    ///
+    /// See the [scheduler module](crate::scheduler) for more details.
+    #[must_use]
+    pub fn scheduler(mut self, config: SchedulerConfig) -> Self {
+        self.scheduler = config;
+        self
+    }
+
+    /// Journal every job call to disk, so that none are lost or submitted twice across restarts
+    ///
+    /// See the [journal module](crate::journal) for more details.
+    #[must_use]
+    pub fn journal(mut self, config: JournalConfig) -> Self {
+        self.journal = Some(config);
+        self
+    }
+
+    /// Serve `/healthz`, `/readyz` and `/status` endpoints for probes and load balancers
+    ///
+    /// The endpoints are mounted on the [metrics server](Self::metrics_server), which must be set
+    /// as well. See the [health module](crate::health) for more details.
+    #[must_use]
+    pub fn health_server(mut self, config: HealthConfig) -> Self {
+        self.health = Some(config);
+        self
+    }
+
+    /// Get a [`RunnerHandle`] to replace the router and toggle jobs once the runner is running
+    ///
+    /// Every handle taken from the same builder controls the same runner.
+    ///
+    /// See the [handle module](crate::handle) for more details.
+    #[must_use]
+    pub fn handle(&self) -> RunnerHandle {
+        self.handle.clone()
+    }
+
     /// Add a custom heartbeat service as a background service
     ///
     /// This method is a convenience wrapper around `background_service` specifically for
@@ -332,8 +484,7 @@ where
         }
 
         let adapter = HeartbeatServiceAdapter { service };
-        self.background_services
-            .push(DynBackgroundService::boxed(adapter));
+        self.push_background_service("heartbeat", adapter);
         self
     }
 
@@ -407,9 +558,9 @@ where
         mut self,
         server: Arc<blueprint_qos::servers::prometheus::PrometheusServer>,
     ) -> Self {
+        self.metrics_server = Some(server.clone());
         let adapter = self::metrics_server::MetricsServerAdapter::new(server);
-        self.background_services
-            .push(DynBackgroundService::boxed(adapter));
+        self.push_background_service("metrics-server", adapter);
         self
     }
 
@@ -550,8 +701,7 @@ where
             qos_service: qos_service_arc,
             ready: qos_ready,
         };
-        self.background_services
-            .push(DynBackgroundService::boxed(adapter));
+        self.push_background_service("qos", adapter);
         self
     }
 
@@ -606,8 +756,7 @@ where
 
             let auth_service = blueprint_tee::TeeAuthService::new(config.key_exchange.clone());
             let adapter = TeeAuthServiceAdapter { auth_service };
-            self.background_services
-                .push(DynBackgroundService::boxed(adapter));
+            self.push_background_service("tee-auth", adapter);
             tracing::info!(
                 mode = ?config.mode,
                 requirement = ?config.requirement,
@@ -667,10 +816,19 @@ where
     /// }
     /// ```
     #[must_use]
-    pub fn background_service(mut self, service: impl BackgroundService + 'static) -> Self {
+    pub fn background_service<S: BackgroundService + 'static>(mut self, service: S) -> Self {
+        self.push_background_service(core::any::type_name::<S>(), service);
+        self
+    }
+
+    fn push_background_service(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        service: impl BackgroundService + 'static,
+    ) {
         self.background_services
             .push(DynBackgroundService::boxed(service));
-        self
+        self.background_service_names.push(name.into());
     }
 
     /// Set the shutdown handler
@@ -683,6 +841,9 @@ where
     ///
     /// Meaning it is a good place to do cleanup logic, such as finalizing database transactions.
     ///
+    /// If the runner [drains](Self::drain) on shutdown, the handler runs once the drain is over,
+    /// after the in-flight jobs finished. [`run()`](Self::run) returns once the handler completes.
+    ///
     /// [Producers]: https://docs.rs/blueprint_sdk/latest/blueprint_sdk/producers/index.html
     /// [Consumers]: https://docs.rs/blueprint_sdk/latest/blueprint_sdk/consumers/index.html
     /// [Background Services]: crate::BackgroundService
@@ -753,8 +914,18 @@ where
             consumers: self.consumers,
             router: self.router,
             background_services: self.background_services,
+            background_service_names: self.background_service_names,
             shutdown_handler: handler,
             faas_registry: self.faas_registry,
+            failure_policies: self.failure_policies,
+            dead_letter_sink: self.dead_letter_sink,
+            drain: self.drain,
+            scheduler: self.scheduler,
+            journal: self.journal,
+            health: self.health,
+            metrics_server: self.metrics_server,
+            handle: self.handle,
+            router_updates: self.router_updates,
             #[cfg(feature = "tee")]
             tee_explicitly_configured: self.tee_explicitly_configured,
         }
@@ -764,6 +935,10 @@ where
     ///
     /// This will block until the runner finishes.
     ///
+    /// If [`REPLAY_LOG_ENV`](record::REPLAY_LOG_ENV) is set and the environment is in both test and
+    /// dry-run mode, the runner replays that log through the router instead, and returns once it is
+    /// done. See the [record module](crate::record).
+    ///
     /// # Errors
     ///
     /// If at any point the runner fails, an error will be returned. See [`Self::with_shutdown_handler`]
@@ -773,10 +948,18 @@ where
             return Err(Error::NoRouter);
         };
 
+        if let Some(result) = record::replay_from_env(&self.env, &router).await {
+            return result.map_err(Into::into);
+        }
+
         if self.producers.is_empty() {
             return Err(Error::NoProducers);
         }
 
+        // Routers swapped in through a `RunnerHandle` are wrapped the same way
+        #[cfg_attr(not(feature = "tee"), allow(unused_mut))]
+        let mut router_layer: fn(Router) -> Router = core::convert::identity;
+
         // TEE auto-detection and auto-wrapping
         #[cfg(feature = "tee")]
         let router = {
@@ -833,7 +1016,8 @@ where
                         target: "blueprint-runner",
                         "Wrapping all routes with TeeLayer for attestation"
                     );
-                    router.layer(blueprint_tee::TeeLayer::new())
+                    router_layer = |router| router.layer(blueprint_tee::TeeLayer::new());
+                    router_layer(router)
                 } else {
                     let is_required = matches!(env_tee_mode, Some(blueprint_tee::TeeMode::Direct));
                     if is_required {
@@ -862,8 +1046,19 @@ where
             router,
             env: self.env,
             background_services: self.background_services,
+            background_service_names: self.background_service_names,
             shutdown_handler: self.shutdown_handler,
             faas_registry: self.faas_registry,
+            failure_policies: self.failure_policies,
+            dead_letter_sink: self.dead_letter_sink,
+            drain: self.drain,
+            scheduler: self.scheduler,
+            journal: self.journal,
+            health: self.health,
+            metrics_server: self.metrics_server,
+            handle: self.handle,
+            router_updates: self.router_updates,
+            router_layer,
         };
 
         runner.run().await
@@ -964,6 +1159,7 @@ impl BlueprintRunner {
         config: Conf,
         env: BlueprintEnvironment,
     ) -> BlueprintRunnerBuilder<impl Future<Output = ()> + Send + 'static> {
+        let (handle, router_updates) = RunnerHandle::new();
         BlueprintRunnerBuilder {
             config: DynBlueprintConfig::boxed(config),
             env,
@@ -971,8 +1167,18 @@ impl BlueprintRunner {
             consumers: Vec::new(),
             router: None,
             background_services: Vec::new(),
-            shutdown_handler: future::pending(),
+            background_service_names: Vec::new(),
+            shutdown_handler: future::ready(()),
             faas_registry: faas::FaasRegistry::new(),
+            failure_policies: FailurePolicies::default(),
+            dead_letter_sink: None,
+            drain: None,
+            scheduler: SchedulerConfig::default(),
+            journal: None,
+            health: None,
+            metrics_server: None,
+            handle,
+            router_updates,
             #[cfg(feature = "tee")]
             tee_explicitly_configured: false,
         }
@@ -982,12 +1188,23 @@ impl BlueprintRunner {
 struct FinalizedBlueprintRunner<F> {
     config: Box<DynBlueprintConfig<'static>>,
     producers: Vec<Producer>,
-    consumers: Vec<Consumer>,
+    consumers: Vec<(Consumer, ConsumerConfig)>,
     router: Router,
     env: BlueprintEnvironment,
     background_services: Vec<Box<DynBackgroundService<'static>>>,
+    background_service_names: Vec<Cow<'static, str>>,
     shutdown_handler: F,
     faas_registry: faas::FaasRegistry,
+    failure_policies: FailurePolicies,
+    dead_letter_sink: Option<DeadLetterSink>,
+    drain: Option<DrainConfig>,
+    scheduler: SchedulerConfig,
+    journal: Option<JournalConfig>,
+    health: Option<HealthConfig>,
+    metrics_server: Option<Arc<blueprint_qos::servers::prometheus::PrometheusServer>>,
+    handle: RunnerHandle,
+    router_updates: mpsc::UnboundedReceiver<Router>,
+    router_layer: fn(Router) -> Router,
 }
 
 impl<F> FinalizedBlueprintRunner<F>
@@ -999,14 +1216,31 @@ where
         let FinalizedBlueprintRunner {
             config,
             producers,
-            mut consumers,
-            mut router,
+            consumers,
+            router,
             env,
             background_services,
+            background_service_names,
             shutdown_handler,
             faas_registry,
+            failure_policies,
+            dead_letter_sink,
+            drain: drain_config,
+            scheduler,
+            journal: journal_config,
+            health,
+            metrics_server,
+            handle,
+            router_updates,
+            router_layer,
         } = self;
 
+        // Started first, so that probes get an answer while registering
+        let status = health.as_ref().map(|_| RunnerStatus::default());
+        if let (Some(config), Some(status)) = (health, &status) {
+            health::serve(config, status.clone(), metrics_server.as_deref()).await?;
+        }
+
         let needs_registration = config.requires_registration(&env).await?;
         let skip_registration = env.dry_run;
 
@@ -1044,7 +1278,30 @@ where
             );
         }
 
-        let mut router = router.as_service();
+        if let Some(status) = &status {
+            status.registration(match (needs_registration, skip_registration) {
+                (false, _) => RegistrationState::NotRequired,
+                (true, false) => RegistrationState::Registered,
+                (true, true) => RegistrationState::Skipped,
+            });
+            status.background_services(background_service_names.iter().map(Cow::as_ref));
+        }
+
+        let journal = match journal_config {
+            Some(config) => Some(Journal::open(config).await?),
+            None => None,
+        };
+
+        let mut dispatcher = JobDispatcher::new(
+            scheduler,
+            router,
+            faas_registry,
+            failure_policies,
+            dead_letter_sink,
+            Consumers::start(consumers),
+            journal,
+        )
+        .with_disabled_jobs(handle.disabled_jobs());
 
         let has_background_services = !background_services.is_empty();
         let mut background_futures = Vec::with_capacity(background_services.len());
@@ -1053,263 +1310,242 @@ where
         // Iterate by reference (&service_box) over `background_services` (the Vec of Boxes)
         // This ensures that the `Box<dyn BackgroundService>` instances themselves remain owned by
         // the `background_services` vector and are not dropped after `start()` is called.
-        for service_box in &background_services {
+        for (index, service_box) in background_services.iter().enumerate() {
             // service_box is &Box<dyn BackgroundService>
             let receiver = service_box.start().await?;
+            let service_status = status.clone();
+            if let Some(status) = &service_status {
+                status.service_running(index);
+            }
             background_futures.push(Box::pin(async move {
-                receiver
+                let result = receiver
                     .await
                     .map_err(|e| Error::BackgroundService(e.to_string()))
-                    .and(Ok(()))
+                    .and(Ok(()));
+                if let Some(status) = &service_status {
+                    status.service_ended(index, &result);
+                }
+                result
             })
                 as Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>);
         }
         // The `background_services` Vec (containing the Boxed service instances) is still alive here
         // and will be dropped only when `FinalizedBlueprintRunner::run` exits.
 
-        let (mut shutdown_tx, shutdown_rx) = oneshot::channel();
-        tokio::spawn(async move {
-            let _ = shutdown_rx.await;
-            blueprint_core::info!(target: "blueprint-runner", "Received graceful shutdown signal. Calling shutdown handler");
-            shutdown_handler.await;
-        });
+        let drain_timeout = drain_config.as_ref().map_or_else(
+            || DrainConfig::default().drain_timeout(),
+            DrainConfig::drain_timeout,
+        );
 
-        poll_fn(|ctx| router.poll_ready(ctx)).await.unwrap_or(());
+        // Runs until the runner has to shut down, returning how long to drain for if it was
+        // requested, or why the runner failed otherwise
+        let serve: Result<Duration, Error> = async {
+            dispatcher.ready().await;
+            dispatcher.recover().await?;
 
-        let producers = producers.into_iter().map(|producer| {
-            futures::stream::unfold(producer, |producer| async move {
-                let result;
-                {
-                    let mut guard = producer.lock().await;
-                    result = guard.next().await;
-                }
-                result.map(|job_call| (job_call, producer))
-            })
-            .boxed()
-        });
-        let mut producer_stream = futures::stream::select_all(producers);
-
-        let mut background_services = if background_futures.is_empty() {
-            futures::future::select_all(vec![Box::pin(futures::future::ready(Ok(())))
-                as Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>])
-        } else {
-            futures::future::select_all(background_futures)
-        };
-
-        let mut pending_jobs = FuturesUnordered::new();
-
-        #[allow(unused_variables)]
-        let bridge = if env.test_mode {
-            blueprint_core::debug!(
-                target: "blueprint-runner",
-                "Test mode enabled; skipping bridge connection"
-            );
-            None
-        } else {
-            let bridge = env.bridge().await.map_err(|e| {
-                blueprint_core::error!(
-                    "[FATAL] Unable to establish bridge connection, aborting runner: {e}"
-                );
-                e
-            })?;
-            bridge.ping().await.map_err(|e| {
-                blueprint_core::error!(
-                    "[FATAL] Unable to establish bridge connection, aborting runner: {e}"
-                );
-                e
-            })?;
-            Some(bridge)
-        };
-
-        // Update TLS configuration if enabled
-        #[cfg(feature = "tls")]
-        if let Some(tls_profile) = &env.tls_profile {
-            blueprint_core::info!(
-                target: "blueprint-runner",
-                "Updating service TLS profile"
-            );
+            let producers = producers.into_iter().map(|producer| {
+                futures::stream::unfold(producer, |producer| async move {
+                    let result;
+                    {
+                        let mut guard = producer.lock().await;
+                        result = guard.next().await;
+                    }
+                    result.map(|job_call| (job_call, producer))
+                })
+                .boxed()
+            });
+            let mut producer_stream = futures::stream::select_all(producers);
+
+            let mut background_status = if background_futures.is_empty() {
+                futures::future::select_all(vec![Box::pin(futures::future::ready(Ok(())))
+                    as Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>])
+            } else {
+                futures::future::select_all(background_futures)
+            };
 
-            let service_id = resolve_service_id(&env).inspect_err(|err| {
-                blueprint_core::error!(
+            let bridge = if env.test_mode {
+                blueprint_core::debug!(
                     target: "blueprint-runner",
-                    error = ?err,
-                    "TLS profile provided but service ID is missing from configuration"
+                    "Test mode enabled; skipping bridge connection"
                 );
-            })?;
-
-            if let Some(bridge) = bridge.as_ref() {
-                bridge
-                    .update_blueprint_service_tls_profile(service_id, Some(tls_profile.clone()))
-                    .await
-                    .map_err(|e| {
-                        blueprint_core::error!(
-                            target: "blueprint-runner",
-                            service_id,
-                            "[FATAL] Failed to update TLS profile for service {service_id}: {e}"
-                        );
-                        e
-                    })?;
+                None
+            } else {
+                let bridge = env.bridge().await.map_err(|e| {
+                    blueprint_core::error!(
+                        "[FATAL] Unable to establish bridge connection, aborting runner: {e}"
+                    );
+                    e
+                })?;
+                bridge.ping().await.map_err(|e| {
+                    blueprint_core::error!(
+                        "[FATAL] Unable to establish bridge connection, aborting runner: {e}"
+                    );
+                    e
+                })?;
+                Some(bridge)
+            };
 
+            // Update TLS configuration if enabled
+            #[cfg(feature = "tls")]
+            if let Some(tls_profile) = &env.tls_profile {
                 blueprint_core::info!(
                     target: "blueprint-runner",
-                    service_id,
-                    "TLS profile updated successfully"
-                );
-            } else {
-                blueprint_core::warn!(
-                    target: "blueprint-runner",
-                    "TLS profile provided but bridge unavailable; skipping update"
+                    "Updating service TLS profile"
                 );
-            }
-        }
 
-        // Backpressure: cap concurrent in-flight jobs. When the limit is
-        // reached we stop polling producers until some jobs complete.
-        const MAX_CONCURRENT_JOBS: usize = 1024;
-
-        loop {
-            tokio::select! {
-                // Receive job calls from producer (only when below concurrency limit)
-                producer_result = producer_stream.next(), if pending_jobs.len() < MAX_CONCURRENT_JOBS => {
-                    match producer_result {
-                        Some(Ok(job_call)) => {
-                            let block_number =
-                                read_metadata_u64(job_call.metadata(), BLOCK_NUMBER_METADATA_KEYS);
-                            let service_id =
-                                read_metadata_u64(job_call.metadata(), SERVICE_ID_METADATA_KEYS);
-                            blueprint_core::info!(
+                let service_id = resolve_service_id(&env).inspect_err(|err| {
+                    blueprint_core::error!(
+                        target: "blueprint-runner",
+                        error = ?err,
+                        "TLS profile provided but service ID is missing from configuration"
+                    );
+                })?;
+
+                if let Some(bridge) = bridge.as_ref() {
+                    bridge
+                        .update_blueprint_service_tls_profile(service_id, Some(tls_profile.clone()))
+                        .await
+                        .map_err(|e| {
+                            blueprint_core::error!(
                                 target: "blueprint-runner",
-                                job_id = ?job_call.job_id(),
-                                block_number = ?block_number,
-                                service_id = ?service_id,
-                                "Received job call from producer stream"
+                                service_id,
+                                "[FATAL] Failed to update TLS profile for service {service_id}: {e}"
                             );
+                            e
+                        })?;
 
-                            // Check if this job should be delegated to FaaS
-                            let job_id: u32 = job_call.job_id().into();
-                            if faas_registry.is_faas_job(job_id) {
-                                let executor = faas_registry.get(job_id)
-                                    .expect("FaaS executor exists for registered job ID")
-                                    .clone();
+                    blueprint_core::info!(
+                        target: "blueprint-runner",
+                        service_id,
+                        "TLS profile updated successfully"
+                    );
+                } else {
+                    blueprint_core::warn!(
+                        target: "blueprint-runner",
+                        "TLS profile provided but bridge unavailable; skipping update"
+                    );
+                }
+            }
 
+            let mut drain_requests = drain::requests(drain_config.as_ref(), bridge.as_deref()).await;
+            let mut control_commands =
+                handle::commands(router_updates, router_layer, bridge.as_deref()).await;
+
+            if let Some(status) = &status {
+                status.accepting();
+            }
+
+            loop {
+                if let Some(status) = &status {
+                    status.observe(&dispatcher);
+                }
+
+                tokio::select! {
+                    // Receive job calls from producer (backpressure: only while every queue has room)
+                    producer_result = producer_stream.next(), if dispatcher.has_capacity() => {
+                        match producer_result {
+                            Some(Ok(job_call)) => {
+                                let block_number =
+                                    read_metadata_u64(job_call.metadata(), BLOCK_NUMBER_METADATA_KEYS);
+                                let service_id =
+                                    read_metadata_u64(job_call.metadata(), SERVICE_ID_METADATA_KEYS);
                                 blueprint_core::info!(
                                     target: "blueprint-runner",
-                                    job_id = %job_call.job_id(),
-                                    provider = executor.provider_name(),
-                                    "Delegating job to FaaS executor"
+                                    job_id = ?job_call.job_id(),
+                                    block_number = ?block_number,
+                                    service_id = ?service_id,
+                                    "Received job call from producer stream"
                                 );
+                                if let Some(status) = &status {
+                                    status.received(block_number);
+                                }
 
-                                pending_jobs.push(tokio::task::spawn(async move {
-                                    match executor.invoke(job_call).await {
-                                        Ok(result) => Ok(Some(vec![result])),
-                                        Err(e) => {
-                                            blueprint_core::error!(
-                                                target: "blueprint-runner",
-                                                error = %e,
-                                                "FaaS invocation failed"
-                                            );
-                                            Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
-                                        }
-                                    }
-                                }));
-                            } else {
-                                // Normal local execution via router
-                                pending_jobs.push(tokio::task::spawn(router.call(job_call)));
+                                if let Err(e) = dispatcher.dispatch(job_call).await {
+                                    break Err(e);
+                                }
+                            },
+                            Some(Err(e)) => {
+                                blueprint_core::error!(target: "blueprint-runner", "Producer error: {:?}", e);
+                                break Err(ProducerError::Failed(e).into());
+                            },
+                            None => {
+                                blueprint_core::error!(target: "blueprint-runner", "Producer stream ended unexpectedly");
+                                break Err(ProducerError::StreamEnded.into());
                             }
-                        },
-                        Some(Err(e)) => {
-                            blueprint_core::error!(target: "blueprint-runner", "Producer error: {:?}", e);
-                            let _ = shutdown_tx.send(true);
-                            return Err(ProducerError::Failed(e).into());
-                        },
-                        None => {
-                            blueprint_core::error!(target: "blueprint-runner", "Producer stream ended unexpectedly");
-                            let _ = shutdown_tx.send(true);
-                            return Err(ProducerError::StreamEnded.into());
+                        }
+                    },
+
+                    // Job call finished
+                    Some(finished) = dispatcher.next_finished() => {
+                        if let Err(e) = dispatcher.finish(finished).await {
+                            break Err(e);
                         }
                     }
-                },
 
-                // Job call finished
-                Some(job_result) = pending_jobs.next() => {
-                    match job_result {
-                        Ok(Ok(Some(results))) => {
-                            blueprint_core::trace!(
-                                target: "blueprint-runner",
-                                count = %results.len(),
-                                "Job call(s) processed by router"
-                            );
-                            let result_stream = stream::iter(results.into_iter().map(Ok));
-
-                            // Broadcast results to all consumers
-                            let send_futures = consumers.iter_mut().map(|consumer| {
-                                let mut stream_clone = result_stream.clone();
-                                async move {
-                                    let mut guard = consumer.lock().await;
-                                    guard.send_all(&mut stream_clone).await
-                                }
-                            });
+                    // Router swapped or job toggled at runtime
+                    Some(command) = control_commands.next() => {
+                        dispatcher.control(command).await;
+                    }
 
-                            let result = futures::future::try_join_all(send_futures).await;
-                            blueprint_core::trace!(
-                                target: "blueprint-runner",
-                                results = ?result.as_ref().map(|_| "success"),
-                                "Job call results were broadcast to consumers"
-                            );
-                            if let Err(e) = result {
-                                let _ = shutdown_tx.send(true);
-                                return Err(Error::Consumer(e));
-                            }
-                        },
-                        Ok(Ok(None)) => {
-                            blueprint_core::debug!(target: "blueprint-runner", "Job call was ignored by router");
-                        },
-                        Ok(Err(e)) => {
-                            blueprint_core::error!(target: "blueprint-runner", "Job call task failed: {:?}", e);
-                            return Err(JobCallError::JobFailed(e).into());
-                        },
-                        Err(e) => {
-                            blueprint_core::error!(target: "blueprint-runner", "Job call failed: {:?}", e);
-                            let _ = shutdown_tx.send(true);
-                            return Err(JobCallError::JobDidntFinish(e).into());
-                        },
+                    // Drain requested
+                    Some(request) = drain_requests.next() => {
+                        blueprint_core::info!(
+                            target: "blueprint-runner",
+                            source = request.source,
+                            "Drain requested"
+                        );
+                        break Ok(request.timeout.unwrap_or(drain_timeout));
                     }
-                }
 
-                // Background service status updates
-                result = &mut background_services => {
-                    let (result, _, remaining_background_services) = result;
-                    match result {
-                        Ok(()) => {
-                            if has_background_services {
-                                blueprint_core::warn!(target: "blueprint-runner", "A background service has finished running");
+                    // Background service status updates
+                    result = &mut background_status => {
+                        let (result, _, remaining_background_services) = result;
+                        match result {
+                            Ok(()) => {
+                                if has_background_services {
+                                    blueprint_core::warn!(target: "blueprint-runner", "A background service has finished running");
+                                }
+                            },
+                            Err(e) => {
+                                blueprint_core::error!(target: "blueprint-runner", "A background service failed: {:?}", e);
+                                break Err(e);
                             }
-                        },
-                        Err(e) => {
-                            blueprint_core::error!(target: "blueprint-runner", "A background service failed: {:?}", e);
-                            let _ = shutdown_tx.send(true);
-                            return Err(e);
                         }
-                    }
 
-                    if remaining_background_services.is_empty() {
-                        if has_background_services {
-                            blueprint_core::warn!(target: "blueprint-runner", "All background services have ended");
+                        if remaining_background_services.is_empty() {
+                            if has_background_services {
+                                blueprint_core::warn!(target: "blueprint-runner", "All background services have ended");
+                            }
+                            continue;
                         }
-                        continue;
-                    }
 
-                    background_services = futures::future::select_all(remaining_background_services);
+                        background_status = futures::future::select_all(remaining_background_services);
+                    }
                 }
+            }
+        }
+        .await;
 
-                // Shutdown handler run, we're done
-                () = shutdown_tx.closed() => {
-                    break;
+        // Whatever stopped the runner, drain it if enabled, and then call the shutdown handler
+        let (result, drain_timeout) = match serve {
+            Ok(timeout) => (Ok(()), Some(timeout)),
+            Err(e) => (Err(e), drain_config.is_some().then_some(drain_timeout)),
+        };
+        let result = match drain_timeout {
+            Some(timeout) => {
+                if let Some(status) = &status {
+                    status.draining();
                 }
+                let drained = drain::drain(&mut dispatcher, &background_services, timeout).await;
+                result.and(drained)
             }
-        }
+            None => result,
+        };
 
-        Ok(())
+        blueprint_core::info!(target: "blueprint-runner", "Runner shutting down. Calling shutdown handler");
+        shutdown_handler.await;
+
+        result
     }
 }
 
@@ -1367,6 +1603,7 @@ async fn capture_registration_inputs(env: &BlueprintEnvironment) -> Result<Vec<u
 }
 
 const SERVICE_ID_METADATA_KEYS: &[&str] = &["tangle.service_id", "X-TANGLE-SERVICE-ID"];
+const CALL_ID_METADATA_KEYS: &[&str] = &["tangle.call_id", "X-TANGLE-CALL-ID"];
 const BLOCK_NUMBER_METADATA_KEYS: &[&str] = &["tangle.block_number", "X-TANGLE-BLOCK-NUMBER"];
 
 fn read_metadata_u64(metadata: &MetadataMap<MetadataValue>, keys: &[&str]) -> Option<u64> {
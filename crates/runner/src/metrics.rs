@@ -0,0 +1,118 @@
+//! Runner metrics — Prometheus counters and gauges for the job execution loop.
+//!
+//! All metrics register on the default prometheus registry, and on the registry
+//! of the runner's [metrics server] when it serves the [health endpoints]. Label
+//! cardinality is bounded by the job IDs served by the [`Router`], the
+//! configured priority classes and consumer names, and by known enum values
+//! (`outcome`, `kind`, `reason`), never by user-supplied strings.
+//!
+//! [`Router`]: blueprint_router::Router
+//! [metrics server]: crate::BlueprintRunnerBuilder::metrics_server
+//! [health endpoints]: crate::health
+
+use prometheus::core::Collector;
+use prometheus::{
+    IntCounterVec, IntGaugeVec, Registry, register_int_counter_vec, register_int_gauge_vec,
+};
+use std::sync::LazyLock;
+
+/// Job call failures, by job ID and the [`FailurePolicy`] outcome that was applied.
+///
+/// `outcome` is one of `continued`, `retried`, `dead_lettered` or `aborted`.
+///
+/// [`FailurePolicy`]: crate::failure::FailurePolicy
+pub static JOB_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "blueprint_runner_job_failures_total",
+        "Job call failures handled by the runner, by failure policy outcome",
+        &["job_id", "outcome"]
+    )
+    .expect("blueprint_runner_job_failures_total")
+});
+
+/// Job results that were errors, by job ID and [`ErrorKind`].
+///
+/// `kind` is the [`ErrorKind::as_str()`] of the error, e.g. `timed_out` for jobs that exceeded
+/// their route's timeout.
+///
+/// [`ErrorKind`]: blueprint_core::error::ErrorKind
+/// [`ErrorKind::as_str()`]: blueprint_core::error::ErrorKind::as_str
+pub static JOB_RESULT_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "blueprint_runner_job_result_errors_total",
+        "Job results that were errors, by job ID and error kind",
+        &["job_id", "kind"]
+    )
+    .expect("blueprint_runner_job_result_errors_total")
+});
+
+/// Jobs handled by a drain, by whether they finished in time.
+///
+/// `outcome` is either `drained` or `abandoned`.
+pub static DRAINED_JOBS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "blueprint_runner_drained_jobs_total",
+        "In-flight jobs at drain time, by whether they finished before the deadline",
+        &["outcome"]
+    )
+    .expect("blueprint_runner_drained_jobs_total")
+});
+
+/// Job results waiting for an isolated consumer, by consumer name.
+///
+/// See [`ConsumerConfig::isolated()`](crate::consumer::ConsumerConfig::isolated).
+pub static CONSUMER_BACKLOG: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "blueprint_runner_consumer_backlog",
+        "Job results queued for an isolated consumer",
+        &["consumer"]
+    )
+    .expect("blueprint_runner_consumer_backlog")
+});
+
+/// Job results an isolated consumer never received, by consumer name and reason.
+///
+/// `reason` is either `queue_full` or `retries_exhausted`.
+pub static CONSUMER_DROPPED_RESULTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "blueprint_runner_consumer_dropped_results_total",
+        "Job results dropped by an isolated consumer, by reason",
+        &["consumer", "reason"]
+    )
+    .expect("blueprint_runner_consumer_dropped_results_total")
+});
+
+/// Job calls waiting to be dispatched, by [`PriorityClass`].
+///
+/// Job calls that don't match any class are reported under the `default` class.
+///
+/// [`PriorityClass`]: crate::scheduler::PriorityClass
+pub static QUEUE_DEPTH: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "blueprint_runner_queue_depth",
+        "Job calls waiting for a concurrency slot, by priority class",
+        &["class"]
+    )
+    .expect("blueprint_runner_queue_depth")
+});
+
+/// Register every runner metric on `registry`, on top of the default registry
+pub(crate) fn register(registry: &Registry) {
+    let collectors: [Box<dyn Collector>; 6] = [
+        Box::new(JOB_FAILURES.clone()),
+        Box::new(JOB_RESULT_ERRORS.clone()),
+        Box::new(DRAINED_JOBS.clone()),
+        Box::new(CONSUMER_BACKLOG.clone()),
+        Box::new(CONSUMER_DROPPED_RESULTS.clone()),
+        Box::new(QUEUE_DEPTH.clone()),
+    ];
+
+    for collector in collectors {
+        match registry.register(collector) {
+            Ok(()) | Err(prometheus::Error::AlreadyReg) => {}
+            Err(e) => {
+                blueprint_core::warn!(target: "blueprint-runner", "Failed to register runner metrics: {e}");
+            }
+        }
+    }
+}
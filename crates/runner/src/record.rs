@@ -0,0 +1,623 @@
+//! Record and replay job calls
+//!
+//! A job that misbehaves in production is hard to reproduce, since the exact [`JobCall`] that
+//! triggered it (its body, and metadata such as the call ID, block number, and caller) is gone once
+//! it has been handled. A [`Recorder`] keeps it around:
+//!
+//! 1. [`Recorder::producer()`] wraps any producer, appending every job call it yields to an on-disk
+//!    log
+//! 2. Once the runner has handled a recorded call, its outcome (the [`JobResult`]s, or the error
+//!    it failed with) is appended as well
+//!
+//! The log can later be opened as a [`Recording`], and fed back through a [`Router`], either with
+//! [`Recording::replay()`] or with a [`ReplayProducer`]. [`Recording::diff()`] compares the
+//! replayed outcomes against the recorded ones.
+//!
+//! `cargo tangle blueprint replay <log>` does all of this against a local build of the blueprint,
+//! with no chain: it runs the blueprint binary in test and dry-run mode with [`REPLAY_LOG_ENV`]
+//! set, which makes [`BlueprintRunner`] replay the log through its router instead of starting up.
+//!
+//! Records are written to the log on a background thread, so recording never blocks the runner.
+//!
+//! NOTE: The log contains job call bodies and metadata verbatim, including metadata marked as
+//! sensitive.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use blueprint_router::Router;
+//! use blueprint_runner::BlueprintRunner;
+//! use blueprint_runner::config::BlueprintEnvironment;
+//! use blueprint_runner::record::Recorder;
+//! # use blueprint_core::JobCall;
+//! # use futures::stream;
+//!
+//! # async fn example() -> std::io::Result<()> {
+//! let env = BlueprintEnvironment::default();
+//! let recorder = Recorder::open(env.data_dir.join("calls.log"))?;
+//! # let producer = stream::pending::<Result<JobCall, std::io::Error>>();
+//!
+//! let builder = BlueprintRunner::builder((), env)
+//!     .router(Router::new().route(0, async || "Hello, world!"))
+//!     // Every call from this producer is recorded, along with its results
+//!     .producer(recorder.producer(producer));
+//! # Ok(()) }
+//! ```
+//!
+//! [`BlueprintRunner`]: crate::BlueprintRunner
+
+use crate::config::BlueprintEnvironment;
+use crate::error::JobCallError;
+use crate::stored::{StoredCall, StoredResult};
+use blueprint_core::error::BoxError;
+use blueprint_core::{JobCall, JobId, JobResult};
+use blueprint_router::Router;
+use core::fmt;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use tower::ServiceExt;
+
+/// The environment variable holding the path of a log for [`BlueprintRunner`] to replay
+///
+/// When set, [`BlueprintRunner::run()`] replays the log through its router, writes the replayed
+/// outcomes to the path in [`REPLAY_OUTPUT_ENV`], and returns. Registration, producers, consumers,
+/// and background services are all skipped.
+///
+/// This only applies when the [`BlueprintEnvironment`] has both `test_mode` and `dry_run` set, as
+/// `cargo tangle blueprint replay` does, so that a stray variable can't turn a production runner
+/// into a replay. Otherwise, the variable is ignored with a warning.
+///
+/// [`BlueprintEnvironment`]: crate::config::BlueprintEnvironment
+/// [`BlueprintRunner`]: crate::BlueprintRunner
+/// [`BlueprintRunner::run()`]: crate::BlueprintRunnerBuilder::run
+pub const REPLAY_LOG_ENV: &str = "BLUEPRINT_REPLAY_LOG";
+
+/// The environment variable holding the path to write replayed outcomes to
+///
+/// Defaults to the [`REPLAY_LOG_ENV`] path with a `.replayed` extension.
+pub const REPLAY_OUTPUT_ENV: &str = "BLUEPRINT_REPLAY_OUTPUT";
+
+/// Appends job calls and their outcomes to a log
+///
+/// Recorders are cheap to clone, and every clone appends to the same log. The log is written by a
+/// background thread, which finishes writing every pending record once the last clone is dropped.
+///
+/// See the [module docs](self) for details.
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Arc<RecorderInner>,
+}
+
+struct RecorderInner {
+    next_seq: AtomicU64,
+    records: Option<mpsc::Sender<Record>>,
+    writer: Option<thread::JoinHandle<()>>,
+}
+
+impl Recorder {
+    /// Open the log at `path`, creating it if it doesn't exist
+    ///
+    /// New calls are appended after any calls already in the log.
+    ///
+    /// # Errors
+    ///
+    /// If the log cannot be read, is corrupted, or cannot be opened for appending.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let next_seq = match Recording::open(path) {
+            Ok(recording) => recording.calls.keys().next_back().map_or(0, |seq| seq + 1),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        let (records, rx) = mpsc::channel();
+        let writer = thread::Builder::new()
+            .name("blueprint-recorder".into())
+            .spawn(move || write_records(file, &rx))?;
+
+        Ok(Self {
+            inner: Arc::new(RecorderInner {
+                next_seq: AtomicU64::new(next_seq),
+                records: Some(records),
+                writer: Some(writer),
+            }),
+        })
+    }
+
+    /// Wrap `producer`, recording every job call it yields
+    ///
+    /// Errors from `producer` are passed through, and are not recorded.
+    pub fn producer<P>(&self, producer: P) -> RecordingProducer<P> {
+        RecordingProducer {
+            producer,
+            recorder: self.clone(),
+        }
+    }
+
+    fn record_call(&self, call: &JobCall) -> u64 {
+        let seq = self.inner.next_seq.fetch_add(1, Ordering::Relaxed);
+        self.inner.send(Record {
+            seq,
+            call: Some(StoredCall::from(call)),
+            outcome: None,
+        });
+        seq
+    }
+
+    fn record_outcome(&self, seq: u64, outcome: Outcome) {
+        self.inner.send(Record {
+            seq,
+            call: None,
+            outcome: Some(outcome),
+        });
+    }
+}
+
+impl RecorderInner {
+    /// Queue a record for the writer thread
+    fn send(&self, record: Record) {
+        let sent = self
+            .records
+            .as_ref()
+            .is_some_and(|records| records.send(record).is_ok());
+        if !sent {
+            blueprint_core::warn!(
+                target: "blueprint-runner",
+                "Job call recording writer has stopped, dropping record"
+            );
+        }
+    }
+}
+
+impl Drop for RecorderInner {
+    fn drop(&mut self) {
+        // Closing the channel stops the writer once it has written everything queued
+        drop(self.records.take());
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Append every record from `records` to `file`, logging any failure
+///
+/// A broken log shouldn't take the runner down with it.
+fn write_records(mut file: File, records: &mpsc::Receiver<Record>) {
+    for record in records {
+        let result = serde_json::to_vec(&record)
+            .map_err(io::Error::from)
+            .and_then(|mut line| {
+                line.push(b'\n');
+                file.write_all(&line)
+            });
+
+        if let Err(e) = result {
+            blueprint_core::warn!(
+                target: "blueprint-runner",
+                seq = record.seq,
+                "Failed to write to the job call recording: {e}"
+            );
+        }
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").finish_non_exhaustive()
+    }
+}
+
+/// A producer that records every job call it yields, see [`Recorder::producer()`]
+#[derive(Debug)]
+pub struct RecordingProducer<P> {
+    producer: P,
+    recorder: Recorder,
+}
+
+impl<P, E> Stream for RecordingProducer<P>
+where
+    P: Stream<Item = Result<JobCall, E>> + Unpin,
+{
+    type Item = Result<JobCall, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = core::task::ready!(Pin::new(&mut self.producer).poll_next(cx));
+        Poll::Ready(item.map(|result| {
+            result.map(|mut call| {
+                let seq = self.recorder.record_call(&call);
+                call.extensions_mut().insert(RecordedCall {
+                    seq,
+                    recorder: self.recorder.clone(),
+                });
+                call
+            })
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.producer.size_hint()
+    }
+}
+
+/// Attached to recorded job calls, so that the dispatcher can record their outcome
+#[derive(Clone)]
+pub(crate) struct RecordedCall {
+    seq: u64,
+    recorder: Recorder,
+}
+
+impl RecordedCall {
+    pub(crate) fn results(&self, results: &[JobResult]) {
+        let results = results.iter().map(StoredResult::from).collect();
+        self.recorder
+            .record_outcome(self.seq, Outcome::Results(results));
+    }
+
+    pub(crate) fn ignored(&self) {
+        self.recorder.record_outcome(self.seq, Outcome::Ignored);
+    }
+
+    pub(crate) fn failed(&self, error: &JobCallError) {
+        self.recorder
+            .record_outcome(self.seq, Outcome::Failed(error.to_string()));
+    }
+}
+
+/// A log of job calls and their outcomes, see the [module docs](self)
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    calls: BTreeMap<u64, RecordedEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct RecordedEntry {
+    call: StoredCall,
+    outcome: Option<Outcome>,
+}
+
+impl Recording {
+    /// Read the log at `path`
+    ///
+    /// # Errors
+    ///
+    /// If the log cannot be read, or is corrupted.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut calls = BTreeMap::new();
+        let mut outcomes = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let record: Record = match serde_json::from_str(&line) {
+                Ok(record) => record,
+                // A torn final write from a crash, everything before it is intact
+                Err(e) if e.is_eof() => break,
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            };
+
+            if let Some(call) = record.call {
+                calls.insert(
+                    record.seq,
+                    RecordedEntry {
+                        call,
+                        outcome: None,
+                    },
+                );
+            }
+            if let Some(outcome) = record.outcome {
+                outcomes.push((record.seq, outcome));
+            }
+        }
+
+        for (seq, outcome) in outcomes {
+            if let Some(entry) = calls.get_mut(&seq) {
+                entry.outcome = Some(outcome);
+            }
+        }
+
+        Ok(Self { calls })
+    }
+
+    /// Write the recording to `path`, replacing any existing file
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut contents = Vec::new();
+        for (seq, entry) in &self.calls {
+            serde_json::to_writer(
+                &mut contents,
+                &Record {
+                    seq: *seq,
+                    call: Some(entry.call.clone()),
+                    outcome: entry.outcome.clone(),
+                },
+            )?;
+            contents.push(b'\n');
+        }
+
+        fs::write(path, contents)
+    }
+
+    /// The number of recorded job calls
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Whether there are no recorded job calls
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// The recorded job calls, in the order they were received
+    pub fn calls(&self) -> impl Iterator<Item = JobCall> + '_ {
+        self.calls.values().map(|entry| entry.call.clone().into())
+    }
+
+    /// A producer that yields every recorded job call, in the order they were received
+    ///
+    /// The producer ends once every call has been yielded.
+    pub fn producer(&self) -> ReplayProducer {
+        ReplayProducer {
+            calls: self.calls().collect::<Vec<_>>().into_iter(),
+        }
+    }
+
+    /// Feed every recorded job call through `router`, one at a time, recording the outcomes
+    ///
+    /// The returned recording holds the same calls, with their replayed outcomes. Calls are always
+    /// run on `router`, even if they were originally delegated to a `FaaS` executor.
+    pub async fn replay(&self, router: Router) -> Recording {
+        let mut replayed = BTreeMap::new();
+        for (seq, entry) in &self.calls {
+            let call = JobCall::from(entry.call.clone());
+            let outcome = match router.clone().oneshot(call).await {
+                Ok(Some(results)) => Outcome::Results(results.iter().map(Into::into).collect()),
+                Ok(None) => Outcome::Ignored,
+                Err(e) => Outcome::Failed(JobCallError::JobFailed(e).to_string()),
+            };
+
+            replayed.insert(
+                *seq,
+                RecordedEntry {
+                    call: entry.call.clone(),
+                    outcome: Some(outcome),
+                },
+            );
+        }
+
+        Recording { calls: replayed }
+    }
+
+    /// Compare the outcomes of this recording against those of `replayed`
+    ///
+    /// Calls without a recorded outcome on either side (for example, because the runner stopped
+    /// before they finished) are not compared.
+    pub fn diff(&self, replayed: &Recording) -> Vec<ReplayDiff> {
+        self.calls
+            .iter()
+            .filter_map(|(seq, entry)| {
+                let recorded = entry.outcome.as_ref()?;
+                let replayed = replayed.calls.get(seq)?.outcome.as_ref()?;
+                (recorded != replayed).then(|| ReplayDiff {
+                    seq: *seq,
+                    job_id: entry.call.job_id(),
+                    recorded: recorded.clone(),
+                    replayed: replayed.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A producer that yields the job calls of a [`Recording`], see [`Recording::producer()`]
+#[derive(Debug)]
+pub struct ReplayProducer {
+    calls: std::vec::IntoIter<JobCall>,
+}
+
+impl Stream for ReplayProducer {
+    type Item = Result<JobCall, BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.calls.next().map(Ok))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.calls.size_hint()
+    }
+}
+
+/// A job call whose replayed outcome differs from its recorded one, see [`Recording::diff()`]
+#[derive(Debug, Clone)]
+pub struct ReplayDiff {
+    seq: u64,
+    job_id: JobId,
+    recorded: Outcome,
+    replayed: Outcome,
+}
+
+impl ReplayDiff {
+    /// The position of the job call in the recording
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// The job the call was for
+    pub fn job_id(&self) -> JobId {
+        self.job_id
+    }
+}
+
+impl fmt::Display for ReplayDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "call #{} (job {}):", self.seq, self.job_id)?;
+        writeln!(f, "  recorded: {}", self.recorded)?;
+        write!(f, "  replayed: {}", self.replayed)
+    }
+}
+
+/// Replay the log in [`REPLAY_LOG_ENV`] through `router`, if it is set and `env` allows it
+///
+/// Returns `None` if replay mode isn't enabled.
+pub(crate) async fn replay_from_env(
+    env: &BlueprintEnvironment,
+    router: &Router,
+) -> Option<io::Result<()>> {
+    let log = std::env::var_os(REPLAY_LOG_ENV)?;
+    if !(env.test_mode && env.dry_run) {
+        blueprint_core::warn!(
+            target: "blueprint-runner",
+            "{REPLAY_LOG_ENV} is set, but replays only run in test and dry-run mode; ignoring it"
+        );
+        return None;
+    }
+    let output = std::env::var_os(REPLAY_OUTPUT_ENV)
+        .map_or_else(|| Path::new(&log).with_extension("replayed"), Into::into);
+
+    Some(
+        async {
+            let recording = Recording::open(&log)?;
+            blueprint_core::info!(
+                target: "blueprint-runner",
+                calls = recording.len(),
+                "Replaying recorded job calls"
+            );
+
+            let replayed = recording.replay(router.clone()).await;
+            replayed.save(&output)?;
+            blueprint_core::info!(
+                target: "blueprint-runner",
+                output = %output.display(),
+                differences = recording.diff(&replayed).len(),
+                "Finished replaying recorded job calls"
+            );
+            Ok(())
+        }
+        .await,
+    )
+}
+
+/// What became of a job call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    /// The job call produced results (which may themselves be errors)
+    Results(Vec<StoredResult>),
+    /// The router ignored the job call
+    Ignored,
+    /// The job call failed, and was not retried
+    Failed(String),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Results(results) => {
+                f.write_str("[")?;
+                for (i, result) in results.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{result}")?;
+                }
+                f.write_str("]")
+            }
+            Outcome::Ignored => f.write_str("ignored"),
+            Outcome::Failed(error) => write!(f, "failed: {error}"),
+        }
+    }
+}
+
+/// A single line of the log
+#[derive(Serialize, Deserialize)]
+struct Record {
+    seq: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    call: Option<StoredCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    outcome: Option<Outcome>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blueprint_core::Bytes;
+    use futures::StreamExt;
+    use futures::stream;
+
+    #[tokio::test]
+    async fn recorder_continues_an_existing_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calls.log");
+
+        for body in [&b"first"[..], b"second"] {
+            let recorder = Recorder::open(&path).unwrap();
+            let calls = stream::iter([Ok::<_, BoxError>(JobCall::new(
+                0u32,
+                Bytes::copy_from_slice(body),
+            ))]);
+            let call = recorder.producer(calls).next().await.unwrap().unwrap();
+            call.extensions()
+                .get::<RecordedCall>()
+                .unwrap()
+                .results(&[JobResult::new(Bytes::from_static(b"done"))]);
+        }
+
+        let recording = Recording::open(&path).unwrap();
+        let bodies = recording
+            .calls()
+            .map(|call| call.body().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(bodies, [&b"first"[..], b"second"]);
+        assert!(
+            recording
+                .calls
+                .values()
+                .all(|entry| entry.outcome.is_some())
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_requires_test_and_dry_run_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("calls.log");
+        let output = dir.path().join("replayed.log");
+        Recording::default().save(&log).unwrap();
+
+        // SAFETY: No other test reads or writes these variables
+        unsafe {
+            std::env::set_var(REPLAY_LOG_ENV, &log);
+            std::env::set_var(REPLAY_OUTPUT_ENV, &output);
+        }
+
+        let router = Router::new().route(0u32, async || "done");
+        let mut env = BlueprintEnvironment::default();
+        env.test_mode = true;
+        assert!(replay_from_env(&env, &router).await.is_none());
+        assert!(!output.exists());
+
+        env.dry_run = true;
+        replay_from_env(&env, &router).await.unwrap().unwrap();
+        assert!(output.exists());
+
+        // SAFETY: See above
+        unsafe {
+            std::env::remove_var(REPLAY_LOG_ENV);
+            std::env::remove_var(REPLAY_OUTPUT_ENV);
+        }
+    }
+}
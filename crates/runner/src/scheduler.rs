@@ -0,0 +1,517 @@
+//! Concurrency limits and priority scheduling for job calls
+//!
+//! Every job call yielded by a producer is placed into a [`PriorityClass`] queue, and only
+//! dispatched once doing so stays within the runner's concurrency limits:
+//!
+//! * The global limit caps how many job calls run at once, across all jobs
+//! * Per-job limits cap how many calls to a specific job run at once (e.g. a GPU job that can only
+//!   run one at a time)
+//!
+//! Whenever there is room to run another job call, queues are served in priority order. Job calls
+//! that don't match any class go into a default queue, served last.
+//!
+//! Queues are bounded, and backpressure is applied per class. A full queue doesn't stop the runner
+//! from accepting job calls for other classes, but once a producer yields a call for a full class,
+//! that call is held back and the runner stops polling its producers until the class has room
+//! again.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use blueprint_router::Router;
+//! use blueprint_runner::BlueprintRunner;
+//! use blueprint_runner::config::BlueprintEnvironment;
+//! use blueprint_runner::scheduler::{PriorityClass, SchedulerConfig};
+//!
+//! const RENDER_JOB: u32 = 0;
+//! const CLEANUP_JOB: u32 = 1;
+//!
+//! let router = Router::new()
+//!     .route(RENDER_JOB, async || "rendered")
+//!     .route(CLEANUP_JOB, async || "cleaned up");
+//!
+//! let scheduler = SchedulerConfig::new()
+//!     .max_concurrency(256)
+//!     // Only one render at a time, it needs the whole GPU
+//!     .job_concurrency(RENDER_JOB, 1)
+//!     // Paid x402 calls are served first...
+//!     .class(PriorityClass::new("paid").metadata("X-X402-ORIGIN", "x402"))
+//!     // ...then the cron-driven cleanup job...
+//!     .class(PriorityClass::new("cron").job(CLEANUP_JOB).queue_capacity(16))
+//!     // ...then everything else
+//!     .default_class_queue_capacity(512);
+//!
+//! let builder = BlueprintRunner::builder((), BlueprintEnvironment::default())
+//!     .router(router)
+//!     .scheduler(scheduler);
+//! ```
+
+use crate::metrics;
+use blueprint_core::metadata::MetadataValue;
+use blueprint_core::{JobCall, JobId};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+
+/// The default global concurrency limit
+pub const DEFAULT_MAX_CONCURRENCY: usize = 1024;
+
+/// The default capacity of a [`PriorityClass`] queue
+pub const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+/// The name of the class that job calls fall into when no [`PriorityClass`] matches them
+pub const DEFAULT_CLASS: &str = "default";
+
+/// Scheduling configuration for a [`BlueprintRunner`]
+///
+/// See the [module docs](self) for details.
+///
+/// [`BlueprintRunner`]: crate::BlueprintRunner
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    max_concurrency: usize,
+    job_concurrency: HashMap<JobId, usize>,
+    classes: Vec<PriorityClass>,
+    default_queue_capacity: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            job_concurrency: HashMap::new(),
+            classes: Vec::new(),
+            default_queue_capacity: DEFAULT_QUEUE_CAPACITY,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// Create a new `SchedulerConfig`
+    ///
+    /// By default, up to [`DEFAULT_MAX_CONCURRENCY`] job calls can run at once, there are no
+    /// per-job limits, and every job call shares a single queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum number of job calls running at once, across all jobs
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is 0
+    pub fn max_concurrency(mut self, limit: usize) -> Self {
+        assert!(limit > 0, "concurrency limit must be non-zero");
+        self.max_concurrency = limit;
+        self
+    }
+
+    /// The maximum number of calls to `job_id` running at once
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is 0
+    pub fn job_concurrency<I: Into<JobId>>(mut self, job_id: I, limit: usize) -> Self {
+        assert!(limit > 0, "concurrency limit must be non-zero");
+        self.job_concurrency.insert(job_id.into(), limit);
+        self
+    }
+
+    /// Add a priority class
+    ///
+    /// Classes are served in the order they are added, from highest to lowest priority. A job
+    /// call belongs to the first class that matches it.
+    ///
+    /// # Panics
+    ///
+    /// If a class with the same name was already added, or the class is named [`DEFAULT_CLASS`]
+    pub fn class(mut self, class: PriorityClass) -> Self {
+        assert!(
+            class.name != DEFAULT_CLASS && self.classes.iter().all(|c| c.name != class.name),
+            "priority class names must be unique, found `{}` twice",
+            class.name
+        );
+        self.classes.push(class);
+        self
+    }
+
+    /// The capacity of the queue for job calls that don't match any class
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is 0
+    pub fn default_class_queue_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "queue capacity must be non-zero");
+        self.default_queue_capacity = capacity;
+        self
+    }
+}
+
+/// A class of job calls that share a queue and a priority
+///
+/// A class matches a job call if *any* of its rules match. A class without rules matches nothing.
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct PriorityClass {
+    name: Cow<'static, str>,
+    job_ids: Vec<JobId>,
+    metadata: Vec<(Cow<'static, str>, Option<MetadataValue>)>,
+    queue_capacity: usize,
+}
+
+impl PriorityClass {
+    /// Create a new, empty class
+    ///
+    /// The `name` is used for logging and as the `class` label of the queue depth metrics.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            name: name.into(),
+            job_ids: Vec::new(),
+            metadata: Vec::new(),
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+        }
+    }
+
+    /// Match calls to `job_id`
+    pub fn job<I: Into<JobId>>(mut self, job_id: I) -> Self {
+        self.job_ids.push(job_id.into());
+        self
+    }
+
+    /// Match job calls with a metadata entry for `key`, with any value
+    pub fn metadata_key(mut self, key: impl Into<Cow<'static, str>>) -> Self {
+        self.metadata.push((key.into(), None));
+        self
+    }
+
+    /// Match job calls with a metadata entry for `key` equal to `value`
+    pub fn metadata(
+        mut self,
+        key: impl Into<Cow<'static, str>>,
+        value: impl Into<MetadataValue>,
+    ) -> Self {
+        self.metadata.push((key.into(), Some(value.into())));
+        self
+    }
+
+    /// The maximum number of job calls waiting in this class
+    ///
+    /// Defaults to [`DEFAULT_QUEUE_CAPACITY`].
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is 0
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "queue capacity must be non-zero");
+        self.queue_capacity = capacity;
+        self
+    }
+
+    fn matches(&self, call: &JobCall) -> bool {
+        self.job_ids.contains(&call.job_id())
+            || self.metadata.iter().any(|(key, expected)| {
+                match (call.metadata().get(key.as_ref()), expected) {
+                    (Some(_), None) => true,
+                    (Some(value), Some(expected)) => value.as_bytes() == expected.as_bytes(),
+                    (None, _) => false,
+                }
+            })
+    }
+}
+
+struct Queue {
+    name: Cow<'static, str>,
+    capacity: usize,
+    calls: VecDeque<JobCall>,
+}
+
+impl Queue {
+    fn update_depth_metric(&self) {
+        metrics::QUEUE_DEPTH
+            .with_label_values(&[self.name.as_ref()])
+            .set(self.calls.len() as i64);
+    }
+}
+
+/// The runtime state of a [`SchedulerConfig`]
+pub(crate) struct Scheduler {
+    max_concurrency: usize,
+    job_concurrency: HashMap<JobId, usize>,
+    classes: Vec<PriorityClass>,
+    /// One queue per class, followed by the default queue
+    queues: Vec<Queue>,
+    /// Job calls that arrived while their queue was full, by queue index
+    held_back: VecDeque<(usize, JobCall)>,
+    running: usize,
+    running_per_job: HashMap<JobId, usize>,
+}
+
+impl Scheduler {
+    pub(crate) fn new(config: SchedulerConfig) -> Self {
+        let SchedulerConfig {
+            max_concurrency,
+            job_concurrency,
+            classes,
+            default_queue_capacity,
+        } = config;
+
+        let queues = classes
+            .iter()
+            .map(|class| (class.name.clone(), class.queue_capacity))
+            .chain([(Cow::Borrowed(DEFAULT_CLASS), default_queue_capacity)])
+            .map(|(name, capacity)| Queue {
+                name,
+                capacity,
+                calls: VecDeque::new(),
+            })
+            .collect::<Vec<_>>();
+
+        for queue in &queues {
+            queue.update_depth_metric();
+        }
+
+        Self {
+            max_concurrency,
+            job_concurrency,
+            classes,
+            queues,
+            held_back: VecDeque::new(),
+            running: 0,
+            running_per_job: HashMap::new(),
+        }
+    }
+
+    /// Whether another job call can be accepted, i.e. no call is held back by a full queue
+    pub(crate) fn has_capacity(&self) -> bool {
+        self.held_back.is_empty()
+    }
+
+    /// The number of job calls waiting to be dispatched
+    pub(crate) fn queued(&self) -> usize {
+        self.queues
+            .iter()
+            .map(|queue| queue.calls.len())
+            .sum::<usize>()
+            + self.held_back.len()
+    }
+
+    /// The job IDs of every queued job call
+    pub(crate) fn queued_jobs(&self) -> impl Iterator<Item = JobId> + '_ {
+        self.queues
+            .iter()
+            .flat_map(|queue| queue.calls.iter())
+            .chain(self.held_back.iter().map(|(_, call)| call))
+            .map(JobCall::job_id)
+    }
+
+    /// Queue a job call in its class
+    pub(crate) fn enqueue(&mut self, call: JobCall) {
+        let index = self
+            .classes
+            .iter()
+            .position(|class| class.matches(&call))
+            .unwrap_or(self.classes.len());
+
+        let queue = &mut self.queues[index];
+        if queue.calls.len() >= queue.capacity {
+            blueprint_core::debug!(
+                target: "blueprint-runner",
+                job_id = %call.job_id(),
+                class = %queue.name,
+                "Queue is full, holding back job call"
+            );
+            self.held_back.push_back((index, call));
+            return;
+        }
+
+        blueprint_core::trace!(
+            target: "blueprint-runner",
+            job_id = %call.job_id(),
+            class = %queue.name,
+            "Queued job call"
+        );
+        queue.calls.push_back(call);
+        queue.update_depth_metric();
+    }
+
+    /// Take the highest priority job call that can run within the concurrency limits
+    ///
+    /// The call is counted as running until [`Self::finished()`] is called for its job.
+    pub(crate) fn next_runnable(&mut self) -> Option<JobCall> {
+        if self.running >= self.max_concurrency {
+            return None;
+        }
+
+        for (index, queue) in self.queues.iter_mut().enumerate() {
+            let position = queue.calls.iter().position(|call| {
+                let job_id = call.job_id();
+                self.job_concurrency.get(&job_id).is_none_or(|limit| {
+                    self.running_per_job.get(&job_id).copied().unwrap_or(0) < *limit
+                })
+            });
+
+            if let Some(call) = position.and_then(|position| queue.calls.remove(position)) {
+                // The queue has room again, for the oldest call held back for it
+                if let Some((_, held)) = self
+                    .held_back
+                    .iter()
+                    .position(|(i, _)| *i == index)
+                    .and_then(|position| self.held_back.remove(position))
+                {
+                    queue.calls.push_back(held);
+                }
+                queue.update_depth_metric();
+                self.running += 1;
+                *self.running_per_job.entry(call.job_id()).or_default() += 1;
+                return Some(call);
+            }
+        }
+
+        None
+    }
+
+    /// Release the concurrency slot held by a call to `job_id`
+    pub(crate) fn finished(&mut self, job_id: JobId) {
+        self.running = self.running.saturating_sub(1);
+        if let Some(running) = self.running_per_job.get_mut(&job_id) {
+            *running = running.saturating_sub(1);
+        }
+    }
+
+    /// Drop every queued job call and release every slot, returning how many calls were queued
+    pub(crate) fn clear(&mut self) -> usize {
+        let queued = self.queued();
+        self.held_back.clear();
+        for queue in &mut self.queues {
+            queue.calls.clear();
+            queue.update_depth_metric();
+        }
+        self.running = 0;
+        self.running_per_job.clear();
+        queued
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    const GPU_JOB: u32 = 0;
+    const CHEAP_JOB: u32 = 1;
+
+    fn call(job_id: u32) -> JobCall {
+        JobCall::new(job_id, Bytes::new())
+    }
+
+    fn paid_call(job_id: u32) -> JobCall {
+        let mut call = call(job_id);
+        call.metadata_mut().insert("X-X402-ORIGIN", "x402");
+        call
+    }
+
+    #[test]
+    fn per_job_limit_does_not_block_other_jobs() {
+        let mut scheduler = Scheduler::new(SchedulerConfig::new().job_concurrency(GPU_JOB, 1));
+        scheduler.enqueue(call(GPU_JOB));
+        scheduler.enqueue(call(GPU_JOB));
+        scheduler.enqueue(call(CHEAP_JOB));
+
+        assert_eq!(scheduler.next_runnable().unwrap().job_id(), GPU_JOB.into());
+        assert_eq!(
+            scheduler.next_runnable().unwrap().job_id(),
+            CHEAP_JOB.into()
+        );
+        assert!(scheduler.next_runnable().is_none());
+
+        scheduler.finished(GPU_JOB.into());
+        assert_eq!(scheduler.next_runnable().unwrap().job_id(), GPU_JOB.into());
+        assert_eq!(scheduler.queued(), 0);
+    }
+
+    #[test]
+    fn higher_priority_classes_are_served_first() {
+        let mut scheduler = Scheduler::new(
+            SchedulerConfig::new()
+                .max_concurrency(1)
+                .class(PriorityClass::new("paid").metadata("X-X402-ORIGIN", "x402"))
+                .class(PriorityClass::new("cheap").job(CHEAP_JOB)),
+        );
+        scheduler.enqueue(call(GPU_JOB));
+        scheduler.enqueue(call(CHEAP_JOB));
+        scheduler.enqueue(paid_call(CHEAP_JOB));
+
+        let order = (0..3)
+            .map(|_| {
+                let call = scheduler.next_runnable().unwrap();
+                assert!(scheduler.next_runnable().is_none(), "global limit exceeded");
+                scheduler.finished(call.job_id());
+                (
+                    call.job_id(),
+                    call.metadata().get("X-X402-ORIGIN").is_some(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            order,
+            [
+                (CHEAP_JOB.into(), true),
+                (CHEAP_JOB.into(), false),
+                (GPU_JOB.into(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn full_queue_reports_no_capacity() {
+        let mut scheduler = Scheduler::new(
+            SchedulerConfig::new().class(PriorityClass::new("gpu").job(GPU_JOB).queue_capacity(1)),
+        );
+        assert!(scheduler.has_capacity());
+        scheduler.enqueue(call(GPU_JOB));
+        assert!(
+            scheduler.has_capacity(),
+            "a full queue only blocks its own calls"
+        );
+        scheduler.enqueue(call(GPU_JOB));
+        assert!(!scheduler.has_capacity());
+        assert_eq!(scheduler.clear(), 2);
+        assert!(scheduler.has_capacity());
+    }
+
+    #[test]
+    fn held_back_call_joins_its_queue_once_it_has_room() {
+        let mut scheduler = Scheduler::new(
+            SchedulerConfig::new()
+                .job_concurrency(GPU_JOB, 1)
+                .class(PriorityClass::new("gpu").job(GPU_JOB).queue_capacity(1)),
+        );
+        scheduler.enqueue(call(GPU_JOB));
+        scheduler.enqueue(call(GPU_JOB));
+        assert!(!scheduler.has_capacity());
+
+        assert_eq!(scheduler.next_runnable().unwrap().job_id(), GPU_JOB.into());
+        assert!(scheduler.has_capacity());
+        assert_eq!(scheduler.queued(), 1);
+        scheduler.enqueue(call(CHEAP_JOB));
+        assert_eq!(
+            scheduler.next_runnable().unwrap().job_id(),
+            CHEAP_JOB.into()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "priority class names must be unique")]
+    fn duplicate_class_names_are_rejected() {
+        let _ = SchedulerConfig::new()
+            .class(PriorityClass::new("paid").job(GPU_JOB))
+            .class(PriorityClass::new("paid").job(CHEAP_JOB));
+    }
+
+    #[test]
+    #[should_panic(expected = "queue capacity must be non-zero")]
+    fn zero_queue_capacity_is_rejected() {
+        let _ = PriorityClass::new("gpu").queue_capacity(0);
+    }
+}
@@ -0,0 +1,234 @@
+//! Serializable forms of [`JobCall`]s and [`JobResult`]s, shared by the [`journal`] and the
+//! [`record`] log
+//!
+//! [`journal`]: crate::journal
+//! [`record`]: crate::record
+
+use blueprint_core::error::ErrorKind;
+use blueprint_core::job::call::Parts as CallParts;
+use blueprint_core::job::result::Parts as ResultParts;
+use blueprint_core::metadata::{MetadataMap, MetadataValue};
+use blueprint_core::{Bytes, JobCall, JobId, JobResult};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+/// A serializable [`JobCall`]
+///
+/// Extensions are not preserved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct StoredCall {
+    job_id: [u64; 4],
+    metadata: Vec<StoredMetadata>,
+    #[serde(with = "hex_bytes")]
+    body: Bytes,
+}
+
+impl StoredCall {
+    pub(crate) fn job_id(&self) -> JobId {
+        JobId(self.job_id)
+    }
+}
+
+impl From<&JobCall> for StoredCall {
+    fn from(call: &JobCall) -> Self {
+        Self {
+            job_id: call.job_id().0,
+            metadata: store_metadata(call.metadata()),
+            body: call.body().clone(),
+        }
+    }
+}
+
+impl From<StoredCall> for JobCall {
+    fn from(call: StoredCall) -> Self {
+        let parts =
+            CallParts::new(JobId(call.job_id)).with_metadata(restore_metadata(call.metadata));
+        JobCall::from_parts(parts, call.body)
+    }
+}
+
+/// A serializable [`JobResult`]
+///
+/// Errors are only preserved as their message and [`ErrorKind`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StoredResult {
+    Ok {
+        metadata: Vec<StoredMetadata>,
+        #[serde(with = "hex_bytes")]
+        body: Bytes,
+    },
+    Err {
+        message: String,
+        #[serde(default, skip_serializing_if = "StoredErrorKind::is_other")]
+        kind: StoredErrorKind,
+    },
+}
+
+/// A serializable [`ErrorKind`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StoredErrorKind {
+    TimedOut,
+    Disabled,
+    #[default]
+    Other,
+}
+
+impl StoredErrorKind {
+    fn is_other(&self) -> bool {
+        *self == StoredErrorKind::Other
+    }
+}
+
+impl From<ErrorKind> for StoredErrorKind {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::TimedOut => StoredErrorKind::TimedOut,
+            ErrorKind::Disabled => StoredErrorKind::Disabled,
+            _ => StoredErrorKind::Other,
+        }
+    }
+}
+
+impl From<StoredErrorKind> for ErrorKind {
+    fn from(kind: StoredErrorKind) -> Self {
+        match kind {
+            StoredErrorKind::TimedOut => ErrorKind::TimedOut,
+            StoredErrorKind::Disabled => ErrorKind::Disabled,
+            StoredErrorKind::Other => ErrorKind::Other,
+        }
+    }
+}
+
+impl From<&JobResult> for StoredResult {
+    fn from(result: &JobResult) -> Self {
+        match result {
+            JobResult::Ok { head, body } => StoredResult::Ok {
+                metadata: store_metadata(&head.metadata),
+                body: body.clone(),
+            },
+            JobResult::Err(e) => StoredResult::Err {
+                message: e.to_string(),
+                kind: e.kind().into(),
+            },
+        }
+    }
+}
+
+impl From<StoredResult> for JobResult {
+    fn from(result: StoredResult) -> Self {
+        match result {
+            StoredResult::Ok { metadata, body } => {
+                let mut head = ResultParts::new();
+                head.metadata = restore_metadata(metadata);
+                JobResult::from_parts(head, body)
+            }
+            StoredResult::Err { message, kind } => {
+                JobResult::Err(blueprint_core::Error::with_kind(kind.into(), message))
+            }
+        }
+    }
+}
+
+impl fmt::Display for StoredResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoredResult::Ok { metadata, body } => {
+                write!(f, "0x{}", hex::encode(body))?;
+                for StoredMetadata {
+                    key,
+                    value,
+                    sensitive,
+                } in metadata
+                {
+                    if *sensitive {
+                        write!(f, " {key}=<sensitive>")?;
+                    } else {
+                        write!(f, " {key}=0x{}", hex::encode(value))?;
+                    }
+                }
+                Ok(())
+            }
+            StoredResult::Err { message, kind } => match kind {
+                StoredErrorKind::TimedOut => write!(f, "timed out: {message}"),
+                StoredErrorKind::Disabled => write!(f, "disabled: {message}"),
+                StoredErrorKind::Other => write!(f, "error: {message}"),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct StoredMetadata {
+    key: String,
+    #[serde(with = "hex_bytes")]
+    value: Bytes,
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    sensitive: bool,
+}
+
+fn store_metadata(metadata: &MetadataMap<MetadataValue>) -> Vec<StoredMetadata> {
+    metadata
+        .iter()
+        .map(|(key, value)| StoredMetadata {
+            key: key.to_string(),
+            value: Bytes::copy_from_slice(value.as_bytes()),
+            sensitive: value.is_sensitive(),
+        })
+        .collect()
+}
+
+fn restore_metadata(metadata: Vec<StoredMetadata>) -> MetadataMap<MetadataValue> {
+    let mut map = MetadataMap::new();
+    for StoredMetadata {
+        key,
+        value,
+        sensitive,
+    } in metadata
+    {
+        let value = if sensitive {
+            MetadataValue::from_sensitive_bytes(value)
+        } else {
+            MetadataValue::from_bytes(value)
+        };
+        map.insert(key, value);
+    }
+    map
+}
+
+mod hex_bytes {
+    use blueprint_core::Bytes;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        let s = <&str>::deserialize(deserializer)?;
+        hex::decode(s)
+            .map(Bytes::from)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_kinds_survive_a_round_trip() {
+        for kind in [ErrorKind::TimedOut, ErrorKind::Disabled, ErrorKind::Other] {
+            let result = JobResult::Err(blueprint_core::Error::with_kind(kind, "failed"));
+            let json = serde_json::to_string(&StoredResult::from(&result)).unwrap();
+            let stored: StoredResult = serde_json::from_str(&json).unwrap();
+
+            let JobResult::Err(e) = JobResult::from(stored) else {
+                panic!("expected an error, got {json}");
+            };
+            assert_eq!(e.kind(), kind);
+            assert_eq!(e.to_string(), "failed");
+        }
+    }
+}
@@ -195,6 +195,8 @@ async fn requires_registration_impl(env: &BlueprintEnvironment) -> Result<bool,
         data_dir: env.data_dir.clone(),
         test_mode: env.test_mode,
         dry_run: env.dry_run,
+        fallback_http_rpc_endpoints: env.fallback_http_rpc_endpoints.clone(),
+        rpc_quorum: env.rpc_quorum,
     };
 
     // Create the EVM client
@@ -4,16 +4,35 @@
 //! when required components are missing. Integration tests for full runner execution
 //! require more complex setup with actual protocol implementations.
 
-use blueprint_core::error::BoxError;
+use blueprint_core::JobResult;
+use blueprint_core::error::{BoxError, ErrorKind};
 use blueprint_core::job::call::JobCall;
+use blueprint_qos::PrometheusServerConfig;
+use blueprint_qos::metrics::opentelemetry::OpenTelemetryConfig;
+use blueprint_qos::metrics::{EnhancedMetricsProvider, MetricsConfig};
+use blueprint_qos::servers::prometheus::PrometheusServer;
 use blueprint_router::Router;
 use blueprint_runner::config::BlueprintEnvironment;
+use blueprint_runner::consumer::ConsumerConfig;
+use blueprint_runner::drain::DrainConfig;
 use blueprint_runner::error::RunnerError;
+use blueprint_runner::failure::{FailurePolicy, RetryPolicy};
+use blueprint_runner::health::{HealthConfig, RegistrationState, StatusReport};
+use blueprint_runner::journal::JournalConfig;
+use blueprint_runner::record::{Recorder, Recording};
+use blueprint_runner::scheduler::{PriorityClass, SchedulerConfig};
 use blueprint_runner::{BackgroundService, BlueprintRunner};
-use futures::Stream;
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::{Stream, StreamExt, future, stream};
+use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::task::{Context, Poll};
 use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
 use tokio::sync::oneshot;
 use tokio::time::timeout;
 
@@ -282,6 +301,479 @@ async fn custom_config_is_accepted() {
     handle.abort();
 }
 
+// =============================================================================
+// FAILURE POLICY TESTS
+// =============================================================================
+
+/// A producer that yields the given calls, then stays pending forever
+fn calls_then_pending(
+    job_ids: &[u32],
+) -> impl Stream<Item = Result<JobCall, BoxError>> + Send + Unpin + 'static {
+    let calls: Vec<Result<JobCall, BoxError>> = job_ids
+        .iter()
+        .map(|id| Ok(JobCall::new(*id, Bytes::new())))
+        .collect();
+    stream::iter(calls).chain(stream::pending())
+}
+
+async fn panicking_job() -> &'static str {
+    panic!("job panicked")
+}
+
+#[tokio::test]
+async fn failing_job_aborts_runner_by_default() {
+    let env = test_env();
+    let router = Router::new().route(0u32, panicking_job);
+
+    let result = timeout(
+        Duration::from_millis(500),
+        BlueprintRunner::builder(ContinueRunningConfig, env)
+            .router(router)
+            .producer(calls_then_pending(&[0]))
+            .run(),
+    )
+    .await;
+
+    match result {
+        Ok(Err(RunnerError::JobCall(_))) => {} // Expected
+        other => panic!("Expected JobCall error, got: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn failing_job_with_log_and_continue_keeps_runner_alive() {
+    let env = test_env();
+    let router = Router::new()
+        .route(0u32, panicking_job)
+        .route(1u32, || async { "still running" });
+
+    let (results_tx, mut results_rx) = mpsc::unbounded();
+
+    let result = timeout(
+        Duration::from_millis(500),
+        BlueprintRunner::builder(ContinueRunningConfig, env)
+            .router(router)
+            .producer(calls_then_pending(&[0, 1]))
+            .consumer(results_tx)
+            .failure_policy(FailurePolicy::LogAndContinue)
+            .run(),
+    )
+    .await;
+
+    assert!(result.is_err(), "runner should still be running");
+    let job_result = results_rx.try_recv().expect("job 1 should complete");
+    assert_eq!(job_result.body().unwrap().as_ref(), b"still running");
+}
+
+#[tokio::test]
+async fn failing_job_is_retried_then_dead_lettered() {
+    static ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+
+    async fn counted_panicking_job() -> &'static str {
+        ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+        panic!("job panicked")
+    }
+
+    let env = test_env();
+    let router = Router::new().route(0u32, counted_panicking_job);
+
+    let (dead_letter_tx, mut dead_letter_rx) = mpsc::unbounded();
+
+    let result = timeout(
+        Duration::from_millis(500),
+        BlueprintRunner::builder(ContinueRunningConfig, env)
+            .router(router)
+            .producer(calls_then_pending(&[0]))
+            .job_failure_policy(
+                0u32,
+                FailurePolicy::Retry(
+                    RetryPolicy::new(2)
+                        .initial_backoff(Duration::from_millis(1))
+                        .on_exhausted(FailurePolicy::DeadLetter),
+                ),
+            )
+            .dead_letter_sink(dead_letter_tx)
+            .run(),
+    )
+    .await;
+
+    assert!(result.is_err(), "runner should still be running");
+    assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 3);
+
+    let letter = dead_letter_rx
+        .try_recv()
+        .expect("call should be dead-lettered");
+    assert_eq!(letter.call.job_id(), 0u32.into());
+    assert_eq!(letter.retries, 2);
+}
+
+// =============================================================================
+// SCHEDULER TESTS
+// =============================================================================
+
+#[tokio::test]
+async fn full_class_queue_does_not_block_other_classes() {
+    const STUCK_JOB: u32 = 0;
+    const FAST_JOB: u32 = 1;
+
+    let (results_tx, mut results_rx) = mpsc::unbounded::<JobResult>();
+    let router = Router::new()
+        .route(STUCK_JOB, || future::pending::<&'static str>())
+        .route(FAST_JOB, || async { "fast" });
+
+    // One stuck call runs, and the next one fills its class queue
+    let result = timeout(
+        Duration::from_millis(500),
+        BlueprintRunner::builder(ContinueRunningConfig, test_env())
+            .router(router)
+            .producer(calls_then_pending(&[
+                STUCK_JOB, STUCK_JOB, FAST_JOB, FAST_JOB, FAST_JOB,
+            ]))
+            .consumer(results_tx)
+            .scheduler(
+                SchedulerConfig::new()
+                    .job_concurrency(STUCK_JOB, 1)
+                    .class(PriorityClass::new("stuck").job(STUCK_JOB).queue_capacity(1)),
+            )
+            .run(),
+    )
+    .await;
+
+    assert!(result.is_err(), "runner should still be running");
+    assert_eq!(std::iter::from_fn(|| results_rx.try_recv().ok()).count(), 3);
+}
+
+// =============================================================================
+// JOURNAL TESTS
+// =============================================================================
+
+/// A producer that yields a call to job 0 with the given Tangle call IDs, then stays pending
+fn tangle_calls_then_pending(
+    call_ids: &[u64],
+) -> impl Stream<Item = Result<JobCall, BoxError>> + Send + Unpin + 'static {
+    let calls: Vec<Result<JobCall, BoxError>> = call_ids
+        .iter()
+        .map(|call_id| {
+            let mut call = JobCall::new(0u32, Bytes::new());
+            call.metadata_mut().insert("X-TANGLE-SERVICE-ID", 1u64);
+            call.metadata_mut().insert("X-TANGLE-CALL-ID", *call_id);
+            Ok(call)
+        })
+        .collect();
+    stream::iter(calls).chain(stream::pending())
+}
+
+#[tokio::test]
+async fn journal_skips_calls_submitted_before_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = JournalConfig::new(dir.path().join("jobs.journal"));
+
+    let run = |call_ids: &'static [u64]| {
+        let (results_tx, results_rx) = mpsc::unbounded();
+        let runner = BlueprintRunner::builder(ContinueRunningConfig, test_env())
+            .router(Router::new().route(0u32, || async { "done" }))
+            .producer(tangle_calls_then_pending(call_ids))
+            .consumer(results_tx)
+            .journal(journal.clone())
+            .run();
+        (timeout(Duration::from_millis(500), runner), results_rx)
+    };
+
+    // The producer yields call 1 twice, but it only runs once
+    let (runner, mut results_rx) = run(&[1, 1, 2]);
+    assert!(runner.await.is_err(), "runner should still be running");
+    assert_eq!(std::iter::from_fn(|| results_rx.try_recv().ok()).count(), 2);
+
+    // After a restart, the producer replays call 2 and yields a new call 3
+    let (runner, mut results_rx) = run(&[2, 3]);
+    assert!(runner.await.is_err(), "runner should still be running");
+    assert_eq!(std::iter::from_fn(|| results_rx.try_recv().ok()).count(), 1);
+}
+
+#[tokio::test]
+async fn journal_reruns_failed_calls_yielded_again() {
+    static ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+
+    async fn fails_once() -> &'static str {
+        assert!(ATTEMPTS.fetch_add(1, Ordering::SeqCst) > 0, "job panicked");
+        "done"
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let journal = JournalConfig::new(dir.path().join("jobs.journal"));
+
+    let run = || {
+        let (results_tx, results_rx) = mpsc::unbounded();
+        let runner = BlueprintRunner::builder(ContinueRunningConfig, test_env())
+            .router(Router::new().route(0u32, fails_once))
+            .producer(tangle_calls_then_pending(&[1]))
+            .consumer(results_tx)
+            .failure_policy(FailurePolicy::LogAndContinue)
+            .journal(journal.clone())
+            .run();
+        (timeout(Duration::from_millis(500), runner), results_rx)
+    };
+
+    let (runner, mut results_rx) = run();
+    assert!(runner.await.is_err(), "runner should still be running");
+    assert!(results_rx.try_recv().is_err());
+
+    // After a restart, the producer yields the failed call again, and it runs again
+    let (runner, mut results_rx) = run();
+    assert!(runner.await.is_err(), "runner should still be running");
+    assert_eq!(std::iter::from_fn(|| results_rx.try_recv().ok()).count(), 1);
+    assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 2);
+}
+
+// =============================================================================
+// RECORD AND REPLAY TESTS
+// =============================================================================
+
+#[tokio::test]
+async fn recorded_calls_replay_against_a_router() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("calls.log");
+    let recorder = Recorder::open(&path).unwrap();
+
+    let runner = BlueprintRunner::builder(ContinueRunningConfig, test_env())
+        .router(Router::new().route(0u32, || async { "done" }))
+        .producer(recorder.producer(tangle_calls_then_pending(&[1, 2])))
+        .run();
+    let result = timeout(Duration::from_millis(500), runner).await;
+    assert!(result.is_err(), "runner should still be running");
+    // Wait for the writer thread to write every pending record
+    drop(recorder);
+
+    let recording = Recording::open(&path).unwrap();
+    assert_eq!(recording.len(), 2);
+    let call_ids = recording
+        .calls()
+        .map(|call| u64::try_from(call.metadata().get("X-TANGLE-CALL-ID").unwrap()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(call_ids, [1, 2]);
+
+    // The same router reproduces every recorded result
+    let replayed = recording
+        .replay(Router::new().route(0u32, || async { "done" }))
+        .await;
+    assert!(recording.diff(&replayed).is_empty());
+
+    // A changed job shows up in the diff
+    let replayed = recording
+        .replay(Router::new().route(0u32, || async { "changed" }))
+        .await;
+    let diff = recording.diff(&replayed);
+    assert_eq!(diff.len(), 2);
+    assert_eq!(diff[0].seq(), 0);
+}
+
+// =============================================================================
+// CONSUMER TESTS
+// =============================================================================
+
+#[tokio::test]
+async fn isolated_consumer_failure_does_not_stop_the_runner() {
+    let (results_tx, mut results_rx) = mpsc::unbounded::<JobResult>();
+    let (job_1_tx, mut job_1_rx) = mpsc::unbounded::<JobResult>();
+    let failing = futures::sink::unfold((), |(), _: JobResult| async {
+        Err::<(), BoxError>("webhook unavailable".into())
+    });
+
+    let result = timeout(
+        Duration::from_millis(500),
+        BlueprintRunner::builder(ContinueRunningConfig, test_env())
+            .router(
+                Router::new()
+                    .route(0u32, || async { "zero" })
+                    .route(1u32, || async { "one" }),
+            )
+            .producer(calls_then_pending(&[0, 1]))
+            .consumer(results_tx)
+            .consumer_with(job_1_tx, ConsumerConfig::new("job-1").job(1u32))
+            .consumer_with(
+                Box::pin(failing),
+                ConsumerConfig::new("webhook")
+                    .isolated(RetryPolicy::new(1).initial_backoff(Duration::from_millis(1))),
+            )
+            .run(),
+    )
+    .await;
+
+    assert!(result.is_err(), "runner should still be running");
+    assert_eq!(std::iter::from_fn(|| results_rx.try_recv().ok()).count(), 2);
+    let job_1_result = job_1_rx.try_recv().expect("job 1 result should be routed");
+    assert_eq!(job_1_result.body().unwrap().as_ref(), b"one");
+    assert!(
+        job_1_rx.try_recv().is_err(),
+        "job 0 result should not be routed"
+    );
+}
+
+// =============================================================================
+// DRAIN TESTS
+// =============================================================================
+
+#[tokio::test]
+async fn shutdown_drains_in_flight_jobs_before_the_shutdown_handler() {
+    let (results_tx, mut results_rx) = mpsc::unbounded::<JobResult>();
+    let (handler_tx, mut handler_rx) = oneshot::channel::<usize>();
+    let router = Router::new().route(0u32, || async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        "slow"
+    });
+
+    // The producer ends right after the call, shutting the runner down while the job runs
+    let calls = stream::iter(vec![Ok::<_, BoxError>(JobCall::new(0u32, Bytes::new()))]);
+    let result = timeout(
+        Duration::from_secs(5),
+        BlueprintRunner::builder(ContinueRunningConfig, test_env())
+            .router(router)
+            .producer(calls)
+            .consumer(results_tx)
+            .drain(DrainConfig::new().on_sigterm(false))
+            .with_shutdown_handler(async move {
+                let delivered = std::iter::from_fn(|| results_rx.try_recv().ok()).count();
+                let _ = handler_tx.send(delivered);
+            })
+            .run(),
+    )
+    .await
+    .expect("runner should shut down");
+
+    assert!(matches!(
+        result,
+        Err(RunnerError::Producer(
+            blueprint_runner::error::ProducerError::StreamEnded
+        ))
+    ));
+    assert_eq!(
+        handler_rx.try_recv(),
+        Ok(1),
+        "the shutdown handler should run after the in-flight job was drained"
+    );
+}
+
+// =============================================================================
+// RUNNER HANDLE TESTS
+// =============================================================================
+
+#[tokio::test]
+async fn handle_toggles_jobs_and_replaces_the_router() {
+    let (calls_tx, calls_rx) = mpsc::unbounded::<Result<JobCall, BoxError>>();
+    let (results_tx, mut results_rx) = mpsc::unbounded::<JobResult>();
+
+    let builder = BlueprintRunner::builder(ContinueRunningConfig, test_env())
+        .router(Router::new().route(0u32, || async { "old" }))
+        .producer(calls_rx)
+        .consumer(results_tx);
+    let handle = builder.handle();
+    let call = || Ok(JobCall::new(0u32, Bytes::new()));
+
+    let control = async {
+        // Calls for a disabled job are rejected without running it
+        handle.disable_job(0u32);
+        calls_tx.unbounded_send(call()).unwrap();
+        match results_rx.next().await.unwrap() {
+            JobResult::Err(e) => assert_eq!(e.kind(), ErrorKind::Disabled),
+            JobResult::Ok { .. } => panic!("disabled job should not run"),
+        }
+
+        handle.enable_job(0u32);
+        assert!(handle.replace_router(Router::new().route(0u32, || async { "new" })));
+        // Give the runner a chance to pick up the new router
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        calls_tx.unbounded_send(call()).unwrap();
+        let result = results_rx.next().await.unwrap();
+        assert_eq!(result.body().unwrap().as_ref(), b"new");
+    };
+
+    tokio::select! {
+        result = builder.run() => panic!("runner exited early: {result:?}"),
+        result = timeout(Duration::from_secs(5), control) => result.expect("control timed out"),
+    }
+}
+
+// =============================================================================
+// HEALTH SERVER TESTS
+// =============================================================================
+
+/// Make a bare HTTP/1.1 `GET` request, returning the status code and body
+async fn http_get(address: SocketAddr, path: &str) -> std::io::Result<(u16, String)> {
+    let mut stream = TcpStream::connect(address).await?;
+    let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let code = head.split(' ').nth(1).unwrap().parse().unwrap();
+    Ok((code, body.to_owned()))
+}
+
+#[tokio::test]
+async fn health_server_reports_runner_status() {
+    let address = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let (calls_tx, calls_rx) = mpsc::unbounded::<Result<JobCall, BoxError>>();
+
+    let provider = Arc::new(
+        EnhancedMetricsProvider::new(MetricsConfig::default(), &OpenTelemetryConfig::default())
+            .unwrap(),
+    );
+    let metrics_server = PrometheusServer::new(
+        PrometheusServerConfig {
+            host: "127.0.0.1".into(),
+            port: address.port(),
+            ..Default::default()
+        },
+        Some(provider.shared_registry()),
+        provider,
+    )
+    .unwrap();
+
+    let runner = BlueprintRunner::builder(ContinueRunningConfig, test_env())
+        .router(Router::new().route(0u32, || future::pending::<()>()))
+        .producer(calls_rx)
+        .metrics_server(Arc::new(metrics_server))
+        .health_server(HealthConfig::new().chain_head(|| async { Ok::<_, BoxError>(20) }))
+        .run();
+
+    let probe = async {
+        // Wait for the runner to start accepting calls
+        while !matches!(http_get(address, "/readyz").await, Ok((200, _))) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(http_get(address, "/healthz").await.unwrap().0, 200);
+
+        let mut call = JobCall::new(0u32, Bytes::new());
+        call.metadata_mut().insert("X-TANGLE-BLOCK-NUMBER", 12u64);
+        calls_tx.unbounded_send(Ok(call)).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let (code, body) = http_get(address, "/status").await.unwrap();
+        assert_eq!(code, 200);
+        let status: StatusReport = serde_json::from_str(&body).unwrap();
+        assert!(status.ready);
+        assert_eq!(status.registration, RegistrationState::NotRequired);
+        assert_eq!(status.producer.last_block, Some(12));
+        assert_eq!(status.producer.lag, Some(8));
+        assert_eq!(status.in_flight.get("0"), Some(&1));
+
+        // The runner metrics are served next to the health endpoints
+        let (code, body) = http_get(address, "/metrics").await.unwrap();
+        assert_eq!(code, 200);
+        assert!(body.contains("blueprint_runner_queue_depth"), "{body}");
+    };
+
+    tokio::select! {
+        result = runner => panic!("runner exited early: {result:?}"),
+        result = timeout(Duration::from_secs(5), probe) => result.expect("probe timed out"),
+    }
+}
+
 // =============================================================================
 // ERROR TYPE TESTS
 // =============================================================================
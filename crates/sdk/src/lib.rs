@@ -109,7 +109,9 @@ pub use blueprint_manager_bridge as bridge;
 
 pub mod producers {
     #[cfg(feature = "cronjob")]
-    pub use blueprint_producers_extra::cron::CronJob;
+    pub use blueprint_producers_extra::cron::{
+        CronError, CronJob, DEFAULT_MAX_CATCH_UP, LastFiredStore, MissedFires, ScheduledAt,
+    };
 }
 
 pub use blueprint_router as router;
@@ -13,3 +13,19 @@ pub enum Error {
     #[error("database mutex poisoned")]
     Poisoned,
 }
+
+/// Errors of the stores built on a [`LocalDatabase`], such as producer checkpoints
+///
+/// Stores that aren't backed by a [`LocalDatabase`] report their own errors as
+/// [`Other`](Self::Other).
+///
+/// [`LocalDatabase`]: crate::LocalDatabase
+#[derive(thiserror::Error, Debug)]
+pub enum StoreError {
+    /// The database couldn't be read or written to
+    #[error(transparent)]
+    Database(#[from] Error),
+    /// Any other store error
+    #[error("{0}")]
+    Other(String),
+}
@@ -1,5 +1,7 @@
 mod error;
-pub use error::Error;
+mod writer;
+pub use error::{Error, StoreError};
+pub use writer::BackgroundWriter;
 
 use blueprint_std::collections::HashMap;
 use blueprint_std::fs;
@@ -56,7 +58,24 @@ where
     /// * The parent of `path` is not a directory
     /// * Unable to write to `path`
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        let path = path.as_ref();
+        Self::open_inner(path.as_ref(), false)
+    }
+
+    /// Reads a `LocalDatabase` from the given path, failing if its contents can't be parsed.
+    ///
+    /// Unlike [`open`](Self::open), which starts from an empty database when the file is
+    /// corrupted, this is meant for stores that must not silently lose their data.
+    ///
+    /// # Errors
+    ///
+    /// * The parent of `path` is not a directory
+    /// * Unable to read or write to `path`
+    /// * The file exists, but isn't a valid database
+    pub fn open_strict<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::open_inner(path.as_ref(), true)
+    }
+
+    fn open_inner(path: &Path, strict: bool) -> Result<Self, Error> {
         let parent_dir = path.parent().ok_or(Error::Io(std::io::Error::new(
             ErrorKind::NotFound,
             "parent directory not found",
@@ -67,7 +86,11 @@ where
 
         let data = if path.exists() {
             let content = fs::read_to_string(path)?;
-            serde_json::from_str(&content).unwrap_or_default()
+            match serde_json::from_str(&content) {
+                Ok(data) => data,
+                Err(e) if strict => return Err(e.into()),
+                Err(_) => HashMap::new(),
+            }
         } else {
             // Create an empty file with default empty JSON object
             let empty_data = HashMap::new();
@@ -82,6 +105,12 @@ where
         })
     }
 
+    /// The path of the database file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Returns the number of key-value pairs in the database.
     pub fn len(&self) -> Result<usize, Error> {
         let data = self.lock()?;
@@ -291,6 +320,19 @@ mod tests {
         assert!(db.is_empty().unwrap());
     }
 
+    #[test]
+    fn test_invalid_json_strict() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.json");
+
+        fs::write(&db_path, "{invalid_json}").unwrap();
+
+        let result = LocalDatabase::<u32>::open_strict(&db_path);
+        assert!(matches!(result, Err(Error::Serialization(_))));
+        // The corrupted file is left untouched
+        assert_eq!(fs::read_to_string(&db_path).unwrap(), "{invalid_json}");
+    }
+
     #[test]
     fn test_remove() {
         let dir = tempdir().unwrap();
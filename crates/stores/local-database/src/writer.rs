@@ -0,0 +1,110 @@
+use blueprint_std::sync::Arc;
+use tokio::sync::watch;
+
+/// Writes values in the background, so that the caller never waits on the disk
+///
+/// Only the latest value is written: values queued while an earlier one is still being written
+/// replace each other. The last value queued is still written after the writer is dropped.
+///
+/// This suits stores that only need the latest of a series of values, such as the position of a
+/// producer in a chain.
+///
+/// # Example
+///
+/// ```no_run
+/// use blueprint_store_local_database::{BackgroundWriter, LocalDatabase};
+///
+/// # async fn example() -> Result<(), blueprint_store_local_database::Error> {
+/// let db = LocalDatabase::<u64>::open("data.json")?;
+/// let writer = BackgroundWriter::spawn(move |block| {
+///     if let Err(e) = db.set("last-block", block) {
+///         eprintln!("Failed to save the last block: {e}");
+///     }
+/// });
+///
+/// for block in 0..100 {
+///     writer.write(block);
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct BackgroundWriter<T> {
+    latest: watch::Sender<Option<T>>,
+}
+
+impl<T> BackgroundWriter<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Spawn the task calling `write` with the latest value
+    ///
+    /// `write` runs on Tokio's blocking thread pool, and has to handle its own errors.
+    ///
+    /// # Panics
+    ///
+    /// If called outside of a Tokio runtime.
+    pub fn spawn<F>(write: F) -> Self
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        let write = Arc::new(write);
+        let (latest, mut pending) = watch::channel(None);
+        tokio::spawn(async move {
+            while pending.changed().await.is_ok() {
+                let Some(value) = pending.borrow_and_update().clone() else {
+                    continue;
+                };
+
+                let write = write.clone();
+                // A panic is already reported by the panic hook, keep writing the next values
+                let _ = tokio::task::spawn_blocking(move || write(value)).await;
+            }
+        });
+
+        Self { latest }
+    }
+
+    /// Queue `value`, replacing any value that wasn't written yet
+    pub fn write(&self, value: T) {
+        self.latest.send_replace(Some(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalDatabase;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_latest_value_is_written() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.json");
+        let db = Arc::new(LocalDatabase::<u32>::open(&db_path).unwrap());
+
+        let writer = {
+            let db = db.clone();
+            BackgroundWriter::spawn(move |value| db.set("key", value).unwrap())
+        };
+        for value in 1..=50 {
+            writer.write(value);
+        }
+        // Dropping the writer doesn't lose the last value
+        drop(writer);
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while LocalDatabase::<u32>::open(&db_path)
+            .unwrap()
+            .get("key")
+            .unwrap()
+            != Some(50)
+        {
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "the last value was never written"
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
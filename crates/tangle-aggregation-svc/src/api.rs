@@ -1,16 +1,25 @@
 //! HTTP API endpoints for the aggregation service
 
-use crate::service::AggregationService;
-use crate::state::{TaskConfig, ThresholdType};
+use crate::auth::EvidenceRecord;
+use crate::service::{AggregationService, ServiceError};
+use crate::state::TaskConfig;
 use crate::types::*;
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Query, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
     routing::{get, post},
-    Json, Router,
+    Extension, Json, Router,
 };
-use std::collections::HashMap;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 /// Build the API router
@@ -23,9 +32,26 @@ pub fn router(service: Arc<AggregationService>) -> Router {
         .route("/v1/tasks/status", post(get_status))
         .route("/v1/tasks/aggregate", post(get_aggregated))
         .route("/v1/tasks/mark-submitted", post(mark_submitted))
+        .route("/v1/tasks/events", get(task_events))
+        .route("/v1/tasks/ws", get(task_events_ws))
+        .route("/v1/evidence", get(get_evidence))
         .with_state(service)
 }
 
+/// Run a service call off the async runtime, as it may wait on the persistence backend
+async fn blocking<T>(
+    service: &Arc<AggregationService>,
+    call: impl FnOnce(&AggregationService) -> T + Send + 'static,
+) -> T
+where
+    T: Send + 'static,
+{
+    let service = Arc::clone(service);
+    tokio::task::spawn_blocking(move || call(&service))
+        .await
+        .unwrap_or_else(|error| std::panic::resume_unwind(error.into_panic()))
+}
+
 /// Health check endpoint
 async fn health() -> &'static str {
     "ok"
@@ -36,46 +62,35 @@ async fn init_task(
     State(service): State<Arc<AggregationService>>,
     Json(req): Json<InitTaskRequest>,
 ) -> impl IntoResponse {
-    let (threshold_type, operator_stakes) = match req.threshold {
-        ThresholdConfig::Count { required_signers } => {
-            (ThresholdType::Count(required_signers), None)
+    let result = if service.has_operator_source() {
+        match service.verify_init_request(req).await {
+            Ok(task) => blocking(&service, move |service| service.init_verified_task(task)).await,
+            Err(e) => Err(e),
         }
-        ThresholdConfig::StakeWeighted {
-            threshold_bps,
-            operator_stakes,
-        } => {
-            let stakes = operator_stakes
-                .into_iter()
-                .map(|stake| (stake.operator_index, stake.stake))
-                .collect::<HashMap<_, _>>();
-            (ThresholdType::StakeWeighted(threshold_bps), Some(stakes))
-        }
-    };
-
-    let config = TaskConfig {
-        threshold_type,
-        operator_stakes,
-        ..Default::default()
-    };
-
-    let result = if req.message.is_empty() {
-        service.init_task_with_config(
-            req.service_id,
-            req.call_id,
-            req.output,
-            req.operator_count,
-            config,
-        )
+    } else if req.message.is_empty() {
+        blocking(&service, move |service| {
+            service.init_task_with_config(
+                req.service_id,
+                req.call_id,
+                req.output,
+                req.operator_count,
+                TaskConfig::from(req.threshold),
+            )
+        })
+        .await
     } else {
-        service.init_task_with_message_config(
-            req.service_id,
-            req.call_id,
-            req.output,
-            req.message,
-            req.signature_scheme,
-            req.operator_count,
-            config,
-        )
+        blocking(&service, move |service| {
+            service.init_task_with_message_config(
+                req.service_id,
+                req.call_id,
+                req.output,
+                req.message,
+                req.signature_scheme,
+                req.operator_count,
+                TaskConfig::from(req.threshold),
+            )
+        })
+        .await
     };
 
     match result {
@@ -91,14 +106,41 @@ async fn init_task(
 }
 
 /// Submit a signature for aggregation
+///
+/// The client address is only known when the router is served with
+/// `into_make_service_with_connect_info::<SocketAddr>()`; otherwise the per-IP
+/// rate limit doesn't apply. Requests from a trusted proxy are attributed to
+/// the client named in their `X-Forwarded-For` header.
 async fn submit_signature(
     State(service): State<Arc<AggregationService>>,
-    Json(req): Json<SubmitSignatureRequest>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
+    Json(submission): Json<SignedSubmission>,
 ) -> impl IntoResponse {
-    match service.submit_signature(req) {
+    let forwarded_for = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+    let source = connect_info.map(|Extension(ConnectInfo(address))| {
+        service.client_ip(
+            address.ip(),
+            Some(forwarded_for.as_str()).filter(|value| !value.is_empty()),
+        )
+    });
+    match blocking(&service, move |service| {
+        service.submit_signed(submission, source)
+    })
+    .await
+    {
         Ok(response) => (StatusCode::OK, Json(response)),
         Err(e) => (
-            StatusCode::BAD_REQUEST,
+            match e {
+                ServiceError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+                ServiceError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::BAD_REQUEST,
+            },
             Json(SubmitSignatureResponse {
                 accepted: false,
                 signatures_collected: 0,
@@ -115,7 +157,10 @@ async fn get_status(
     State(service): State<Arc<AggregationService>>,
     Json(req): Json<GetStatusRequest>,
 ) -> impl IntoResponse {
-    let response = service.get_status(req.service_id, req.call_id);
+    let response = blocking(&service, move |service| {
+        service.get_status(req.service_id, req.call_id)
+    })
+    .await;
     Json(response)
 }
 
@@ -124,7 +169,11 @@ async fn get_aggregated(
     State(service): State<Arc<AggregationService>>,
     Json(req): Json<GetStatusRequest>,
 ) -> impl IntoResponse {
-    match service.get_aggregated_result(req.service_id, req.call_id) {
+    let result = blocking(&service, move |service| {
+        service.get_aggregated_result(req.service_id, req.call_id)
+    })
+    .await;
+    match result {
         Some(result) => (StatusCode::OK, Json(Some(result))),
         None => (StatusCode::NOT_FOUND, Json(None)),
     }
@@ -132,19 +181,77 @@ async fn get_aggregated(
 
 /// Get service statistics
 async fn get_stats(State(service): State<Arc<AggregationService>>) -> impl IntoResponse {
-    Json(service.get_stats())
+    Json(blocking(&service, AggregationService::get_stats).await)
 }
 
 /// Mark a task as submitted to the chain
 async fn mark_submitted(
     State(service): State<Arc<AggregationService>>,
-    Json(req): Json<GetStatusRequest>,
+    Json(req): Json<SignedMarkSubmitted>,
 ) -> impl IntoResponse {
-    match service.mark_submitted(req.service_id, req.call_id) {
+    match blocking(&service, move |service| service.mark_submitted_signed(req)).await {
         Ok(()) => (StatusCode::OK, Json(serde_json::json!({ "success": true }))),
         Err(e) => (
-            StatusCode::BAD_REQUEST,
+            match e {
+                ServiceError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+                _ => StatusCode::BAD_REQUEST,
+            },
             Json(serde_json::json!({ "success": false, "error": e.to_string() })),
         ),
     }
 }
+
+/// Query selecting the evidence to export
+#[derive(Debug, Deserialize)]
+struct EvidenceQuery {
+    service_id: Option<u64>,
+}
+
+/// Export evidence of invalid submissions
+async fn get_evidence(
+    State(service): State<Arc<AggregationService>>,
+    Query(query): Query<EvidenceQuery>,
+) -> Json<Vec<EvidenceRecord>> {
+    Json(blocking(&service, move |service| service.evidence(query.service_id)).await)
+}
+
+/// Stream task events as server-sent events
+async fn task_events(
+    State(service): State<Arc<AggregationService>>,
+    Query(filter): Query<TaskEventFilter>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let events = service
+        .subscribe(filter)
+        .map(|event| Event::default().json_data(event));
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Stream task events over a WebSocket, as JSON text messages
+async fn task_events_ws(
+    ws: WebSocketUpgrade,
+    State(service): State<Arc<AggregationService>>,
+    Query(filter): Query<TaskEventFilter>,
+) -> impl IntoResponse {
+    // Subscribe before the upgrade, so no event published meanwhile is missed
+    let events = service.subscribe(filter);
+    ws.on_upgrade(move |socket| forward_events(socket, events))
+}
+
+async fn forward_events(mut socket: WebSocket, events: impl Stream<Item = TaskEvent>) {
+    let mut events = std::pin::pin!(events);
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else { break };
+                let Ok(json) = serde_json::to_string(&event) else { continue };
+                if socket.send(Message::Text(json.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
@@ -0,0 +1,509 @@
+//! Authentication and rate limiting of signature submissions
+//!
+//! Checking a BLS signature costs a pairing, so an open `/v1/tasks/submit`
+//! endpoint is cheap to flood. Submissions can carry an envelope: an ECDSA
+//! signature over [`submission_digest`] by the operator's registered address.
+//! The envelope is checked before the BLS signature, against the operator
+//! addresses of the task (see [`crate::TaskConfig::operator_addresses`]), and
+//! submissions are rate limited per operator and per client IP.
+//!
+//! A submission with a valid envelope but a malformed BLS signature or public
+//! key is attributable to the operator, and is recorded as an
+//! [`EvidenceRecord`]. Signatures failing verification and mismatched outputs
+//! aren't, as the task they're checked against is initialized by an
+//! unauthenticated caller. Records are saved through the service's
+//! [`crate::PersistenceBackend`], and exported with
+//! [`AggregationService::evidence`] or `GET /v1/evidence`.
+//!
+//! The per-IP limit keys on the peer address of the connection. Behind a
+//! reverse proxy or load balancer every submission comes from the proxy, so
+//! list it in [`SubmissionAuthConfig::trusted_proxies`]; the client address is
+//! then taken from the `X-Forwarded-For` header the proxy appends to.
+//!
+//! ```rust,ignore
+//! let signer = PrivateKeySigner::from_bytes(&operator_key)?;
+//! let submission = sign_submission(request, &signer)?;
+//! service.submit_signed(submission, None)?;
+//! ```
+//!
+//! Marking a task as submitted stops the other operators from submitting it,
+//! so `/v1/tasks/mark-submitted` takes an envelope as well, over
+//! [`mark_submitted_digest`] by the address of the submitting operator. It is
+//! required under the same [`SubmissionAuthConfig::require_envelope`].
+//! Operators should still confirm a submission on chain before relying on it.
+//!
+//! [`AggregationService::evidence`]: crate::AggregationService::evidence
+
+use crate::types::{
+    MarkSubmittedRequest, SignedMarkSubmitted, SignedSubmission, SubmitSignatureRequest,
+};
+use alloy_primitives::{keccak256, Address, Signature, B256};
+use alloy_signer::SignerSync;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Domain separator of submission envelopes
+const ENVELOPE_DOMAIN: &[u8] = b"TANGLE_AGGREGATION_SUBMISSION_V1";
+
+/// Domain separator of mark-submitted envelopes
+const MARK_SUBMITTED_DOMAIN: &[u8] = b"TANGLE_AGGREGATION_MARK_SUBMITTED_V1";
+
+/// Number of idle buckets tracked before full ones are dropped
+const MAX_IDLE_BUCKETS: usize = 10_000;
+
+/// Errors from checking a submission envelope
+#[derive(Debug, Error)]
+pub enum AuthError {
+    /// The submission has no envelope
+    #[error("Submission is not signed by the operator")]
+    MissingEnvelope,
+    /// The envelope isn't a valid ECDSA signature
+    #[error("Invalid envelope signature: {0}")]
+    InvalidEnvelope(String),
+    /// The envelope was signed by another address than the operator's
+    #[error("Envelope signed by {signer}, but operator {operator_index} is {expected}")]
+    WrongSigner {
+        operator_index: u32,
+        signer: Address,
+        expected: Address,
+    },
+    /// The operator has no registered address to check the envelope against
+    #[error("Operator {0} has no registered address")]
+    UnknownOperator(u32),
+    /// A mark-submitted request doesn't name the operator that signed it
+    #[error("Request does not name the submitting operator")]
+    MissingOperator,
+    /// Signing the envelope failed
+    #[error("Signing failed: {0}")]
+    Signing(String),
+}
+
+/// The hash signed by a submission envelope
+///
+/// It commits to every field of the request, so an envelope can't be replayed
+/// for another task, operator, or signature.
+pub fn submission_digest(request: &SubmitSignatureRequest) -> B256 {
+    let mut preimage = Vec::with_capacity(ENVELOPE_DOMAIN.len() + 20 + 3 * 32);
+    preimage.extend_from_slice(ENVELOPE_DOMAIN);
+    preimage.extend_from_slice(&request.service_id.to_be_bytes());
+    preimage.extend_from_slice(&request.call_id.to_be_bytes());
+    preimage.extend_from_slice(&request.operator_index.to_be_bytes());
+    preimage.extend_from_slice(keccak256(&request.output).as_slice());
+    preimage.extend_from_slice(keccak256(&request.signature).as_slice());
+    preimage.extend_from_slice(keccak256(&request.public_key).as_slice());
+    keccak256(preimage)
+}
+
+/// Sign `request` with the operator's ECDSA key
+pub fn sign_submission(
+    request: SubmitSignatureRequest,
+    signer: &impl SignerSync,
+) -> Result<SignedSubmission, AuthError> {
+    let signature = signer
+        .sign_hash_sync(&submission_digest(&request))
+        .map_err(|error| AuthError::Signing(error.to_string()))?;
+    Ok(SignedSubmission {
+        request,
+        envelope_signature: signature.as_bytes().to_vec(),
+    })
+}
+
+/// Recover the address that signed the envelope of `submission`
+pub fn recover_submitter(submission: &SignedSubmission) -> Result<Address, AuthError> {
+    recover_envelope(
+        &submission.envelope_signature,
+        &submission_digest(&submission.request),
+    )
+}
+
+/// The hash signed by a mark-submitted envelope
+///
+/// It commits to the task, the transaction hash, and the operator, so an
+/// envelope can't be replayed for another task or transaction.
+pub fn mark_submitted_digest(request: &MarkSubmittedRequest) -> B256 {
+    let mut preimage = Vec::with_capacity(MARK_SUBMITTED_DOMAIN.len() + 16 + 2 * 33);
+    preimage.extend_from_slice(MARK_SUBMITTED_DOMAIN);
+    preimage.extend_from_slice(&request.service_id.to_be_bytes());
+    preimage.extend_from_slice(&request.call_id.to_be_bytes());
+    match request.tx_hash {
+        Some(tx_hash) => {
+            preimage.push(1);
+            preimage.extend_from_slice(tx_hash.as_slice());
+        }
+        None => preimage.push(0),
+    }
+    match request.operator_index {
+        Some(operator_index) => {
+            preimage.push(1);
+            preimage.extend_from_slice(&operator_index.to_be_bytes());
+        }
+        None => preimage.push(0),
+    }
+    keccak256(preimage)
+}
+
+/// Sign a mark-submitted `request` with the operator's ECDSA key
+pub fn sign_mark_submitted(
+    request: MarkSubmittedRequest,
+    signer: &impl SignerSync,
+) -> Result<SignedMarkSubmitted, AuthError> {
+    let signature = signer
+        .sign_hash_sync(&mark_submitted_digest(&request))
+        .map_err(|error| AuthError::Signing(error.to_string()))?;
+    Ok(SignedMarkSubmitted {
+        request,
+        envelope_signature: signature.as_bytes().to_vec(),
+    })
+}
+
+/// Recover the address that signed the envelope of a mark-submitted request
+pub fn recover_mark_submitter(request: &SignedMarkSubmitted) -> Result<Address, AuthError> {
+    recover_envelope(
+        &request.envelope_signature,
+        &mark_submitted_digest(&request.request),
+    )
+}
+
+fn recover_envelope(envelope: &[u8], digest: &B256) -> Result<Address, AuthError> {
+    if envelope.is_empty() {
+        return Err(AuthError::MissingEnvelope);
+    }
+    let signature = Signature::try_from(envelope)
+        .map_err(|error| AuthError::InvalidEnvelope(error.to_string()))?;
+    signature
+        .recover_address_from_prehash(digest)
+        .map_err(|error| AuthError::InvalidEnvelope(error.to_string()))
+}
+
+/// Allowed submission rate: `burst` submissions, refilled over `period`
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    /// Submissions allowed at once
+    pub burst: u32,
+    /// Time to refill the whole burst
+    pub period: Duration,
+}
+
+impl RateLimit {
+    /// Allow `burst` submissions per `period`
+    pub fn new(burst: u32, period: Duration) -> Self {
+        Self { burst, period }
+    }
+}
+
+/// Authentication and rate limits of signature submissions
+#[derive(Debug, Clone, Default)]
+pub struct SubmissionAuthConfig {
+    /// Reject submissions without a valid envelope from the operator's address
+    ///
+    /// Tasks then need registered operator addresses, which tasks initialized
+    /// through an [`crate::OperatorSetSource`] have.
+    pub require_envelope: bool,
+    /// Limit per operator of a service
+    pub per_operator: Option<RateLimit>,
+    /// Limit per client IP address
+    pub per_ip: Option<RateLimit>,
+    /// Proxies whose `X-Forwarded-For` header names the client address
+    ///
+    /// Without any, the per-IP limit applies to the peer address of the
+    /// connection. Only list proxies that append the address they received
+    /// the request from, since clients can send the header themselves.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl SubmissionAuthConfig {
+    /// The client address of a request from `peer`, for the per-IP limit
+    ///
+    /// If `peer` is a trusted proxy, `forwarded_for` is walked from the last
+    /// hop back, and the first address that isn't a trusted proxy is the
+    /// client. An unparsable hop stops the walk at the hop before it.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        let mut client = peer;
+        if !self.trusted_proxies.contains(&peer) {
+            return client;
+        }
+        for hop in forwarded_for
+            .into_iter()
+            .flat_map(|value| value.rsplit(','))
+        {
+            let Ok(address) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = address;
+            if !self.trusted_proxies.contains(&address) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// Token-bucket rate limiter, keyed by the submitter
+#[derive(Debug)]
+pub struct RateLimiter<K> {
+    limit: RateLimit,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl<K: Eq + Hash> RateLimiter<K> {
+    /// Create a limiter allowing `limit` per key
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `key`, returning `false` if it is over its limit
+    pub fn check(&self, key: K) -> bool {
+        let now = Instant::now();
+        let burst = f64::from(self.limit.burst);
+        let refill_per_sec = burst / self.limit.period.as_secs_f64().max(f64::EPSILON);
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_IDLE_BUCKETS {
+            // Full buckets behave like fresh ones, so they can be dropped
+            buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens + elapsed * refill_per_sec < burst
+            });
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(burst);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+/// An invalid submission, signed by the operator that sent it
+///
+/// The envelope signature makes the record verifiable by anyone, with
+/// [`recover_submitter`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceRecord {
+    /// Address that signed the envelope
+    pub operator: Address,
+    /// The submission as received
+    pub submission: SignedSubmission,
+    /// Why the submission was rejected
+    pub reason: String,
+    /// Invalid submissions recorded for the operator so far, this one included
+    pub offence_count: u64,
+    /// When the submission was received (unix timestamp millis)
+    pub recorded_at_ms: u64,
+}
+
+/// Bounded log of [`EvidenceRecord`]s, dropping the oldest when full
+///
+/// Offence counts are kept for at most `capacity` operators beyond those with
+/// retained records; once the map is over that, the counts of operators
+/// without retained records are dropped and start again from zero.
+#[derive(Debug)]
+pub(crate) struct EvidenceLog {
+    capacity: usize,
+    records: Mutex<(VecDeque<EvidenceRecord>, HashMap<Address, u64>)>,
+}
+
+impl EvidenceLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new((VecDeque::new(), HashMap::new())),
+        }
+    }
+
+    pub(crate) fn record(
+        &self,
+        operator: Address,
+        submission: SignedSubmission,
+        reason: String,
+    ) -> EvidenceRecord {
+        let mut guard = self.records.lock();
+        let (records, offences) = &mut *guard;
+        let offence_count = offences.entry(operator).or_default();
+        *offence_count += 1;
+        let record = EvidenceRecord {
+            operator,
+            submission,
+            reason,
+            offence_count: *offence_count,
+            recorded_at_ms: crate::persistence::now_millis(),
+        };
+        if records.len() >= self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record.clone());
+        if offences.len() > 2 * self.capacity {
+            let retained: HashSet<Address> = records.iter().map(|record| record.operator).collect();
+            offences.retain(|operator, _| retained.contains(operator));
+        }
+        record
+    }
+
+    /// Replace the log with `stored`, oldest first, as loaded from persistence
+    pub(crate) fn restore(&self, stored: Vec<EvidenceRecord>) {
+        let skip = stored.len().saturating_sub(self.capacity);
+        let records: VecDeque<_> = stored.into_iter().skip(skip).collect();
+        let mut offences = HashMap::new();
+        for record in &records {
+            let count = offences.entry(record.operator).or_insert(0);
+            *count = record.offence_count.max(*count);
+        }
+        *self.records.lock() = (records, offences);
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn export(&self, service_id: Option<u64>) -> Vec<EvidenceRecord> {
+        self.records
+            .lock()
+            .0
+            .iter()
+            .filter(|record| service_id.is_none_or(|id| id == record.submission.request.service_id))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_signer_local::PrivateKeySigner;
+
+    fn request() -> SubmitSignatureRequest {
+        SubmitSignatureRequest {
+            service_id: 1,
+            call_id: 2,
+            operator_index: 0,
+            output: vec![1, 2, 3],
+            signature: vec![4; 32],
+            public_key: vec![5; 64],
+        }
+    }
+
+    #[test]
+    fn envelope_recovers_the_signing_operator() {
+        let signer = PrivateKeySigner::random();
+        let submission = sign_submission(request(), &signer).unwrap();
+        assert_eq!(recover_submitter(&submission).unwrap(), signer.address());
+
+        // The envelope doesn't carry over to another signature
+        let mut tampered = submission.clone();
+        tampered.request.signature[0] ^= 1;
+        assert_ne!(recover_submitter(&tampered).unwrap(), signer.address());
+
+        let unsigned = SignedSubmission::from(request());
+        assert!(matches!(
+            recover_submitter(&unsigned),
+            Err(AuthError::MissingEnvelope)
+        ));
+    }
+
+    #[test]
+    fn mark_submitted_envelope_commits_to_the_transaction() {
+        let signer = PrivateKeySigner::random();
+        let request = MarkSubmittedRequest {
+            service_id: 1,
+            call_id: 2,
+            tx_hash: Some(B256::repeat_byte(1)),
+            operator_index: Some(0),
+        };
+        let signed = sign_mark_submitted(request, &signer).unwrap();
+        assert_eq!(recover_mark_submitter(&signed).unwrap(), signer.address());
+
+        let mut tampered = signed.clone();
+        tampered.request.tx_hash = Some(B256::repeat_byte(2));
+        assert_ne!(recover_mark_submitter(&tampered).unwrap(), signer.address());
+    }
+
+    #[test]
+    fn rate_limiter_allows_bursts_per_key() {
+        let limiter = RateLimiter::new(RateLimit::new(2, Duration::from_secs(3600)));
+        assert!(limiter.check(1));
+        assert!(limiter.check(1));
+        assert!(!limiter.check(1));
+        assert!(limiter.check(2));
+    }
+
+    #[test]
+    fn evidence_log_counts_offences_and_drops_oldest() {
+        let log = EvidenceLog::new(2);
+        let operator = Address::with_last_byte(1);
+        for _ in 0..3 {
+            log.record(operator, request().into(), "bad signature".to_string());
+        }
+        let records = log.export(None);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].offence_count, 3);
+        assert!(log.export(Some(9)).is_empty());
+
+        // Offence counts survive a restore of the retained records
+        let restored = EvidenceLog::new(2);
+        restored.restore(records);
+        let record = restored.record(operator, request().into(), "bad signature".to_string());
+        assert_eq!(record.offence_count, 4);
+    }
+
+    #[test]
+    fn evidence_log_bounds_offence_counts() {
+        let log = EvidenceLog::new(2);
+        for operator in 0..=4 {
+            log.record(
+                Address::with_last_byte(operator),
+                request().into(),
+                "bad signature".to_string(),
+            );
+        }
+        // Only operators with retained records keep their count
+        assert_eq!(log.records.lock().1.len(), 2);
+        let record = log.record(
+            Address::with_last_byte(0),
+            request().into(),
+            "bad signature".to_string(),
+        );
+        assert_eq!(record.offence_count, 1);
+    }
+
+    #[test]
+    fn client_ip_only_trusts_forwarded_for_from_proxies() {
+        let proxy = IpAddr::from([10, 0, 0, 1]);
+        let client = IpAddr::from([203, 0, 113, 7]);
+        let config = SubmissionAuthConfig {
+            trusted_proxies: vec![proxy],
+            ..Default::default()
+        };
+        assert_eq!(config.client_ip(client, Some("198.51.100.1")), client);
+        assert_eq!(config.client_ip(proxy, None), proxy);
+        // A client can prepend addresses, but not past the proxy's hop
+        assert_eq!(
+            config.client_ip(proxy, Some("198.51.100.1, 203.0.113.7")),
+            client
+        );
+        assert_eq!(
+            config.client_ip(proxy, Some("203.0.113.7, 10.0.0.1")),
+            client
+        );
+        assert_eq!(config.client_ip(proxy, Some("garbage")), proxy);
+    }
+}
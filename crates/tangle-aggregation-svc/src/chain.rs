@@ -0,0 +1,345 @@
+//! On-chain operator sets for verified task initialization
+//!
+//! By default, `POST /v1/tasks/init` trusts the operator count and threshold
+//! sent by the caller. A service configured with an [`OperatorSetSource`]
+//! (see [`AggregationService::with_operator_source`]) instead derives them
+//! from the chain, and rejects requests that disagree with it.
+//!
+//! [`TangleClient`] implements [`OperatorSetSource`], reading the operators of
+//! the service, their weights and BLS public keys, and the aggregation config
+//! of the called job.
+//!
+//! ```rust,ignore
+//! use blueprint_tangle_aggregation_svc::{AggregationService, ServiceConfig};
+//!
+//! let client = TangleClient::with_keystore(config, keystore).await?;
+//! let service = AggregationService::new(ServiceConfig::default()).with_operator_source(client);
+//! ```
+//!
+//! [`AggregationService::with_operator_source`]: crate::AggregationService::with_operator_source
+
+use crate::types::{OperatorStake, ThresholdConfig};
+use alloy_primitives::{Address, U256};
+use ark_bn254::{Fq, Fq2, G2Affine};
+use ark_ff::{BigInt, PrimeField};
+use blueprint_client_tangle::{AggregationConfig, TangleClient, ThresholdType};
+use blueprint_crypto_bn254::ArkBlsBn254Public;
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+use tracing::warn;
+
+/// Errors from an [`OperatorSetSource`]
+#[derive(Debug, Error)]
+pub enum ChainError {
+    /// The chain couldn't be queried
+    #[error("Chain query failed: {0}")]
+    Client(String),
+    /// The called job doesn't use BLS aggregation
+    #[error("Job {job_index} of service {service_id} does not require aggregation")]
+    NotAggregated { service_id: u64, job_index: u8 },
+    /// The service has no operators
+    #[error("No operators registered for service {0}")]
+    NoOperators(u64),
+}
+
+/// An operator of a service, as registered on chain
+#[derive(Debug, Clone)]
+pub struct ChainOperator {
+    /// Operator address
+    pub address: Address,
+    /// Weight used for stake-weighted thresholds
+    pub weight: u64,
+    /// Registered BLS public key, if known
+    pub bls_public_key: Option<ArkBlsBn254Public>,
+}
+
+/// Task parameters derived from the chain
+#[derive(Debug, Clone)]
+pub struct ChainTaskConfig {
+    /// Operators of the service, in operator index order
+    pub operators: Vec<ChainOperator>,
+    /// Threshold required for the called job
+    pub threshold: ThresholdConfig,
+}
+
+impl ChainTaskConfig {
+    /// Number of operators in the service
+    pub fn operator_count(&self) -> u32 {
+        self.operators.len() as u32
+    }
+
+    /// Registered BLS public keys by operator index
+    pub fn operator_public_keys(&self) -> HashMap<u32, ArkBlsBn254Public> {
+        self.operators
+            .iter()
+            .enumerate()
+            .filter_map(|(index, operator)| {
+                let key = operator.bls_public_key.clone()?;
+                Some((index as u32, key))
+            })
+            .collect()
+    }
+
+    /// Registered operator addresses by operator index
+    pub fn operator_addresses(&self) -> HashMap<u32, Address> {
+        self.operators
+            .iter()
+            .enumerate()
+            .map(|(index, operator)| (index as u32, operator.address))
+            .collect()
+    }
+
+    /// Build the task parameters for `operators` and the job's aggregation config
+    ///
+    /// The threshold is computed the same way as the Tangle aggregating
+    /// consumer does, so honest callers always agree with it.
+    pub fn new(operators: Vec<ChainOperator>, config: &AggregationConfig) -> Self {
+        let threshold = match config.threshold_type {
+            ThresholdType::StakeWeighted if operators.iter().any(|op| op.weight > 0) => {
+                ThresholdConfig::StakeWeighted {
+                    threshold_bps: u32::from(config.threshold_bps),
+                    operator_stakes: operators
+                        .iter()
+                        .enumerate()
+                        .map(|(index, operator)| OperatorStake {
+                            operator_index: index as u32,
+                            stake: operator.weight,
+                        })
+                        .collect(),
+                }
+            }
+            _ => ThresholdConfig::Count {
+                required_signers: required_signers(operators.len(), config.threshold_bps),
+            },
+        };
+        Self {
+            operators,
+            threshold,
+        }
+    }
+}
+
+/// `ceil(total * threshold_bps / 10000)`, clamped to `1..=total`
+fn required_signers(total: usize, threshold_bps: u16) -> u32 {
+    let required = (total as u64 * u64::from(threshold_bps)).div_ceil(10_000);
+    required.clamp(1, total.max(1) as u64) as u32
+}
+
+/// Source of the operator set and threshold of a job call
+#[async_trait::async_trait]
+pub trait OperatorSetSource: Send + Sync {
+    /// Derive the task parameters of `call_id` in `service_id`
+    async fn task_config(
+        &self,
+        service_id: u64,
+        call_id: u64,
+    ) -> Result<ChainTaskConfig, ChainError>;
+}
+
+/// Decode a G2 public key in contract format, `[x.c1, x.c0, y.c1, y.c0]`
+///
+/// Returns `None` for coordinates outside the field, or a point that isn't on
+/// the curve or in the G2 subgroup.
+fn public_key_from_words(words: [U256; 4]) -> Option<ArkBlsBn254Public> {
+    let field = |word: U256| Fq::from_bigint(BigInt::new(word.into_limbs()));
+    let x = Fq2::new(field(words[1])?, field(words[0])?);
+    let y = Fq2::new(field(words[3])?, field(words[2])?);
+    let point = G2Affine::new_unchecked(x, y);
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve())
+        .then_some(ArkBlsBn254Public(point))
+}
+
+/// Reads the operator set from the Tangle contract
+///
+/// Operators are indexed in the order returned by `getServiceOperators`, and
+/// inactive operators have a weight of zero. The BLS key each operator
+/// registered on approval is read with `getOperatorBlsPubkey` and pinned;
+/// operators without a (valid) key can't contribute signatures.
+#[async_trait::async_trait]
+impl OperatorSetSource for TangleClient {
+    async fn task_config(
+        &self,
+        service_id: u64,
+        call_id: u64,
+    ) -> Result<ChainTaskConfig, ChainError> {
+        let client_error =
+            |error: blueprint_client_tangle::Error| ChainError::Client(error.to_string());
+
+        let job_index = self
+            .get_job_call(service_id, call_id)
+            .await
+            .map_err(client_error)?
+            .jobIndex;
+        let config = self
+            .get_aggregation_config(service_id, job_index)
+            .await
+            .map_err(client_error)?;
+        if !config.required {
+            return Err(ChainError::NotAggregated {
+                service_id,
+                job_index,
+            });
+        }
+
+        let addresses = self
+            .get_service_operators(service_id)
+            .await
+            .map_err(client_error)?;
+        if addresses.is_empty() {
+            return Err(ChainError::NoOperators(service_id));
+        }
+
+        let weights: BTreeMap<Address, u16> = match config.threshold_type {
+            ThresholdType::CountBased => BTreeMap::new(),
+            ThresholdType::StakeWeighted => self
+                .get_service_operator_weights(service_id)
+                .await
+                .map_err(client_error)?,
+        };
+        if config.threshold_type == ThresholdType::StakeWeighted
+            && weights.values().all(|weight| *weight == 0)
+        {
+            warn!(
+                service_id,
+                job_index, "No operator weights found; falling back to count-based threshold"
+            );
+        }
+
+        let public_keys = futures::future::try_join_all(
+            addresses
+                .iter()
+                .map(|address| self.get_operator_bls_pubkey(service_id, *address)),
+        )
+        .await
+        .map_err(client_error)?;
+
+        let operators = addresses
+            .into_iter()
+            .zip(public_keys)
+            .map(|(address, words)| {
+                let bls_public_key = if words.iter().all(U256::is_zero) {
+                    None
+                } else {
+                    let key = public_key_from_words(words);
+                    if key.is_none() {
+                        warn!(service_id, %address, "Ignoring invalid registered BLS public key");
+                    }
+                    key
+                };
+                ChainOperator {
+                    address,
+                    weight: weights.get(&address).copied().map_or(0, u64::from),
+                    bls_public_key,
+                }
+            })
+            .collect();
+        Ok(ChainTaskConfig::new(operators, &config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operators(weights: &[u64]) -> Vec<ChainOperator> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(index, weight)| ChainOperator {
+                address: Address::with_last_byte(index as u8 + 1),
+                weight: *weight,
+                bls_public_key: None,
+            })
+            .collect()
+    }
+
+    fn aggregation_config(threshold_type: ThresholdType, threshold_bps: u16) -> AggregationConfig {
+        AggregationConfig {
+            required: true,
+            threshold_bps,
+            threshold_type,
+        }
+    }
+
+    #[test]
+    fn count_threshold_rounds_up() {
+        let config = ChainTaskConfig::new(
+            operators(&[0, 0, 0]),
+            &aggregation_config(ThresholdType::CountBased, 6700),
+        );
+        assert_eq!(config.operator_count(), 3);
+        assert!(matches!(
+            config.threshold,
+            ThresholdConfig::Count {
+                required_signers: 3
+            }
+        ));
+
+        let config = ChainTaskConfig::new(
+            operators(&[0, 0, 0]),
+            &aggregation_config(ThresholdType::CountBased, 0),
+        );
+        assert!(matches!(
+            config.threshold,
+            ThresholdConfig::Count {
+                required_signers: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn stake_weighted_threshold_carries_every_operator() {
+        let config = ChainTaskConfig::new(
+            operators(&[5000, 0, 3000]),
+            &aggregation_config(ThresholdType::StakeWeighted, 5000),
+        );
+        let ThresholdConfig::StakeWeighted {
+            threshold_bps,
+            operator_stakes,
+        } = config.threshold
+        else {
+            panic!("expected a stake-weighted threshold");
+        };
+        assert_eq!(threshold_bps, 5000);
+        let stakes: Vec<_> = operator_stakes.iter().map(|s| s.stake).collect();
+        assert_eq!(stakes, [5000, 0, 3000]);
+    }
+
+    #[test]
+    fn registered_public_keys_decode_from_contract_format() {
+        use blueprint_crypto_bn254::ArkBlsBn254;
+        use blueprint_crypto_core::KeyType;
+
+        let public_key = ArkBlsBn254::public_from_secret(
+            &ArkBlsBn254::generate_with_seed(Some(b"registered-operator")).unwrap(),
+        );
+        let word = |element: Fq| U256::from_limbs(element.into_bigint().0);
+        let point = public_key.0;
+        let words = [
+            word(point.x.c1),
+            word(point.x.c0),
+            word(point.y.c1),
+            word(point.y.c0),
+        ];
+        assert_eq!(public_key_from_words(words), Some(public_key));
+
+        let mut swapped = words;
+        swapped.swap(0, 1);
+        assert_eq!(public_key_from_words(swapped), None);
+        assert_eq!(public_key_from_words([U256::MAX; 4]), None);
+    }
+
+    #[test]
+    fn zero_weights_fall_back_to_count() {
+        let config = ChainTaskConfig::new(
+            operators(&[0, 0]),
+            &aggregation_config(ThresholdType::StakeWeighted, 5000),
+        );
+        assert!(matches!(
+            config.threshold,
+            ThresholdConfig::Count {
+                required_signers: 1
+            }
+        ));
+    }
+}
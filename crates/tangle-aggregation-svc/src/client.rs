@@ -22,7 +22,8 @@
 //!     )
 //!     .await?;
 //!
-//! // Submit a signature
+//! // Submit a signature, authenticated by the operator's ECDSA key
+//! let client = client.with_envelope_signer(operator_signer);
 //! let response = client.submit_signature(request).await?;
 //!
 //! // Check status
@@ -32,9 +33,23 @@
 //! if let Some(result) = client.get_aggregated(service_id, call_id).await? {
 //!     // Submit to chain
 //! }
+//!
+//! // Or wait for the aggregate through task events, re-checking every 5 seconds
+//! let result = client
+//!     .wait_for_threshold_streaming(
+//!         service_id,
+//!         call_id,
+//!         Duration::from_secs(5),
+//!         Duration::from_secs(60),
+//!     )
+//!     .await?;
 //! ```
 
+use crate::auth::EvidenceRecord;
 use crate::types::*;
+use alloy_primitives::B256;
+use alloy_signer_local::PrivateKeySigner;
+use futures::{Stream, StreamExt};
 use std::time::Duration;
 use thiserror::Error;
 
@@ -59,6 +74,9 @@ pub enum ClientError {
     /// Another operator already submitted this task to the chain.
     #[error("Aggregation task was already submitted to the chain")]
     AlreadySubmitted,
+    /// The submission envelope couldn't be signed
+    #[error("Envelope signing failed: {0}")]
+    Envelope(#[from] crate::auth::AuthError),
 }
 
 /// Outcome of waiting for an aggregation task.
@@ -75,6 +93,7 @@ pub enum ThresholdWaitResult {
 pub struct AggregationServiceClient {
     client: reqwest::Client,
     base_url: String,
+    envelope_signer: Option<PrivateKeySigner>,
 }
 
 impl AggregationServiceClient {
@@ -85,10 +104,7 @@ impl AggregationServiceClient {
             .build()
             .expect("Failed to create HTTP client");
 
-        Self {
-            client,
-            base_url: base_url.into().trim_end_matches('/').to_string(),
-        }
+        Self::with_client(client, base_url)
     }
 
     /// Create a client with custom reqwest client
@@ -96,9 +112,19 @@ impl AggregationServiceClient {
         Self {
             client,
             base_url: base_url.into().trim_end_matches('/').to_string(),
+            envelope_signer: None,
         }
     }
 
+    /// Sign every submission with the operator's ECDSA key
+    ///
+    /// Services requiring authenticated submissions reject unsigned ones.
+    #[must_use]
+    pub fn with_envelope_signer(mut self, signer: PrivateKeySigner) -> Self {
+        self.envelope_signer = Some(signer);
+        self
+    }
+
     /// Health check
     pub async fn health(&self) -> Result<bool, ClientError> {
         let url = format!("{}/health", self.base_url);
@@ -177,10 +203,14 @@ impl AggregationServiceClient {
         request: SubmitSignatureRequest,
     ) -> Result<SubmitSignatureResponse, ClientError> {
         let url = format!("{}/v1/tasks/submit", self.base_url);
+        let submission = match &self.envelope_signer {
+            Some(signer) => crate::auth::sign_submission(request, signer)?,
+            None => SignedSubmission::from(request),
+        };
         let response: SubmitSignatureResponse = self
             .client
             .post(&url)
-            .json(&request)
+            .json(&submission)
             .send()
             .await?
             .json()
@@ -277,12 +307,56 @@ impl AggregationServiceClient {
 
     /// Mark a task as submitted to the chain
     pub async fn mark_submitted(&self, service_id: u64, call_id: u64) -> Result<(), ClientError> {
-        let url = format!("{}/v1/tasks/mark-submitted", self.base_url);
-        let request = GetStatusRequest {
+        self.mark_submitted_with_tx(service_id, call_id, None).await
+    }
+
+    /// Mark a task as submitted to chain by the transaction `tx_hash`
+    ///
+    /// The request is unsigned, so services requiring envelopes reject it. See
+    /// [`Self::mark_submitted_by`].
+    pub async fn mark_submitted_with_tx(
+        &self,
+        service_id: u64,
+        call_id: u64,
+        tx_hash: Option<B256>,
+    ) -> Result<(), ClientError> {
+        self.send_mark_submitted(
+            MarkSubmittedRequest {
+                service_id,
+                call_id,
+                tx_hash,
+                operator_index: None,
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Mark a task as submitted to chain by `operator_index`'s transaction `tx_hash`
+    ///
+    /// The request is signed with the envelope signer, if one is set.
+    pub async fn mark_submitted_by(
+        &self,
+        service_id: u64,
+        call_id: u64,
+        operator_index: u32,
+        tx_hash: Option<B256>,
+    ) -> Result<(), ClientError> {
+        let request = MarkSubmittedRequest {
             service_id,
             call_id,
+            tx_hash,
+            operator_index: Some(operator_index),
+        };
+        let request = match &self.envelope_signer {
+            Some(signer) => crate::auth::sign_mark_submitted(request, signer)?,
+            None => SignedMarkSubmitted::from(request),
         };
+        self.send_mark_submitted(request).await
+    }
 
+    async fn send_mark_submitted(&self, request: SignedMarkSubmitted) -> Result<(), ClientError> {
+        let url = format!("{}/v1/tasks/mark-submitted", self.base_url);
         let response = self.client.post(&url).json(&request).send().await?;
 
         if !response.status().is_success() {
@@ -314,6 +388,28 @@ impl AggregationServiceClient {
         Ok(stats)
     }
 
+    /// Export evidence of invalid submissions, optionally only for `service_id`
+    pub async fn get_evidence(
+        &self,
+        service_id: Option<u64>,
+    ) -> Result<Vec<EvidenceRecord>, ClientError> {
+        let url = format!("{}/v1/evidence", self.base_url);
+        let mut request = self.client.get(&url);
+        if let Some(service_id) = service_id {
+            request = request.query(&[("service_id", service_id)]);
+        }
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Server(format!(
+                "Server returned {}",
+                response.status()
+            )));
+        }
+
+        Ok(response.json().await?)
+    }
+
     /// Wait for threshold to be met, with polling
     ///
     /// Returns the aggregated result once threshold is met, or an error if timeout is reached.
@@ -374,6 +470,173 @@ impl AggregationServiceClient {
             tokio::time::sleep(poll_interval).await;
         }
     }
+
+    /// Subscribe to the events of the tasks selected by `filter`
+    ///
+    /// Events are streamed from `/v1/tasks/events`, as server-sent events. The
+    /// stream ends when the server closes it, or fails once `timeout` elapses.
+    /// An event that can't be decoded is yielded as
+    /// [`ClientError::InvalidResponse`], and the stream goes on.
+    pub async fn subscribe_events(
+        &self,
+        filter: TaskEventFilter,
+        timeout: Duration,
+    ) -> Result<impl Stream<Item = Result<TaskEvent, ClientError>>, ClientError> {
+        let url = format!("{}/v1/tasks/events", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .query(&filter)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .timeout(timeout)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Server(format!(
+                "Server returned {}",
+                response.status()
+            )));
+        }
+
+        let reader = EventReader {
+            response,
+            buffer: Vec::new(),
+            data: String::new(),
+        };
+        Ok(futures::stream::unfold(Some(reader), |reader| async move {
+            let mut reader = reader?;
+            match reader.next_event().await? {
+                Ok(event) => Some((Ok(event), Some(reader))),
+                // An undecodable event leaves the stream usable
+                Err(error @ ClientError::InvalidResponse(_)) => Some((Err(error), Some(reader))),
+                Err(error) => Some((Err(error), None)),
+            }
+        }))
+    }
+
+    /// Wait for an aggregate or learn that another operator submitted it,
+    /// listening to task events
+    ///
+    /// Requires a service exposing `/v1/tasks/events`. The task is still
+    /// re-checked every `poll_interval` without events, as a replica may not
+    /// publish the events of tasks changed by another one. Events that can't
+    /// be decoded are skipped, and a stream ended by the server is resubscribed
+    /// to after a poll interval. See [`Self::wait_for_threshold_or_submitted`]
+    /// for the polling equivalent.
+    pub async fn wait_for_threshold_streaming(
+        &self,
+        service_id: u64,
+        call_id: u64,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<ThresholdWaitResult, ClientError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+
+            // Subscribing before checking the current state, no event is missed in between
+            let events = self
+                .subscribe_events(TaskEventFilter::task(service_id, call_id), remaining)
+                .await?;
+            match self.get_aggregated_or_submitted(service_id, call_id).await {
+                Err(ClientError::ThresholdNotMet { .. }) => {}
+                result => return result,
+            }
+
+            let mut events = std::pin::pin!(events);
+            let mut next_check = tokio::time::Instant::now() + poll_interval;
+            loop {
+                match tokio::time::timeout_at(next_check.min(deadline), events.next()).await {
+                    Ok(Some(Ok(TaskEvent::AggregateReady(result)))) => {
+                        return Ok(ThresholdWaitResult::Aggregated(result));
+                    }
+                    Ok(Some(Ok(TaskEvent::Submitted { .. }))) => {
+                        return Ok(ThresholdWaitResult::Submitted);
+                    }
+                    Ok(Some(Ok(_) | Err(ClientError::InvalidResponse(_)))) => {}
+                    Ok(Some(Err(error))) if tokio::time::Instant::now() < deadline => {
+                        return Err(error);
+                    }
+                    // No event for a poll interval, re-check the task
+                    Err(_) if tokio::time::Instant::now() < deadline => {
+                        match self.get_aggregated_or_submitted(service_id, call_id).await {
+                            Err(ClientError::ThresholdNotMet { .. }) => {}
+                            result => return result,
+                        }
+                        next_check = tokio::time::Instant::now() + poll_interval;
+                    }
+                    // The server ended the stream, resubscribe after a poll interval
+                    Ok(None) => {
+                        tokio::time::sleep_until(
+                            (tokio::time::Instant::now() + poll_interval).min(deadline),
+                        )
+                        .await;
+                        break;
+                    }
+                    Ok(Some(Err(_))) | Err(_) => break,
+                }
+            }
+        }
+
+        let status = self.get_status(service_id, call_id).await?;
+        if status.submitted {
+            return Ok(ThresholdWaitResult::Submitted);
+        }
+        Err(ClientError::ThresholdNotMet {
+            collected: status.signatures_collected,
+            required: status.threshold_required,
+        })
+    }
+}
+
+/// Reads task events from a server-sent events response
+struct EventReader {
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    /// Data of the event being read
+    data: String,
+}
+
+impl EventReader {
+    async fn next_event(&mut self) -> Option<Result<TaskEvent, ClientError>> {
+        loop {
+            while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end_matches(['\n', '\r']);
+
+                // A blank line dispatches the event. Comments, like keep-alives,
+                // and other fields are ignored.
+                if line.is_empty() {
+                    if self.data.is_empty() {
+                        continue;
+                    }
+                    let data = std::mem::take(&mut self.data);
+                    return Some(
+                        serde_json::from_str(&data)
+                            .map_err(|e| ClientError::InvalidResponse(e.to_string())),
+                    );
+                }
+                if let Some(value) = line.strip_prefix("data:") {
+                    if !self.data.is_empty() {
+                        self.data.push('\n');
+                    }
+                    self.data.push_str(value.strip_prefix(' ').unwrap_or(value));
+                }
+            }
+
+            match self.response.chunk().await {
+                Ok(Some(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Ok(None) => return None,
+                Err(error) => return Some(Err(error.into())),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -426,4 +689,276 @@ mod tests {
         server.abort();
         let _ = server.await;
     }
+
+    #[tokio::test]
+    async fn streaming_wait_receives_the_aggregate_without_polling() {
+        use ark_ec::AffineRepr;
+        use ark_serialize::CanonicalSerialize;
+
+        let service = Arc::new(AggregationService::new(ServiceConfig::minimal()));
+        service.init_task(1, 1, vec![1], 2, 1).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn({
+            let service = Arc::clone(&service);
+            async move {
+                axum::serve(listener, api::router(service)).await.unwrap();
+            }
+        });
+
+        let client = AggregationServiceClient::new(format!("http://{address}"));
+        let waiter = tokio::spawn(async move {
+            client
+                .wait_for_threshold_streaming(
+                    1,
+                    1,
+                    Duration::from_secs(60),
+                    Duration::from_secs(30),
+                )
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        fn serialize(point: &impl CanonicalSerialize) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            point.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        }
+        service
+            .submit_signature(SubmitSignatureRequest {
+                service_id: 1,
+                call_id: 1,
+                operator_index: 1,
+                output: vec![1],
+                signature: serialize(&ark_bn254::G1Affine::generator()),
+                public_key: serialize(&ark_bn254::G2Affine::generator()),
+            })
+            .unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .expect("the aggregate should be streamed promptly")
+            .unwrap()
+            .unwrap();
+        let ThresholdWaitResult::Aggregated(result) = result else {
+            panic!("expected the aggregate");
+        };
+        assert_eq!(result.non_signer_indices, [0]);
+
+        server.abort();
+        let _ = server.await;
+    }
+
+    #[tokio::test]
+    async fn streaming_wait_skips_undecodable_events_and_resubscribes_later() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let service = Arc::new(AggregationService::new(ServiceConfig::minimal()));
+        service.init_task(1, 1, vec![1], 2, 2).unwrap();
+
+        // The first stream ends at once, the second starts with a malformed event
+        let subscriptions = Arc::new(AtomicUsize::new(0));
+        let events = {
+            let subscriptions = Arc::clone(&subscriptions);
+            axum::routing::get(move || async move {
+                let body = if subscriptions.fetch_add(1, Ordering::SeqCst) == 0 {
+                    String::new()
+                } else {
+                    let submitted = TaskEvent::Submitted {
+                        service_id: 1,
+                        call_id: 1,
+                        tx_hash: None,
+                    };
+                    format!(
+                        "data: not json\n\ndata: {}\n\n",
+                        serde_json::to_string(&submitted).unwrap()
+                    )
+                };
+                (
+                    [(axum::http::header::CONTENT_TYPE, "text/event-stream")],
+                    body,
+                )
+            })
+        };
+        let app = axum::Router::new()
+            .route("/v1/tasks/events", events)
+            .fallback_service(api::router(service));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let client = AggregationServiceClient::new(format!("http://{address}"));
+        let poll_interval = Duration::from_millis(200);
+        let started = tokio::time::Instant::now();
+        let result = client
+            .wait_for_threshold_streaming(1, 1, poll_interval, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(matches!(result, ThresholdWaitResult::Submitted));
+        assert_eq!(subscriptions.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() >= poll_interval);
+
+        server.abort();
+        let _ = server.await;
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn streaming_wait_rechecks_tasks_changed_without_events() {
+        use crate::persistence::SqlitePersistence;
+        use ark_ec::AffineRepr;
+        use ark_serialize::CanonicalSerialize;
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("aggregation.db");
+        let replica = || {
+            Arc::new(
+                AggregationService::with_persistence(
+                    ServiceConfig::minimal(),
+                    SqlitePersistence::open(&path).unwrap(),
+                )
+                .unwrap(),
+            )
+        };
+        let first = replica();
+        first.init_task(1, 1, vec![1], 2, 1).unwrap();
+        // The served replica does not publish the events of the other one
+        let second = replica();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, api::router(second)).await.unwrap();
+        });
+
+        let client = AggregationServiceClient::new(format!("http://{address}"));
+        let waiter = tokio::spawn(async move {
+            client
+                .wait_for_threshold_streaming(
+                    1,
+                    1,
+                    Duration::from_millis(100),
+                    Duration::from_secs(30),
+                )
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        fn serialize(point: &impl CanonicalSerialize) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            point.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        }
+        first
+            .submit_signature(SubmitSignatureRequest {
+                service_id: 1,
+                call_id: 1,
+                operator_index: 1,
+                output: vec![1],
+                signature: serialize(&ark_bn254::G1Affine::generator()),
+                public_key: serialize(&ark_bn254::G2Affine::generator()),
+            })
+            .unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .expect("the aggregate should be found by re-checking the task")
+            .unwrap()
+            .unwrap();
+        assert!(matches!(result, ThresholdWaitResult::Aggregated(_)));
+
+        server.abort();
+        let _ = server.await;
+    }
+
+    #[tokio::test]
+    async fn signed_submissions_are_accepted_and_invalid_ones_exported() {
+        use crate::{SubmissionAuthConfig, TaskConfig};
+        use ark_ec::AffineRepr;
+        use ark_serialize::CanonicalSerialize;
+
+        let signer = PrivateKeySigner::random();
+        let config = ServiceConfig {
+            validate_output: true,
+            submission_auth: SubmissionAuthConfig {
+                require_envelope: true,
+                ..Default::default()
+            },
+            ..ServiceConfig::minimal()
+        };
+        let service = Arc::new(AggregationService::new(config));
+        service
+            .init_task_with_config(
+                1,
+                1,
+                vec![1],
+                1,
+                TaskConfig {
+                    operator_addresses: [(0, signer.address())].into(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let app = api::router(service);
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        fn serialize(point: &impl CanonicalSerialize) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            point.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        }
+        let request = |output: Vec<u8>| SubmitSignatureRequest {
+            service_id: 1,
+            call_id: 1,
+            operator_index: 0,
+            output,
+            signature: serialize(&ark_bn254::G1Affine::generator()),
+            public_key: serialize(&ark_bn254::G2Affine::generator()),
+        };
+
+        let unsigned = AggregationServiceClient::new(format!("http://{address}"));
+        assert!(matches!(
+            unsigned.submit_signature(request(vec![1])).await,
+            Err(ClientError::Server(_))
+        ));
+
+        let client = unsigned.with_envelope_signer(signer.clone());
+        // A mismatched output is rejected, but only a malformed key is evidence
+        assert!(client.submit_signature(request(vec![2])).await.is_err());
+        let malformed = SubmitSignatureRequest {
+            public_key: vec![0xff; 3],
+            ..request(vec![1])
+        };
+        assert!(client.submit_signature(malformed).await.is_err());
+        assert!(
+            client
+                .submit_signature(request(vec![1]))
+                .await
+                .unwrap()
+                .accepted
+        );
+
+        let evidence = client.get_evidence(Some(1)).await.unwrap();
+        assert_eq!(evidence.len(), 1);
+        assert_eq!(evidence[0].operator, signer.address());
+        assert_eq!(evidence[0].submission.request.public_key, [0xff; 3]);
+        assert!(client.get_evidence(Some(2)).await.unwrap().is_empty());
+
+        server.abort();
+        let _ = server.await;
+    }
 }
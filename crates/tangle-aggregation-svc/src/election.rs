@@ -0,0 +1,91 @@
+//! Election of the operator submitting an aggregate to chain
+//!
+//! Once a task reaches its threshold every operator can fetch the aggregate,
+//! but the contract only accepts one submission, so racing for it wastes gas.
+//! Operators agree on a submitter without talking to each other: the operator
+//! set is rotated by the call id, spreading submissions over the operators,
+//! and each operator waits one fallback timeout per operator ahead of it
+//! before submitting. If the elected operator is offline, the next one takes
+//! over.
+//!
+//! ```rust,ignore
+//! let election = SubmitterElection::new(Duration::from_secs(30));
+//! tokio::time::sleep(election.delay(call_id, operator_count, operator_index)).await;
+//! if !client.get_status(service_id, call_id).await?.submitted {
+//!     let tx_hash = submit_to_chain(aggregate).await?;
+//!     client
+//!         .mark_submitted_by(service_id, call_id, operator_index, Some(tx_hash))
+//!         .await?;
+//! }
+//! ```
+
+use std::time::Duration;
+
+/// Deterministic election of the operator submitting each task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubmitterElection {
+    /// How long each operator has to submit before the next one takes over
+    pub fallback_timeout: Duration,
+}
+
+impl SubmitterElection {
+    /// Create an election giving each operator `fallback_timeout` to submit
+    pub fn new(fallback_timeout: Duration) -> Self {
+        Self { fallback_timeout }
+    }
+
+    /// Index of the operator elected to submit `call_id`
+    pub fn elected(call_id: u64, operator_count: u32) -> u32 {
+        // The remainder is below `operator_count`, so it fits in a u32
+        (call_id % u64::from(operator_count.max(1))) as u32
+    }
+
+    /// Position of `operator_index` in the submission order of `call_id`
+    ///
+    /// The elected operator is at position 0, followed by the next indices,
+    /// wrapping around the operator set.
+    pub fn rank(call_id: u64, operator_count: u32, operator_index: u32) -> u32 {
+        let count = u64::from(operator_count.max(1));
+        let index = u64::from(operator_index) % count;
+        let elected = u64::from(Self::elected(call_id, operator_count));
+        ((index + count - elected) % count) as u32
+    }
+
+    /// Whether `operator_index` is elected to submit `call_id`
+    pub fn is_elected(call_id: u64, operator_count: u32, operator_index: u32) -> bool {
+        Self::rank(call_id, operator_count, operator_index) == 0
+    }
+
+    /// How long `operator_index` waits for the operators ahead of it before submitting
+    pub fn delay(&self, call_id: u64, operator_count: u32, operator_index: u32) -> Duration {
+        self.fallback_timeout
+            .saturating_mul(Self::rank(call_id, operator_count, operator_index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn election_rotates_with_the_call_id() {
+        let elected: Vec<u32> = (0..6)
+            .map(|call_id| SubmitterElection::elected(call_id, 3))
+            .collect();
+        assert_eq!(elected, [0, 1, 2, 0, 1, 2]);
+        assert!(SubmitterElection::is_elected(4, 3, 1));
+        assert_eq!(SubmitterElection::elected(7, 0), 0);
+    }
+
+    #[test]
+    fn every_operator_has_a_distinct_fallback_slot() {
+        let election = SubmitterElection::new(Duration::from_secs(10));
+        let mut delays: Vec<Duration> = (0..4).map(|index| election.delay(6, 4, index)).collect();
+        assert_eq!(delays[2], Duration::ZERO);
+        assert_eq!(delays[3], Duration::from_secs(10));
+        assert_eq!(delays[0], Duration::from_secs(20));
+        delays.sort();
+        delays.dedup();
+        assert_eq!(delays.len(), 4);
+    }
+}
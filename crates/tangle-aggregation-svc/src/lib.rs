@@ -38,27 +38,80 @@
 //! - `POST /v1/tasks/submit` - Submit a signature
 //! - `POST /v1/tasks/status` - Get task status
 //! - `POST /v1/tasks/aggregate` - Get aggregated result
+//! - `POST /v1/tasks/mark-submitted` - Mark a task as submitted to the chain
+//! - `GET /v1/tasks/events` - Stream task events (server-sent events)
+//! - `GET /v1/tasks/ws` - Stream task events (WebSocket)
+//! - `GET /v1/evidence` - Export evidence of invalid submissions
+//!
+//! The event endpoints take optional `service_id` and `call_id` query
+//! parameters, and push a [`TaskEvent`] when a signature is received, the
+//! threshold is met, a new aggregate is ready, or the task is submitted.
 //!
 //! ### Operator Flow
 //!
 //! 1. Someone (often the first operator) initializes the task
 //! 2. Each operator signs the output and submits their signature
-//! 3. Once threshold is met, anyone can fetch the aggregated result
-//! 4. Submit the aggregated result to the Tangle contract
+//! 3. Once threshold is met, anyone can fetch the aggregated result, or
+//!    receive it by subscribing to the task's events
+//! 4. Submit the aggregated result to the Tangle contract, and mark the task
+//!    as submitted with the transaction hash
+//!
+//! ### Electing the submitter
+//!
+//! Rather than having every operator race to submit the aggregate, operators
+//! can agree on one with [`SubmitterElection`]: the submitter rotates with
+//! the call id, and the others take over in turn after a fallback timeout if
+//! the task isn't marked as submitted. See the [`election`] module.
+//!
+//! ### Chain-verified initialization
+//!
+//! By default the operator count and threshold of a task are trusted from the
+//! init request. With [`AggregationService::with_operator_source`], they are
+//! derived from the chain instead, and requests disagreeing with it are
+//! rejected. See the [`chain`] module.
+//!
+//! ### Authenticated submissions
+//!
+//! Submissions can carry an ECDSA envelope from the operator's registered
+//! address, checked before the costly BLS verification, and are rate limited
+//! per operator and per client IP. See [`ServiceConfig::submission_auth`] and
+//! the [`auth`] module.
+//!
+//! ### Running several instances
+//!
+//! With the `sqlite` feature, replicas behind a load balancer can share one
+//! `SqlitePersistence` database. Each task change is applied with a
+//! compare-and-set on the task's version, so concurrent submissions to
+//! different instances are never lost. Each instance streams the task events
+//! of its own changes right away, and those of other instances once
+//! [`AggregationService::start_event_sync_worker`] picks them up from the
+//! database, every [`ServiceConfig::event_sync_interval`]. See the
+//! [`persistence`] module.
 
 pub mod api;
+pub mod auth;
+pub mod chain;
 #[cfg(feature = "client")]
 pub mod client;
+pub mod election;
 pub mod persistence;
 pub mod service;
 pub mod state;
 pub mod types;
 
+pub use auth::{
+    sign_mark_submitted, sign_submission, AuthError, EvidenceRecord, RateLimit,
+    SubmissionAuthConfig,
+};
+pub use chain::{ChainError, ChainOperator, ChainTaskConfig, OperatorSetSource};
 #[cfg(feature = "client")]
 pub use client::{AggregationServiceClient, ClientError, ThresholdWaitResult};
+pub use election::SubmitterElection;
+#[cfg(feature = "sqlite")]
+pub use persistence::SqlitePersistence;
 pub use persistence::{
     FilePersistence, NoPersistence, PersistedTaskState, PersistedThresholdType, PersistenceBackend,
-    PersistenceError,
+    PersistenceError, TaskChanges,
 };
 pub use service::{
     create_signing_message, AggregationService, CleanupWorkerHandle, ServiceConfig, ServiceError,
@@ -81,6 +134,10 @@ pub async fn run(addr: &str, config: ServiceConfig) -> Result<(), Box<dyn std::e
     let listener = tokio::net::TcpListener::bind(addr).await?;
     info!("Aggregation service listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
@@ -18,16 +18,42 @@
 //! // Create service with persistence
 //! let service = AggregationService::with_persistence(config, persistence)?;
 //! ```
+//!
+//! ## Shared state
+//!
+//! [`FilePersistence`] is only safe for a single service instance. To run
+//! several replicas behind a load balancer, enable the `sqlite` feature and
+//! point every instance at the same `SqlitePersistence` database. Shared
+//! backends report [`PersistenceBackend::is_shared`]; services then reload
+//! tasks before using them, and save changes with
+//! [`PersistenceBackend::compare_and_save`], retrying when another instance
+//! changed the task first. Task events of changes made by other instances are
+//! published by the event sync worker, which polls the tasks changed since its
+//! last poll.
+//!
+//! ```rust,ignore
+//! let persistence = SqlitePersistence::open("/var/lib/aggregation/state.db")?;
+//! // One-off import of the tasks of a previous file-backed deployment
+//! persistence.migrate_from_file("/var/lib/aggregation/state.json")?;
+//! let service = Arc::new(AggregationService::with_persistence(config, persistence)?);
+//! let _events = service.start_event_sync_worker();
+//! ```
 
+use crate::auth::EvidenceRecord;
 use crate::state::{TaskState, ThresholdType};
 use crate::types::{Bn254SignatureScheme, TaskId};
-use alloy_primitives::U256;
+use alloy_primitives::{Address, B256, U256};
 use ark_bn254::{G1Affine, G2Affine};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqlitePersistence;
+
 /// Error type for persistence operations
 #[derive(Debug, thiserror::Error)]
 pub enum PersistenceError {
@@ -74,6 +100,15 @@ pub struct PersistedTaskState {
     pub signatures: HashMap<u32, String>,
     /// Collected public keys indexed by operator index (hex encoded)
     pub public_keys: HashMap<u32, String>,
+    /// Registered public keys operators must sign with (hex encoded)
+    #[serde(default)]
+    pub operator_public_keys: HashMap<u32, String>,
+    /// Whether operators without a registered public key are refused
+    #[serde(default)]
+    pub require_registered_keys: bool,
+    /// Registered operator addresses
+    #[serde(default)]
+    pub operator_addresses: HashMap<u32, Address>,
     /// Operator stakes for stake-weighted thresholds
     pub operator_stakes: HashMap<u32, u64>,
     /// Total stake of all operators
@@ -83,10 +118,16 @@ pub struct PersistedTaskState {
     /// When this task was submitted to chain (unix timestamp millis)
     #[serde(default)]
     pub submitted_at_ms: Option<u64>,
+    /// Hash of the transaction submitting the aggregate
+    #[serde(default)]
+    pub submission_tx: Option<B256>,
     /// When this task was created (unix timestamp millis)
     pub created_at_ms: u64,
     /// When this task expires (unix timestamp millis, None = never)
     pub expires_at_ms: Option<u64>,
+    /// Revision of the task, bumped on every save
+    #[serde(default)]
+    pub version: u64,
 }
 
 /// Serializable threshold type
@@ -128,6 +169,11 @@ impl TryFrom<&TaskState> for PersistedTaskState {
             .iter()
             .map(|(index, public_key)| Ok((*index, encode_point(&public_key.0)?)))
             .collect::<Result<HashMap<_, _>>>()?;
+        let operator_public_keys = task
+            .operator_public_keys
+            .iter()
+            .map(|(index, public_key)| Ok((*index, encode_point(&public_key.0)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
 
         Ok(Self {
             service_id: task.service_id,
@@ -140,12 +186,17 @@ impl TryFrom<&TaskState> for PersistedTaskState {
             signer_bitmap: format!("{:#x}", task.signer_bitmap),
             signatures,
             public_keys,
+            operator_public_keys,
+            require_registered_keys: task.require_registered_keys,
+            operator_addresses: task.operator_addresses.clone(),
             operator_stakes: task.operator_stakes.clone(),
             total_stake: task.total_stake,
             submitted: task.submitted,
             submitted_at_ms: task.submitted_at.map(instant_to_millis),
+            submission_tx: task.submission_tx,
             created_at_ms: instant_to_millis(task.created_at),
             expires_at_ms: task.expires_at.map(instant_to_millis),
+            version: task.version,
         })
     }
 }
@@ -175,6 +226,14 @@ impl TryFrom<PersistedTaskState> for TaskState {
             )));
         }
 
+        task.operator_public_keys = persisted
+            .operator_public_keys
+            .iter()
+            .map(|(index, public_key)| Ok((*index, decode_public_key(public_key)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        task.require_registered_keys = persisted.require_registered_keys;
+        task.operator_addresses = persisted.operator_addresses;
+
         if persisted.signatures.len() != persisted.public_keys.len() {
             return Err(PersistenceError::Serialization(format!(
                 "task {}:{} has an incomplete signature/public-key pair",
@@ -213,8 +272,10 @@ impl TryFrom<PersistedTaskState> for TaskState {
             .submitted_at_ms
             .map(millis_to_instant)
             .or_else(|| task.submitted.then_some(Instant::now()));
+        task.submission_tx = persisted.submission_tx;
         task.created_at = millis_to_instant(persisted.created_at_ms);
         task.expires_at = persisted.expires_at_ms.map(millis_to_instant);
+        task.version = persisted.version;
         Ok(task)
     }
 }
@@ -278,6 +339,20 @@ fn millis_to_instant(timestamp_ms: u64) -> Instant {
     }
 }
 
+/// Tasks changed in a backend since a [`PersistenceBackend::load_changes`] cursor
+#[derive(Debug, Clone, Default)]
+pub struct TaskChanges {
+    /// Tasks saved since the cursor
+    pub saved: Vec<PersistedTaskState>,
+    /// Tasks deleted since the cursor
+    pub deleted: Vec<TaskId>,
+    /// Whether `saved` holds every stored task, because the changes since the
+    /// cursor aren't known. Tasks missing from it were deleted.
+    pub complete: bool,
+    /// Cursor to load the next changes from
+    pub cursor: u64,
+}
+
 /// Trait for persistence backends
 ///
 /// Implement this trait to provide custom storage for aggregation state.
@@ -303,6 +378,67 @@ pub trait PersistenceBackend: Send + Sync {
     fn flush(&self) -> Result<()> {
         Ok(())
     }
+
+    /// Append an evidence record, keeping only the `capacity` newest
+    fn save_evidence(&self, _record: &EvidenceRecord, _capacity: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Load the stored evidence records, oldest first
+    fn load_evidence(&self) -> Result<Vec<EvidenceRecord>> {
+        Ok(Vec::new())
+    }
+
+    /// Whether other service instances read and write the same storage
+    ///
+    /// Services reload tasks from a shared backend before using them, and only
+    /// change them through [`Self::compare_and_save`] and
+    /// [`Self::compare_and_delete`].
+    fn is_shared(&self) -> bool {
+        false
+    }
+
+    /// Save `task` only if the stored task is still at `expected_version`
+    ///
+    /// A missing task is at version 0. Returns `false`, leaving the store
+    /// untouched, if the stored version differs. The default implementation is
+    /// not atomic, so shared backends must override it.
+    fn compare_and_save(&self, task: &PersistedTaskState, expected_version: u64) -> Result<bool> {
+        let task_id = TaskId::new(task.service_id, task.call_id);
+        let current = self.load_task(&task_id)?.map_or(0, |task| task.version);
+        if current != expected_version {
+            return Ok(false);
+        }
+        self.save_task(task)?;
+        Ok(true)
+    }
+
+    /// Delete a task only if the stored task is still at `expected_version`
+    ///
+    /// Returns `false`, leaving the store untouched, if the task was changed
+    /// since. A task that is already gone counts as deleted.
+    fn compare_and_delete(&self, task_id: &TaskId, expected_version: u64) -> Result<bool> {
+        match self.load_task(task_id)? {
+            Some(task) if task.version != expected_version => Ok(false),
+            Some(_) => {
+                self.delete_task(task_id)?;
+                Ok(true)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Load the tasks changed since `cursor`, as returned by an earlier call
+    ///
+    /// Without a cursor, every task is loaded. The default implementation
+    /// always loads every task; shared backends should only load the changes.
+    fn load_changes(&self, _cursor: Option<u64>) -> Result<TaskChanges> {
+        Ok(TaskChanges {
+            saved: self.load_all_tasks()?,
+            complete: true,
+            ..TaskChanges::default()
+        })
+    }
 }
 
 /// No-op persistence backend (in-memory only)
@@ -332,7 +468,8 @@ impl PersistenceBackend for NoPersistence {
 
 /// File-based persistence backend
 ///
-/// Stores all tasks in a single JSON file. Suitable for small deployments.
+/// Stores all tasks in a single JSON file, and evidence records in a second
+/// one at `<path>.evidence`. Suitable for small deployments.
 /// For high-throughput scenarios, consider using a database backend.
 #[derive(Debug)]
 pub struct FilePersistence {
@@ -366,21 +503,30 @@ impl FilePersistence {
 
     fn write_all(&self, tasks: &HashMap<String, PersistedTaskState>) -> Result<()> {
         let _guard = self.lock.write();
-
-        // Create parent directory if needed
-        if let Some(parent) = self.path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-
         let contents = serde_json::to_string_pretty(tasks)
             .map_err(|e| PersistenceError::Serialization(e.to_string()))?;
+        write_atomic(&self.path, &contents)
+    }
 
-        // Write to temp file first, then rename for atomicity
-        let temp_path = self.path.with_extension("tmp");
-        std::fs::write(&temp_path, contents)?;
-        std::fs::rename(&temp_path, &self.path)?;
+    /// Path of the file holding the evidence records
+    pub fn evidence_path(&self) -> std::path::PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(".evidence");
+        path.into()
+    }
 
-        Ok(())
+    fn read_evidence(&self) -> Result<Vec<EvidenceRecord>> {
+        let path = self.evidence_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        if contents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        serde_json::from_str(&contents).map_err(|e| PersistenceError::Serialization(e.to_string()))
     }
 
     fn task_key(task_id: &TaskId) -> String {
@@ -418,6 +564,36 @@ impl PersistenceBackend for FilePersistence {
         // File writes are already flushed on each operation
         Ok(())
     }
+
+    fn save_evidence(&self, record: &EvidenceRecord, capacity: usize) -> Result<()> {
+        let _guard = self.lock.write();
+        let mut records = self.read_evidence()?;
+        records.push(record.clone());
+        let excess = records.len().saturating_sub(capacity);
+        records.drain(..excess);
+
+        let contents = serde_json::to_string(&records)
+            .map_err(|e| PersistenceError::Serialization(e.to_string()))?;
+        write_atomic(&self.evidence_path(), &contents)
+    }
+
+    fn load_evidence(&self) -> Result<Vec<EvidenceRecord>> {
+        let _guard = self.lock.read();
+        self.read_evidence()
+    }
+}
+
+/// Write `contents` to a temp file first, then rename it over `path` for atomicity
+fn write_atomic(path: &std::path::Path, contents: &str) -> Result<()> {
+    // Create parent directory if needed
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    std::fs::write(&temp_path, contents)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
 }
 
 /// Helper to get current timestamp in milliseconds
@@ -473,7 +649,7 @@ mod tests {
     use super::*;
     use tempfile::NamedTempFile;
 
-    fn sample_task() -> PersistedTaskState {
+    pub(super) fn sample_task() -> PersistedTaskState {
         PersistedTaskState {
             service_id: 1,
             call_id: 100,
@@ -493,12 +669,17 @@ mod tests {
                 (1, "0xpk2".to_string()),
                 (2, "0xpk3".to_string()),
             ]),
+            operator_public_keys: HashMap::new(),
+            require_registered_keys: false,
+            operator_addresses: HashMap::new(),
             operator_stakes: HashMap::from([(0, 100), (1, 100), (2, 100), (3, 100), (4, 100)]),
             total_stake: 500,
             submitted: false,
             submitted_at_ms: None,
+            submission_tx: None,
             created_at_ms: 1700000000000,
             expires_at_ms: Some(1700001000000),
+            version: 1,
         }
     }
 
@@ -570,6 +751,47 @@ mod tests {
         assert_eq!(all.len(), 4);
     }
 
+    pub(super) fn sample_evidence(call_id: u64) -> EvidenceRecord {
+        EvidenceRecord {
+            operator: Address::with_last_byte(1),
+            submission: crate::types::SubmitSignatureRequest {
+                service_id: 1,
+                call_id,
+                operator_index: 0,
+                output: vec![1, 2, 3],
+                signature: vec![4; 32],
+                public_key: vec![5; 64],
+            }
+            .into(),
+            reason: "bad signature".to_string(),
+            offence_count: call_id,
+            recorded_at_ms: 1700000000000,
+        }
+    }
+
+    #[test]
+    fn test_file_persistence_evidence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let backend = FilePersistence::new(&path);
+        assert!(backend.load_evidence().unwrap().is_empty());
+
+        for call_id in 1..=3 {
+            backend.save_evidence(&sample_evidence(call_id), 2).unwrap();
+        }
+        backend.save_task(&sample_task()).unwrap();
+
+        // Only the newest records are kept, next to the tasks
+        let reopened = FilePersistence::new(&path);
+        let evidence = reopened.load_evidence().unwrap();
+        let call_ids: Vec<_> = evidence
+            .iter()
+            .map(|record| record.submission.request.call_id)
+            .collect();
+        assert_eq!(call_ids, [2, 3]);
+        assert_eq!(reopened.load_all_tasks().unwrap().len(), 1);
+    }
+
     #[test]
     fn test_threshold_type_conversion() {
         let count = ThresholdType::Count(5);
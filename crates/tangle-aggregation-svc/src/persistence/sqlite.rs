@@ -0,0 +1,682 @@
+//! SQLite persistence backend, shareable between service instances
+//!
+//! Every instance opens the same database file. SQLite serializes writers
+//! across processes, and each change is a single conditional statement on the
+//! task's version, so concurrent updates of a task never overwrite each other.
+//! Every change also takes the next number of a shared sequence, so instances
+//! only load the tasks changed since they last looked.
+//! The database must live on a filesystem with working file locks, so
+//! instances on different hosts need a shared volume that supports them.
+
+use super::{
+    FilePersistence, PersistedTaskState, PersistenceBackend, PersistenceError, Result, TaskChanges,
+};
+use crate::auth::EvidenceRecord;
+use crate::types::TaskId;
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+/// Version of the database schema, stored as `PRAGMA user_version`
+const SCHEMA_VERSION: i64 = 3;
+
+/// How long to wait for another instance's write to finish
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS aggregation_tasks (
+    service_id INTEGER NOT NULL,
+    call_id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    task TEXT NOT NULL,
+    seq INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (service_id, call_id)
+);
+CREATE TABLE IF NOT EXISTS aggregation_evidence (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    record TEXT NOT NULL
+);
+";
+
+/// Change tracking, applied once `aggregation_tasks` has its `seq` column
+///
+/// `aggregation_sequence` holds the number of the last change, and the newest
+/// change whose tombstone was pruned: readers behind it reload every task.
+/// Tombstones of deleted tasks are kept for an hour.
+const CHANGE_TRACKING: &str = "
+CREATE INDEX IF NOT EXISTS aggregation_tasks_seq ON aggregation_tasks (seq);
+CREATE TABLE IF NOT EXISTS aggregation_sequence (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    seq INTEGER NOT NULL,
+    pruned INTEGER NOT NULL
+);
+INSERT OR IGNORE INTO aggregation_sequence (id, seq, pruned) VALUES (0, 0, 0);
+CREATE TABLE IF NOT EXISTS aggregation_deleted_tasks (
+    service_id INTEGER NOT NULL,
+    call_id INTEGER NOT NULL,
+    seq INTEGER NOT NULL,
+    deleted_at INTEGER NOT NULL,
+    PRIMARY KEY (service_id, call_id)
+);
+CREATE INDEX IF NOT EXISTS aggregation_deleted_tasks_seq ON aggregation_deleted_tasks (seq);
+CREATE TRIGGER IF NOT EXISTS aggregation_tasks_inserted AFTER INSERT ON aggregation_tasks
+BEGIN
+    UPDATE aggregation_sequence SET seq = seq + 1;
+    UPDATE aggregation_tasks SET seq = (SELECT seq FROM aggregation_sequence)
+    WHERE rowid = NEW.rowid;
+    DELETE FROM aggregation_deleted_tasks
+    WHERE service_id = NEW.service_id AND call_id = NEW.call_id;
+END;
+CREATE TRIGGER IF NOT EXISTS aggregation_tasks_updated
+AFTER UPDATE OF version, task ON aggregation_tasks
+BEGIN
+    UPDATE aggregation_sequence SET seq = seq + 1;
+    UPDATE aggregation_tasks SET seq = (SELECT seq FROM aggregation_sequence)
+    WHERE rowid = NEW.rowid;
+END;
+CREATE TRIGGER IF NOT EXISTS aggregation_tasks_deleted AFTER DELETE ON aggregation_tasks
+BEGIN
+    UPDATE aggregation_sequence SET seq = seq + 1;
+    INSERT INTO aggregation_deleted_tasks (service_id, call_id, seq, deleted_at)
+    VALUES (
+        OLD.service_id,
+        OLD.call_id,
+        (SELECT seq FROM aggregation_sequence),
+        CAST(strftime('%s', 'now') AS INTEGER)
+    )
+    ON CONFLICT (service_id, call_id)
+    DO UPDATE SET seq = excluded.seq, deleted_at = excluded.deleted_at;
+    UPDATE aggregation_sequence SET pruned = MAX(pruned, (
+        SELECT COALESCE(MAX(seq), 0) FROM aggregation_deleted_tasks
+        WHERE deleted_at < CAST(strftime('%s', 'now') AS INTEGER) - 3600
+    ));
+    DELETE FROM aggregation_deleted_tasks
+    WHERE deleted_at < CAST(strftime('%s', 'now') AS INTEGER) - 3600;
+END;
+";
+
+/// SQLite-based persistence backend
+///
+/// Tasks are stored as JSON next to their version. The database runs in WAL
+/// mode, so readers don't block the writer.
+#[derive(Debug)]
+pub struct SqlitePersistence {
+    connection: Mutex<Connection>,
+}
+
+impl SqlitePersistence {
+    /// Open the database at `path`, creating it if needed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let connection = Connection::open(path).map_err(backend_error)?;
+        Self::from_connection(connection)
+    }
+
+    /// Open a private in-memory database
+    ///
+    /// Nothing is shared or kept across restarts; meant for tests.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory().map_err(backend_error)?)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self> {
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .map_err(backend_error)?;
+        connection
+            .query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))
+            .map_err(backend_error)?;
+
+        let version: i64 = connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(backend_error)?;
+        if version > SCHEMA_VERSION {
+            return Err(PersistenceError::Backend(format!(
+                "database schema version {version} is newer than the supported version {SCHEMA_VERSION}"
+            )));
+        }
+
+        // Instances may start together, so only one of them migrates
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(backend_error)?;
+        transaction.execute_batch(SCHEMA).map_err(backend_error)?;
+        let has_seq: bool = transaction
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('aggregation_tasks')
+                 WHERE name = 'seq'",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(backend_error)?;
+        if !has_seq {
+            transaction
+                .execute_batch(
+                    "ALTER TABLE aggregation_tasks ADD COLUMN seq INTEGER NOT NULL DEFAULT 0",
+                )
+                .map_err(backend_error)?;
+        }
+        transaction
+            .execute_batch(CHANGE_TRACKING)
+            .map_err(backend_error)?;
+        transaction
+            .pragma_update(None, "user_version", SCHEMA_VERSION)
+            .map_err(backend_error)?;
+        transaction.commit().map_err(backend_error)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Copy the tasks of `source` into the database
+    ///
+    /// Tasks already in the database are kept as they are. Returns the number
+    /// of imported tasks.
+    pub fn import_from(&self, source: &dyn PersistenceBackend) -> Result<usize> {
+        let tasks = source.load_all_tasks()?;
+        let mut connection = self.connection.lock();
+        let transaction = connection.transaction().map_err(backend_error)?;
+        let mut imported = 0;
+        for task in &tasks {
+            imported += transaction
+                .execute(
+                    "INSERT INTO aggregation_tasks (service_id, call_id, version, task)
+                     VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (service_id, call_id) DO NOTHING",
+                    params![
+                        to_sql_id(task.service_id),
+                        to_sql_id(task.call_id),
+                        to_sql_id(task.version),
+                        encode_task(task)?,
+                    ],
+                )
+                .map_err(backend_error)?;
+        }
+        transaction.commit().map_err(backend_error)?;
+        Ok(imported)
+    }
+
+    /// Import the tasks of a [`FilePersistence`] file, then retire the file
+    ///
+    /// The file is renamed to `<path>.migrated` once its tasks are committed,
+    /// so later starts don't bring back tasks that were since deleted. Its
+    /// evidence records are appended, and their file retired the same way. A
+    /// missing file imports nothing. Returns the number of imported tasks.
+    pub fn migrate_from_file(&self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+        let file = FilePersistence::new(path);
+        let evidence_path = file.evidence_path();
+        if evidence_path.exists() {
+            let mut connection = self.connection.lock();
+            let transaction = connection.transaction().map_err(backend_error)?;
+            for record in file.load_evidence()? {
+                insert_evidence(&transaction, &record)?;
+            }
+            transaction.commit().map_err(backend_error)?;
+            drop(connection);
+            std::fs::rename(&evidence_path, retired_path(&evidence_path))?;
+        }
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let imported = self.import_from(&file)?;
+        std::fs::rename(path, retired_path(path))?;
+        info!(imported, path = %path.display(), "Migrated aggregation tasks from file");
+        Ok(imported)
+    }
+}
+
+impl PersistenceBackend for SqlitePersistence {
+    fn save_task(&self, task: &PersistedTaskState) -> Result<()> {
+        self.connection
+            .lock()
+            .execute(
+                "INSERT INTO aggregation_tasks (service_id, call_id, version, task)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (service_id, call_id)
+                 DO UPDATE SET version = excluded.version, task = excluded.task",
+                params![
+                    to_sql_id(task.service_id),
+                    to_sql_id(task.call_id),
+                    to_sql_id(task.version),
+                    encode_task(task)?,
+                ],
+            )
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    fn load_task(&self, task_id: &TaskId) -> Result<Option<PersistedTaskState>> {
+        let row = self
+            .connection
+            .lock()
+            .query_row(
+                "SELECT version, task FROM aggregation_tasks
+                 WHERE service_id = ?1 AND call_id = ?2",
+                params![to_sql_id(task_id.service_id), to_sql_id(task_id.call_id)],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()
+            .map_err(backend_error)?;
+        row.map(|(version, task)| decode_task(version, &task))
+            .transpose()
+    }
+
+    fn delete_task(&self, task_id: &TaskId) -> Result<()> {
+        self.connection
+            .lock()
+            .execute(
+                "DELETE FROM aggregation_tasks WHERE service_id = ?1 AND call_id = ?2",
+                params![to_sql_id(task_id.service_id), to_sql_id(task_id.call_id)],
+            )
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    fn load_all_tasks(&self) -> Result<Vec<PersistedTaskState>> {
+        let connection = self.connection.lock();
+        let mut statement = connection
+            .prepare("SELECT version, task FROM aggregation_tasks")
+            .map_err(backend_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(backend_error)?;
+        rows.map(|row| {
+            let (version, task) = row.map_err(backend_error)?;
+            decode_task(version, &task)
+        })
+        .collect()
+    }
+
+    fn task_exists(&self, task_id: &TaskId) -> Result<bool> {
+        self.connection
+            .lock()
+            .query_row(
+                "SELECT 1 FROM aggregation_tasks WHERE service_id = ?1 AND call_id = ?2",
+                params![to_sql_id(task_id.service_id), to_sql_id(task_id.call_id)],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|row| row.is_some())
+            .map_err(backend_error)
+    }
+
+    fn is_shared(&self) -> bool {
+        true
+    }
+
+    fn save_evidence(&self, record: &EvidenceRecord, capacity: usize) -> Result<()> {
+        let mut connection = self.connection.lock();
+        let transaction = connection.transaction().map_err(backend_error)?;
+        let id = insert_evidence(&transaction, record)?;
+        transaction
+            .execute(
+                "DELETE FROM aggregation_evidence WHERE id <= ?1",
+                params![id.saturating_sub(i64::try_from(capacity).unwrap_or(i64::MAX))],
+            )
+            .map_err(backend_error)?;
+        transaction.commit().map_err(backend_error)
+    }
+
+    fn load_evidence(&self) -> Result<Vec<EvidenceRecord>> {
+        let connection = self.connection.lock();
+        let mut statement = connection
+            .prepare("SELECT record FROM aggregation_evidence ORDER BY id")
+            .map_err(backend_error)?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(backend_error)?;
+        rows.map(|row| {
+            serde_json::from_str(&row.map_err(backend_error)?)
+                .map_err(|e| PersistenceError::Serialization(e.to_string()))
+        })
+        .collect()
+    }
+
+    fn compare_and_save(&self, task: &PersistedTaskState, expected_version: u64) -> Result<bool> {
+        // A missing task is at version 0, so only then may the task be inserted
+        let sql = if expected_version == 0 {
+            "INSERT INTO aggregation_tasks (service_id, call_id, version, task)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (service_id, call_id)
+             DO UPDATE SET version = excluded.version, task = excluded.task
+             WHERE aggregation_tasks.version = ?5"
+        } else {
+            "UPDATE aggregation_tasks SET version = ?3, task = ?4
+             WHERE service_id = ?1 AND call_id = ?2 AND version = ?5"
+        };
+        let changed = self
+            .connection
+            .lock()
+            .execute(
+                sql,
+                params![
+                    to_sql_id(task.service_id),
+                    to_sql_id(task.call_id),
+                    to_sql_id(task.version),
+                    encode_task(task)?,
+                    to_sql_id(expected_version),
+                ],
+            )
+            .map_err(backend_error)?;
+        Ok(changed == 1)
+    }
+
+    fn load_changes(&self, cursor: Option<u64>) -> Result<TaskChanges> {
+        let mut connection = self.connection.lock();
+        // A single read transaction, so the cursor matches the changes
+        let transaction = connection.transaction().map_err(backend_error)?;
+        let (seq, pruned) = transaction
+            .query_row("SELECT seq, pruned FROM aggregation_sequence", [], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(backend_error)?;
+        // Deletions older than the pruned tombstones are unknown
+        let since = cursor.map(to_sql_id).filter(|cursor| *cursor >= pruned);
+
+        let mut statement = transaction
+            .prepare_cached("SELECT version, task FROM aggregation_tasks WHERE seq > ?1")
+            .map_err(backend_error)?;
+        let saved = statement
+            .query_map(params![since.unwrap_or(-1)], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(backend_error)?
+            .map(|row| {
+                let (version, task) = row.map_err(backend_error)?;
+                decode_task(version, &task)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        drop(statement);
+
+        let deleted = match since {
+            Some(since) => {
+                let mut statement = transaction
+                    .prepare_cached(
+                        "SELECT service_id, call_id FROM aggregation_deleted_tasks
+                         WHERE seq > ?1",
+                    )
+                    .map_err(backend_error)?;
+                let rows = statement
+                    .query_map(params![since], |row| {
+                        Ok(TaskId::new(
+                            row.get::<_, i64>(0)? as u64,
+                            row.get::<_, i64>(1)? as u64,
+                        ))
+                    })
+                    .map_err(backend_error)?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()
+                    .map_err(backend_error)?
+            }
+            None => Vec::new(),
+        };
+
+        Ok(TaskChanges {
+            saved,
+            deleted,
+            complete: since.is_none(),
+            cursor: seq as u64,
+        })
+    }
+
+    fn compare_and_delete(&self, task_id: &TaskId, expected_version: u64) -> Result<bool> {
+        let deleted = self
+            .connection
+            .lock()
+            .execute(
+                "DELETE FROM aggregation_tasks
+                 WHERE service_id = ?1 AND call_id = ?2 AND version = ?3",
+                params![
+                    to_sql_id(task_id.service_id),
+                    to_sql_id(task_id.call_id),
+                    to_sql_id(expected_version),
+                ],
+            )
+            .map_err(backend_error)?;
+        Ok(deleted == 1 || !self.task_exists(task_id)?)
+    }
+}
+
+/// Append `record`, returning its row id
+fn insert_evidence(connection: &Connection, record: &EvidenceRecord) -> Result<i64> {
+    let record = serde_json::to_string(record)
+        .map_err(|e| PersistenceError::Serialization(e.to_string()))?;
+    connection
+        .execute(
+            "INSERT INTO aggregation_evidence (record) VALUES (?1)",
+            params![record],
+        )
+        .map_err(backend_error)?;
+    Ok(connection.last_insert_rowid())
+}
+
+fn retired_path(path: &Path) -> PathBuf {
+    let mut retired = path.as_os_str().to_owned();
+    retired.push(".migrated");
+    PathBuf::from(retired)
+}
+
+fn backend_error(error: rusqlite::Error) -> PersistenceError {
+    PersistenceError::Backend(error.to_string())
+}
+
+/// SQLite integers are signed, so ids are stored with the same bits
+fn to_sql_id(id: u64) -> i64 {
+    id as i64
+}
+
+fn encode_task(task: &PersistedTaskState) -> Result<String> {
+    serde_json::to_string(task).map_err(|e| PersistenceError::Serialization(e.to_string()))
+}
+
+fn decode_task(version: i64, task: &str) -> Result<PersistedTaskState> {
+    let mut task: PersistedTaskState =
+        serde_json::from_str(task).map_err(|e| PersistenceError::Serialization(e.to_string()))?;
+    task.version = version as u64;
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{sample_evidence, sample_task};
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_persistence() {
+        let backend = SqlitePersistence::open_in_memory().unwrap();
+        let task = sample_task();
+        let task_id = TaskId::new(task.service_id, task.call_id);
+
+        backend.save_task(&task).unwrap();
+        let loaded = backend.load_task(&task_id).unwrap().unwrap();
+        assert_eq!(loaded.output, task.output);
+        assert_eq!(loaded.version, task.version);
+        assert_eq!(backend.load_all_tasks().unwrap().len(), 1);
+        assert!(backend.task_exists(&task_id).unwrap());
+
+        backend.delete_task(&task_id).unwrap();
+        assert!(backend.load_task(&task_id).unwrap().is_none());
+        assert!(!backend.task_exists(&task_id).unwrap());
+    }
+
+    #[test]
+    fn compare_and_save_rejects_stale_versions() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.db");
+        let first = SqlitePersistence::open(&path).unwrap();
+        let second = SqlitePersistence::open(&path).unwrap();
+        let mut task = sample_task();
+        let task_id = TaskId::new(task.service_id, task.call_id);
+
+        task.version = 1;
+        assert!(first.compare_and_save(&task, 0).unwrap());
+        // The task exists now, so a second insert loses
+        assert!(!second.compare_and_save(&task, 0).unwrap());
+
+        task.version = 2;
+        task.submitted = true;
+        assert!(second.compare_and_save(&task, 1).unwrap());
+        task.version = 2;
+        task.submitted = false;
+        assert!(!first.compare_and_save(&task, 1).unwrap());
+
+        let stored = first.load_task(&task_id).unwrap().unwrap();
+        assert_eq!(stored.version, 2);
+        assert!(stored.submitted);
+
+        assert!(!first.compare_and_delete(&task_id, 1).unwrap());
+        assert!(first.compare_and_delete(&task_id, 2).unwrap());
+        assert!(second.load_task(&task_id).unwrap().is_none());
+        // Updating a task deleted by another instance must not recreate it
+        task.version = 3;
+        assert!(!second.compare_and_save(&task, 2).unwrap());
+        assert!(second.load_task(&task_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn only_changes_since_the_cursor_are_loaded() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.db");
+        let writer = SqlitePersistence::open(&path).unwrap();
+        let reader = SqlitePersistence::open(&path).unwrap();
+        for call_id in 0..3 {
+            let mut task = sample_task();
+            task.call_id = call_id;
+            writer.save_task(&task).unwrap();
+        }
+
+        let all = reader.load_changes(None).unwrap();
+        assert!(all.complete);
+        assert_eq!(all.saved.len(), 3);
+        let unchanged = reader.load_changes(Some(all.cursor)).unwrap();
+        assert!(!unchanged.complete);
+        assert!(unchanged.saved.is_empty() && unchanged.deleted.is_empty());
+
+        let mut task = sample_task();
+        task.call_id = 1;
+        task.version = 2;
+        assert!(writer.compare_and_save(&task, 1).unwrap());
+        // A failed update is no change
+        assert!(!writer.compare_and_save(&task, 1).unwrap());
+        writer.delete_task(&TaskId::new(1, 2)).unwrap();
+
+        let changes = reader.load_changes(Some(all.cursor)).unwrap();
+        assert!(!changes.complete);
+        assert_eq!(changes.saved.len(), 1);
+        assert_eq!(changes.saved[0].call_id, 1);
+        assert_eq!(changes.saved[0].version, 2);
+        assert_eq!(changes.deleted, [TaskId::new(1, 2)]);
+
+        // A task saved again after its deletion is no longer reported deleted
+        let mut task = sample_task();
+        task.call_id = 2;
+        writer.save_task(&task).unwrap();
+        let changes = reader.load_changes(Some(all.cursor)).unwrap();
+        assert_eq!(changes.saved.len(), 2);
+        assert!(changes.deleted.is_empty());
+    }
+
+    #[test]
+    fn schema_version_2_databases_are_migrated() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.db");
+        {
+            let connection = Connection::open(&path).unwrap();
+            connection
+                .execute_batch(
+                    "CREATE TABLE aggregation_tasks (
+                        service_id INTEGER NOT NULL,
+                        call_id INTEGER NOT NULL,
+                        version INTEGER NOT NULL,
+                        task TEXT NOT NULL,
+                        PRIMARY KEY (service_id, call_id)
+                    );
+                    PRAGMA user_version = 2;",
+                )
+                .unwrap();
+            connection
+                .execute(
+                    "INSERT INTO aggregation_tasks (service_id, call_id, version, task)
+                     VALUES (1, 100, 1, ?1)",
+                    params![encode_task(&sample_task()).unwrap()],
+                )
+                .unwrap();
+        }
+
+        let backend = SqlitePersistence::open(&path).unwrap();
+        let all = backend.load_changes(None).unwrap();
+        assert_eq!(all.saved.len(), 1);
+        backend.delete_task(&TaskId::new(1, 100)).unwrap();
+        let changes = backend.load_changes(Some(all.cursor)).unwrap();
+        assert_eq!(changes.deleted, [TaskId::new(1, 100)]);
+    }
+
+    #[test]
+    fn migrates_tasks_from_file_persistence() {
+        let dir = TempDir::new().unwrap();
+        let json = dir.path().join("state.json");
+        let file = FilePersistence::new(&json);
+        for call_id in 0..3 {
+            let mut task = sample_task();
+            task.call_id = call_id;
+            task.version = 0;
+            file.save_task(&task).unwrap();
+        }
+
+        let backend = SqlitePersistence::open(dir.path().join("state.db")).unwrap();
+        let mut existing = sample_task();
+        existing.call_id = 0;
+        existing.version = 5;
+        backend.save_task(&existing).unwrap();
+
+        assert_eq!(backend.migrate_from_file(&json).unwrap(), 2);
+        assert!(!json.exists());
+        assert!(dir.path().join("state.json.migrated").exists());
+        assert_eq!(backend.load_all_tasks().unwrap().len(), 3);
+        let kept = backend.load_task(&TaskId::new(1, 0)).unwrap().unwrap();
+        assert_eq!(kept.version, 5);
+
+        // Tasks from the file format start at version 0, and can be updated as such
+        let mut imported = backend.load_task(&TaskId::new(1, 1)).unwrap().unwrap();
+        assert_eq!(imported.version, 0);
+        imported.version = 1;
+        assert!(backend.compare_and_save(&imported, 0).unwrap());
+
+        assert_eq!(backend.migrate_from_file(&json).unwrap(), 0);
+    }
+
+    #[test]
+    fn evidence_is_shared_and_bounded() {
+        let dir = TempDir::new().unwrap();
+        let json = dir.path().join("state.json");
+        FilePersistence::new(&json)
+            .save_evidence(&sample_evidence(1), 10)
+            .unwrap();
+
+        let path = dir.path().join("state.db");
+        let first = SqlitePersistence::open(&path).unwrap();
+        let second = SqlitePersistence::open(&path).unwrap();
+        first.migrate_from_file(&json).unwrap();
+        assert!(dir.path().join("state.json.evidence.migrated").exists());
+
+        second.save_evidence(&sample_evidence(2), 2).unwrap();
+        first.save_evidence(&sample_evidence(3), 2).unwrap();
+        let call_ids: Vec<_> = second
+            .load_evidence()
+            .unwrap()
+            .iter()
+            .map(|record| record.submission.request.call_id)
+            .collect();
+        assert_eq!(call_ids, [2, 3]);
+    }
+}
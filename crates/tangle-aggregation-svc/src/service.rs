@@ -1,17 +1,24 @@
 //! Main aggregation service logic
 
+use crate::auth::{
+    recover_mark_submitter, recover_submitter, AuthError, EvidenceLog, EvidenceRecord, RateLimiter,
+    SubmissionAuthConfig,
+};
+use crate::chain::{ChainError, OperatorSetSource};
 use crate::persistence::{NoPersistence, PersistedTaskState, PersistenceBackend, PersistenceError};
-use crate::state::{AggregationState, TaskConfig, ThresholdType};
+use crate::state::{AggregationState, TaskConfig, TaskStatus, ThresholdType};
 use crate::types::*;
-use alloy_primitives::U256;
+use alloy_primitives::{Address, B256, U256};
 use ark_serialize::CanonicalDeserialize;
 use blueprint_crypto_bn254::{ArkBlsBn254, ArkBlsBn254Public, ArkBlsBn254Signature};
 use blueprint_crypto_core::{aggregation::AggregatableSignature, KeyType};
 use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
-use tokio::sync::watch;
+use tokio::sync::{broadcast, watch};
 use tracing::{debug, info, warn};
 
 /// Errors from the aggregation service
@@ -34,6 +41,16 @@ pub enum ServiceError {
     #[error("Aggregation failed: {0}")]
     AggregationFailed(String),
     #[error("{0}")]
+    Chain(#[from] ChainError),
+    #[error("Request does not match the chain: {0}")]
+    ChainMismatch(String),
+    #[error("Task was concurrently modified by another service instance")]
+    Conflict,
+    #[error("{0}")]
+    Unauthorized(#[from] AuthError),
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(String),
+    #[error("{0}")]
     Other(String),
 }
 
@@ -52,6 +69,11 @@ pub struct ServiceConfig {
     pub auto_cleanup_submitted: bool,
     /// How long submitted tasks remain observable before cleanup
     pub submitted_task_retention: Duration,
+    /// Authentication and rate limits of signature submissions
+    pub submission_auth: SubmissionAuthConfig,
+    /// How often the changes other instances made on a shared backend are
+    /// published as task events, see [`AggregationService::start_event_sync_worker`]
+    pub event_sync_interval: Option<Duration>,
 }
 
 impl Default for ServiceConfig {
@@ -63,6 +85,8 @@ impl Default for ServiceConfig {
             cleanup_interval: Some(Duration::from_secs(60)),   // Cleanup every minute
             auto_cleanup_submitted: true,
             submitted_task_retention: Duration::from_secs(120),
+            submission_auth: SubmissionAuthConfig::default(),
+            event_sync_interval: Some(Duration::from_secs(1)),
         }
     }
 }
@@ -77,15 +101,51 @@ impl ServiceConfig {
             cleanup_interval: None,
             auto_cleanup_submitted: false,
             submitted_task_retention: Duration::ZERO,
+            submission_auth: SubmissionAuthConfig::default(),
+            event_sync_interval: None,
         }
     }
 }
 
+/// Number of task events buffered for slow subscribers
+const EVENT_CAPACITY: usize = 1024;
+
+/// How often a change racing with another instance is retried on a shared backend
+const MAX_CONFLICT_RETRIES: usize = 8;
+
+/// Number of evidence records kept, the oldest are dropped first
+const EVIDENCE_CAPACITY: usize = 4096;
+
+/// Rate-limited submitter: service, claimed operator index, and the address
+/// that signed the envelope, so unsigned submissions can't use up the limit
+/// of an authenticated operator
+type SubmitterKey = (u64, u32, Option<Address>);
+
+/// A task initialization checked against the operator source
+pub(crate) struct VerifiedTask {
+    service_id: u64,
+    call_id: u64,
+    output: Vec<u8>,
+    message: Vec<u8>,
+    signature_scheme: Bn254SignatureScheme,
+    operator_count: u32,
+    config: TaskConfig,
+}
+
 /// The main aggregation service
 pub struct AggregationService {
     state: AggregationState,
     config: ServiceConfig,
     persistence: Arc<dyn PersistenceBackend>,
+    operator_source: Option<Arc<dyn OperatorSetSource>>,
+    events: broadcast::Sender<TaskEvent>,
+    operator_limiter: Option<RateLimiter<SubmitterKey>>,
+    ip_limiter: Option<RateLimiter<IpAddr>>,
+    evidence: EvidenceLog,
+    /// Version and status of each task as last published, on a shared backend
+    published: Mutex<HashMap<TaskId, (u64, TaskStatus)>>,
+    /// Where the next sync picks up the changes of a shared backend
+    sync_cursor: Mutex<Option<u64>>,
     mutation_lock: Mutex<()>,
 }
 
@@ -96,6 +156,8 @@ impl std::fmt::Debug for AggregationService {
             .field("state", &self.state)
             .field("config", &self.config)
             .field("persistence", &"configured")
+            .field("operator_source", &self.operator_source.is_some())
+            .field("subscribers", &self.events.receiver_count())
             .finish()
     }
 }
@@ -112,17 +174,24 @@ impl AggregationService {
     pub fn new(config: ServiceConfig) -> Self {
         Self {
             state: AggregationState::new(),
+            operator_limiter: config.submission_auth.per_operator.map(RateLimiter::new),
+            ip_limiter: config.submission_auth.per_ip.map(RateLimiter::new),
             config,
             persistence: Arc::new(NoPersistence),
+            operator_source: None,
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            evidence: EvidenceLog::new(EVIDENCE_CAPACITY),
+            published: Mutex::new(HashMap::new()),
+            sync_cursor: Mutex::new(None),
             mutation_lock: Mutex::new(()),
         }
     }
 
     /// Create a service backed by a persistence implementation.
     ///
-    /// Existing tasks are loaded before the service is returned. A malformed
-    /// or unreadable store fails construction instead of silently starting
-    /// with an empty task set.
+    /// Existing tasks and evidence records are loaded before the service is
+    /// returned. A malformed or unreadable store fails construction instead of
+    /// silently starting with an empty task set.
     pub fn with_persistence<P>(config: ServiceConfig, persistence: P) -> Result<Self, ServiceError>
     where
         P: PersistenceBackend + 'static,
@@ -130,18 +199,237 @@ impl AggregationService {
         let persistence = Arc::new(persistence);
         let state = AggregationState::new();
         let persisted = persistence
-            .load_all_tasks()
+            .load_changes(None)
+            .map_err(Self::persistence_error)?;
+        state
+            .restore(persisted.saved)
             .map_err(Self::persistence_error)?;
-        state.restore(persisted).map_err(Self::persistence_error)?;
+        let evidence = EvidenceLog::new(EVIDENCE_CAPACITY);
+        evidence.restore(
+            persistence
+                .load_evidence()
+                .map_err(Self::persistence_error)?,
+        );
 
-        Ok(Self {
+        let service = Self {
             state,
-            config,
             persistence,
-            mutation_lock: Mutex::new(()),
+            evidence,
+            sync_cursor: Mutex::new(Some(persisted.cursor)),
+            ..Self::new(config)
+        };
+        if service.persistence.is_shared() {
+            for task in service.snapshot()? {
+                service.note_published(TaskId::new(task.service_id, task.call_id), task.version);
+            }
+        }
+        Ok(service)
+    }
+
+    /// Derive the operator set and threshold of new tasks from `source`
+    ///
+    /// Tasks initialized through the API are then checked against the chain,
+    /// see [`Self::init_task_verified`].
+    #[must_use]
+    pub fn with_operator_source<S>(mut self, source: S) -> Self
+    where
+        S: OperatorSetSource + 'static,
+    {
+        self.operator_source = Some(Arc::new(source));
+        self
+    }
+
+    /// Whether new tasks are checked against an operator source
+    pub fn has_operator_source(&self) -> bool {
+        self.operator_source.is_some()
+    }
+
+    /// Stream the events of the tasks selected by `filter`
+    ///
+    /// Only events published after subscribing are received. A subscriber
+    /// falling too far behind has its stream ended, and should resubscribe and
+    /// check the current task status. On a shared backend, changes made by
+    /// other instances are only received while the event sync worker runs
+    /// (see [`Self::start_event_sync_worker`]), up to one interval late.
+    pub fn subscribe(
+        &self,
+        filter: TaskEventFilter,
+    ) -> impl futures::Stream<Item = TaskEvent> + Send + 'static {
+        futures::stream::unfold(self.events.subscribe(), move |mut events| async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) if filter.matches(&event) => return Some((event, events)),
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!(missed, "Task event subscriber lagged, ending its stream");
+                        return None;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
         })
     }
 
+    fn publish(&self, event: TaskEvent) {
+        // Sending only fails without subscribers
+        let _ = self.events.send(event);
+    }
+
+    /// Record that subscribers were told about `version` of a task
+    fn note_published(&self, task_id: TaskId, version: u64) {
+        if !self.persistence.is_shared() {
+            return;
+        }
+        if let Some(status) = self.state.get_status(task_id.service_id, task_id.call_id) {
+            self.published.lock().insert(task_id, (version, status));
+        }
+    }
+
+    /// Publish the events of task changes made by other instances on a shared backend
+    ///
+    /// Only the tasks changed since the last sync are loaded, and those at a
+    /// newer version than last published are compared with what subscribers
+    /// were told. Returns the number of published events; without a shared
+    /// backend there are none.
+    ///
+    /// Changes to tasks aren't held up while the backend is read, but this
+    /// blocks on the backend, so call it from a blocking thread rather than an
+    /// async task.
+    pub fn sync_shared_events(&self) -> Result<usize, ServiceError> {
+        if !self.persistence.is_shared() {
+            return Ok(0);
+        }
+
+        let cursor = *self.sync_cursor.lock();
+        let changes = self
+            .persistence
+            .load_changes(cursor)
+            .map_err(Self::persistence_error)?;
+
+        let mut events = Vec::new();
+        {
+            let _guard = self.mutation_lock.lock();
+            {
+                let mut sync_cursor = self.sync_cursor.lock();
+                // A concurrent sync applied its changes first, newer ones are
+                // picked up by the next sync
+                if *sync_cursor != cursor {
+                    return Ok(0);
+                }
+                *sync_cursor = Some(changes.cursor);
+            }
+
+            // A task this instance created since the read may be dropped here,
+            // it is reloaded with the next changes
+            let deleted: Vec<TaskId> = if changes.complete {
+                let live: HashSet<TaskId> = changes
+                    .saved
+                    .iter()
+                    .map(|task| TaskId::new(task.service_id, task.call_id))
+                    .collect();
+                self.state
+                    .task_ids()
+                    .into_iter()
+                    .filter(|task_id| !live.contains(task_id))
+                    .collect()
+            } else {
+                changes.deleted
+            };
+            for task_id in deleted {
+                self.published.lock().remove(&task_id);
+                if let Err(error) = self.state.replace_task(task_id, None) {
+                    warn!(%error, service_id = task_id.service_id, call_id = task_id.call_id, "failed to drop aggregation task");
+                }
+            }
+
+            for task in changes.saved {
+                let task_id = TaskId::new(task.service_id, task.call_id);
+                let version = task.version;
+                // Skip versions older than this instance's copy, or already
+                // published, such as its own changes
+                if self
+                    .state
+                    .task_version(&task_id)
+                    .is_some_and(|current| current > version)
+                    || self
+                        .published
+                        .lock()
+                        .get(&task_id)
+                        .is_some_and(|(published, _)| *published >= version)
+                {
+                    continue;
+                }
+                if let Err(error) = self.state.replace_task(task_id, Some(task)) {
+                    warn!(%error, service_id = task_id.service_id, call_id = task_id.call_id, "failed to reload aggregation task");
+                    continue;
+                }
+                let Some(status) = self.state.get_status(task_id.service_id, task_id.call_id)
+                else {
+                    continue;
+                };
+                let previous = self
+                    .published
+                    .lock()
+                    .insert(task_id, (version, status.clone()))
+                    .map(|(_, previous)| previous);
+                events.extend(self.task_changes(task_id, previous.as_ref(), &status));
+            }
+        }
+
+        let published = events.len();
+        for event in events {
+            self.publish(event);
+        }
+        Ok(published)
+    }
+
+    /// Events telling subscribers who saw `previous` about `status`
+    fn task_changes(
+        &self,
+        task_id: TaskId,
+        previous: Option<&TaskStatus>,
+        status: &TaskStatus,
+    ) -> Vec<TaskEvent> {
+        let TaskId {
+            service_id,
+            call_id,
+        } = task_id;
+        let new_signers = status.signer_bitmap & !previous.map_or(U256::ZERO, |p| p.signer_bitmap);
+        let mut signatures_collected = previous.map_or(0, |p| p.signatures_collected);
+        let mut events = Vec::new();
+        for operator_index in (0..U256::BITS).filter(|bit| new_signers.bit(*bit)) {
+            signatures_collected = (signatures_collected + 1).min(status.signatures_collected);
+            events.push(TaskEvent::SignatureReceived {
+                service_id,
+                call_id,
+                operator_index: operator_index as u32,
+                signatures_collected,
+                threshold_required: status.threshold_required,
+            });
+        }
+        if status.threshold_met && !previous.is_some_and(|p| p.threshold_met) {
+            events.push(TaskEvent::ThresholdMet {
+                service_id,
+                call_id,
+                signatures_collected: status.signatures_collected,
+                threshold_required: status.threshold_required,
+            });
+        }
+        if status.threshold_met && !new_signers.is_zero() {
+            if let Some(result) = self.aggregate(service_id, call_id) {
+                events.push(TaskEvent::AggregateReady(result));
+            }
+        }
+        if status.submitted && !previous.is_some_and(|p| p.submitted) {
+            events.push(TaskEvent::Submitted {
+                service_id,
+                call_id,
+                tx_hash: status.submission_tx,
+            });
+        }
+        events
+    }
+
     /// Create a new aggregation service wrapped in Arc
     pub fn new_shared(config: ServiceConfig) -> Arc<Self> {
         Arc::new(Self::new(config))
@@ -156,18 +444,110 @@ impl AggregationService {
     }
 
     fn persist_task(&self, service_id: u64, call_id: u64) -> Result<(), ServiceError> {
-        let task = self
+        let mut task = self
             .snapshot()?
             .into_iter()
             .find(|task| task.service_id == service_id && task.call_id == call_id)
             .ok_or(ServiceError::TaskNotFound)?;
-        self.persistence
-            .save_task(&task)
+        let expected_version = task.version;
+        task.version += 1;
+        if self.persistence.is_shared() {
+            let saved = self
+                .persistence
+                .compare_and_save(&task, expected_version)
+                .map_err(Self::persistence_error)?;
+            if !saved {
+                return Err(ServiceError::Conflict);
+            }
+        } else {
+            self.persistence
+                .save_task(&task)
+                .map_err(Self::persistence_error)?;
+        }
+        self.persistence.flush().map_err(Self::persistence_error)?;
+        let task_id = TaskId::new(service_id, call_id);
+        self.state.set_task_version(&task_id, task.version);
+        self.note_published(task_id, task.version);
+        Ok(())
+    }
+
+    /// Reload a task from a shared backend, picking up changes of other instances
+    ///
+    /// Must be called with the mutation lock held, so a change in progress
+    /// isn't overwritten.
+    fn refresh_task(&self, task_id: TaskId) -> Result<(), ServiceError> {
+        if !self.persistence.is_shared() {
+            return Ok(());
+        }
+        let persisted = self
+            .persistence
+            .load_task(&task_id)
             .map_err(Self::persistence_error)?;
-        self.persistence.flush().map_err(Self::persistence_error)
+        self.state
+            .replace_task(task_id, persisted)
+            .map_err(Self::persistence_error)
+    }
+
+    /// Reload every task from a shared backend
+    ///
+    /// Must be called with the mutation lock held.
+    fn refresh_all(&self) -> Result<(), ServiceError> {
+        if !self.persistence.is_shared() {
+            return Ok(());
+        }
+        let persisted = self
+            .persistence
+            .load_all_tasks()
+            .map_err(Self::persistence_error)?;
+        self.state
+            .restore(persisted)
+            .map_err(Self::persistence_error)
+    }
+
+    /// Reload a task for reading, keeping the cached copy if the backend fails
+    fn refresh_for_read(&self, service_id: u64, call_id: u64) {
+        if !self.persistence.is_shared() {
+            return;
+        }
+        let _guard = self.mutation_lock.lock();
+        if let Err(error) = self.refresh_task(TaskId::new(service_id, call_id)) {
+            warn!(%error, service_id, call_id, "failed to reload aggregation task");
+        }
+    }
+
+    /// Run `change` on the latest state of a task, under the mutation lock
+    ///
+    /// On a shared backend, a change losing the race with another instance
+    /// fails with [`ServiceError::Conflict`], and is retried on the reloaded task.
+    fn mutate_task<T>(
+        &self,
+        task_id: TaskId,
+        mut change: impl FnMut() -> Result<T, ServiceError>,
+    ) -> Result<T, ServiceError> {
+        let _guard = self.mutation_lock.lock();
+        let mut attempt = 0;
+        loop {
+            self.refresh_task(task_id)?;
+            match change() {
+                Err(ServiceError::Conflict) if attempt < MAX_CONFLICT_RETRIES => {
+                    attempt += 1;
+                    debug!(
+                        service_id = task_id.service_id,
+                        call_id = task_id.call_id,
+                        attempt,
+                        "Task changed by another instance, retrying"
+                    );
+                }
+                result => return result,
+            }
+        }
     }
 
     fn restore_persisted_task(&self, before: &[PersistedTaskState], task_id: &TaskId) {
+        // Changes to a shared backend are conditional, so a failed one left it untouched
+        if self.persistence.is_shared() {
+            return;
+        }
         let result = match before
             .iter()
             .find(|task| task.service_id == task_id.service_id && task.call_id == task_id.call_id)
@@ -185,6 +565,9 @@ impl AggregationService {
     }
 
     fn restore_persisted_tasks(&self, tasks: &[PersistedTaskState]) {
+        if self.persistence.is_shared() {
+            return;
+        }
         for task in tasks {
             if let Err(error) = self.persistence.save_task(task) {
                 warn!(%error, service_id = task.service_id, call_id = task.call_id, "failed to restore persisted aggregation task");
@@ -203,6 +586,7 @@ impl AggregationService {
 
     fn cleanup_with_persistence(&self, kind: CleanupKind) -> Result<usize, ServiceError> {
         let _guard = self.mutation_lock.lock();
+        self.refresh_all()?;
         let before = self.snapshot()?;
         let removed = match kind {
             CleanupKind::All => self
@@ -233,14 +617,28 @@ impl AggregationService {
             .filter(|task| !remaining.contains(&(task.service_id, task.call_id)))
             .cloned()
             .collect();
+        let mut removed = removed;
         for (index, task) in removed_tasks.iter().enumerate() {
-            if let Err(error) = self
-                .persistence
-                .delete_task(&TaskId::new(task.service_id, task.call_id))
-            {
-                self.restore_persisted_tasks(&removed_tasks[..=index]);
-                self.restore_snapshot(before);
-                return Err(Self::persistence_error(error));
+            let task_id = TaskId::new(task.service_id, task.call_id);
+            let result = if self.persistence.is_shared() {
+                self.persistence.compare_and_delete(&task_id, task.version)
+            } else {
+                self.persistence.delete_task(&task_id).map(|()| true)
+            };
+            match result {
+                Ok(true) => {}
+                Ok(false) => {
+                    // Changed by another instance since; reconsidered on the next cleanup
+                    removed -= 1;
+                    if let Err(error) = self.refresh_task(task_id) {
+                        warn!(%error, service_id = task.service_id, call_id = task.call_id, "failed to reload aggregation task");
+                    }
+                }
+                Err(error) => {
+                    self.restore_persisted_tasks(&removed_tasks[..=index]);
+                    self.restore_snapshot(before);
+                    return Err(Self::persistence_error(error));
+                }
             }
         }
         if let Err(error) = self.persistence.flush() {
@@ -265,17 +663,23 @@ impl AggregationService {
             loop {
                 tokio::select! {
                     _ = interval_timer.tick() => {
-                        let result = if service.config.auto_cleanup_submitted {
-                            service.cleanup_with_persistence(CleanupKind::All)
+                        let kind = if service.config.auto_cleanup_submitted {
+                            CleanupKind::All
                         } else {
-                            service.cleanup_with_persistence(CleanupKind::Expired)
+                            CleanupKind::Expired
                         };
+                        let cleanup = Arc::clone(&service);
+                        let result = tokio::task::spawn_blocking(move || {
+                            cleanup.cleanup_with_persistence(kind)
+                        })
+                        .await;
                         match result {
-                            Ok(removed) if removed > 0 => {
-                            debug!(removed, "Cleaned up tasks");
+                            Ok(Ok(removed)) if removed > 0 => {
+                                debug!(removed, "Cleaned up tasks");
                             }
-                            Ok(_) => {}
-                            Err(error) => warn!(%error, "failed to persist aggregation cleanup"),
+                            Ok(Ok(_)) => {}
+                            Ok(Err(error)) => warn!(%error, "failed to persist aggregation cleanup"),
+                            Err(error) => warn!(%error, "aggregation cleanup panicked"),
                         }
                     }
                     _ = shutdown_rx.changed() => {
@@ -294,6 +698,50 @@ impl AggregationService {
         })
     }
 
+    /// Start the background worker publishing the changes of other instances
+    ///
+    /// Only runs on a shared backend with an [`ServiceConfig::event_sync_interval`].
+    /// Returns a handle that can be used to stop the worker
+    pub fn start_event_sync_worker(self: &Arc<Self>) -> Option<CleanupWorkerHandle> {
+        if !self.persistence.is_shared() {
+            return None;
+        }
+        let interval = self.config.event_sync_interval?;
+        let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+
+        let service = Arc::clone(self);
+
+        let handle = tokio::spawn(async move {
+            let mut interval_timer = tokio::time::interval(interval);
+
+            loop {
+                tokio::select! {
+                    _ = interval_timer.tick() => {
+                        let sync = Arc::clone(&service);
+                        match tokio::task::spawn_blocking(move || sync.sync_shared_events()).await {
+                            Ok(Ok(_)) => {}
+                            Ok(Err(error)) => {
+                                warn!(%error, "failed to sync task events from the shared backend");
+                            }
+                            Err(error) => warn!(%error, "task event sync panicked"),
+                        }
+                    }
+                    _ = shutdown_rx.changed() => {
+                        if *shutdown_rx.borrow() {
+                            info!("Event sync worker shutting down");
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        Some(CleanupWorkerHandle {
+            shutdown_tx,
+            handle,
+        })
+    }
+
     /// Initialize a new aggregation task
     pub fn init_task(
         &self,
@@ -358,35 +806,283 @@ impl AggregationService {
             "Initializing aggregation task"
         );
 
-        let _guard = self.mutation_lock.lock();
-        let before = self.snapshot()?;
-        let result = self
-            .state
-            .init_task_with_message_config(
-                service_id,
-                call_id,
-                output,
-                message,
-                signature_scheme,
-                operator_count,
-                config,
-            )
-            .map_err(|e| ServiceError::Other(e.to_string()));
-        result?;
-        if let Err(error) = self.persist_task(service_id, call_id) {
-            self.restore_persisted_task(&before, &TaskId::new(service_id, call_id));
-            self.restore_snapshot(before);
-            return Err(error);
+        self.mutate_task(TaskId::new(service_id, call_id), || {
+            let before = self.snapshot()?;
+            self.state
+                .init_task_with_message_config(
+                    service_id,
+                    call_id,
+                    output.clone(),
+                    message.clone(),
+                    signature_scheme,
+                    operator_count,
+                    config.clone(),
+                )
+                .map_err(|e| ServiceError::Other(e.to_string()))?;
+            if let Err(error) = self.persist_task(service_id, call_id) {
+                self.restore_persisted_task(&before, &TaskId::new(service_id, call_id));
+                self.restore_snapshot(before);
+                return Err(error);
+            }
+            Ok(())
+        })
+    }
+
+    /// Initialize a task after checking the request against the operator source
+    ///
+    /// The operator count and threshold are derived from the chain, and a
+    /// request disagreeing with them is rejected. Registered operator public
+    /// keys are pinned, so signatures under any other key, or from operators
+    /// without a registered key, are refused.
+    pub async fn init_task_verified(&self, req: InitTaskRequest) -> Result<(), ServiceError> {
+        let task = self.verify_init_request(req).await?;
+        self.init_verified_task(task)
+    }
+
+    /// Check a task initialization against the operator source, see [`Self::init_task_verified`]
+    pub(crate) async fn verify_init_request(
+        &self,
+        req: InitTaskRequest,
+    ) -> Result<VerifiedTask, ServiceError> {
+        let source = self
+            .operator_source
+            .as_ref()
+            .ok_or_else(|| ServiceError::Other("no operator source configured".to_string()))?;
+        let chain = source.task_config(req.service_id, req.call_id).await?;
+
+        if req.operator_count != chain.operator_count() {
+            return Err(ServiceError::ChainMismatch(format!(
+                "operator count {} differs from the {} operators on chain",
+                req.operator_count,
+                chain.operator_count()
+            )));
         }
-        Ok(())
+        if let Some(mismatch) = threshold_mismatch(&req.threshold, &chain.threshold) {
+            warn!(
+                service_id = req.service_id,
+                call_id = req.call_id,
+                %mismatch,
+                "Rejecting task initialization"
+            );
+            return Err(ServiceError::ChainMismatch(mismatch));
+        }
+
+        let operator_count = chain.operator_count();
+        let operator_public_keys = chain.operator_public_keys();
+        let operator_addresses = chain.operator_addresses();
+        let config = TaskConfig {
+            operator_public_keys,
+            require_registered_keys: true,
+            operator_addresses,
+            ..TaskConfig::from(chain.threshold)
+        };
+        let (message, signature_scheme) = if req.message.is_empty() {
+            (
+                create_signing_message(req.service_id, req.call_id, &req.output),
+                Bn254SignatureScheme::ArkworksSha256,
+            )
+        } else {
+            (req.message, req.signature_scheme)
+        };
+        Ok(VerifiedTask {
+            service_id: req.service_id,
+            call_id: req.call_id,
+            output: req.output,
+            message,
+            signature_scheme,
+            operator_count,
+            config,
+        })
+    }
+
+    /// Initialize a task checked by [`Self::verify_init_request`]
+    pub(crate) fn init_verified_task(&self, task: VerifiedTask) -> Result<(), ServiceError> {
+        self.init_task_with_message_config(
+            task.service_id,
+            task.call_id,
+            task.output,
+            task.message,
+            task.signature_scheme,
+            task.operator_count,
+            task.config,
+        )
     }
 
     /// Submit a signature for aggregation
+    ///
+    /// The submission carries no envelope, so it is rejected if envelopes are
+    /// required. See [`Self::submit_signed`].
     pub fn submit_signature(
         &self,
         req: SubmitSignatureRequest,
     ) -> Result<SubmitSignatureResponse, ServiceError> {
-        let _guard = self.mutation_lock.lock();
+        self.submit_signed(req.into(), None)
+    }
+
+    /// Submit a signature, checking its envelope and rate limits first
+    ///
+    /// `source` is the client address, for the per-IP rate limit. A
+    /// submission with a valid envelope but a malformed BLS signature or
+    /// public key is recorded as evidence against the operator. Failed
+    /// verifications and output mismatches aren't, as they're checked against
+    /// a task anyone can initialize.
+    pub fn submit_signed(
+        &self,
+        submission: SignedSubmission,
+        source: Option<IpAddr>,
+    ) -> Result<SubmitSignatureResponse, ServiceError> {
+        let req = &submission.request;
+        if let (Some(limiter), Some(ip)) = (&self.ip_limiter, source) {
+            if !limiter.check(ip) {
+                debug!(%ip, "Rejecting submission over the per-IP rate limit");
+                return Err(ServiceError::RateLimited(format!(
+                    "too many submissions from {ip}"
+                )));
+            }
+        }
+
+        let operator = self.authenticate(&submission)?;
+        if let Some(limiter) = &self.operator_limiter {
+            if !limiter.check((req.service_id, req.operator_index, operator)) {
+                debug!(
+                    service_id = req.service_id,
+                    operator_index = req.operator_index,
+                    "Rejecting submission over the per-operator rate limit"
+                );
+                return Err(ServiceError::RateLimited(format!(
+                    "too many submissions for operator {}",
+                    req.operator_index
+                )));
+            }
+        }
+
+        let result = self.mutate_task(TaskId::new(req.service_id, req.call_id), || {
+            self.submit_signature_once(req)
+        });
+        if let (Err(error), Some(operator)) = (&result, operator) {
+            if matches!(
+                error,
+                ServiceError::InvalidSignature | ServiceError::InvalidPublicKey
+            ) {
+                self.refresh_evidence();
+                let record = self
+                    .evidence
+                    .record(operator, submission.clone(), error.to_string());
+                if let Err(error) = self
+                    .persistence
+                    .save_evidence(&record, self.evidence.capacity())
+                {
+                    warn!(%error, %operator, "failed to persist evidence");
+                }
+                warn!(
+                    service_id = req.service_id,
+                    call_id = req.call_id,
+                    operator_index = req.operator_index,
+                    %operator,
+                    offence_count = record.offence_count,
+                    "Recorded evidence of an invalid submission"
+                );
+            }
+        }
+        result
+    }
+
+    /// The client address of a request from `peer`, for the per-IP rate limit
+    ///
+    /// See [`SubmissionAuthConfig::client_ip`].
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        self.config.submission_auth.client_ip(peer, forwarded_for)
+    }
+
+    /// Check the envelope of `submission` against the operator's registered address
+    ///
+    /// Returns the authenticated operator, or `None` for an accepted
+    /// submission that can't be attributed.
+    fn authenticate(&self, submission: &SignedSubmission) -> Result<Option<Address>, ServiceError> {
+        let req = &submission.request;
+        self.authenticate_operator(
+            TaskId::new(req.service_id, req.call_id),
+            req.operator_index,
+            &submission.envelope_signature,
+            || recover_submitter(submission),
+        )
+    }
+
+    /// Check an envelope recovered by `recover` against the registered address
+    /// of `operator_index` in the task
+    fn authenticate_operator(
+        &self,
+        task_id: TaskId,
+        operator_index: u32,
+        envelope: &[u8],
+        recover: impl FnOnce() -> Result<Address, AuthError>,
+    ) -> Result<Option<Address>, ServiceError> {
+        let TaskId {
+            service_id,
+            call_id,
+        } = task_id;
+        let required = self.config.submission_auth.require_envelope;
+        if envelope.is_empty() && !required {
+            return Ok(None);
+        }
+
+        self.refresh_for_read(service_id, call_id);
+        let expected = self
+            .state
+            .get_operator_address(service_id, call_id, operator_index)
+            .ok_or(ServiceError::TaskNotFound)?;
+        let Some(expected) = expected else {
+            return if required {
+                Err(AuthError::UnknownOperator(operator_index).into())
+            } else {
+                Ok(None)
+            };
+        };
+
+        let signer = recover()?;
+        if signer != expected {
+            warn!(
+                service_id,
+                call_id,
+                operator_index,
+                %signer,
+                "Envelope not signed by the operator"
+            );
+            return Err(AuthError::WrongSigner {
+                operator_index,
+                signer,
+                expected,
+            }
+            .into());
+        }
+        Ok(Some(signer))
+    }
+
+    /// Evidence of invalid submissions, optionally only for `service_id`
+    ///
+    /// On a shared backend, this includes the evidence recorded by other
+    /// instances.
+    pub fn evidence(&self, service_id: Option<u64>) -> Vec<EvidenceRecord> {
+        self.refresh_evidence();
+        self.evidence.export(service_id)
+    }
+
+    /// Reload the evidence log from a shared backend, so records and offence
+    /// counts include those of other instances
+    fn refresh_evidence(&self) {
+        if !self.persistence.is_shared() {
+            return;
+        }
+        match self.persistence.load_evidence() {
+            Ok(records) => self.evidence.restore(records),
+            Err(error) => warn!(%error, "failed to reload evidence"),
+        }
+    }
+
+    fn submit_signature_once(
+        &self,
+        req: &SubmitSignatureRequest,
+    ) -> Result<SubmitSignatureResponse, ServiceError> {
         let before = self.snapshot()?;
         debug!(
             service_id = req.service_id,
@@ -488,6 +1184,29 @@ impl AggregationService {
             "Signature accepted"
         );
 
+        if self.events.receiver_count() > 0 {
+            self.publish(TaskEvent::SignatureReceived {
+                service_id: req.service_id,
+                call_id: req.call_id,
+                operator_index: req.operator_index,
+                signatures_collected: count,
+                threshold_required: status.threshold_required,
+            });
+            if threshold_met && !status.threshold_met {
+                self.publish(TaskEvent::ThresholdMet {
+                    service_id: req.service_id,
+                    call_id: req.call_id,
+                    signatures_collected: count,
+                    threshold_required: status.threshold_required,
+                });
+            }
+            if threshold_met {
+                if let Some(result) = self.aggregate(req.service_id, req.call_id) {
+                    self.publish(TaskEvent::AggregateReady(result));
+                }
+            }
+        }
+
         Ok(SubmitSignatureResponse {
             accepted: true,
             signatures_collected: count,
@@ -499,6 +1218,7 @@ impl AggregationService {
 
     /// Get status of an aggregation task
     pub fn get_status(&self, service_id: u64, call_id: u64) -> GetStatusResponse {
+        self.refresh_for_read(service_id, call_id);
         match self.state.get_status(service_id, call_id) {
             Some(status) => GetStatusResponse {
                 exists: true,
@@ -508,6 +1228,7 @@ impl AggregationService {
                 signer_bitmap: status.signer_bitmap,
                 signed_stake_bps: Some(status.signed_stake_bps),
                 submitted: status.submitted,
+                submission_tx: status.submission_tx,
                 is_expired: Some(status.is_expired),
                 time_remaining_secs: status.time_remaining_secs,
             },
@@ -519,6 +1240,7 @@ impl AggregationService {
                 signer_bitmap: U256::ZERO,
                 signed_stake_bps: None,
                 submitted: false,
+                submission_tx: None,
                 is_expired: None,
                 time_remaining_secs: None,
             },
@@ -531,6 +1253,11 @@ impl AggregationService {
         service_id: u64,
         call_id: u64,
     ) -> Option<AggregatedResultResponse> {
+        self.refresh_for_read(service_id, call_id);
+        self.aggregate(service_id, call_id)
+    }
+
+    fn aggregate(&self, service_id: u64, call_id: u64) -> Option<AggregatedResultResponse> {
         let task = self.state.get_for_aggregation(service_id, call_id)?;
 
         // Aggregate signatures and public keys
@@ -574,22 +1301,70 @@ impl AggregationService {
 
     /// Mark a task as submitted to chain
     pub fn mark_submitted(&self, service_id: u64, call_id: u64) -> Result<(), ServiceError> {
-        let _guard = self.mutation_lock.lock();
-        let before = self.snapshot()?;
-        self.state
-            .mark_submitted(service_id, call_id)
-            .map_err(|e| ServiceError::Other(e.to_string()))?;
-        if let Err(error) = self.persist_task(service_id, call_id) {
-            self.restore_persisted_task(&before, &TaskId::new(service_id, call_id));
-            self.restore_snapshot(before);
-            return Err(error);
+        self.mark_submitted_with_tx(service_id, call_id, None)
+    }
+
+    /// Mark a task as submitted, checking the operator's envelope first
+    ///
+    /// This is what `POST /v1/tasks/mark-submitted` calls. Envelopes are
+    /// checked like those of submissions, and required under the same
+    /// [`SubmissionAuthConfig::require_envelope`].
+    pub fn mark_submitted_signed(&self, request: SignedMarkSubmitted) -> Result<(), ServiceError> {
+        let req = &request.request;
+        let required = self.config.submission_auth.require_envelope;
+        if required || !request.envelope_signature.is_empty() {
+            let operator_index = req.operator_index.ok_or(AuthError::MissingOperator)?;
+            self.authenticate_operator(
+                TaskId::new(req.service_id, req.call_id),
+                operator_index,
+                &request.envelope_signature,
+                || recover_mark_submitter(&request),
+            )?;
         }
+        self.mark_submitted_with_tx(req.service_id, req.call_id, req.tx_hash)
+    }
+
+    /// Mark a task as submitted to chain by the transaction `tx_hash`
+    ///
+    /// The hash is recorded in the task, returned in its status, and sent with
+    /// the [`TaskEvent::Submitted`] event.
+    pub fn mark_submitted_with_tx(
+        &self,
+        service_id: u64,
+        call_id: u64,
+        tx_hash: Option<B256>,
+    ) -> Result<(), ServiceError> {
+        let tx_hash = self.mutate_task(TaskId::new(service_id, call_id), || {
+            let before = self.snapshot()?;
+            self.state
+                .mark_submitted_with_tx(service_id, call_id, tx_hash)
+                .map_err(|e| ServiceError::Other(e.to_string()))?;
+            if let Err(error) = self.persist_task(service_id, call_id) {
+                self.restore_persisted_task(&before, &TaskId::new(service_id, call_id));
+                self.restore_snapshot(before);
+                return Err(error);
+            }
+            Ok(self
+                .state
+                .get_status(service_id, call_id)
+                .and_then(|status| status.submission_tx))
+        })?;
+        self.publish(TaskEvent::Submitted {
+            service_id,
+            call_id,
+            tx_hash,
+        });
         Ok(())
     }
 
     /// Remove a task
     pub fn remove_task(&self, service_id: u64, call_id: u64) -> bool {
         let _guard = self.mutation_lock.lock();
+        let task_id = TaskId::new(service_id, call_id);
+        if let Err(error) = self.refresh_task(task_id) {
+            warn!(%error, service_id, call_id, "failed to reload aggregation task before removal");
+            return false;
+        }
         let before = match self.snapshot() {
             Ok(before) => before,
             Err(error) => {
@@ -597,11 +1372,25 @@ impl AggregationService {
                 return false;
             }
         };
-        let exists = self.state.get_status(service_id, call_id).is_some();
-        if !exists {
+        let Some(version) = self.state.task_version(&task_id) else {
             return false;
+        };
+        if self.persistence.is_shared() {
+            return match self.persistence.compare_and_delete(&task_id, version) {
+                Ok(true) => self.state.remove_task(service_id, call_id),
+                Ok(false) => {
+                    warn!(
+                        service_id,
+                        call_id, "aggregation task changed by another instance, not removed"
+                    );
+                    false
+                }
+                Err(error) => {
+                    warn!(%error, service_id, call_id, "failed to remove persisted aggregation task");
+                    false
+                }
+            };
         }
-        let task_id = TaskId::new(service_id, call_id);
         if let Err(error) = self.persistence.delete_task(&task_id) {
             self.restore_persisted_task(&before, &task_id);
             warn!(%error, service_id, call_id, "failed to remove persisted aggregation task");
@@ -617,6 +1406,9 @@ impl AggregationService {
 
     /// Get task statistics
     pub fn get_stats(&self) -> ServiceStats {
+        if let Err(error) = self.sync_shared_events() {
+            warn!(%error, "failed to reload aggregation tasks");
+        }
         let counts = self.state.task_counts();
         ServiceStats {
             total_tasks: counts.total,
@@ -675,20 +1467,65 @@ pub fn create_signing_message(service_id: u64, call_id: u64, output: &[u8]) -> V
     message
 }
 
+/// Describe how a requested threshold differs from the one on chain, if it does
+fn threshold_mismatch(requested: &ThresholdConfig, chain: &ThresholdConfig) -> Option<String> {
+    match (requested, chain) {
+        (
+            ThresholdConfig::Count {
+                required_signers: requested,
+            },
+            ThresholdConfig::Count {
+                required_signers: expected,
+            },
+        ) => (requested != expected)
+            .then(|| format!("{requested} required signers differs from {expected} on chain")),
+        (
+            ThresholdConfig::StakeWeighted {
+                threshold_bps: requested_bps,
+                operator_stakes: requested_stakes,
+            },
+            ThresholdConfig::StakeWeighted {
+                threshold_bps: expected_bps,
+                operator_stakes: expected_stakes,
+            },
+        ) => {
+            if requested_bps != expected_bps {
+                Some(format!(
+                    "threshold of {requested_bps} bps differs from {expected_bps} bps on chain"
+                ))
+            } else if nonzero_stakes(requested_stakes) != nonzero_stakes(expected_stakes) {
+                Some("operator stakes differ from the weights on chain".to_string())
+            } else {
+                None
+            }
+        }
+        _ => Some("threshold type differs from the chain".to_string()),
+    }
+}
+
+/// Stakes by operator index, leaving out operators without stake
+fn nonzero_stakes(stakes: &[OperatorStake]) -> BTreeMap<u32, u64> {
+    stakes
+        .iter()
+        .filter(|stake| stake.stake > 0)
+        .map(|stake| (stake.operator_index, stake.stake))
+        .collect()
+}
+
 impl Default for AggregationService {
     fn default() -> Self {
         Self::new(ServiceConfig::default())
     }
 }
 
-/// Handle for the cleanup worker
+/// Handle for a background worker (cleanup or event sync)
 pub struct CleanupWorkerHandle {
     shutdown_tx: watch::Sender<bool>,
     handle: tokio::task::JoinHandle<()>,
 }
 
 impl CleanupWorkerHandle {
-    /// Stop the cleanup worker
+    /// Stop the worker
     pub async fn stop(self) {
         let _ = self.shutdown_tx.send(true);
         let _ = self.handle.await;
@@ -1032,4 +1869,517 @@ mod tests {
             Err(ServiceError::Other(message)) if message.contains("persistence error")
         ));
     }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn replicas_share_tasks_through_sqlite() {
+        use crate::persistence::SqlitePersistence;
+
+        let directory = tempdir().unwrap();
+        let path = directory.path().join("aggregation.db");
+        let replica = || {
+            AggregationService::with_persistence(
+                ServiceConfig::minimal(),
+                SqlitePersistence::open(&path).unwrap(),
+            )
+            .unwrap()
+        };
+        let first = replica();
+        let second = replica();
+        let signature = serialize_point(&ark_bn254::G1Affine::generator());
+        let public_key = serialize_point(&ark_bn254::G2Affine::generator());
+        let submit = |service: &AggregationService, operator_index| {
+            service.submit_signature(SubmitSignatureRequest {
+                service_id: 7,
+                call_id: 11,
+                operator_index,
+                output: vec![1, 2, 3],
+                signature: signature.clone(),
+                public_key: public_key.clone(),
+            })
+        };
+
+        first.init_task(7, 11, vec![1, 2, 3], 3, 2).unwrap();
+        assert!(second.get_status(7, 11).exists);
+
+        // Each replica builds on the signatures accepted by the other
+        assert_eq!(submit(&second, 0).unwrap().signatures_collected, 1);
+        let response = submit(&first, 1).unwrap();
+        assert_eq!(response.signatures_collected, 2);
+        assert!(response.threshold_met);
+        assert_eq!(second.get_status(7, 11).signatures_collected, 2);
+        assert!(second.get_aggregated_result(7, 11).is_some());
+
+        second.mark_submitted(7, 11).unwrap();
+        assert!(first.get_status(7, 11).submitted);
+        assert_eq!(first.get_stats().submitted_tasks, 1);
+
+        assert!(first.remove_task(7, 11));
+        assert!(!second.get_status(7, 11).exists);
+        assert!(matches!(
+            submit(&second, 2),
+            Err(ServiceError::TaskNotFound)
+        ));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn replicas_publish_each_others_changes() {
+        use crate::persistence::SqlitePersistence;
+        use futures::StreamExt;
+
+        let directory = tempdir().unwrap();
+        let path = directory.path().join("aggregation.db");
+        let replica = || {
+            AggregationService::with_persistence(
+                ServiceConfig::minimal(),
+                SqlitePersistence::open(&path).unwrap(),
+            )
+            .unwrap()
+        };
+        let first = replica();
+        first.init_task(7, 11, vec![1, 2, 3], 2, 1).unwrap();
+        let second = replica();
+        let mut events = std::pin::pin!(second.subscribe(TaskEventFilter::task(7, 11)));
+
+        // Nothing changed since the second replica loaded the task
+        assert_eq!(second.sync_shared_events().unwrap(), 0);
+
+        first
+            .submit_signature(SubmitSignatureRequest {
+                service_id: 7,
+                call_id: 11,
+                operator_index: 1,
+                output: vec![1, 2, 3],
+                signature: serialize_point(&ark_bn254::G1Affine::generator()),
+                public_key: serialize_point(&ark_bn254::G2Affine::generator()),
+            })
+            .unwrap();
+        assert_eq!(second.sync_shared_events().unwrap(), 3);
+        assert!(matches!(
+            events.next().await.unwrap(),
+            TaskEvent::SignatureReceived {
+                operator_index: 1,
+                signatures_collected: 1,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events.next().await.unwrap(),
+            TaskEvent::ThresholdMet { .. }
+        ));
+        assert!(matches!(
+            events.next().await.unwrap(),
+            TaskEvent::AggregateReady(_)
+        ));
+
+        first
+            .mark_submitted_with_tx(7, 11, Some(B256::repeat_byte(1)))
+            .unwrap();
+        assert_eq!(second.sync_shared_events().unwrap(), 1);
+        assert!(matches!(
+            events.next().await.unwrap(),
+            TaskEvent::Submitted {
+                tx_hash: Some(_),
+                ..
+            }
+        ));
+
+        // Changes are published once, and the replica's own changes not again
+        assert_eq!(second.sync_shared_events().unwrap(), 0);
+        second.remove_task(7, 11);
+        assert_eq!(second.sync_shared_events().unwrap(), 0);
+    }
+
+    struct StaticOperatorSource(crate::chain::ChainTaskConfig);
+
+    #[async_trait::async_trait]
+    impl OperatorSetSource for StaticOperatorSource {
+        async fn task_config(
+            &self,
+            _service_id: u64,
+            _call_id: u64,
+        ) -> Result<crate::chain::ChainTaskConfig, ChainError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn chain_operators(keys: Vec<Option<ArkBlsBn254Public>>) -> Vec<crate::chain::ChainOperator> {
+        keys.into_iter()
+            .enumerate()
+            .map(|(index, bls_public_key)| crate::chain::ChainOperator {
+                address: alloy_primitives::Address::with_last_byte(index as u8 + 1),
+                weight: 100,
+                bls_public_key,
+            })
+            .collect()
+    }
+
+    fn init_request(operator_count: u32, threshold: ThresholdConfig) -> InitTaskRequest {
+        InitTaskRequest {
+            service_id: 1,
+            call_id: 100,
+            operator_count,
+            threshold,
+            output: vec![1, 2, 3],
+            message: Vec::new(),
+            signature_scheme: Bn254SignatureScheme::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn verified_init_rejects_requests_disagreeing_with_chain() {
+        let source = StaticOperatorSource(crate::chain::ChainTaskConfig {
+            operators: chain_operators(vec![None, None, None]),
+            threshold: ThresholdConfig::StakeWeighted {
+                threshold_bps: 6700,
+                operator_stakes: (0..3)
+                    .map(|operator_index| OperatorStake {
+                        operator_index,
+                        stake: 100,
+                    })
+                    .collect(),
+            },
+        });
+        let service =
+            AggregationService::new(ServiceConfig::minimal()).with_operator_source(source);
+        let stakes = |stakes: &[u64]| {
+            stakes
+                .iter()
+                .enumerate()
+                .map(|(index, stake)| OperatorStake {
+                    operator_index: index as u32,
+                    stake: *stake,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let rejected = [
+            init_request(
+                4,
+                ThresholdConfig::StakeWeighted {
+                    threshold_bps: 6700,
+                    operator_stakes: stakes(&[100, 100, 100]),
+                },
+            ),
+            init_request(
+                3,
+                ThresholdConfig::Count {
+                    required_signers: 1,
+                },
+            ),
+            init_request(
+                3,
+                ThresholdConfig::StakeWeighted {
+                    threshold_bps: 100,
+                    operator_stakes: stakes(&[100, 100, 100]),
+                },
+            ),
+            init_request(
+                3,
+                ThresholdConfig::StakeWeighted {
+                    threshold_bps: 6700,
+                    operator_stakes: stakes(&[10_000, 1, 1]),
+                },
+            ),
+        ];
+        for request in rejected {
+            let error = service.init_task_verified(request).await.unwrap_err();
+            assert!(matches!(error, ServiceError::ChainMismatch(_)), "{error}");
+        }
+        assert!(!service.get_status(1, 100).exists);
+
+        service
+            .init_task_verified(init_request(
+                3,
+                ThresholdConfig::StakeWeighted {
+                    threshold_bps: 6700,
+                    operator_stakes: stakes(&[100, 100, 100]),
+                },
+            ))
+            .await
+            .unwrap();
+        let status = service.get_status(1, 100);
+        assert!(status.exists);
+        assert_eq!(status.threshold_required, 6700);
+    }
+
+    #[tokio::test]
+    async fn verified_init_pins_registered_public_keys() {
+        let registered = ArkBlsBn254::public_from_secret(
+            &ArkBlsBn254::generate_with_seed(Some(b"registered-operator")).unwrap(),
+        );
+        let other = ArkBlsBn254::public_from_secret(
+            &ArkBlsBn254::generate_with_seed(Some(b"other-operator")).unwrap(),
+        );
+        let source = StaticOperatorSource(crate::chain::ChainTaskConfig {
+            operators: chain_operators(vec![Some(registered.clone()), None]),
+            threshold: ThresholdConfig::Count {
+                required_signers: 2,
+            },
+        });
+        let service =
+            AggregationService::new(ServiceConfig::minimal()).with_operator_source(source);
+        service
+            .init_task_verified(init_request(
+                2,
+                ThresholdConfig::Count {
+                    required_signers: 2,
+                },
+            ))
+            .await
+            .unwrap();
+
+        let signature = ArkBlsBn254Signature(ark_bn254::G1Affine::generator());
+        let submit = |operator_index, public_key: &ArkBlsBn254Public| SubmitSignatureRequest {
+            service_id: 1,
+            call_id: 100,
+            operator_index,
+            output: vec![1, 2, 3],
+            signature: serialize_point(&signature.0),
+            public_key: serialize_point(&public_key.0),
+        };
+
+        assert!(service.submit_signature(submit(0, &other)).is_err());
+        assert!(service.submit_signature(submit(0, &registered)).is_ok());
+        // Operators without a registered key can't sign under an unpinned key
+        assert!(matches!(
+            service.submit_signature(submit(1, &other)),
+            Err(ServiceError::Other(error)) if error.contains("no registered public key")
+        ));
+    }
+
+    #[tokio::test]
+    async fn submission_transaction_is_recorded_and_persisted() {
+        use futures::StreamExt;
+
+        let directory = tempdir().unwrap();
+        let path = directory.path().join("aggregation.json");
+        let config = ServiceConfig::minimal();
+        let service = AggregationService::with_persistence(
+            config.clone(),
+            crate::persistence::FilePersistence::new(&path),
+        )
+        .unwrap();
+        let mut events = std::pin::pin!(service.subscribe(TaskEventFilter::task(3, 5)));
+        service.init_task(3, 5, vec![], 1, 1).unwrap();
+
+        let tx_hash = B256::repeat_byte(0xab);
+        service.mark_submitted_with_tx(3, 5, Some(tx_hash)).unwrap();
+        // Marking again without a hash keeps the recorded one
+        service.mark_submitted(3, 5).unwrap();
+        assert_eq!(service.get_status(3, 5).submission_tx, Some(tx_hash));
+        assert!(matches!(
+            events.next().await.unwrap(),
+            TaskEvent::Submitted { tx_hash: Some(hash), .. } if hash == tx_hash
+        ));
+        drop(service);
+
+        let recovered = AggregationService::with_persistence(
+            config,
+            crate::persistence::FilePersistence::new(&path),
+        )
+        .unwrap();
+        let status = recovered.get_status(3, 5);
+        assert!(status.submitted);
+        assert_eq!(status.submission_tx, Some(tx_hash));
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_the_events_of_their_tasks() {
+        use futures::StreamExt;
+
+        let service = AggregationService::new(ServiceConfig::minimal());
+        let mut events = std::pin::pin!(service.subscribe(TaskEventFilter::task(1, 100)));
+        service.init_task(1, 100, vec![1], 2, 1).unwrap();
+        service.init_task(1, 101, vec![1], 2, 1).unwrap();
+
+        let signature = ArkBlsBn254Signature(ark_bn254::G1Affine::generator());
+        let public_key = ArkBlsBn254Public(ark_bn254::G2Affine::generator());
+        for call_id in [101, 100] {
+            service
+                .submit_signature(SubmitSignatureRequest {
+                    service_id: 1,
+                    call_id,
+                    operator_index: 0,
+                    output: vec![1],
+                    signature: serialize_point(&signature.0),
+                    public_key: serialize_point(&public_key.0),
+                })
+                .unwrap();
+        }
+        service.mark_submitted(1, 101).unwrap();
+        service.mark_submitted(1, 100).unwrap();
+
+        assert!(matches!(
+            events.next().await.unwrap(),
+            TaskEvent::SignatureReceived {
+                call_id: 100,
+                operator_index: 0,
+                signatures_collected: 1,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events.next().await.unwrap(),
+            TaskEvent::ThresholdMet { call_id: 100, .. }
+        ));
+        let TaskEvent::AggregateReady(result) = events.next().await.unwrap() else {
+            panic!("expected the aggregate");
+        };
+        assert_eq!(result.call_id, 100);
+        assert_eq!(result.non_signer_indices, [1]);
+        assert!(matches!(
+            events.next().await.unwrap(),
+            TaskEvent::Submitted { call_id: 100, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn submissions_are_authenticated_and_rate_limited() {
+        use crate::auth::{sign_mark_submitted, sign_submission, RateLimit};
+        use alloy_signer_local::PrivateKeySigner;
+
+        let signers = [PrivateKeySigner::random(), PrivateKeySigner::random()];
+        let secret = ArkBlsBn254::generate_with_seed(Some(b"operator-0")).unwrap();
+        let operators = signers
+            .iter()
+            .enumerate()
+            .map(|(index, signer)| crate::chain::ChainOperator {
+                address: signer.address(),
+                weight: 100,
+                bls_public_key: (index == 0).then(|| ArkBlsBn254::public_from_secret(&secret)),
+            })
+            .collect();
+        let threshold = ThresholdConfig::Count {
+            required_signers: 2,
+        };
+        let source = StaticOperatorSource(crate::chain::ChainTaskConfig {
+            operators,
+            threshold: threshold.clone(),
+        });
+        let config = ServiceConfig {
+            verify_on_submit: true,
+            submission_auth: SubmissionAuthConfig {
+                require_envelope: true,
+                per_operator: Some(RateLimit::new(4, Duration::from_secs(3600))),
+                per_ip: Some(RateLimit::new(1, Duration::from_secs(3600))),
+                ..Default::default()
+            },
+            ..ServiceConfig::minimal()
+        };
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let service = AggregationService::with_persistence(
+            config.clone(),
+            crate::persistence::FilePersistence::new(&path),
+        )
+        .unwrap()
+        .with_operator_source(source);
+        service
+            .init_task_verified(init_request(2, threshold))
+            .await
+            .unwrap();
+
+        let message = create_signing_message(1, 100, &[1, 2, 3]);
+        let signature = ArkBlsBn254::sign_with_secret(&mut secret.clone(), &message).unwrap();
+        let request = |signature: &ArkBlsBn254Signature| SubmitSignatureRequest {
+            service_id: 1,
+            call_id: 100,
+            operator_index: 0,
+            output: vec![1, 2, 3],
+            signature: serialize_point(&signature.0),
+            public_key: serialize_point(&ArkBlsBn254::public_from_secret(&secret).0),
+        };
+        let forged = ArkBlsBn254Signature(ark_bn254::G1Affine::generator());
+
+        assert!(matches!(
+            service.submit_signature(request(&signature)),
+            Err(ServiceError::Unauthorized(AuthError::MissingEnvelope))
+        ));
+        let impersonated = sign_submission(request(&signature), &signers[1]).unwrap();
+        assert!(matches!(
+            service.submit_signed(impersonated, None),
+            Err(ServiceError::Unauthorized(AuthError::WrongSigner { .. }))
+        ));
+        assert!(service.evidence(None).is_empty());
+
+        // A signature that fails verification isn't evidence, the task may
+        // have been initialized with the wrong message
+        let unverified = sign_submission(request(&forged), &signers[0]).unwrap();
+        assert!(matches!(
+            service.submit_signed(unverified, None),
+            Err(ServiceError::VerificationFailed)
+        ));
+        assert!(service.evidence(None).is_empty());
+
+        // A malformed signature under a valid envelope is evidence against the operator
+        let malformed = SubmitSignatureRequest {
+            signature: vec![0xff; 3],
+            ..request(&signature)
+        };
+        let invalid = sign_submission(malformed, &signers[0]).unwrap();
+        assert!(matches!(
+            service.submit_signed(invalid, None),
+            Err(ServiceError::InvalidSignature)
+        ));
+        let evidence = service.evidence(Some(1));
+        assert_eq!(evidence.len(), 1);
+        assert_eq!(evidence[0].operator, signers[0].address());
+        assert_eq!(
+            crate::auth::recover_submitter(&evidence[0].submission).unwrap(),
+            signers[0].address()
+        );
+        // Evidence survives a restart
+        let restarted = AggregationService::with_persistence(
+            config,
+            crate::persistence::FilePersistence::new(&path),
+        )
+        .unwrap();
+        assert_eq!(restarted.evidence(Some(1)).len(), 1);
+
+        let valid = sign_submission(request(&signature), &signers[0]).unwrap();
+        assert!(service.submit_signed(valid.clone(), None).unwrap().accepted);
+        assert!(service.submit_signed(valid.clone(), None).is_err());
+        assert!(matches!(
+            service.submit_signed(valid.clone(), None),
+            Err(ServiceError::RateLimited(_))
+        ));
+
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        let other = SignedSubmission::from(SubmitSignatureRequest {
+            call_id: 101,
+            ..request(&signature)
+        });
+        assert!(matches!(
+            service.submit_signed(other.clone(), Some(ip)),
+            Err(ServiceError::TaskNotFound)
+        ));
+        assert!(matches!(
+            service.submit_signed(other, Some(ip)),
+            Err(ServiceError::RateLimited(_))
+        ));
+
+        // Marking the task as submitted takes the submitting operator's envelope too
+        let mark = MarkSubmittedRequest {
+            service_id: 1,
+            call_id: 100,
+            tx_hash: Some(B256::repeat_byte(1)),
+            operator_index: Some(0),
+        };
+        assert!(matches!(
+            service.mark_submitted_signed(mark.clone().into()),
+            Err(ServiceError::Unauthorized(AuthError::MissingEnvelope))
+        ));
+        let forged_mark = sign_mark_submitted(mark.clone(), &signers[1]).unwrap();
+        assert!(matches!(
+            service.mark_submitted_signed(forged_mark),
+            Err(ServiceError::Unauthorized(AuthError::WrongSigner { .. }))
+        ));
+        assert!(!service.get_status(1, 100).submitted);
+        service
+            .mark_submitted_signed(sign_mark_submitted(mark, &signers[0]).unwrap())
+            .unwrap();
+        assert!(service.get_status(1, 100).submitted);
+    }
 }
@@ -1,8 +1,8 @@
 //! In-memory aggregation state management
 
 use crate::persistence::{PersistedTaskState, PersistenceError};
-use crate::types::{Bn254SignatureScheme, TaskId};
-use alloy_primitives::U256;
+use crate::types::{Bn254SignatureScheme, TaskId, ThresholdConfig};
+use alloy_primitives::{Address, B256, U256};
 use blueprint_crypto_bn254::{ArkBlsBn254Public, ArkBlsBn254Signature};
 use parking_lot::RwLock;
 use std::collections::HashMap;
@@ -65,6 +65,12 @@ pub struct TaskState {
     pub signatures: HashMap<u32, ArkBlsBn254Signature>,
     /// Collected public keys indexed by operator index
     pub public_keys: HashMap<u32, ArkBlsBn254Public>,
+    /// Registered public keys that operators must sign with, by operator index
+    pub operator_public_keys: HashMap<u32, ArkBlsBn254Public>,
+    /// Whether operators without a registered public key are refused
+    pub require_registered_keys: bool,
+    /// Registered operator addresses, authenticating submission envelopes
+    pub operator_addresses: HashMap<u32, Address>,
     /// Operator stakes for stake-weighted thresholds
     pub operator_stakes: HashMap<u32, u64>,
     /// Total stake of all operators
@@ -73,10 +79,14 @@ pub struct TaskState {
     pub submitted: bool,
     /// When this task was submitted to chain
     pub submitted_at: Option<Instant>,
+    /// Hash of the transaction submitting the aggregate, if reported
+    pub submission_tx: Option<B256>,
     /// When this task was created
     pub created_at: Instant,
     /// When this task expires (None = never)
     pub expires_at: Option<Instant>,
+    /// Revision of the task in the persistence backend, bumped on every save
+    pub version: u64,
 }
 
 impl TaskState {
@@ -160,12 +170,17 @@ impl TaskState {
             signer_bitmap: U256::ZERO,
             signatures: HashMap::new(),
             public_keys: HashMap::new(),
+            operator_public_keys: HashMap::new(),
+            require_registered_keys: false,
+            operator_addresses: HashMap::new(),
             operator_stakes: stakes,
             total_stake,
             submitted: false,
             submitted_at: None,
+            submission_tx: None,
             created_at: now,
             expires_at,
+            version: 0,
         }
     }
 
@@ -205,6 +220,16 @@ impl TaskState {
             return Err("Task has expired");
         }
 
+        match self.operator_public_keys.get(&operator_index) {
+            Some(registered) if *registered != public_key => {
+                return Err("Public key does not match the operator's registered key");
+            }
+            None if self.require_registered_keys => {
+                return Err("Operator has no registered public key");
+            }
+            _ => {}
+        }
+
         // Set bit in bitmap
         self.signer_bitmap |= U256::from(1u64) << operator_index as usize;
 
@@ -299,6 +324,12 @@ pub struct TaskConfig {
     pub threshold_type: ThresholdType,
     /// Operator stakes (optional, defaults to equal weight)
     pub operator_stakes: Option<HashMap<u32, u64>>,
+    /// Registered operator public keys (optional, any key is accepted for missing operators)
+    pub operator_public_keys: HashMap<u32, ArkBlsBn254Public>,
+    /// Refuse operators missing from `operator_public_keys` instead of accepting any key
+    pub require_registered_keys: bool,
+    /// Registered operator addresses (optional, submissions of missing operators can't be authenticated)
+    pub operator_addresses: HashMap<u32, Address>,
     /// Time-to-live for the task
     pub ttl: Option<Duration>,
 }
@@ -308,11 +339,39 @@ impl Default for TaskConfig {
         Self {
             threshold_type: ThresholdType::Count(1),
             operator_stakes: None,
+            operator_public_keys: HashMap::new(),
+            require_registered_keys: false,
+            operator_addresses: HashMap::new(),
             ttl: None,
         }
     }
 }
 
+impl From<ThresholdConfig> for TaskConfig {
+    fn from(threshold: ThresholdConfig) -> Self {
+        let (threshold_type, operator_stakes) = match threshold {
+            ThresholdConfig::Count { required_signers } => {
+                (ThresholdType::Count(required_signers), None)
+            }
+            ThresholdConfig::StakeWeighted {
+                threshold_bps,
+                operator_stakes,
+            } => {
+                let stakes = operator_stakes
+                    .into_iter()
+                    .map(|stake| (stake.operator_index, stake.stake))
+                    .collect::<HashMap<_, _>>();
+                (ThresholdType::StakeWeighted(threshold_bps), Some(stakes))
+            }
+        };
+        Self {
+            threshold_type,
+            operator_stakes,
+            ..Default::default()
+        }
+    }
+}
+
 /// Global aggregation state manager
 #[derive(Debug, Clone)]
 pub struct AggregationState {
@@ -361,6 +420,39 @@ impl AggregationState {
         Ok(())
     }
 
+    /// Replace a single task with its persisted state, or drop it if it is gone
+    pub(crate) fn replace_task(
+        &self,
+        task_id: TaskId,
+        persisted: Option<PersistedTaskState>,
+    ) -> Result<(), PersistenceError> {
+        let task = persisted.map(TaskState::try_from).transpose()?;
+        let mut tasks = self.tasks.write();
+        match task {
+            Some(task) => {
+                tasks.insert(task_id, task);
+            }
+            None => {
+                tasks.remove(&task_id);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn task_ids(&self) -> Vec<TaskId> {
+        self.tasks.read().keys().copied().collect()
+    }
+
+    pub(crate) fn task_version(&self, task_id: &TaskId) -> Option<u64> {
+        self.tasks.read().get(task_id).map(|task| task.version)
+    }
+
+    pub(crate) fn set_task_version(&self, task_id: &TaskId, version: u64) {
+        if let Some(task) = self.tasks.write().get_mut(task_id) {
+            task.version = version;
+        }
+    }
+
     /// Initialize a new aggregation task (simple API)
     pub fn init_task(
         &self,
@@ -417,7 +509,7 @@ impl AggregationState {
         let task_id = TaskId::new(service_id, call_id);
         let mut tasks = self.tasks.write();
 
-        let state = TaskState::with_message_config(
+        let mut state = TaskState::with_message_config(
             service_id,
             call_id,
             output,
@@ -428,6 +520,9 @@ impl AggregationState {
             config.operator_stakes,
             config.ttl,
         );
+        state.operator_public_keys = config.operator_public_keys;
+        state.require_registered_keys = config.require_registered_keys;
+        state.operator_addresses = config.operator_addresses;
 
         if let Some(existing) = tasks.get(&task_id) {
             let same_context = existing.output == state.output
@@ -435,7 +530,10 @@ impl AggregationState {
                 && existing.signature_scheme == state.signature_scheme
                 && existing.operator_count == state.operator_count
                 && existing.threshold_type == state.threshold_type
-                && existing.operator_stakes == state.operator_stakes;
+                && existing.operator_stakes == state.operator_stakes
+                && existing.operator_public_keys == state.operator_public_keys
+                && existing.require_registered_keys == state.require_registered_keys
+                && existing.operator_addresses == state.operator_addresses;
             return if same_context {
                 Ok(())
             } else {
@@ -467,6 +565,23 @@ impl AggregationState {
             .map(|task| (task.message.clone(), task.signature_scheme))
     }
 
+    /// Get the registered address of an operator of a task
+    ///
+    /// Returns `None` if the task doesn't exist, and `Some(None)` if the
+    /// operator has no registered address.
+    pub fn get_operator_address(
+        &self,
+        service_id: u64,
+        call_id: u64,
+        operator_index: u32,
+    ) -> Option<Option<Address>> {
+        let task_id = TaskId::new(service_id, call_id);
+        let tasks = self.tasks.read();
+        tasks
+            .get(&task_id)
+            .map(|task| task.operator_addresses.get(&operator_index).copied())
+    }
+
     /// Submit a signature for a task
     pub fn submit_signature(
         &self,
@@ -507,6 +622,7 @@ impl AggregationState {
             signer_bitmap: task.signer_bitmap,
             signed_stake_bps: task.signed_stake_bps(),
             submitted: task.submitted,
+            submission_tx: task.submission_tx,
             is_expired: task.is_expired(),
             time_remaining_secs: task.time_remaining().map(|d| d.as_secs()),
         })
@@ -538,12 +654,27 @@ impl AggregationState {
 
     /// Mark task as submitted
     pub fn mark_submitted(&self, service_id: u64, call_id: u64) -> Result<(), &'static str> {
+        self.mark_submitted_with_tx(service_id, call_id, None)
+    }
+
+    /// Mark task as submitted by the transaction `tx_hash`
+    ///
+    /// A recorded hash is kept when the task is marked again without one.
+    pub fn mark_submitted_with_tx(
+        &self,
+        service_id: u64,
+        call_id: u64,
+        tx_hash: Option<B256>,
+    ) -> Result<(), &'static str> {
         let task_id = TaskId::new(service_id, call_id);
         let mut tasks = self.tasks.write();
 
         let task = tasks.get_mut(&task_id).ok_or("Task not found")?;
         task.submitted = true;
         task.submitted_at = Some(Instant::now());
+        if tx_hash.is_some() {
+            task.submission_tx = tx_hash;
+        }
         Ok(())
     }
 
@@ -639,6 +770,7 @@ pub struct TaskStatus {
     pub signer_bitmap: U256,
     pub signed_stake_bps: u32,
     pub submitted: bool,
+    pub submission_tx: Option<B256>,
     pub is_expired: bool,
     pub time_remaining_secs: Option<u64>,
 }
@@ -1,6 +1,6 @@
 //! Request and response types for the aggregation service API
 
-use alloy_primitives::U256;
+use alloy_primitives::{B256, U256};
 use serde::{Deserialize, Serialize};
 
 /// Unique identifier for a job aggregation task
@@ -79,6 +79,29 @@ pub struct SubmitSignatureRequest {
     pub public_key: Vec<u8>,
 }
 
+/// A signature submission, optionally authenticated by the operator
+///
+/// This is the body of `POST /v1/tasks/submit`: the fields of a
+/// [`SubmitSignatureRequest`], and an envelope signature made with
+/// [`crate::auth::sign_submission`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedSubmission {
+    #[serde(flatten)]
+    pub request: SubmitSignatureRequest,
+    /// ECDSA signature over the request by the operator's address (65 bytes, empty if unsigned)
+    #[serde(default, with = "hex_bytes", skip_serializing_if = "Vec::is_empty")]
+    pub envelope_signature: Vec<u8>,
+}
+
+impl From<SubmitSignatureRequest> for SignedSubmission {
+    fn from(request: SubmitSignatureRequest) -> Self {
+        Self {
+            request,
+            envelope_signature: Vec::new(),
+        }
+    }
+}
+
 /// Response after submitting a signature
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubmitSignatureResponse {
@@ -120,6 +143,9 @@ pub struct GetStatusResponse {
     pub signed_stake_bps: Option<u32>,
     /// Whether already submitted to chain
     pub submitted: bool,
+    /// Hash of the transaction that submitted the aggregate, if reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submission_tx: Option<B256>,
     /// Whether the task has expired
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_expired: Option<bool>,
@@ -128,6 +154,41 @@ pub struct GetStatusResponse {
     pub time_remaining_secs: Option<u64>,
 }
 
+/// Request to mark a task as submitted to chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkSubmittedRequest {
+    pub service_id: u64,
+    pub call_id: u64,
+    /// Hash of the submitting transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<B256>,
+    /// Index of the operator that submitted, whose address signs the envelope
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_index: Option<u32>,
+}
+
+/// A [`MarkSubmittedRequest`] signed by the submitting operator
+///
+/// This is the body of `POST /v1/tasks/mark-submitted`, with an envelope
+/// signature made with [`crate::auth::sign_mark_submitted`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedMarkSubmitted {
+    #[serde(flatten)]
+    pub request: MarkSubmittedRequest,
+    /// ECDSA signature over the request by the operator's address (65 bytes, empty if unsigned)
+    #[serde(default, with = "hex_bytes", skip_serializing_if = "Vec::is_empty")]
+    pub envelope_signature: Vec<u8>,
+}
+
+impl From<MarkSubmittedRequest> for SignedMarkSubmitted {
+    fn from(request: MarkSubmittedRequest) -> Self {
+        Self {
+            request,
+            envelope_signature: Vec::new(),
+        }
+    }
+}
+
 /// Request to initialize an aggregation task
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InitTaskRequest {
@@ -174,6 +235,89 @@ pub struct AggregatedResultResponse {
     pub aggregated_pubkey: Vec<u8>,
 }
 
+/// Query selecting the tasks to stream events for
+///
+/// Unset fields match every task, so an empty query streams every event.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TaskEventFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call_id: Option<u64>,
+}
+
+impl TaskEventFilter {
+    /// Only match events of the task `call_id` in `service_id`
+    pub fn task(service_id: u64, call_id: u64) -> Self {
+        Self {
+            service_id: Some(service_id),
+            call_id: Some(call_id),
+        }
+    }
+
+    /// Whether `event` belongs to a selected task
+    pub fn matches(&self, event: &TaskEvent) -> bool {
+        let task = event.task_id();
+        self.service_id.is_none_or(|id| id == task.service_id)
+            && self.call_id.is_none_or(|id| id == task.call_id)
+    }
+}
+
+/// A change in the state of a task, streamed to subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskEvent {
+    /// A signature was accepted
+    SignatureReceived {
+        service_id: u64,
+        call_id: u64,
+        operator_index: u32,
+        signatures_collected: usize,
+        threshold_required: usize,
+    },
+    /// The task reached its threshold
+    ThresholdMet {
+        service_id: u64,
+        call_id: u64,
+        signatures_collected: usize,
+        threshold_required: usize,
+    },
+    /// A new aggregate is available, sent on every signature once the threshold is met
+    AggregateReady(AggregatedResultResponse),
+    /// The task was marked as submitted to the chain
+    Submitted {
+        service_id: u64,
+        call_id: u64,
+        /// Hash of the submitting transaction, if reported
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tx_hash: Option<B256>,
+    },
+}
+
+impl TaskEvent {
+    /// The task the event is about
+    pub fn task_id(&self) -> TaskId {
+        match self {
+            Self::SignatureReceived {
+                service_id,
+                call_id,
+                ..
+            }
+            | Self::ThresholdMet {
+                service_id,
+                call_id,
+                ..
+            }
+            | Self::Submitted {
+                service_id,
+                call_id,
+                ..
+            } => TaskId::new(*service_id, *call_id),
+            Self::AggregateReady(result) => TaskId::new(result.service_id, result.call_id),
+        }
+    }
+}
+
 /// Hex encoding/decoding for byte arrays in JSON
 mod hex_bytes {
     use serde::{Deserialize, Deserializer, Serializer};
@@ -212,4 +356,43 @@ mod tests {
             Bn254SignatureScheme::ArkworksSha256
         );
     }
+
+    #[test]
+    fn unsigned_submission_body_is_accepted() {
+        let submission: SignedSubmission = serde_json::from_str(
+            r#"{"service_id":1,"call_id":2,"operator_index":0,"output":"0x01","signature":"0x02","public_key":"0x03"}"#,
+        )
+        .unwrap();
+        assert_eq!(submission.request.call_id, 2);
+        assert!(submission.envelope_signature.is_empty());
+
+        let json = serde_json::to_string(&SignedSubmission {
+            envelope_signature: vec![4],
+            ..submission
+        })
+        .unwrap();
+        let signed: SignedSubmission = serde_json::from_str(&json).unwrap();
+        assert_eq!(signed.request.output, [1]);
+        assert_eq!(signed.envelope_signature, [4]);
+    }
+
+    #[test]
+    fn event_filter_matches_selected_tasks() {
+        let event = TaskEvent::Submitted {
+            service_id: 1,
+            call_id: 2,
+            tx_hash: None,
+        };
+        assert!(TaskEventFilter::default().matches(&event));
+        assert!(TaskEventFilter::task(1, 2).matches(&event));
+        assert!(!TaskEventFilter::task(1, 3).matches(&event));
+        let service = TaskEventFilter {
+            service_id: Some(1),
+            call_id: None,
+        };
+        assert!(service.matches(&event));
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(json, r#"{"type":"submitted","service_id":1,"call_id":2}"#);
+    }
 }
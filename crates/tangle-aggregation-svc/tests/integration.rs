@@ -539,6 +539,7 @@ fn test_stake_weighted_threshold() {
                 threshold_type: ThresholdType::StakeWeighted(5000), // 50%
                 operator_stakes: Some(stakes),
                 ttl: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -730,6 +731,7 @@ fn test_task_expiry() {
                 threshold_type: ThresholdType::Count(1),
                 operator_stakes: None,
                 ttl: Some(Duration::from_millis(50)),
+                ..Default::default()
             },
         )
         .unwrap();
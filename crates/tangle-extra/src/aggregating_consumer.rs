@@ -21,6 +21,7 @@
 
 use crate::aggregation::AggregationError;
 use crate::extract;
+use crate::tx_manager::{LazyTxManager, TxError, TxManager};
 use alloy_primitives::{Address, Bytes};
 #[cfg(feature = "aggregation")]
 use alloy_provider::Provider;
@@ -36,7 +37,9 @@ use blueprint_std::sync::{Arc, Mutex};
 use blueprint_std::time::Duration;
 use blueprint_std::vec::Vec;
 #[cfg(feature = "aggregation")]
-use blueprint_tangle_aggregation_svc::{OperatorStake, ThresholdConfig, ThresholdWaitResult};
+use blueprint_tangle_aggregation_svc::{
+    OperatorStake, SubmitterElection, ThresholdConfig, ThresholdWaitResult,
+};
 use core::pin::Pin;
 use core::task::{Context, Poll};
 use futures_util::Sink;
@@ -130,9 +133,15 @@ pub struct AggregationServiceConfig {
     pub threshold_timeout: Duration,
     /// Poll interval when waiting for threshold (default: 1s)
     pub poll_interval: Duration,
+    /// Whether to wait for threshold on the services' task event streams, re-checking
+    /// every `poll_interval` (default: false)
+    pub stream_events: bool,
     /// Whether to try to submit the aggregated result to chain (default: true)
     /// When true, all operators race to submit; first valid submission wins
     pub submit_to_chain: bool,
+    /// Election of a single operator to submit each aggregated result (default: none)
+    /// When set, operators submit in turn, rotating by call ID, instead of racing
+    pub submitter_election: Option<SubmitterElection>,
 }
 
 #[cfg(feature = "aggregation")]
@@ -157,7 +166,9 @@ impl AggregationServiceConfig {
             wait_for_threshold: false,
             threshold_timeout: Duration::from_secs(60),
             poll_interval: Duration::from_secs(1),
+            stream_events: false,
             submit_to_chain: true, // Everyone tries to submit by default
+            submitter_election: None,
         }
     }
 
@@ -188,7 +199,9 @@ impl AggregationServiceConfig {
             wait_for_threshold: false,
             threshold_timeout: Duration::from_secs(60),
             poll_interval: Duration::from_secs(1),
+            stream_events: false,
             submit_to_chain: true,
+            submitter_election: None,
         }
     }
 
@@ -211,6 +224,30 @@ impl AggregationServiceConfig {
         self
     }
 
+    /// Set whether to wait for threshold on the services' task event streams
+    ///
+    /// The consumer subscribes to the events of the task and is notified as soon
+    /// as the aggregate is ready or the task is submitted. Without events, the task
+    /// is still re-checked every `poll_interval`. It falls back to polling, for
+    /// another `threshold_timeout`, if no service streams events.
+    pub fn with_event_stream(mut self, stream: bool) -> Self {
+        self.stream_events = stream;
+        self
+    }
+
+    /// Sign submissions with the operator's ECDSA key
+    ///
+    /// Services requiring authenticated submissions reject unsigned ones. Only
+    /// applies to the services added so far.
+    pub fn with_envelope_signer(mut self, signer: alloy_signer_local::PrivateKeySigner) -> Self {
+        self.clients = self
+            .clients
+            .into_iter()
+            .map(|client| client.with_envelope_signer(signer.clone()))
+            .collect();
+        self
+    }
+
     /// Set whether to submit the aggregated result to chain
     ///
     /// When true (default), this operator will attempt to submit the aggregated
@@ -221,6 +258,18 @@ impl AggregationServiceConfig {
         self
     }
 
+    /// Elect a single operator to submit each aggregated result
+    ///
+    /// Instead of racing, the operator elected for the call ID submits, and the
+    /// others each wait `fallback_timeout` per operator ahead of them before
+    /// submitting, unless the task was marked as submitted in the meantime.
+    /// Every operator waits for the threshold, even if `wait_for_threshold` is
+    /// unset, so that the elected one sees it.
+    pub fn with_submitter_election(mut self, fallback_timeout: Duration) -> Self {
+        self.submitter_election = Some(SubmitterElection::new(fallback_timeout));
+        self
+    }
+
     /// Get the first client (for backwards compatibility)
     pub fn client(&self) -> &blueprint_tangle_aggregation_svc::AggregationServiceClient {
         self.clients
@@ -348,6 +397,9 @@ impl AggregationServiceConfig {
 /// ```
 pub struct AggregatingConsumer {
     client: Arc<TangleClient>,
+    /// Sends results on-chain, shared with everything sending from the client's account unless
+    /// provided
+    tx: LazyTxManager,
     buffer: Mutex<VecDeque<PendingJobResult>>,
     state: Mutex<State>,
     /// Shared cache for service configs (aggregation configs, operator weights)
@@ -365,6 +417,7 @@ impl AggregatingConsumer {
     pub fn new(client: TangleClient) -> Self {
         Self {
             client: Arc::new(client),
+            tx: LazyTxManager::default(),
             buffer: Mutex::new(VecDeque::new()),
             state: Mutex::new(State::WaitingForResult),
             cache: crate::cache::shared_cache(),
@@ -396,6 +449,7 @@ impl AggregatingConsumer {
     pub fn with_cache(client: TangleClient, cache: crate::cache::SharedServiceConfigCache) -> Self {
         Self {
             client: Arc::new(client),
+            tx: LazyTxManager::default(),
             buffer: Mutex::new(VecDeque::new()),
             state: Mutex::new(State::WaitingForResult),
             cache,
@@ -467,6 +521,16 @@ impl AggregatingConsumer {
         self
     }
 
+    /// Send results through `tx`
+    ///
+    /// Share a single [`TxManager`] between everything sending transactions from the operator's
+    /// account, so their nonces don't collide.
+    #[must_use]
+    pub fn with_tx_manager(mut self, tx: TxManager) -> Self {
+        self.tx = LazyTxManager::new(tx);
+        self
+    }
+
     /// Get the underlying client
     #[must_use]
     pub fn client(&self) -> &TangleClient {
@@ -625,6 +689,7 @@ impl Sink<JobResult> for AggregatingConsumer {
                     };
 
                     let client = Arc::clone(&consumer.client);
+                    let tx = consumer.tx.clone();
                     let cache = Arc::clone(&consumer.cache);
 
                     #[cfg(feature = "aggregation")]
@@ -636,6 +701,7 @@ impl Sink<JobResult> for AggregatingConsumer {
                             submit_job_result(
                                 cache,
                                 client,
+                                tx,
                                 pending.service_id,
                                 pending.call_id,
                                 pending.job_index,
@@ -649,6 +715,7 @@ impl Sink<JobResult> for AggregatingConsumer {
                             submit_job_result(
                                 cache,
                                 client,
+                                tx,
                                 pending.service_id,
                                 pending.call_id,
                                 pending.job_index,
@@ -684,6 +751,7 @@ impl Sink<JobResult> for AggregatingConsumer {
 async fn submit_job_result(
     cache: crate::cache::SharedServiceConfigCache,
     client: Arc<TangleClient>,
+    tx: LazyTxManager,
     service_id: u64,
     call_id: u64,
     job_index: u8,
@@ -708,12 +776,12 @@ async fn submit_job_result(
         let agg = agg_config.ok_or(AggregatingConsumerError::AggregationNotConfigured)?;
 
         submit_aggregated_result(
-            cache, client, service_id, call_id, job_index, output, config, agg,
+            cache, client, tx, service_id, call_id, job_index, output, config, agg,
         )
         .await
     } else {
         // No aggregation needed, submit directly
-        submit_direct_result(client, service_id, call_id, output).await
+        submit_direct_result(client, tx, service_id, call_id, output).await
     }
 }
 
@@ -839,6 +907,7 @@ async fn prepare_aggregation_task(
 async fn submit_job_result(
     cache: crate::cache::SharedServiceConfigCache,
     client: Arc<TangleClient>,
+    tx: LazyTxManager,
     service_id: u64,
     call_id: u64,
     job_index: u8,
@@ -858,7 +927,7 @@ async fn submit_job_result(
         );
         Ok(())
     } else {
-        submit_direct_result(client, service_id, call_id, output).await
+        submit_direct_result(client, tx, service_id, call_id, output).await
     }
 }
 
@@ -868,11 +937,13 @@ async fn submit_job_result(
 /// 1. Signs the output with the operator's BLS key
 /// 2. Sends the signature to ALL configured aggregation services (for redundancy)
 /// 3. Waits for threshold if configured
-/// 4. Submits the aggregated result to chain if enabled (all operators can race to submit)
+/// 4. Submits the aggregated result to chain if enabled (all operators can race to submit,
+///    or only the elected submitter, with the others as fallback)
 #[cfg(feature = "aggregation")]
 async fn submit_aggregated_result(
     cache: crate::cache::SharedServiceConfigCache,
     client: Arc<TangleClient>,
+    tx: LazyTxManager,
     service_id: u64,
     call_id: u64,
     job_index: u8,
@@ -1010,9 +1081,9 @@ async fn submit_aggregated_result(
     // Try to submit to chain
     let response = last_response.unwrap();
 
-    if response.threshold_met {
+    if response.threshold_met && agg.submitter_election.is_none() {
         // Threshold already met, try to submit immediately
-        match try_submit_aggregated_to_chain(client.clone(), &agg, service_id, call_id).await {
+        match try_submit_aggregated_to_chain(client.clone(), &tx, &agg, service_id, call_id).await {
             Ok(()) => {}
             Err(error) if is_job_already_completed(&error) => {
                 blueprint_core::debug!(
@@ -1024,7 +1095,7 @@ async fn submit_aggregated_result(
             }
             Err(error) => return Err(error),
         }
-    } else if agg.wait_for_threshold {
+    } else if agg.wait_for_threshold || agg.submitter_election.is_some() {
         // Wait for threshold to be met, then submit
         blueprint_core::debug!(
             target: "tangle-aggregating-consumer",
@@ -1043,8 +1114,22 @@ async fn submit_aggregated_result(
             return Ok(());
         };
 
-        // Try to submit to chain (race with other operators)
-        match submit_aggregated_to_chain_with_result(client, &agg, service_id, call_id, result)
+        if let Some(election) = agg.submitter_election {
+            let delay = election.delay(call_id, task_init.operator_count, agg.operator_index);
+            let completed = || job_completed_on_chain(&client, service_id, call_id);
+            if wait_for_submitter_turn(&agg, service_id, call_id, delay, completed).await {
+                blueprint_core::debug!(
+                    target: "tangle-aggregating-consumer",
+                    service_id,
+                    call_id,
+                    "The elected operator already submitted the aggregated result"
+                );
+                return Ok(());
+            }
+        }
+
+        // Try to submit to chain (race with other operators, unless elected in turn)
+        match submit_aggregated_to_chain_with_result(client, &tx, &agg, service_id, call_id, result)
             .await
         {
             Ok(()) => {}
@@ -1072,10 +1157,32 @@ async fn wait_for_threshold_any_service(
 ) -> Result<ThresholdWaitResult, AggregatingConsumerError> {
     use blueprint_std::time::Instant;
 
-    let start = Instant::now();
     let timeout = agg.threshold_timeout;
     let poll_interval = agg.poll_interval;
 
+    if agg.stream_events && !agg.clients.is_empty() {
+        let waits = agg.clients.iter().map(|client| {
+            Box::pin(client.wait_for_threshold_streaming(
+                service_id,
+                call_id,
+                poll_interval,
+                timeout,
+            ))
+        });
+        match futures_util::future::select_ok(waits).await {
+            Ok((result, _)) => return Ok(result),
+            Err(e) => {
+                blueprint_core::debug!(
+                    target: "tangle-aggregating-consumer",
+                    "Error waiting on aggregation task events, polling instead: {}",
+                    e
+                );
+            }
+        }
+    }
+
+    // The polling fallback gets its own budget, the streaming wait may have used all of it
+    let start = Instant::now();
     while start.elapsed() < timeout {
         // Try each service until one returns a result
         for client in &agg.clients {
@@ -1136,10 +1243,89 @@ async fn wait_for_threshold_any_service(
     ))
 }
 
+/// Whether the job call is completed on chain, treating query errors as not completed
+#[cfg(feature = "aggregation")]
+async fn job_completed_on_chain(client: &TangleClient, service_id: u64, call_id: u64) -> bool {
+    match client.get_job_call(service_id, call_id).await {
+        Ok(job_call) => job_call.completed,
+        Err(e) => {
+            blueprint_core::debug!(
+                target: "tangle-aggregating-consumer",
+                service_id,
+                call_id,
+                "Failed to check job completion on chain: {}",
+                e
+            );
+            false
+        }
+    }
+}
+
+/// Wait for the operators ahead of this one in the submitter election
+///
+/// Returns true if the task was submitted before `delay` elapsed. Anyone can
+/// mark a task as submitted on a service that doesn't require envelopes, so a
+/// submission reported by a service only counts once `completed_on_chain`
+/// confirms it.
+#[cfg(feature = "aggregation")]
+async fn wait_for_submitter_turn<F, Fut>(
+    agg: &AggregationServiceConfig,
+    service_id: u64,
+    call_id: u64,
+    delay: Duration,
+    completed_on_chain: F,
+) -> bool
+where
+    F: Fn() -> Fut,
+    Fut: core::future::Future<Output = bool>,
+{
+    use blueprint_std::time::Instant;
+
+    if delay.is_zero() {
+        return false;
+    }
+
+    blueprint_core::debug!(
+        target: "tangle-aggregating-consumer",
+        service_id,
+        call_id,
+        ?delay,
+        "Waiting for the elected operator to submit the aggregated result"
+    );
+
+    let deadline = Instant::now() + delay;
+    loop {
+        for client in &agg.clients {
+            if matches!(
+                client.get_status(service_id, call_id).await,
+                Ok(status) if status.submitted
+            ) {
+                if completed_on_chain().await {
+                    return true;
+                }
+                blueprint_core::debug!(
+                    target: "tangle-aggregating-consumer",
+                    service_id,
+                    call_id,
+                    "Task marked as submitted, but not completed on chain yet"
+                );
+                break;
+            }
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return false;
+        }
+        tokio::time::sleep(agg.poll_interval.min(remaining)).await;
+    }
+}
+
 /// Try to submit the aggregated result to chain.
 #[cfg(feature = "aggregation")]
 async fn try_submit_aggregated_to_chain(
     client: Arc<TangleClient>,
+    tx: &LazyTxManager,
     agg: &AggregationServiceConfig,
     service_id: u64,
     call_id: u64,
@@ -1153,7 +1339,7 @@ async fn try_submit_aggregated_to_chain(
             Ok(ThresholdWaitResult::Submitted) => return Ok(()),
             Ok(ThresholdWaitResult::Aggregated(result)) => {
                 return submit_aggregated_to_chain_with_result(
-                    client, agg, service_id, call_id, result,
+                    client, tx, agg, service_id, call_id, result,
                 )
                 .await;
             }
@@ -1185,6 +1371,7 @@ fn is_job_already_completed(error: &AggregatingConsumerError) -> bool {
 #[cfg(feature = "aggregation")]
 async fn submit_aggregated_to_chain_with_result(
     client: Arc<TangleClient>,
+    tx: &LazyTxManager,
     agg: &AggregationServiceConfig,
     service_id: u64,
     call_id: u64,
@@ -1240,13 +1427,17 @@ async fn submit_aggregated_to_chain_with_result(
     );
 
     // Submit to the contract
-    aggregated
-        .submit(&Arc::new(client.as_ref().clone()))
-        .await?;
+    let tx = tx
+        .get(&client)
+        .await
+        .map_err(|e| AggregatingConsumerError::Client(e.to_string()))?;
+    let tx_hash = aggregated.submit_with(&client, tx).await?;
 
-    // Mark as submitted in all aggregation services
+    // Mark as submitted in all aggregation services, recording the transaction
     for client in &agg.clients {
-        let _ = client.mark_submitted(service_id, call_id).await;
+        let _ = client
+            .mark_submitted_by(service_id, call_id, agg.operator_index, Some(tx_hash))
+            .await;
     }
 
     blueprint_core::info!(
@@ -1262,6 +1453,7 @@ async fn submit_aggregated_to_chain_with_result(
 /// Submit a result directly without aggregation
 async fn submit_direct_result(
     client: Arc<TangleClient>,
+    tx: LazyTxManager,
     service_id: u64,
     call_id: u64,
     output: Bytes,
@@ -1283,27 +1475,28 @@ async fn submit_direct_result(
         return Ok(());
     }
 
-    let result = client
-        .submit_result(service_id, call_id, output)
+    let tx = tx
+        .get(&client)
         .await
-        .map_err(|e| {
-            AggregatingConsumerError::Transaction(format!("Failed to submit result: {e}"))
+        .map_err(|e| AggregatingConsumerError::Client(e.to_string()))?;
+    let receipt = tx
+        .submit_result(client.tangle_address(), service_id, call_id, output)
+        .await
+        .map_err(|e| match e {
+            TxError::Reverted { tx_hash, .. } => AggregatingConsumerError::Transaction(format!(
+                "Transaction reverted for service {} call {}: tx_hash={:?}",
+                service_id, call_id, tx_hash
+            )),
+            e => AggregatingConsumerError::Transaction(format!("Failed to submit result: {e}")),
         })?;
 
-    if result.success {
-        blueprint_core::info!(
-            target: "tangle-aggregating-consumer",
-            "Successfully submitted direct result for service {} call {}: tx_hash={:?}",
-            service_id,
-            call_id,
-            result.tx_hash
-        );
-    } else {
-        return Err(AggregatingConsumerError::Transaction(format!(
-            "Transaction reverted for service {} call {}: tx_hash={:?}",
-            service_id, call_id, result.tx_hash
-        )));
-    }
+    blueprint_core::info!(
+        target: "tangle-aggregating-consumer",
+        "Successfully submitted direct result for service {} call {}: tx_hash={:?}",
+        service_id,
+        call_id,
+        receipt.transaction_hash
+    );
 
     Ok(())
 }
@@ -1538,6 +1731,159 @@ mod tests {
         let _ = server.await;
     }
 
+    #[cfg(feature = "aggregation")]
+    #[tokio::test]
+    async fn wait_for_threshold_streams_task_events() {
+        use blueprint_crypto_bn254::ArkBlsBn254;
+        use blueprint_crypto_core::KeyType;
+        use blueprint_tangle_aggregation_svc::{
+            AggregationService, ServiceConfig, ThresholdWaitResult, api,
+        };
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+
+        let service = Arc::new(AggregationService::new(ServiceConfig::minimal()));
+        service.init_task(1, 1, vec![1], 2, 2).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server_service = Arc::clone(&service);
+        let server = tokio::spawn(async move {
+            axum::serve(listener, api::router(server_service))
+                .await
+                .unwrap();
+        });
+
+        let submitter = Arc::clone(&service);
+        let marker = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            submitter.mark_submitted(1, 1).unwrap();
+        });
+
+        let secret = ArkBlsBn254::generate_with_seed(Some(&[1u8; 32])).unwrap();
+        let mut config =
+            super::AggregationServiceConfig::new(format!("http://{address}"), secret, 0)
+                .with_event_stream(true)
+                .with_threshold_timeout(Duration::from_secs(30));
+        // Polling would not notice the submission before the test times out
+        config.poll_interval = Duration::from_secs(60);
+
+        let start = Instant::now();
+        let result = super::wait_for_threshold_any_service(&config, 1, 1).await;
+        assert!(matches!(result, Ok(ThresholdWaitResult::Submitted)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        marker.await.unwrap();
+        server.abort();
+        let _ = server.await;
+    }
+
+    #[cfg(feature = "aggregation")]
+    #[tokio::test]
+    async fn wait_for_threshold_polls_after_the_event_stream_fails() {
+        use axum::response::IntoResponse;
+        use blueprint_crypto_bn254::ArkBlsBn254;
+        use blueprint_crypto_core::KeyType;
+        use blueprint_tangle_aggregation_svc::{
+            AggregationService, ServiceConfig, ThresholdWaitResult, api,
+        };
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let service = Arc::new(AggregationService::new(ServiceConfig::minimal()));
+        service.init_task(1, 1, vec![1], 2, 2).unwrap();
+
+        // Task events only fail once the whole threshold timeout has elapsed
+        let router = api::router(Arc::clone(&service)).layer(axum::middleware::from_fn(
+            |request: axum::extract::Request, next: axum::middleware::Next| async move {
+                if request.uri().path().ends_with("/events") {
+                    tokio::time::sleep(Duration::from_millis(250)).await;
+                    return axum::http::StatusCode::SERVICE_UNAVAILABLE.into_response();
+                }
+                next.run(request).await
+            },
+        ));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        let submitter = Arc::clone(&service);
+        let marker = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            submitter.mark_submitted(1, 1).unwrap();
+        });
+
+        let secret = ArkBlsBn254::generate_with_seed(Some(&[1u8; 32])).unwrap();
+        let mut config =
+            super::AggregationServiceConfig::new(format!("http://{address}"), secret, 0)
+                .with_event_stream(true)
+                .with_threshold_timeout(Duration::from_millis(200));
+        config.poll_interval = Duration::from_millis(25);
+
+        let result = super::wait_for_threshold_any_service(&config, 1, 1).await;
+        assert!(matches!(result, Ok(ThresholdWaitResult::Submitted)));
+
+        marker.await.unwrap();
+        server.abort();
+        let _ = server.await;
+    }
+
+    #[cfg(feature = "aggregation")]
+    #[tokio::test]
+    async fn fallback_submitter_stops_waiting_once_task_is_submitted() {
+        use blueprint_crypto_bn254::ArkBlsBn254;
+        use blueprint_crypto_core::KeyType;
+        use blueprint_tangle_aggregation_svc::{AggregationService, ServiceConfig, api};
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+
+        let service = Arc::new(AggregationService::new(ServiceConfig::minimal()));
+        service.init_task(1, 1, vec![1], 2, 2).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server_service = Arc::clone(&service);
+        let server = tokio::spawn(async move {
+            axum::serve(listener, api::router(server_service))
+                .await
+                .unwrap();
+        });
+
+        let secret = ArkBlsBn254::generate_with_seed(Some(&[1u8; 32])).unwrap();
+        let mut config =
+            super::AggregationServiceConfig::new(format!("http://{address}"), secret, 0)
+                .with_submitter_election(Duration::from_secs(30));
+        config.poll_interval = Duration::from_millis(10);
+
+        // Operator 0 is second in line for call 1 of a two-operator service
+        let election = config.submitter_election.unwrap();
+        let delay = election.delay(1, 2, config.operator_index);
+        assert_eq!(delay, Duration::from_secs(30));
+        let completed = || async { true };
+        assert!(!super::wait_for_submitter_turn(&config, 1, 1, Duration::ZERO, completed).await);
+
+        let submitter = Arc::clone(&service);
+        let marker = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            submitter.mark_submitted(1, 1).unwrap();
+        });
+
+        let start = Instant::now();
+        assert!(super::wait_for_submitter_turn(&config, 1, 1, delay, completed).await);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // A task marked as submitted, but not completed on chain, doesn't count
+        let not_completed = || async { false };
+        let short_delay = Duration::from_millis(100);
+        assert!(!super::wait_for_submitter_turn(&config, 1, 1, short_delay, not_completed).await);
+
+        marker.await.unwrap();
+        server.abort();
+        let _ = server.await;
+    }
+
     // ═══════════════════════════════════════════════════════════════════════════
     // calculate_required_signers tests - Count Based
     // ═══════════════════════════════════════════════════════════════════════════
@@ -1773,6 +2119,18 @@ mod tests {
             assert!(!config.submit_to_chain);
         }
 
+        #[test]
+        fn test_config_with_submitter_election() {
+            let config =
+                AggregationServiceConfig::new("http://localhost:8080", test_bls_secret(), 0);
+            // Default is racing
+            assert!(config.submitter_election.is_none());
+
+            let config = config.with_submitter_election(Duration::from_secs(30));
+            let election = config.submitter_election.unwrap();
+            assert_eq!(election.fallback_timeout, Duration::from_secs(30));
+        }
+
         #[test]
         fn test_config_with_wait_for_threshold() {
             let config =
@@ -28,7 +28,7 @@
 //! result.submit(&client).await?;
 //! ```
 
-use alloy_primitives::{Address, Bytes, U256, keccak256};
+use alloy_primitives::{Address, B256, Bytes, U256, keccak256};
 use alloy_sol_types::{SolType, sol_data};
 use blueprint_client_tangle::TangleClient;
 use blueprint_std::format;
@@ -36,6 +36,8 @@ use blueprint_std::string::String;
 use blueprint_std::sync::Arc;
 use thiserror::Error;
 
+use crate::tx_manager::TxManager;
+
 /// Build the exact message verified by TNT core v0.19 `JobsAggregation`.
 pub fn tangle_signing_message(
     chain_id: u64,
@@ -294,6 +296,49 @@ impl AggregatedResult {
             )))
         }
     }
+
+    /// Submit the aggregated result to the client's Tangle contract through `tx`
+    ///
+    /// Same as [`AggregatedResult::submit()`], with the transaction's nonce and fees managed by
+    /// the [`TxManager`]. Returns the hash of the submitting transaction.
+    pub async fn submit_with(
+        &self,
+        client: &TangleClient,
+        tx: &TxManager,
+    ) -> Result<B256, AggregationError> {
+        blueprint_core::debug!(
+            target: "tangle-aggregation",
+            "Submitting aggregated result for service {} call {} with {} signers",
+            self.service_id,
+            self.call_id,
+            self.signer_bitmap.count_signers()
+        );
+
+        let receipt = tx
+            .submit_aggregated_result(
+                client.tangle_address(),
+                self.service_id,
+                self.call_id,
+                self.output.clone(),
+                self.signer_bitmap.as_u256(),
+                self.signature.to_array(),
+                self.pubkey.to_array(),
+            )
+            .await
+            .map_err(|e| {
+                AggregationError::ContractError(format!("Failed to submit aggregated result: {e}"))
+            })?;
+
+        blueprint_core::info!(
+            target: "tangle-aggregation",
+            "Successfully submitted aggregated result for service {} call {} with {} signers: tx_hash={:?}",
+            self.service_id,
+            self.call_id,
+            self.signer_bitmap.count_signers(),
+            receipt.transaction_hash
+        );
+        Ok(receipt.transaction_hash)
+    }
 }
 
 /// Metadata key for storing job index in job result
@@ -0,0 +1,201 @@
+//! Producer Checkpoints
+//!
+//! A [`TangleProducer`] only keeps its position in the chain in memory, so without a
+//! [`CheckpointStore`] every restart starts again from the latest block, and any job submitted
+//! while the operator was down is missed.
+//!
+//! With a store set through [`TangleProducer::with_checkpoint_store()`], the producer loads its
+//! last [`Checkpoint`] on the first poll and resumes right after it. A new checkpoint is committed
+//! after every job call it yields, and after every poll that found nothing, so a restart doesn't
+//! yield the same calls again.
+//!
+//! Checkpoints are written by a background task, so that a slow store never holds up the
+//! producer. Of the checkpoints committed while an earlier one is still being written, only the
+//! latest is written. A crash can therefore lose the last few checkpoints, and the calls yielded
+//! since are produced again after a restart. The runner's journal skips the ones it already
+//! handled.
+//!
+//! Since a call is checkpointed as soon as it is yielded, a call that was yielded but not yet
+//! processed is not produced again after a crash. Pair the producer with the runner's journal to
+//! recover those.
+//!
+//! The hash of the checkpointed block is stored along with it, so that a reorg that happened while
+//! the operator was down is still detected on boot.
+//!
+//! If the operator was down for a long time, [`TangleProducer::with_max_catch_up()`] limits how
+//! many blocks are replayed on boot. Anything older is skipped, with a warning.
+//!
+//! Checkpoints can be kept in a [`LocalDatabase`]. Open it with [`LocalDatabase::open_strict()`],
+//! so that a corrupted file is an error rather than a silent restart from the latest block.
+//!
+//! ```rust,ignore
+//! use blueprint_store_local_database::LocalDatabase;
+//! use blueprint_tangle_extra::TangleProducer;
+//! use blueprint_tangle_extra::checkpoint::Checkpoint;
+//!
+//! let store = LocalDatabase::<Checkpoint>::open_strict(env.data_dir.join("tangle-producer.json"))?;
+//! let producer = TangleProducer::new(client, service_id)
+//!     .with_checkpoint_store(store)
+//!     // Never replay more than ~1 day of blocks
+//!     .with_max_catch_up(43_200);
+//! ```
+//!
+//! [`TangleProducer`]: crate::TangleProducer
+//! [`TangleProducer::with_checkpoint_store()`]: crate::TangleProducer::with_checkpoint_store
+//! [`TangleProducer::with_max_catch_up()`]: crate::TangleProducer::with_max_catch_up
+
+use alloy_primitives::B256;
+#[cfg(feature = "std")]
+use blueprint_std::string::ToString;
+use blueprint_std::sync::Arc;
+use blueprint_store_local_database::{BackgroundWriter, LocalDatabase, StoreError};
+
+/// The position of a producer in the chain
+///
+/// The producer resumes from `block`, skipping every log in it up to and including `log_index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint {
+    /// The last block that was processed
+    pub block: u64,
+    /// The index of the last log processed in `block`, if any
+    pub log_index: Option<u64>,
+    /// The hash of `block`, if it was known when the checkpoint was taken
+    #[cfg_attr(feature = "std", serde(default))]
+    pub block_hash: Option<B256>,
+}
+
+/// Error type for checkpoint stores
+pub type CheckpointError = StoreError;
+
+/// The producer a [`Checkpoint`] belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CheckpointKey {
+    /// A [`TangleProducer`] following a single service
+    ///
+    /// [`TangleProducer`]: crate::TangleProducer
+    Service(u64),
+    /// A [`MultiServiceProducer`] following every service of a blueprint
+    ///
+    /// [`MultiServiceProducer`]: crate::MultiServiceProducer
+    Blueprint(u64),
+}
+
+impl core::fmt::Display for CheckpointKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Service(service_id) => write!(f, "{service_id}"),
+            Self::Blueprint(blueprint_id) => write!(f, "blueprint-{blueprint_id}"),
+        }
+    }
+}
+
+/// Persistent storage for producer [`Checkpoint`]s
+///
+/// Checkpoints are keyed by [`CheckpointKey`], so a single store can be shared by several
+/// producers.
+pub trait CheckpointStore: Send + Sync {
+    /// Load the last checkpoint committed for `key`
+    ///
+    /// # Errors
+    ///
+    /// If the store cannot be read.
+    fn load(&self, key: CheckpointKey) -> Result<Option<Checkpoint>, CheckpointError>;
+
+    /// Persist `checkpoint` as the latest position for `key`
+    ///
+    /// # Errors
+    ///
+    /// If the store cannot be written to.
+    fn commit(&self, key: CheckpointKey, checkpoint: Checkpoint) -> Result<(), CheckpointError>;
+}
+
+#[cfg(feature = "std")]
+impl CheckpointStore for LocalDatabase<Checkpoint> {
+    fn load(&self, key: CheckpointKey) -> Result<Option<Checkpoint>, CheckpointError> {
+        Ok(self.get(&key.to_string())?)
+    }
+
+    fn commit(&self, key: CheckpointKey, checkpoint: Checkpoint) -> Result<(), CheckpointError> {
+        Ok(self.set(&key.to_string(), checkpoint)?)
+    }
+}
+
+/// Commit the checkpoints of `key` to `store` in the background
+///
+/// Failures are only logged. The producer then resumes from an older checkpoint after a restart,
+/// and the runner's journal skips the calls it already handled.
+pub(crate) fn checkpoint_writer(
+    store: Arc<dyn CheckpointStore>,
+    key: CheckpointKey,
+) -> BackgroundWriter<Checkpoint> {
+    BackgroundWriter::spawn(move |checkpoint: Checkpoint| {
+        if let Err(e) = store.commit(key, checkpoint) {
+            blueprint_core::warn!(
+                target: "tangle-producer",
+                %key,
+                block = checkpoint.block,
+                "Failed to commit checkpoint: {e}"
+            );
+        }
+    })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_database_store_survives_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoints").join("producer.json");
+
+        let store = LocalDatabase::<Checkpoint>::open_strict(&path).unwrap();
+        assert_eq!(store.load(CheckpointKey::Service(1)).unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            block: 120,
+            log_index: Some(3),
+            block_hash: Some(B256::repeat_byte(0x12)),
+        };
+        store.commit(CheckpointKey::Service(1), checkpoint).unwrap();
+        store
+            .commit(
+                CheckpointKey::Service(2),
+                Checkpoint {
+                    block: 80,
+                    log_index: None,
+                    block_hash: None,
+                },
+            )
+            .unwrap();
+
+        let reopened = LocalDatabase::<Checkpoint>::open_strict(&path).unwrap();
+        assert_eq!(
+            reopened.load(CheckpointKey::Service(1)).unwrap(),
+            Some(checkpoint)
+        );
+        assert_eq!(
+            reopened
+                .load(CheckpointKey::Service(2))
+                .unwrap()
+                .unwrap()
+                .block,
+            80
+        );
+        assert_eq!(reopened.load(CheckpointKey::Service(3)).unwrap(), None);
+        assert_eq!(reopened.load(CheckpointKey::Blueprint(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn checkpoints_without_a_block_hash_still_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("producer.json");
+        std::fs::write(&path, r#"{"4":{"block":10,"log_index":null}}"#).unwrap();
+
+        let store = LocalDatabase::<Checkpoint>::open_strict(&path).unwrap();
+        let checkpoint = store.load(CheckpointKey::Service(4)).unwrap().unwrap();
+        assert_eq!(checkpoint.block, 10);
+        assert_eq!(checkpoint.block_hash, None);
+    }
+}
@@ -1,8 +1,22 @@
 //! Tangle Consumer
 //!
 //! Consumes [`JobResult`]s and submits them to the Tangle contract.
+//!
+//! Results are sent through a [`TxManager`], which takes care of nonces, fee bumping and
+//! stuck transactions. Unless one is [provided](TangleConsumer::with_tx_manager), the one shared by
+//! everything sending from the client's account is used, and created on the first submission.
+//!
+//! Results whose submission fails are queued again, and retried with an exponential backoff. A
+//! result still failing after [a few retries](TangleConsumer::with_max_retries) is given up on, and
+//! the error returned.
+//!
+//! Results are only kept in memory until they are submitted, unless an [outbox](crate::outbox) is
+//! set through [`TangleConsumer::with_outbox()`]. Given up results stay in the outbox, and are
+//! retried on the next run.
 
 use crate::extract;
+use crate::outbox::{OutboxEntry, OutboxError, OutboxStore};
+use crate::tx_manager::{LazyTxManager, TxError, TxManager};
 use alloy_primitives::Bytes;
 use blueprint_client_tangle::TangleClient;
 use blueprint_core::JobResult;
@@ -10,11 +24,14 @@ use blueprint_core::error::BoxError;
 use blueprint_std::boxed::Box;
 use blueprint_std::collections::VecDeque;
 use blueprint_std::format;
-use blueprint_std::string::String;
+use blueprint_std::string::{String, ToString};
 use blueprint_std::sync::{Arc, Mutex};
+use blueprint_std::vec::Vec;
 use core::pin::Pin;
 use core::task::{Context, Poll};
+use core::time::Duration;
 use futures_util::Sink;
+use tokio::time::Instant;
 
 /// Error type for the consumer
 #[derive(Debug, thiserror::Error)]
@@ -31,6 +48,9 @@ pub enum ConsumerError {
     /// Transaction error
     #[error("Transaction error: {0}")]
     Transaction(String),
+    /// Outbox error
+    #[error("Outbox error: {0}")]
+    Outbox(#[from] OutboxError),
 }
 
 /// Derived job result for submission
@@ -38,13 +58,45 @@ struct DerivedJobResult {
     service_id: u64,
     call_id: u64,
     output: Bytes,
+    /// Left in the outbox by a previous run
+    replayed: bool,
+    /// Failed submissions so far
+    retries: u32,
+    /// Not submitted again before then
+    retry_at: Option<Instant>,
+}
+
+impl DerivedJobResult {
+    fn new(service_id: u64, call_id: u64, output: Bytes, replayed: bool) -> Self {
+        Self {
+            service_id,
+            call_id,
+            output,
+            replayed,
+            retries: 0,
+            retry_at: None,
+        }
+    }
+
+    fn is_ready(&self, now: Instant) -> bool {
+        self.retry_at.is_none_or(|at| at <= now)
+    }
+}
+
+/// Results whose submission failed
+struct FailedSubmission {
+    results: Vec<DerivedJobResult>,
+    /// The first error
+    error: ConsumerError,
 }
 
 enum State {
     WaitingForResult,
     ProcessingSubmission(
-        Pin<Box<dyn core::future::Future<Output = Result<(), ConsumerError>> + Send>>,
+        Pin<Box<dyn core::future::Future<Output = Result<(), FailedSubmission>> + Send>>,
     ),
+    /// Every queued result is waiting to be retried
+    Backoff(Pin<Box<tokio::time::Sleep>>),
 }
 
 impl State {
@@ -56,6 +108,12 @@ impl State {
 /// A consumer of Tangle [`JobResult`]s
 pub struct TangleConsumer {
     client: Arc<TangleClient>,
+    tx: LazyTxManager,
+    batch_size: usize,
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    outbox: Option<Arc<dyn OutboxStore>>,
     buffer: Mutex<VecDeque<DerivedJobResult>>,
     state: Mutex<State>,
 }
@@ -65,16 +123,134 @@ impl TangleConsumer {
     pub fn new(client: TangleClient) -> Self {
         Self {
             client: Arc::new(client),
+            tx: LazyTxManager::default(),
+            batch_size: 1,
+            max_retries: 4,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            outbox: None,
             buffer: Mutex::new(VecDeque::new()),
             state: Mutex::new(State::WaitingForResult),
         }
     }
 
+    /// Send results through `tx`
+    ///
+    /// Share a single [`TxManager`] between everything sending transactions from the operator's
+    /// account, so their nonces don't collide.
+    #[must_use]
+    pub fn with_tx_manager(mut self, tx: TxManager) -> Self {
+        self.tx = LazyTxManager::new(tx);
+        self
+    }
+
+    /// Submit up to `size` results of the same service in a single transaction
+    ///
+    /// Results are batched when several of them are waiting to be submitted. If a batch reverts,
+    /// its results are submitted one at a time, so a single result that can't be submitted doesn't
+    /// hold back the others. Defaults to 1, submitting every result on its own.
+    #[must_use]
+    pub fn with_batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// Retry a result whose submission failed up to `max_retries` times
+    ///
+    /// A result still failing is dropped from memory, and the error returned. Defaults to 4.
+    #[must_use]
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Delay before the first retry, doubled after every retry up to `max`
+    ///
+    /// Defaults to 1 second, capped at 60 seconds.
+    #[must_use]
+    pub fn with_retry_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Keep results in `outbox` until their submission is confirmed
+    ///
+    /// Every result left in the outbox by a previous run is queued again. See the
+    /// [outbox module](crate::outbox) for details.
+    ///
+    /// # Errors
+    ///
+    /// If the outbox cannot be read.
+    pub fn with_outbox(
+        mut self,
+        outbox: impl OutboxStore + 'static,
+    ) -> Result<Self, ConsumerError> {
+        let pending = outbox.pending()?;
+        if !pending.is_empty() {
+            blueprint_core::info!(
+                target: "tangle-consumer",
+                "Resubmitting {} results left in the outbox",
+                pending.len()
+            );
+        }
+
+        let buffer = self.buffer.get_mut().unwrap();
+        for entry in pending {
+            buffer.push_back(DerivedJobResult::new(
+                entry.service_id,
+                entry.call_id,
+                entry.output,
+                true,
+            ));
+        }
+
+        self.outbox = Some(Arc::new(outbox));
+        Ok(self)
+    }
+
     /// Get the client
     #[must_use]
     pub fn client(&self) -> &TangleClient {
         &self.client
     }
+
+    /// Queue the results of a failed submission again, returning the error if any was given up on
+    fn retry(&self, failed: FailedSubmission) -> Result<(), ConsumerError> {
+        let mut buffer = self.buffer.lock().unwrap();
+        let mut gave_up = false;
+        for mut result in failed.results {
+            if result.retries >= self.max_retries {
+                blueprint_core::error!(
+                    target: "tangle-consumer",
+                    "Giving up on the result for service {} call {} after {} retries",
+                    result.service_id,
+                    result.call_id,
+                    result.retries
+                );
+                gave_up = true;
+                continue;
+            }
+
+            let backoff = self
+                .initial_backoff
+                .saturating_mul(2u32.saturating_pow(result.retries))
+                .min(self.max_backoff);
+            blueprint_core::warn!(
+                target: "tangle-consumer",
+                "Submission failed for service {} call {}, retrying in {:?}: {}",
+                result.service_id,
+                result.call_id,
+                backoff,
+                failed.error
+            );
+            result.retries += 1;
+            result.retry_at = Some(Instant::now() + backoff);
+            buffer.push_back(result);
+        }
+
+        if gave_up { Err(failed.error) } else { Ok(()) }
+    }
 }
 
 impl Sink<JobResult> for TangleConsumer {
@@ -109,15 +285,21 @@ impl Sink<JobResult> for TangleConsumer {
             .try_into()
             .map_err(|_| ConsumerError::InvalidMetadata("service_id"))?;
 
-        self.get_mut()
+        let output = Bytes::copy_from_slice(body);
+        let consumer = self.get_mut();
+        if let Some(outbox) = &consumer.outbox {
+            outbox.insert(OutboxEntry {
+                service_id,
+                call_id,
+                output: output.clone(),
+            })?;
+        }
+
+        consumer
             .buffer
             .lock()
             .unwrap()
-            .push_back(DerivedJobResult {
-                service_id,
-                call_id,
-                output: Bytes::copy_from_slice(body),
-            });
+            .push_back(DerivedJobResult::new(service_id, call_id, output, false));
         Ok(())
     }
 
@@ -135,24 +317,44 @@ impl Sink<JobResult> for TangleConsumer {
         loop {
             match &mut *state {
                 State::WaitingForResult => {
-                    let result = {
+                    let (service_id, results) = {
                         let mut buffer = consumer.buffer.lock().unwrap();
-                        buffer.pop_front()
-                    };
+                        let now = Instant::now();
+                        let Some(index) = buffer.iter().position(|result| result.is_ready(now))
+                        else {
+                            match buffer.iter().filter_map(|result| result.retry_at).min() {
+                                Some(retry_at) => {
+                                    *state = State::Backoff(Box::pin(tokio::time::sleep_until(
+                                        retry_at,
+                                    )));
+                                    continue;
+                                }
+                                None => return Poll::Ready(Ok(())),
+                            }
+                        };
+                        let first = buffer.remove(index).expect("index was found");
 
-                    let Some(DerivedJobResult {
-                        service_id,
-                        call_id,
-                        output,
-                    }) = result
-                    else {
-                        return Poll::Ready(Ok(()));
+                        let service_id = first.service_id;
+                        let mut results = Vec::from([first]);
+                        while results.len() < consumer.batch_size
+                            && buffer.get(index).is_some_and(|next| {
+                                next.service_id == service_id && next.is_ready(now)
+                            })
+                        {
+                            results.push(buffer.remove(index).expect("next was checked"));
+                        }
+                        (service_id, results)
                     };
 
-                    let client = Arc::clone(&consumer.client);
-                    let fut = Box::pin(async move {
-                        submit_result(client, service_id, call_id, output).await
-                    });
+                    let submitter = Submitter {
+                        client: Arc::clone(&consumer.client),
+                        tx: consumer.tx.clone(),
+                        outbox: consumer.outbox.clone(),
+                    };
+                    let fut =
+                        Box::pin(
+                            async move { submitter.submit_results(service_id, results).await },
+                        );
 
                     *state = State::ProcessingSubmission(fut);
                 }
@@ -160,68 +362,243 @@ impl Sink<JobResult> for TangleConsumer {
                     Poll::Ready(Ok(())) => {
                         *state = State::WaitingForResult;
                     }
-                    Poll::Ready(Err(e)) => {
+                    Poll::Ready(Err(failed)) => {
                         *state = State::WaitingForResult;
-                        return Poll::Ready(Err(e.into()));
+                        consumer.retry(failed)?;
                     }
                     Poll::Pending => return Poll::Pending,
                 },
+                State::Backoff(sleep) => {
+                    // Results received in the meantime don't wait for the retries
+                    let now = Instant::now();
+                    let ready = consumer
+                        .buffer
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .any(|result| result.is_ready(now));
+                    if !ready && sleep.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    *state = State::WaitingForResult;
+                }
             }
         }
     }
 
-    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        let buffer = self.buffer.lock().unwrap();
-        if buffer.is_empty() {
-            Poll::Ready(Ok(()))
-        } else {
-            Poll::Pending
-        }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
     }
 }
 
-/// Submit a result to the Tangle contract
-async fn submit_result(
+/// Everything needed to submit results, moved into the submission future
+struct Submitter {
     client: Arc<TangleClient>,
-    service_id: u64,
-    call_id: u64,
-    output: Bytes,
-) -> Result<(), ConsumerError> {
-    blueprint_core::debug!(
-        target: "tangle-consumer",
-        "Submitting result for service {} call {}",
-        service_id,
-        call_id
-    );
-
-    if client.config.dry_run {
-        blueprint_core::info!(
+    tx: LazyTxManager,
+    outbox: Option<Arc<dyn OutboxStore>>,
+}
+
+impl Submitter {
+    /// Submit results of a service to the Tangle contract, in a single transaction if there are
+    /// several
+    async fn submit_results(
+        &self,
+        service_id: u64,
+        results: Vec<DerivedJobResult>,
+    ) -> Result<(), FailedSubmission> {
+        let mut pending = Vec::with_capacity(results.len());
+        for result in results {
+            // The result may have been submitted right before the operator went down
+            if result.replayed
+                && matches!(
+                    self.is_completed(service_id, result.call_id).await,
+                    Ok(true)
+                )
+            {
+                blueprint_core::info!(
+                    target: "tangle-consumer",
+                    "Service {} call {} was already completed, dropping it from the outbox",
+                    service_id,
+                    result.call_id
+                );
+                self.confirmed(service_id, result.call_id);
+                continue;
+            }
+            pending.push(result);
+        }
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let call_ids: Vec<u64> = pending.iter().map(|result| result.call_id).collect();
+        blueprint_core::debug!(
             target: "tangle-consumer",
-            "Dry run enabled; skipping on-chain result submission for service {} call {}",
+            "Submitting results for service {} calls {:?}",
             service_id,
-            call_id
+            call_ids
         );
-        return Ok(());
+
+        if self.client.config.dry_run {
+            blueprint_core::info!(
+                target: "tangle-consumer",
+                "Dry run enabled; skipping on-chain result submission for service {} calls {:?}",
+                service_id,
+                call_ids
+            );
+            for call_id in call_ids {
+                self.confirmed(service_id, call_id);
+            }
+            return Ok(());
+        }
+
+        let tx = match self.tx.get(&self.client).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                return Err(FailedSubmission {
+                    results: pending,
+                    error: ConsumerError::Client(e.to_string()),
+                });
+            }
+        };
+
+        if pending.len() == 1 {
+            return match self.submit_result(tx, service_id, &pending[0]).await {
+                Ok(()) => Ok(()),
+                Err(error) => Err(FailedSubmission {
+                    results: pending,
+                    error,
+                }),
+            };
+        }
+
+        let outputs: Vec<(u64, Bytes)> = pending
+            .iter()
+            .map(|result| (result.call_id, result.output.clone()))
+            .collect();
+        match tx
+            .submit_results(self.client.tangle_address(), service_id, &outputs)
+            .await
+        {
+            Ok(receipt) => {
+                blueprint_core::info!(
+                    target: "tangle-consumer",
+                    "Successfully submitted results for service {} calls {:?}: tx_hash={:?}",
+                    service_id,
+                    call_ids,
+                    receipt.transaction_hash
+                );
+                for call_id in call_ids {
+                    self.confirmed(service_id, call_id);
+                }
+                Ok(())
+            }
+            Err(TxError::Reverted { tx_hash, .. }) => {
+                blueprint_core::warn!(
+                    target: "tangle-consumer",
+                    "Batch for service {} calls {:?} reverted (tx_hash={:?}), submitting results one at a time",
+                    service_id,
+                    call_ids,
+                    tx_hash
+                );
+
+                let mut failed = Vec::new();
+                let mut first_error = None;
+                for result in pending {
+                    if let Err(e) = self.submit_result(tx, service_id, &result).await {
+                        first_error.get_or_insert(e);
+                        failed.push(result);
+                    }
+                }
+                match first_error {
+                    Some(error) => Err(FailedSubmission {
+                        results: failed,
+                        error,
+                    }),
+                    None => Ok(()),
+                }
+            }
+            Err(e) => Err(FailedSubmission {
+                results: pending,
+                error: ConsumerError::Transaction(format!("Failed to submit results: {e}")),
+            }),
+        }
     }
 
-    let result = client
-        .submit_result(service_id, call_id, output)
-        .await
-        .map_err(|e| ConsumerError::Transaction(format!("Failed to submit result: {e}")))?;
+    /// Submit a result to the Tangle contract
+    async fn submit_result(
+        &self,
+        tx: &TxManager,
+        service_id: u64,
+        result: &DerivedJobResult,
+    ) -> Result<(), ConsumerError> {
+        let call_id = result.call_id;
+        match tx
+            .submit_result(
+                self.client.tangle_address(),
+                service_id,
+                call_id,
+                result.output.clone(),
+            )
+            .await
+        {
+            Ok(receipt) => {
+                blueprint_core::info!(
+                    target: "tangle-consumer",
+                    "Successfully submitted result for service {} call {}: tx_hash={:?}",
+                    service_id,
+                    call_id,
+                    receipt.transaction_hash
+                );
+                self.confirmed(service_id, call_id);
+                Ok(())
+            }
+            Err(TxError::Reverted { tx_hash, .. }) => {
+                // Nothing left to submit if the call was completed in the meantime
+                if matches!(self.is_completed(service_id, call_id).await, Ok(true)) {
+                    blueprint_core::warn!(
+                        target: "tangle-consumer",
+                        "Transaction reverted for service {} call {} (tx_hash={:?}), but the call is already completed",
+                        service_id,
+                        call_id,
+                        tx_hash
+                    );
+                    self.confirmed(service_id, call_id);
+                    return Ok(());
+                }
+                Err(ConsumerError::Transaction(format!(
+                    "Transaction reverted for service {} call {}: tx_hash={:?}",
+                    service_id, call_id, tx_hash
+                )))
+            }
+            Err(e) => Err(ConsumerError::Transaction(format!(
+                "Failed to submit result: {e}"
+            ))),
+        }
+    }
 
-    if result.success {
-        blueprint_core::info!(
-            target: "tangle-consumer",
-            "Successfully submitted result for service {} call {}: tx_hash={:?}",
-            service_id,
-            call_id,
-            result.tx_hash
-        );
-        Ok(())
-    } else {
-        Err(ConsumerError::Transaction(format!(
-            "Transaction reverted for service {} call {}: tx_hash={:?}",
-            service_id, call_id, result.tx_hash
-        )))
+    async fn is_completed(&self, service_id: u64, call_id: u64) -> Result<bool, ConsumerError> {
+        let call = self
+            .client
+            .get_job_call(service_id, call_id)
+            .await
+            .map_err(|e| ConsumerError::Client(e.to_string()))?;
+        Ok(call.completed)
+    }
+
+    /// Drop a result from the outbox, once it needs no more submitting
+    fn confirmed(&self, service_id: u64, call_id: u64) {
+        let Some(outbox) = &self.outbox else {
+            return;
+        };
+        // At worst, the result is checked again on the next boot
+        if let Err(e) = outbox.remove(service_id, call_id) {
+            blueprint_core::warn!(
+                target: "tangle-consumer",
+                "Failed to remove service {} call {} from the outbox: {}",
+                service_id,
+                call_id,
+                e
+            );
+        }
     }
 }
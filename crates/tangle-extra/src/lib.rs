@@ -6,7 +6,13 @@
 //! ## Overview
 //!
 //! - **Producer**: Polls for `JobSubmitted` events and converts them to `JobCall` streams
+//! - **Multi-service producer**: Follows every service the operator is part of, with one query per poll
+//! - **Checkpoints**: Persist the producer's position, so restarts resume where they left off
+//! - **Subscriptions**: Optionally wake the producer up on `eth_subscribe` log notifications
 //! - **Consumer**: Submits job results via the `submitResult` contract function
+//! - **Outbox**: Optionally persist results until their submission is confirmed
+//! - **Transaction manager**: Local nonces, fee bumping and stuck-transaction recovery, shared by
+//!   the consumers and keepers
 //! - **Extractors**: Extract metadata from job calls (call_id, service_id, etc.)
 //! - **Keepers**: Background services for lifecycle automation (epoch, round, stream)
 //!
@@ -53,11 +59,19 @@ extern crate alloc;
 pub mod aggregating_consumer;
 pub mod aggregation;
 pub mod cache;
+pub mod checkpoint;
 pub mod consumer;
 pub mod extract;
 pub mod layers;
+#[cfg(feature = "std")]
+pub mod metrics;
+pub mod multi_service;
+pub mod outbox;
 pub mod producer;
+#[cfg(feature = "std")]
+pub mod schema;
 pub mod strategy;
+pub mod tx_manager;
 
 /// Per-job RFQ quote signing and verification
 ///
@@ -82,7 +96,9 @@ pub use cache::{
 };
 pub use consumer::TangleConsumer;
 pub use layers::TangleLayer;
+pub use multi_service::MultiServiceProducer;
 pub use producer::TangleProducer;
+pub use tx_manager::{TxError, TxManager, TxManagerConfig};
 
 // Strategy exports
 #[cfg(feature = "aggregation")]
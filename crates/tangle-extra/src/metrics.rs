@@ -0,0 +1,19 @@
+//! Tangle producer metrics
+//!
+//! All metrics register on the default prometheus registry, so they are served by the runner's
+//! metrics endpoint alongside its own.
+
+use prometheus::{IntCounterVec, register_int_counter_vec};
+use std::sync::LazyLock;
+
+/// Chain reorganizations detected by a [`TangleProducer`], by service ID.
+///
+/// [`TangleProducer`]: crate::TangleProducer
+pub static PRODUCER_REORGS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "blueprint_tangle_producer_reorgs_total",
+        "Chain reorganizations detected by the Tangle producer, by service ID",
+        &["service_id"]
+    )
+    .expect("blueprint_tangle_producer_reorgs_total")
+});
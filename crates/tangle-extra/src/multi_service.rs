@@ -0,0 +1,310 @@
+//! Multi-Service Producer
+//!
+//! A [`TangleProducer`] follows a single service, so an operator running a blueprint for many
+//! services would need one producer, and one `eth_getLogs` query per poll, for each of them.
+//!
+//! A [`MultiServiceProducer`] instead follows every service of the client's blueprint that the
+//! operator is part of, with a single log query per poll. The set of services is looked up on the
+//! first poll, and then kept up to date from the contract's events:
+//!
+//! * A service is added when the operator joins it, or when it is activated with the operator in
+//!   it.
+//! * A service is dropped when the operator leaves it, or when it is terminated.
+//!
+//! Every [`JobCall`] is tagged with the ID of its service, so the [`ServiceId`] extractor works
+//! the same way as with a [`TangleProducer`].
+//!
+//! A single checkpoint covers every followed service, see
+//! [`with_checkpoint_store()`](MultiServiceProducer::with_checkpoint_store).
+//!
+//! ```rust,ignore
+//! use blueprint_tangle_extra::MultiServiceProducer;
+//!
+//! let producer = MultiServiceProducer::new(client).with_confirmations(2);
+//! ```
+//!
+//! [`TangleProducer`]: crate::TangleProducer
+//! [`JobCall`]: blueprint_core::JobCall
+//! [`ServiceId`]: crate::extract::ServiceId
+
+use alloy_primitives::{Address, B256};
+use alloy_rpc_types::Log;
+use alloy_sol_types::SolEvent;
+use blueprint_client_tangle::TangleClient;
+use blueprint_client_tangle::contracts::ITangle;
+use blueprint_core::JobCall;
+use blueprint_std::collections::BTreeSet;
+use blueprint_std::sync::{Arc, PoisonError, RwLock};
+use blueprint_std::vec::Vec;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use futures_core::Stream;
+
+use crate::checkpoint::CheckpointStore;
+use crate::producer::{ProducerError, ServiceScope, TangleProducer};
+
+/// A producer of Tangle [`JobCall`]s for every service the operator is part of
+///
+/// See the [module docs](self) for details.
+pub struct MultiServiceProducer {
+    inner: TangleProducer,
+    services: OperatorServices,
+}
+
+impl MultiServiceProducer {
+    /// Create a producer for the services of the client's blueprint that its operator is part of
+    pub fn new(client: TangleClient) -> Self {
+        Self::from_block(client, 0)
+    }
+
+    /// Create a producer starting from a specific block
+    pub fn from_block(client: TangleClient, start_block: u64) -> Self {
+        let services = OperatorServices::new(client.config.settings.blueprint_id, client.account());
+        let inner = TangleProducer::with_scope(
+            client,
+            ServiceScope::Operator(services.clone()),
+            start_block,
+        );
+        Self { inner, services }
+    }
+
+    /// Start with `services`, instead of looking up every service the operator is part of
+    ///
+    /// Looking them up takes a few calls for every service on the chain, which may be slow.
+    #[must_use]
+    pub fn with_services(self, services: impl IntoIterator<Item = u64>) -> Self {
+        self.services.commit(services.into_iter().collect());
+        self
+    }
+
+    /// Set the polling interval
+    ///
+    /// See [`TangleProducer::with_poll_interval()`].
+    #[must_use]
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.with_poll_interval(interval);
+        self
+    }
+
+    /// Persist the producer's position in `store`, and resume from it on boot
+    ///
+    /// The position is shared by every followed service, and is stored under
+    /// [`CheckpointKey::Blueprint`]. See [`TangleProducer::with_checkpoint_store()`].
+    ///
+    /// [`CheckpointKey::Blueprint`]: crate::checkpoint::CheckpointKey::Blueprint
+    #[must_use]
+    pub fn with_checkpoint_store(mut self, store: impl CheckpointStore + 'static) -> Self {
+        self.inner = self.inner.with_checkpoint_store(store);
+        self
+    }
+
+    /// Replay at most `blocks` blocks when resuming from a checkpoint or start block
+    ///
+    /// See [`TangleProducer::with_max_catch_up()`].
+    #[must_use]
+    pub fn with_max_catch_up(mut self, blocks: u64) -> Self {
+        self.inner = self.inner.with_max_catch_up(blocks);
+        self
+    }
+
+    /// Only read blocks that are at least `confirmations` blocks behind the chain head
+    ///
+    /// See [`TangleProducer::with_confirmations()`].
+    #[must_use]
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.inner = self.inner.with_confirmations(confirmations);
+        self
+    }
+
+    /// Read new blocks when notified of new logs over the client's websocket endpoint
+    ///
+    /// See [`TangleProducer::with_log_subscription()`].
+    #[must_use]
+    pub fn with_log_subscription(mut self) -> Self {
+        self.inner = self.inner.with_log_subscription();
+        self
+    }
+
+    /// The services currently followed
+    ///
+    /// This is empty until they are looked up, on the first poll.
+    #[must_use]
+    pub fn services(&self) -> Vec<u64> {
+        self.services
+            .get()
+            .map(|services| services.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get the blueprint ID
+    #[must_use]
+    pub fn blueprint_id(&self) -> u64 {
+        self.services.blueprint_id
+    }
+
+    /// Get the client
+    #[must_use]
+    pub fn client(&self) -> &TangleClient {
+        self.inner.client()
+    }
+}
+
+impl Stream for MultiServiceProducer {
+    type Item = Result<JobCall, ProducerError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().inner).poll_next(cx)
+    }
+}
+
+/// The services of a blueprint that an operator is part of
+///
+/// Shared between a [`MultiServiceProducer`] and its polls. Polls work on a copy of the set, and
+/// only [commit](Self::commit) it once they succeed, so a retried poll never sees its own changes.
+#[derive(Debug, Clone)]
+pub(crate) struct OperatorServices {
+    blueprint_id: u64,
+    operator: Address,
+    /// `None` until looked up
+    joined: Arc<RwLock<Option<BTreeSet<u64>>>>,
+}
+
+impl OperatorServices {
+    fn new(blueprint_id: u64, operator: Address) -> Self {
+        Self {
+            blueprint_id,
+            operator,
+            joined: Arc::default(),
+        }
+    }
+
+    pub(crate) fn blueprint_id(&self) -> u64 {
+        self.blueprint_id
+    }
+
+    fn get(&self) -> Option<BTreeSet<u64>> {
+        self.joined
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub(crate) fn commit(&self, services: BTreeSet<u64>) {
+        *self.joined.write().unwrap_or_else(PoisonError::into_inner) = Some(services);
+    }
+
+    /// The services the operator is part of, looking them up if that wasn't done yet
+    pub(crate) async fn current(
+        &self,
+        client: &TangleClient,
+    ) -> Result<BTreeSet<u64>, blueprint_client_tangle::Error> {
+        if let Some(services) = self.get() {
+            return Ok(services);
+        }
+
+        let mut services = BTreeSet::new();
+        for service_id in client.get_operator_services(self.operator).await? {
+            if self.in_blueprint(client, service_id).await? {
+                services.insert(service_id);
+            }
+        }
+
+        blueprint_core::info!(
+            target: "tangle-producer",
+            blueprint_id = self.blueprint_id,
+            services = ?services,
+            "Following the operator's services"
+        );
+        self.commit(services.clone());
+        Ok(services)
+    }
+
+    /// Apply `log` to `services`, if it changes the operator's membership
+    ///
+    /// Returns whether `log` was a membership event.
+    pub(crate) async fn apply(
+        &self,
+        client: &TangleClient,
+        log: &Log,
+        services: &mut BTreeSet<u64>,
+    ) -> Result<bool, blueprint_client_tangle::Error> {
+        if let Ok(event) = log.log_decode::<ITangle::OperatorJoinedService>() {
+            let service_id = event.inner.serviceId;
+            if event.inner.operator == self.operator
+                && !services.contains(&service_id)
+                && self.in_blueprint(client, service_id).await?
+            {
+                self.add(services, service_id);
+            }
+            return Ok(true);
+        }
+
+        if let Ok(event) = log.log_decode::<ITangle::ServiceActivated>() {
+            let service_id = event.inner.serviceId;
+            if !services.contains(&service_id)
+                && self.in_blueprint(client, service_id).await?
+                && client
+                    .is_service_operator(service_id, self.operator)
+                    .await?
+            {
+                self.add(services, service_id);
+            }
+            return Ok(true);
+        }
+
+        if let Ok(event) = log.log_decode::<ITangle::OperatorLeftService>() {
+            if event.inner.operator == self.operator {
+                self.remove(services, event.inner.serviceId);
+            }
+            return Ok(true);
+        }
+
+        if let Ok(event) = log.log_decode::<ITangle::ServiceTerminated>() {
+            self.remove(services, event.inner.serviceId);
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    async fn in_blueprint(
+        &self,
+        client: &TangleClient,
+        service_id: u64,
+    ) -> Result<bool, blueprint_client_tangle::Error> {
+        let service = client.get_service(service_id).await?;
+        Ok(service.blueprintId == self.blueprint_id)
+    }
+
+    fn add(&self, services: &mut BTreeSet<u64>, service_id: u64) {
+        services.insert(service_id);
+        blueprint_core::info!(
+            target: "tangle-producer",
+            blueprint_id = self.blueprint_id,
+            service_id,
+            "Operator joined service, following it"
+        );
+    }
+
+    fn remove(&self, services: &mut BTreeSet<u64>, service_id: u64) {
+        if services.remove(&service_id) {
+            blueprint_core::info!(
+                target: "tangle-producer",
+                blueprint_id = self.blueprint_id,
+                service_id,
+                "Operator left service, no longer following it"
+            );
+        }
+    }
+}
+
+/// Signatures of the events that change which services an operator is part of
+pub(crate) fn membership_events() -> [B256; 4] {
+    [
+        ITangle::OperatorJoinedService::SIGNATURE_HASH,
+        ITangle::OperatorLeftService::SIGNATURE_HASH,
+        ITangle::ServiceActivated::SIGNATURE_HASH,
+        ITangle::ServiceTerminated::SIGNATURE_HASH,
+    ]
+}
@@ -0,0 +1,150 @@
+//! Result Outbox
+//!
+//! A [`TangleConsumer`] only keeps results in memory until their `submitResult` transaction is
+//! confirmed. If the operator goes down in the meantime, the result is lost, and the service may
+//! slash the operator for not responding.
+//!
+//! With an [`OutboxStore`] set through [`TangleConsumer::with_outbox()`], every result is written
+//! to the outbox as soon as the consumer receives it, and deleted only once its submission has a
+//! confirmed receipt, or the contract reports its call as completed. A result the consumer gives
+//! up on after retrying it stays in the outbox.
+//!
+//! On boot, the consumer queues every entry left in the outbox again. Before resubmitting one, it
+//! checks whether its call was completed in the meantime, which happens when its transaction was
+//! confirmed right before the operator went down. Pending entries are submitted with the next
+//! results, or right away by flushing the consumer.
+//!
+//! The outbox can be a [`LocalDatabase`]. Open it with [`LocalDatabase::open_strict()`], so that a
+//! corrupted file is an error instead of an empty outbox, whose results would never be submitted.
+//!
+//! ```rust,ignore
+//! use blueprint_store_local_database::LocalDatabase;
+//! use blueprint_tangle_extra::TangleConsumer;
+//! use blueprint_tangle_extra::outbox::OutboxEntry;
+//! use futures_util::SinkExt;
+//!
+//! let outbox = LocalDatabase::<OutboxEntry>::open_strict(env.data_dir.join("tangle-outbox.json"))?;
+//! let mut consumer = TangleConsumer::new(client).with_outbox(outbox)?;
+//! // Resubmit whatever was left over from the last run
+//! consumer.flush().await?;
+//! ```
+//!
+//! [`TangleConsumer`]: crate::TangleConsumer
+//! [`TangleConsumer::with_outbox()`]: crate::TangleConsumer::with_outbox
+
+use alloy_primitives::Bytes;
+use blueprint_std::string::String;
+use blueprint_std::vec::Vec;
+use blueprint_store_local_database::{LocalDatabase, StoreError};
+
+/// A job result waiting for its submission to be confirmed
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutboxEntry {
+    /// The service the job belongs to
+    pub service_id: u64,
+    /// The call the result is for
+    pub call_id: u64,
+    /// The encoded result
+    pub output: Bytes,
+}
+
+/// Error type for outbox stores
+pub type OutboxError = StoreError;
+
+/// Persistent storage for results awaiting confirmation
+///
+/// Entries are keyed by service ID and call ID, so a single store can be shared by several
+/// consumers.
+pub trait OutboxStore: Send + Sync {
+    /// Add `entry`, replacing any entry for the same call
+    ///
+    /// # Errors
+    ///
+    /// If the store cannot be written to.
+    fn insert(&self, entry: OutboxEntry) -> Result<(), OutboxError>;
+
+    /// Delete the entry for `call_id` of `service_id`, if there is one
+    ///
+    /// # Errors
+    ///
+    /// If the store cannot be written to.
+    fn remove(&self, service_id: u64, call_id: u64) -> Result<(), OutboxError>;
+
+    /// Every entry in the store, ordered by service ID and call ID
+    ///
+    /// # Errors
+    ///
+    /// If the store cannot be read.
+    fn pending(&self) -> Result<Vec<OutboxEntry>, OutboxError>;
+}
+
+#[cfg(feature = "std")]
+impl OutboxStore for LocalDatabase<OutboxEntry> {
+    fn insert(&self, entry: OutboxEntry) -> Result<(), OutboxError> {
+        Ok(self.set(&local_database_key(entry.service_id, entry.call_id), entry)?)
+    }
+
+    fn remove(&self, service_id: u64, call_id: u64) -> Result<(), OutboxError> {
+        self.remove(&local_database_key(service_id, call_id))?;
+        Ok(())
+    }
+
+    fn pending(&self) -> Result<Vec<OutboxEntry>, OutboxError> {
+        let mut entries = self.values()?;
+        entries.sort_by_key(|entry| (entry.service_id, entry.call_id));
+        Ok(entries)
+    }
+}
+
+#[cfg(feature = "std")]
+fn local_database_key(service_id: u64, call_id: u64) -> String {
+    blueprint_std::format!("{service_id}/{call_id}")
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    fn entry(service_id: u64, call_id: u64) -> OutboxEntry {
+        OutboxEntry {
+            service_id,
+            call_id,
+            output: Bytes::from(Vec::from([service_id as u8, call_id as u8])),
+        }
+    }
+
+    #[test]
+    fn local_database_store_survives_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox").join("results.json");
+
+        let store = LocalDatabase::<OutboxEntry>::open_strict(&path).unwrap();
+        assert!(store.pending().unwrap().is_empty());
+
+        store.insert(entry(2, 1)).unwrap();
+        store.insert(entry(1, 7)).unwrap();
+        store.insert(entry(1, 3)).unwrap();
+        OutboxStore::remove(&store, 1, 7).unwrap();
+        // Removing an unknown entry is a no-op
+        OutboxStore::remove(&store, 9, 9).unwrap();
+
+        let reopened = LocalDatabase::<OutboxEntry>::open_strict(&path).unwrap();
+        assert_eq!(reopened.pending().unwrap(), [entry(1, 3), entry(2, 1)]);
+    }
+
+    #[test]
+    fn inserting_the_same_call_replaces_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let store =
+            LocalDatabase::<OutboxEntry>::open_strict(dir.path().join("results.json")).unwrap();
+
+        store.insert(entry(1, 1)).unwrap();
+        let replacement = OutboxEntry {
+            output: Bytes::from_static(b"new"),
+            ..entry(1, 1)
+        };
+        store.insert(replacement.clone()).unwrap();
+        assert_eq!(store.pending().unwrap(), [replacement]);
+    }
+}
@@ -1,8 +1,32 @@
 //! Tangle Producer
 //!
 //! Produces [`JobCall`]s from Tangle contract events.
+//!
+//! The producer's position in the chain can be persisted with a [`CheckpointStore`], see the
+//! [checkpoint module](crate::checkpoint).
+//!
+//! # Reorgs
+//!
+//! By default, the producer reads logs right up to the chain head. On chains with probabilistic
+//! finality, set [`TangleProducer::with_confirmations()`] so that only blocks that are unlikely
+//! to be reorged out are read.
+//!
+//! Either way, the producer keeps the hashes of the blocks it read in the last
+//! [`REORG_TRACKING_DEPTH`] blocks, and checks them against the chain before every poll. When a
+//! block it read is no longer canonical, the producer goes back to the last block that still is,
+//! and reads everything after it again. Job calls that were already produced with the same
+//! contents are not produced twice, so only calls that are new or changed on the new fork are
+//! yielded. Detected reorgs are counted in the `blueprint_tangle_producer_reorgs_total` metric.
+//!
+//! # Subscriptions
+//!
+//! With [`TangleProducer::with_log_subscription()`], the producer subscribes to new logs over the
+//! client's websocket endpoint, and reads new blocks as soon as it is notified of a job, instead
+//! of on every poll interval. It still polls once the subscription is silent for a poll interval,
+//! in case it stalled. If the subscription can't be established or drops, the producer polls on
+//! its interval until it can resubscribe, and then reads every block it missed.
 
-use alloy_primitives::{Address, B256, U256, hex_literal::hex};
+use alloy_primitives::{Address, B256, U256, hex_literal::hex, keccak256};
 use alloy_rpc_types::{BlockNumberOrTag, Filter, Log};
 use blueprint_client_tangle::TangleClient;
 use blueprint_core::JobCall;
@@ -10,9 +34,9 @@ use blueprint_core::extensions::Extensions;
 use blueprint_core::job::call::Parts;
 use blueprint_core::metadata::MetadataMap;
 use blueprint_std::boxed::Box;
-use blueprint_std::collections::{BTreeMap, VecDeque};
+use blueprint_std::collections::{BTreeMap, BTreeSet, VecDeque};
 use blueprint_std::string::{String, ToString};
-use blueprint_std::sync::Mutex;
+use blueprint_std::sync::{Arc, Mutex};
 use blueprint_std::vec::Vec;
 use core::convert::TryFrom;
 use core::future::Future;
@@ -22,10 +46,19 @@ use core::time::Duration;
 use futures_core::Stream;
 use tokio::time::sleep;
 
+use crate::checkpoint::{Checkpoint, CheckpointKey, CheckpointStore, checkpoint_writer};
 use crate::extract;
+use crate::multi_service::{OperatorServices, membership_events};
+use blueprint_evm_extra::producer::LogSubscriber;
+use blueprint_store_local_database::BackgroundWriter;
 
 const MAX_LOG_RANGE_BLOCKS: u64 = 10;
 
+/// How many blocks behind the last read block are checked for reorgs
+///
+/// Reorgs deeper than this are not detected.
+pub const REORG_TRACKING_DEPTH: u64 = 64;
+
 /// Error type for the producer
 #[derive(Debug, thiserror::Error)]
 pub enum ProducerError {
@@ -35,20 +68,40 @@ pub enum ProducerError {
     /// Event decoding error
     #[error("Event decoding error: {0}")]
     Decoding(String),
+    /// Checkpoint store error
+    #[error("Checkpoint error: {0}")]
+    Checkpoint(#[from] crate::checkpoint::CheckpointError),
 }
 
 /// A producer of Tangle [`JobCall`]s
 pub struct TangleProducer {
     client: TangleClient,
-    service_id: u64,
+    scope: ServiceScope,
     state: Mutex<ProducerState>,
     poll_interval: Duration,
+    checkpoints: Option<Arc<dyn CheckpointStore>>,
+    max_catch_up: Option<u64>,
+    confirmations: u64,
+    subscription_endpoint: Option<String>,
 }
 
 struct ProducerState {
     last_block: u64,
     last_log_index: Option<u64>,
-    buffer: VecDeque<JobCall>,
+    /// Job calls waiting to be yielded, along with the checkpoint to commit once they are
+    buffer: VecDeque<(JobCall, Checkpoint)>,
+    /// The end of the last poll, committed once every job call it found has been yielded
+    uncommitted: Option<Checkpoint>,
+    /// Whether the checkpoint store still needs to be loaded
+    resuming: bool,
+    /// Commits checkpoints to the store, started once it was loaded
+    writer: Option<BackgroundWriter<Checkpoint>>,
+    /// Whether the last poll stopped short of the chain head
+    behind: bool,
+    /// Recently read blocks and produced calls, taken by the ongoing poll
+    tracker: Option<ReorgTracker>,
+    /// The log subscription, if enabled, taken by the ongoing poll
+    subscriber: Option<LogSubscriber>,
     poll_future:
         Option<Pin<Box<dyn Future<Output = Result<ProducerPollResult, ProducerError>> + Send>>>,
 }
@@ -59,15 +112,32 @@ impl ProducerState {
             last_block: start_block,
             last_log_index: None,
             buffer: VecDeque::new(),
+            uncommitted: None,
+            resuming: true,
+            writer: None,
+            behind: false,
+            tracker: Some(ReorgTracker::default()),
+            subscriber: None,
             poll_future: None,
         }
     }
+
+    /// Persist `checkpoint` in the background, if there's a [`CheckpointStore`]
+    fn commit(&self, checkpoint: Checkpoint) {
+        if let Some(writer) = &self.writer {
+            writer.write(checkpoint);
+        }
+    }
 }
 
 struct ProducerPollResult {
-    jobs: Vec<JobCall>,
+    jobs: Vec<(JobCall, Checkpoint)>,
     last_block: u64,
     last_log_index: Option<u64>,
+    last_block_hash: Option<B256>,
+    caught_up: bool,
+    tracker: ReorgTracker,
+    subscriber: Option<LogSubscriber>,
 }
 
 impl TangleProducer {
@@ -77,35 +147,86 @@ impl TangleProducer {
     /// * `client` - The Tangle client
     /// * `service_id` - The service ID to filter events for
     pub fn new(client: TangleClient, service_id: u64) -> Self {
-        Self {
-            client,
-            service_id,
-            state: Mutex::new(ProducerState::new(0)),
-            poll_interval: Duration::from_secs(2),
-        }
+        Self::from_block(client, service_id, 0)
     }
 
     /// Create a producer starting from a specific block
+    ///
+    /// A checkpoint loaded from a [`CheckpointStore`] takes precedence over `start_block`.
     pub fn from_block(client: TangleClient, service_id: u64, start_block: u64) -> Self {
+        Self::with_scope(client, ServiceScope::Single(service_id), start_block)
+    }
+
+    pub(crate) fn with_scope(client: TangleClient, scope: ServiceScope, start_block: u64) -> Self {
         Self {
             client,
-            service_id,
+            scope,
             state: Mutex::new(ProducerState::new(start_block)),
             poll_interval: Duration::from_secs(2),
+            checkpoints: None,
+            max_catch_up: None,
+            confirmations: 0,
+            subscription_endpoint: None,
         }
     }
 
     /// Set the polling interval
+    ///
+    /// While catching up on past blocks, the producer polls again without waiting.
     #[must_use]
     pub fn with_poll_interval(mut self, interval: Duration) -> Self {
         self.poll_interval = interval;
         self
     }
 
+    /// Persist the producer's position in `store`, and resume from it on boot
+    ///
+    /// See the [checkpoint module](crate::checkpoint) for details.
+    #[must_use]
+    pub fn with_checkpoint_store(mut self, store: impl CheckpointStore + 'static) -> Self {
+        self.checkpoints = Some(Arc::new(store));
+        self
+    }
+
+    /// Replay at most `blocks` blocks when resuming from a checkpoint or start block
+    ///
+    /// By default, every block since the checkpoint is replayed, however old it is.
+    #[must_use]
+    pub fn with_max_catch_up(mut self, blocks: u64) -> Self {
+        self.max_catch_up = Some(blocks);
+        self
+    }
+
+    /// Only read blocks that are at least `confirmations` blocks behind the chain head
+    ///
+    /// Defaults to 0, which reads logs up to the head itself. See the [module docs](self) for how
+    /// reorgs are handled.
+    #[must_use]
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// Read new blocks when notified of new logs over the client's websocket endpoint
+    ///
+    /// The poll interval is only used while the subscription is down or silent, or while waiting
+    /// for [confirmations](Self::with_confirmations()). See the [module docs](self) for details.
+    #[must_use]
+    pub fn with_log_subscription(mut self) -> Self {
+        self.subscription_endpoint = Some(self.client.config.ws_rpc_endpoint.to_string());
+        self
+    }
+
     /// Get the service ID
+    ///
+    /// Returns `None` for producers following several services, see
+    /// [`MultiServiceProducer`](crate::MultiServiceProducer).
     #[must_use]
-    pub fn service_id(&self) -> u64 {
-        self.service_id
+    pub fn service_id(&self) -> Option<u64> {
+        match &self.scope {
+            ServiceScope::Single(service_id) => Some(*service_id),
+            ServiceScope::Operator(_) => None,
+        }
     }
 
     /// Get the client
@@ -122,9 +243,39 @@ impl Stream for TangleProducer {
         let producer = self.get_mut();
         let mut state = producer.state.lock().unwrap();
 
+        if state.resuming
+            && let Some(store) = &producer.checkpoints
+        {
+            match store.load(producer.scope.checkpoint_key()) {
+                Ok(Some(checkpoint)) => {
+                    blueprint_core::info!(
+                        target: "tangle-producer",
+                        services = %producer.scope,
+                        block = checkpoint.block,
+                        log_index = ?checkpoint.log_index,
+                        "Resuming from checkpoint"
+                    );
+                    state.last_block = checkpoint.block;
+                    state.last_log_index = checkpoint.log_index;
+                    if let Some(hash) = checkpoint.block_hash {
+                        let mut tracker = ReorgTracker::default();
+                        tracker.record_block(checkpoint.block, hash);
+                        state.tracker = Some(tracker);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            }
+            state.writer = Some(checkpoint_writer(
+                store.clone(),
+                producer.scope.checkpoint_key(),
+            ));
+        }
+
         loop {
             // First, check if there are buffered items
-            if let Some(job) = state.buffer.pop_front() {
+            if let Some((job, checkpoint)) = state.buffer.pop_front() {
+                state.commit(checkpoint);
                 return Poll::Ready(Some(Ok(job)));
             }
 
@@ -134,51 +285,185 @@ impl Stream for TangleProducer {
                     Poll::Ready(Ok(result)) => {
                         state.last_block = result.last_block;
                         state.last_log_index = result.last_log_index;
+                        state.behind = !result.caught_up;
+                        state.uncommitted = Some(Checkpoint {
+                            block: result.last_block,
+                            log_index: result.last_log_index,
+                            block_hash: result.last_block_hash,
+                        });
+                        state.tracker = Some(result.tracker);
+                        state.subscriber = result.subscriber;
                         state.buffer.extend(result.jobs);
                         state.poll_future = None;
 
-                        if let Some(job) = state.buffer.pop_front() {
+                        if let Some((job, checkpoint)) = state.buffer.pop_front() {
+                            state.commit(checkpoint);
                             return Poll::Ready(Some(Ok(job)));
                         }
                         // No jobs found, start a new poll
                     }
                     Poll::Ready(Err(e)) => {
                         state.poll_future = None;
+                        state.tracker = Some(ReorgTracker::default());
                         return Poll::Ready(Some(Err(e)));
                     }
                     Poll::Pending => return Poll::Pending,
                 }
             }
 
-            // Start a new poll for events after waiting poll_interval.
-            // Without this sleep the loop spins immediately when no jobs are
+            // Every job call of the last poll has been yielded
+            if let Some(checkpoint) = state.uncommitted.take() {
+                state.commit(checkpoint);
+            }
+
+            // Start a new poll for events after waiting poll_interval, or for a
+            // log notification when subscribed.
+            // Without this wait the loop spins immediately when no jobs are
             // found, starving the tokio runtime and preventing other tasks
-            // (HTTP servers, workflow ticks) from making progress.
+            // (HTTP servers, workflow ticks) from making progress. Polls that
+            // are catching up on past blocks still yield on their RPC calls.
             let client = producer.client.clone();
-            let service_id = producer.service_id;
+            let scope = producer.scope.clone();
             let last_block = state.last_block;
             let last_log_index = state.last_log_index;
-            let poll_interval = producer.poll_interval;
+            // Only the first poll skips ahead, the producer never falls behind on its own
+            let max_catch_up = if core::mem::take(&mut state.resuming) {
+                producer.max_catch_up
+            } else {
+                None
+            };
+            let poll_interval = if state.behind {
+                Duration::ZERO
+            } else {
+                producer.poll_interval
+            };
+            let confirmations = producer.confirmations;
+            let tracker = state.tracker.take().unwrap_or_default();
+            let mut subscriber = state.subscriber.take().or_else(|| producer.subscriber());
 
             let fut = Box::pin(async move {
-                sleep(poll_interval).await;
-                poll_for_jobs(client, service_id, last_block, last_log_index).await
+                match &mut subscriber {
+                    Some(subscriber) => subscriber.wait(poll_interval, last_block).await,
+                    None => sleep(poll_interval).await,
+                }
+                let mut result = poll_for_jobs(
+                    client,
+                    scope,
+                    last_block,
+                    last_log_index,
+                    max_catch_up,
+                    confirmations,
+                    tracker,
+                )
+                .await?;
+                result.subscriber = subscriber;
+                Ok(result)
             });
             state.poll_future = Some(fut);
         }
     }
 }
 
+impl TangleProducer {
+    /// A new [`LogSubscriber`] for this producer's job events, if subscriptions are enabled
+    fn subscriber(&self) -> Option<LogSubscriber> {
+        let endpoint = self.subscription_endpoint.clone()?;
+        let mut events = Vec::from([
+            B256::from(JOB_SUBMITTED_SIG),
+            B256::from(JOB_SUBMITTED_FROM_QUOTE_SIG),
+        ]);
+        let filter = Filter::new().address(self.client.tangle_address());
+        let filter = match &self.scope {
+            ServiceScope::Single(service_id) => filter
+                .event_signature(events)
+                .topic1(B256::from(U256::from(*service_id).to_be_bytes::<32>())),
+            ServiceScope::Operator(_) => {
+                // Membership changes are needed too, for services that aren't followed yet
+                events.extend(membership_events());
+                filter.event_signature(events)
+            }
+        };
+        Some(LogSubscriber::new(endpoint, filter))
+    }
+}
+
+/// The services a producer yields job calls for
+#[derive(Debug, Clone)]
+pub(crate) enum ServiceScope {
+    /// A single, fixed service
+    Single(u64),
+    /// Every service of a blueprint that the operator is part of, see [`MultiServiceProducer`]
+    ///
+    /// [`MultiServiceProducer`]: crate::MultiServiceProducer
+    Operator(OperatorServices),
+}
+
+impl ServiceScope {
+    /// The key of the producer's checkpoints
+    fn checkpoint_key(&self) -> CheckpointKey {
+        match self {
+            Self::Single(service_id) => CheckpointKey::Service(*service_id),
+            Self::Operator(operator) => CheckpointKey::Blueprint(operator.blueprint_id()),
+        }
+    }
+
+    /// The services to yield job calls for, as of the start of a poll
+    async fn services(
+        &self,
+        client: &TangleClient,
+    ) -> Result<BTreeSet<u64>, blueprint_client_tangle::Error> {
+        match self {
+            Self::Single(service_id) => Ok(BTreeSet::from([*service_id])),
+            Self::Operator(operator) => operator.current(client).await,
+        }
+    }
+}
+
+impl core::fmt::Display for ServiceScope {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Single(service_id) => write!(f, "{service_id}"),
+            Self::Operator(operator) => write!(f, "blueprint-{}", operator.blueprint_id()),
+        }
+    }
+}
+
+/// Where a poll starts, given the last position and the chain head
+///
+/// Returns `from_block` and `from_log_index` unchanged, unless `max_catch_up` would be exceeded,
+/// in which case the poll skips ahead to `max_catch_up` blocks before the head.
+fn catch_up_start(
+    from_block: u64,
+    from_log_index: Option<u64>,
+    latest_block: u64,
+    max_catch_up: Option<u64>,
+) -> (u64, Option<u64>) {
+    let Some(window) = max_catch_up else {
+        return (from_block, from_log_index);
+    };
+
+    let earliest = latest_block.saturating_sub(window);
+    if from_block < earliest {
+        (earliest, None)
+    } else {
+        (from_block, from_log_index)
+    }
+}
+
 /// Poll for new job events
 async fn poll_for_jobs(
     client: TangleClient,
-    service_id: u64,
-    from_block: u64,
-    from_log_index: Option<u64>,
+    scope: ServiceScope,
+    mut from_block: u64,
+    mut from_log_index: Option<u64>,
+    mut max_catch_up: Option<u64>,
+    confirmations: u64,
+    mut tracker: ReorgTracker,
 ) -> Result<ProducerPollResult, ProducerError> {
     let mut block_number_failures = 0u32;
     let mut get_logs_failures = 0u32;
     let mut block_fetch_failures = 0u32;
+    let mut membership_failures = 0u32;
 
     'poll_loop: loop {
         let latest_block = match client.block_number().await {
@@ -219,20 +504,94 @@ async fn poll_for_jobs(
             }
         };
 
+        // Changes in membership are only kept once the poll succeeds
+        let mut services = match scope.services(&client).await {
+            Ok(services) => services,
+            Err(err) => {
+                membership_backoff(&mut membership_failures, &err).await;
+                continue;
+            }
+        };
+
+        // Blocks past this one don't have enough confirmations to be read yet
+        let safe_head = latest_block.saturating_sub(confirmations);
+
+        if from_block != 0 {
+            // Only the first attempt skips ahead, later ones start from where it landed
+            let (start, log_index) =
+                catch_up_start(from_block, from_log_index, safe_head, max_catch_up.take());
+            if start != from_block {
+                blueprint_core::warn!(
+                    target: "tangle-producer",
+                    services = %scope,
+                    from_block,
+                    skipped_blocks = start - from_block,
+                    "Checkpoint is older than the maximum catch-up window, skipping ahead"
+                );
+            }
+            from_block = start;
+            from_log_index = log_index;
+        }
+
+        match find_fork(&client, &tracker).await {
+            Ok(None) => {}
+            Ok(Some(fork)) => {
+                let (tracked_head, _) = tracker.latest().unwrap_or_default();
+                blueprint_core::warn!(
+                    target: "tangle-producer",
+                    services = %scope,
+                    fork,
+                    depth = tracked_head.saturating_sub(fork),
+                    "Chain reorganization detected, reading blocks after the fork again"
+                );
+                #[cfg(feature = "std")]
+                crate::metrics::PRODUCER_REORGS
+                    .with_label_values(&[scope.to_string().as_str()])
+                    .inc();
+
+                tracker.rewind(fork);
+                if fork < from_block {
+                    from_block = fork + 1;
+                    from_log_index = None;
+                }
+            }
+            Err((block, err)) => {
+                block_fetch_backoff(&mut block_fetch_failures, block, &err).await;
+                continue 'poll_loop;
+            }
+        }
+
         let effective_from_block = if from_block == 0 {
-            latest_block.saturating_sub(MAX_LOG_RANGE_BLOCKS.saturating_sub(1))
+            safe_head.saturating_sub(MAX_LOG_RANGE_BLOCKS.saturating_sub(1))
         } else {
             from_block
         };
 
-        if latest_block < effective_from_block {
+        if safe_head < effective_from_block {
             sleep(Duration::from_millis(250)).await;
             continue;
         }
 
-        let to_block = latest_block
+        let to_block = safe_head
             .min(effective_from_block.saturating_add(MAX_LOG_RANGE_BLOCKS.saturating_sub(1)));
 
+        // Read the hash of the last block before its logs, so logs from a version of it that is
+        // reorged out in the meantime can be told apart
+        let to_block_hash = match canonical_hash(&client, to_block).await {
+            Ok(Some(hash)) => {
+                block_fetch_failures = 0;
+                hash
+            }
+            Ok(None) => {
+                block_fetch_backoff(&mut block_fetch_failures, to_block, &"block not found").await;
+                continue 'poll_loop;
+            }
+            Err(err) => {
+                block_fetch_backoff(&mut block_fetch_failures, to_block, &err).await;
+                continue 'poll_loop;
+            }
+        };
+
         let filter = Filter::new()
             .address(client.tangle_address())
             .from_block(effective_from_block)
@@ -282,6 +641,7 @@ async fn poll_for_jobs(
             }
         };
 
+        logs.retain(|log| !log.removed);
         logs.sort_by_key(|log| {
             (
                 log.block_number.unwrap_or_default(),
@@ -307,6 +667,19 @@ async fn poll_for_jobs(
             logs
         };
 
+        if filtered_logs
+            .iter()
+            .any(|log| log.block_number == Some(to_block) && log.block_hash != Some(to_block_hash))
+        {
+            blueprint_core::debug!(
+                target: "tangle-producer",
+                block = to_block,
+                "Block changed while reading its logs, reading it again"
+            );
+            sleep(Duration::from_millis(250)).await;
+            continue 'poll_loop;
+        }
+
         let (last_block, last_log_index) = if let Some(last) = filtered_logs.last() {
             (last.block_number.unwrap_or(to_block), last.log_index)
         } else if to_block == effective_from_block {
@@ -316,9 +689,21 @@ async fn poll_for_jobs(
         };
 
         let mut jobs = Vec::new();
+        let mut emitted = Vec::new();
         let mut block_timestamps = BTreeMap::new();
 
         for log in &filtered_logs {
+            if let ServiceScope::Operator(operator) = &scope {
+                match operator.apply(&client, log, &mut services).await {
+                    Ok(true) => continue,
+                    Ok(false) => {}
+                    Err(err) => {
+                        membership_backoff(&mut membership_failures, &err).await;
+                        continue 'poll_loop;
+                    }
+                }
+            }
+
             // Try both JobSubmitted and JobSubmittedFromQuote events
             let job_event = match decode_job_submitted(log) {
                 Ok(event) => event,
@@ -334,10 +719,26 @@ async fn poll_for_jobs(
                     }
                 },
             };
-            if job_event.service_id != service_id {
+            if !services.contains(&job_event.service_id) {
                 continue;
             }
 
+            let fingerprint = call_fingerprint(&job_event);
+            if !tracker.is_new(job_event.service_id, job_event.call_id, fingerprint) {
+                blueprint_core::debug!(
+                    target: "tangle-producer",
+                    service_id = job_event.service_id,
+                    call_id = job_event.call_id,
+                    "Skipping job call that was already produced before a reorg"
+                );
+                continue;
+            }
+            emitted.push((
+                (job_event.service_id, job_event.call_id),
+                job_event.block_number,
+                fingerprint,
+            ));
+
             let log_block = job_event.block_number;
             let timestamp = if let Some(ts) = log.block_timestamp {
                 block_timestamps.insert(log_block, ts);
@@ -417,7 +818,27 @@ async fn poll_for_jobs(
 
             let block_hash = job_event.block_hash;
             let job_call = job_submitted_to_call(job_event, log_block, block_hash.0, timestamp);
-            jobs.push(job_call);
+            let checkpoint = Checkpoint {
+                block: log_block,
+                log_index: log.log_index,
+                block_hash: Some(block_hash),
+            };
+            jobs.push((job_call, checkpoint));
+        }
+
+        // Only remember what this poll read once it can no longer be retried
+        for log in &filtered_logs {
+            if let (Some(number), Some(hash)) = (log.block_number, log.block_hash) {
+                tracker.record_block(number, hash);
+            }
+        }
+        tracker.record_block(to_block, to_block_hash);
+        for (call, block, fingerprint) in emitted {
+            tracker.record_call(call, block, fingerprint);
+        }
+        tracker.prune(to_block);
+        if let ServiceScope::Operator(operator) = &scope {
+            operator.commit(services);
         }
 
         if jobs.is_empty() {
@@ -428,7 +849,7 @@ async fn poll_for_jobs(
                 "No jobs discovered during this poll"
             );
         } else {
-            for job in &jobs {
+            for (job, _) in &jobs {
                 let block_number = job
                     .metadata()
                     .get(extract::BlockNumber::METADATA_KEY)
@@ -451,6 +872,10 @@ async fn poll_for_jobs(
             jobs,
             last_block,
             last_log_index,
+            last_block_hash: tracker.hash(last_block),
+            caught_up: to_block >= safe_head,
+            tracker,
+            subscriber: None,
         });
     }
 }
@@ -689,6 +1114,154 @@ fn job_submitted_to_call(
     JobCall::from_parts(parts, event.inputs.into())
 }
 
+/// Identifies the contents of a job call, to tell whether a reorg changed it
+fn call_fingerprint(event: &JobSubmittedEvent) -> B256 {
+    let mut preimage = Vec::with_capacity(8 + 1 + 20 + event.inputs.len());
+    preimage.extend_from_slice(&event.service_id.to_be_bytes());
+    preimage.push(event.job_index);
+    preimage.extend_from_slice(event.caller.as_slice());
+    preimage.extend_from_slice(&event.inputs);
+    keccak256(preimage)
+}
+
+/// Blocks read and job calls produced within [`REORG_TRACKING_DEPTH`] blocks of the last poll
+#[derive(Debug, Default)]
+struct ReorgTracker {
+    /// Hashes of the blocks that were read, by number
+    hashes: BTreeMap<u64, B256>,
+    /// Produced job calls by service and call ID, with the block they were in and their fingerprint
+    emitted: BTreeMap<(u64, u64), (u64, B256)>,
+}
+
+impl ReorgTracker {
+    fn record_block(&mut self, number: u64, hash: B256) {
+        self.hashes.insert(number, hash);
+    }
+
+    fn record_call(&mut self, call: (u64, u64), block: u64, fingerprint: B256) {
+        self.emitted.insert(call, (block, fingerprint));
+    }
+
+    fn hash(&self, number: u64) -> Option<B256> {
+        self.hashes.get(&number).copied()
+    }
+
+    /// The most recent block that was read
+    fn latest(&self) -> Option<(u64, B256)> {
+        self.hashes.last_key_value().map(|(n, h)| (*n, *h))
+    }
+
+    /// Whether `call_id` of `service_id` wasn't produced yet, or was produced with different
+    /// contents
+    fn is_new(&self, service_id: u64, call_id: u64, fingerprint: B256) -> bool {
+        self.emitted
+            .get(&(service_id, call_id))
+            .is_none_or(|(_, emitted)| *emitted != fingerprint)
+    }
+
+    /// Forget every block after `fork`
+    ///
+    /// Produced calls are kept, so calls that made it to the new fork unchanged aren't produced
+    /// again.
+    fn rewind(&mut self, fork: u64) {
+        let _ = self.hashes.split_off(&(fork + 1));
+    }
+
+    /// Forget everything older than [`REORG_TRACKING_DEPTH`] blocks before `head`
+    fn prune(&mut self, head: u64) {
+        let oldest = head.saturating_sub(REORG_TRACKING_DEPTH);
+        self.hashes = self.hashes.split_off(&oldest);
+        self.emitted.retain(|_, (block, _)| *block >= oldest);
+    }
+}
+
+/// Find the last block read by `tracker` that is still canonical, if any block it read isn't
+///
+/// Returns `None` if the most recent block that was read is still canonical. If none of the
+/// tracked blocks are, the block before the oldest one is assumed to be the fork point.
+///
+/// On failure, returns the block that couldn't be fetched along with the error.
+async fn find_fork(
+    client: &TangleClient,
+    tracker: &ReorgTracker,
+) -> Result<Option<u64>, (u64, blueprint_client_tangle::Error)> {
+    let mut newest = true;
+    for (&number, &hash) in tracker.hashes.iter().rev() {
+        let canonical = canonical_hash(client, number)
+            .await
+            .map_err(|e| (number, e))?;
+        if canonical == Some(hash) {
+            return Ok((!newest).then_some(number));
+        }
+        newest = false;
+    }
+
+    Ok(tracker
+        .hashes
+        .first_key_value()
+        .map(|(oldest, _)| oldest.saturating_sub(1)))
+}
+
+async fn canonical_hash(
+    client: &TangleClient,
+    number: u64,
+) -> blueprint_client_tangle::Result<Option<B256>> {
+    let block = client.get_block(BlockNumberOrTag::Number(number)).await?;
+    Ok(block.map(|block| block.header.hash))
+}
+
+/// Log a failed block fetch, and wait before it is retried
+async fn block_fetch_backoff(
+    failures: &mut u32,
+    block: u64,
+    err: &(dyn core::fmt::Display + Sync),
+) {
+    *failures += 1;
+    let delay = rpc_retry_delay(*failures);
+    if *failures >= RPC_ERROR_ESCALATION_ATTEMPTS {
+        blueprint_core::error!(
+            target: "tangle-producer",
+            rpc = "eth_getBlockByNumber",
+            attempts = *failures,
+            block,
+            delay_ms = delay.as_millis() as u64,
+            "Failed to read block hash: {err}"
+        );
+    } else {
+        blueprint_core::warn!(
+            target: "tangle-producer",
+            rpc = "eth_getBlockByNumber",
+            attempts = *failures,
+            block,
+            delay_ms = delay.as_millis() as u64,
+            "Failed to read block hash: {err}; retrying"
+        );
+    }
+    sleep(delay).await;
+}
+
+/// Log a failure to read which services the operator is part of, and wait before it is retried
+async fn membership_backoff(failures: &mut u32, err: &(dyn core::fmt::Display + Sync)) {
+    *failures += 1;
+    let delay = rpc_retry_delay(*failures);
+    if *failures >= RPC_ERROR_ESCALATION_ATTEMPTS {
+        blueprint_core::error!(
+            target: "tangle-producer",
+            attempts = *failures,
+            delay_ms = delay.as_millis() as u64,
+            "Failed to read the operator's services: {err}"
+        );
+    } else {
+        blueprint_core::warn!(
+            target: "tangle-producer",
+            attempts = *failures,
+            delay_ms = delay.as_millis() as u64,
+            "Failed to read the operator's services: {err}; retrying"
+        );
+    }
+    sleep(delay).await;
+}
+
 fn rpc_retry_delay(attempt: u32) -> Duration {
     let capped = attempt
         .max(1)
@@ -993,6 +1566,99 @@ mod tests {
         assert_eq!(call_id, 7);
     }
 
+    // ── Checkpoint catch-up window ──────────────────────────────────────
+
+    #[test]
+    fn test_catch_up_replays_everything_by_default() {
+        assert_eq!(catch_up_start(100, Some(4), 5_000, None), (100, Some(4)));
+    }
+
+    #[test]
+    fn test_catch_up_within_window_keeps_position() {
+        assert_eq!(
+            catch_up_start(950, Some(4), 1_000, Some(100)),
+            (950, Some(4))
+        );
+    }
+
+    #[test]
+    fn test_catch_up_skips_blocks_beyond_window() {
+        assert_eq!(catch_up_start(100, Some(4), 1_000, Some(100)), (900, None));
+    }
+
+    // ── Reorg tracking ──────────────────────────────────────────────────
+
+    fn event(call_id: u64, inputs: &[u8]) -> JobSubmittedEvent {
+        JobSubmittedEvent {
+            service_id: 1,
+            call_id,
+            job_index: 0,
+            caller: Address::repeat_byte(0x01),
+            inputs: inputs.to_vec(),
+            block_number: 100,
+            block_hash: B256::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_reorg_tracker_skips_unchanged_calls() {
+        let mut tracker = ReorgTracker::default();
+        let original = call_fingerprint(&event(7, &[1, 2, 3]));
+        assert!(tracker.is_new(1, 7, original));
+
+        tracker.record_call((1, 7), 100, original);
+        assert!(!tracker.is_new(1, 7, call_fingerprint(&event(7, &[1, 2, 3]))));
+        assert!(tracker.is_new(1, 7, call_fingerprint(&event(7, &[4]))));
+        assert!(tracker.is_new(1, 8, original));
+    }
+
+    #[test]
+    fn test_reorg_tracker_separates_services() {
+        let mut tracker = ReorgTracker::default();
+        let first = event(7, &[1, 2, 3]);
+        let second = JobSubmittedEvent {
+            service_id: 2,
+            ..event(7, &[1, 2, 3])
+        };
+        assert_ne!(call_fingerprint(&first), call_fingerprint(&second));
+
+        tracker.record_call((1, 7), 100, call_fingerprint(&first));
+        assert!(tracker.is_new(2, 7, call_fingerprint(&second)));
+        tracker.record_call((2, 7), 100, call_fingerprint(&second));
+        assert!(!tracker.is_new(1, 7, call_fingerprint(&first)));
+        assert!(!tracker.is_new(2, 7, call_fingerprint(&second)));
+    }
+
+    #[test]
+    fn test_reorg_tracker_rewind_keeps_produced_calls() {
+        let mut tracker = ReorgTracker::default();
+        for number in 100..=105 {
+            tracker.record_block(number, B256::repeat_byte(number as u8));
+        }
+        let fingerprint = call_fingerprint(&event(7, &[]));
+        tracker.record_call((1, 7), 104, fingerprint);
+
+        tracker.rewind(102);
+        assert_eq!(tracker.latest(), Some((102, B256::repeat_byte(102))));
+        assert_eq!(tracker.hash(104), None);
+        assert!(!tracker.is_new(1, 7, fingerprint));
+    }
+
+    #[test]
+    fn test_reorg_tracker_prunes_old_blocks() {
+        let mut tracker = ReorgTracker::default();
+        tracker.record_block(10, B256::repeat_byte(1));
+        tracker.record_block(200, B256::repeat_byte(2));
+        tracker.record_call((1, 1), 10, B256::ZERO);
+        tracker.record_call((1, 2), 200, B256::ZERO);
+
+        tracker.prune(200);
+        assert_eq!(tracker.hash(10), None);
+        assert_eq!(tracker.hash(200), Some(B256::repeat_byte(2)));
+        assert!(tracker.is_new(1, 1, B256::ZERO));
+        assert!(!tracker.is_new(1, 2, B256::ZERO));
+    }
+
     // ── Existing decoder (decode_job_submitted) edge cases ──────────────
 
     #[test]
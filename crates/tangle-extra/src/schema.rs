@@ -0,0 +1,511 @@
+//! Job Schemas
+//!
+//! Derives the params and result schemas of a job's `definition.json` entry from the types that
+//! [`TangleArg`] decodes and [`TangleResult`] encodes, so the two can't drift apart.
+//!
+//! The schemas are normally generated by the `#[job_schema]` attribute from `blueprint-macros`,
+//! collected into [`JobDefinitions`], and written from the blueprint binary's `build.rs`:
+//!
+//! ```rust,ignore
+//! // build.rs
+//! use blueprint_sdk::tangle::schema::JobDefinitions;
+//!
+//! fn main() {
+//!     let definitions = JobDefinitions::new()
+//!         .job(SQUARE_JOB_ID, square_schema().unwrap())
+//!         .job(MULTIPLY_JOB_ID, multiply_schema().unwrap());
+//!
+//!     // Fail the build if a routed job has no definition
+//!     if let Err(e) = definitions.check(my_blueprint_lib::router().job_ids()) {
+//!         panic!("{e}");
+//!     }
+//!
+//!     definitions.write("../jobs.json").unwrap();
+//! }
+//! ```
+//!
+//! `definition.json` then points at the generated list with `"jobs_file": "jobs.json"`, and
+//! `cargo tangle blueprint deploy` builds the blueprint to regenerate it, then uses it as the
+//! definition's job list. Deploying fails if the file is still missing.
+//!
+//! ## Mapping
+//!
+//! * A tuple argument, `TangleArg<(A, B)>`, is one param per element, named after the tuple
+//!   pattern it's destructured with
+//! * Any other argument, including a `sol!` struct, is a single param
+//! * Results follow the same rules, and are never named
+//!
+//! Structs are only supported at the top level of an argument or result, or as fields of other
+//! structs. A struct nested inside a tuple or array (e.g. `TangleArg<(u64, MyStruct)>`) has no name
+//! in its Solidity type, and produces a [`SchemaError::UnsupportedType`].
+//!
+//! [`TangleArg`]: crate::extract::TangleArg
+//! [`TangleResult`]: crate::extract::TangleResult
+
+use alloy_json_abi::InternalType;
+use blueprint_core::JobId;
+use blueprint_std::collections::BTreeMap;
+use blueprint_std::format;
+use blueprint_std::path::Path;
+use blueprint_std::string::{String, ToString};
+use blueprint_std::vec::Vec;
+
+pub use alloy_json_abi::Param;
+
+/// Error type for schema generation
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    /// The type can't be described as a job param
+    #[error("`{ty}` cannot be used in a job schema: {reason}")]
+    UnsupportedType {
+        /// The Solidity type
+        ty: String,
+        /// Why the type is unsupported
+        reason: &'static str,
+    },
+    /// A job is routed, but has no definition
+    #[error("job {0} is routed but has no job definition")]
+    MissingDefinition(JobId),
+    /// The definitions don't cover every job index up to the highest one
+    #[error(
+        "job definitions must cover every index from 0 to {last}, but job {missing} is missing"
+    )]
+    Gap {
+        /// The highest defined job index
+        last: u8,
+        /// The first missing index
+        missing: u8,
+    },
+    /// Failed to write the definitions
+    #[error("failed to write job definitions: {0}")]
+    Io(#[from] std::io::Error),
+    /// Failed to serialize the definitions
+    #[error("failed to serialize job definitions: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The schema of a single job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobSchema {
+    /// The job's name
+    pub name: String,
+    /// A human-readable description
+    pub description: String,
+    /// The job's params, in order
+    pub params: Vec<Param>,
+    /// The job's result
+    pub result: Vec<Param>,
+}
+
+/// The job list of a blueprint definition
+///
+/// See the [module docs](self).
+#[must_use]
+#[derive(Debug, Clone, Default)]
+pub struct JobDefinitions {
+    jobs: BTreeMap<u8, JobSchema>,
+}
+
+impl JobDefinitions {
+    /// Create an empty set of definitions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define the job at `job_index`, replacing any previous definition
+    pub fn job(mut self, job_index: u8, schema: JobSchema) -> Self {
+        self.jobs.insert(job_index, schema);
+        self
+    }
+
+    /// Check that every job ID in `job_ids` has a definition
+    ///
+    /// This is meant to be given `Router::job_ids()`, to catch jobs that were added to the router
+    /// but not to the definition.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchemaError::MissingDefinition`] for the first job ID without a definition.
+    pub fn check<I>(&self, job_ids: I) -> Result<(), SchemaError>
+    where
+        I: IntoIterator<Item = JobId>,
+    {
+        for job_id in job_ids {
+            let defined = self
+                .jobs
+                .keys()
+                .any(|&job_index| JobId::from(job_index) == job_id);
+            if !defined {
+                return Err(SchemaError::MissingDefinition(job_id));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the definitions as the `jobs` list of a `definition.json`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchemaError::Gap`] if the job indices aren't contiguous from 0, since a job's
+    /// index in the list is its job ID on-chain.
+    pub fn to_json(&self) -> Result<String, SchemaError> {
+        let mut jobs = Vec::with_capacity(self.jobs.len());
+        for (expected, (&job_index, schema)) in (0..=u8::MAX).zip(&self.jobs) {
+            if job_index != expected {
+                return Err(SchemaError::Gap {
+                    last: self.jobs.last_key_value().map_or(0, |(&last, _)| last),
+                    missing: expected,
+                });
+            }
+
+            jobs.push(serde_json::json!({
+                "name": schema.name,
+                "description": schema.description,
+                "params_schema": serde_json::to_string(&schema.params)?,
+                "result_schema": serde_json::to_string(&schema.result)?,
+            }));
+        }
+
+        Ok(serde_json::to_string_pretty(&jobs)?)
+    }
+
+    /// Write the definitions to `path`
+    ///
+    /// The file is only rewritten if its contents changed, to avoid needlessly triggering
+    /// rebuilds.
+    ///
+    /// # Errors
+    ///
+    /// See [`JobDefinitions::to_json()`]. Also returns [`SchemaError::Io`] if the file can't be
+    /// written.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SchemaError> {
+        let path = path.as_ref();
+        let json = self.to_json()?;
+        if std::fs::read_to_string(path).is_ok_and(|existing| existing == json) {
+            return Ok(());
+        }
+
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// Parse the `encodeType` of an EIP-712 struct into a `tuple` param
+///
+/// `encode_type` is the root struct followed by every struct it references, e.g.
+/// `Outer(Inner a,uint64 b)Inner(address c)`.
+fn struct_param(encode_type: &str) -> Result<Param, SchemaError> {
+    let mut structs = BTreeMap::new();
+    let mut root = None;
+    for def in encode_type.split_terminator(')') {
+        let (name, fields) = def
+            .split_once('(')
+            .ok_or_else(|| unsupported(encode_type))?;
+        let fields = fields
+            .split_terminator(',')
+            .map(|field| {
+                field
+                    .rsplit_once(' ')
+                    .ok_or_else(|| unsupported(encode_type))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        root.get_or_insert(name);
+        structs.insert(name, fields);
+    }
+
+    let root = root.ok_or_else(|| unsupported(encode_type))?;
+    resolve_struct(root, "", "tuple", &structs)
+}
+
+fn resolve_struct(
+    struct_name: &str,
+    name: &str,
+    ty: &str,
+    structs: &BTreeMap<&str, Vec<(&str, &str)>>,
+) -> Result<Param, SchemaError> {
+    let mut components = Vec::new();
+    for &(field_ty, field_name) in &structs[struct_name] {
+        let (base, array) = split_array(field_ty);
+        let component = if structs.contains_key(base) {
+            resolve_struct(base, field_name, &format!("tuple{array}"), structs)?
+        } else {
+            let param = Param::parse(&format!("{field_ty} {field_name}"))
+                .map_err(|_| unsupported(field_ty))?;
+            validate(&param)?;
+            param
+        };
+        components.push(component);
+    }
+
+    Ok(Param {
+        ty: ty.to_string(),
+        name: name.to_string(),
+        components,
+        internal_type: Some(InternalType::Struct {
+            contract: None,
+            ty: format!("{struct_name}{}", split_array(ty).1),
+        }),
+    })
+}
+
+/// Parse a (non-struct) Solidity type name, e.g. `(uint64,string)[]`
+fn value_param(sol_name: &str) -> Result<Param, SchemaError> {
+    if sol_name == "()" {
+        return Ok(Param {
+            ty: "tuple".to_string(),
+            name: String::new(),
+            components: Vec::new(),
+            internal_type: None,
+        });
+    }
+
+    let param = Param::parse(sol_name).map_err(|_| unsupported(sol_name))?;
+    validate(&param)?;
+    Ok(param)
+}
+
+/// Reject types that only parse because they look like a user-defined type, which is how a
+/// struct nested in a tuple or array shows up
+fn validate(param: &Param) -> Result<(), SchemaError> {
+    let (base, _) = split_array(&param.ty);
+    if base == "tuple" {
+        return param.components.iter().try_for_each(validate);
+    }
+
+    if is_elementary(base) {
+        Ok(())
+    } else {
+        Err(SchemaError::UnsupportedType {
+            ty: param.ty.clone(),
+            reason: "structs nested in tuples or arrays are not supported, use a struct field instead",
+        })
+    }
+}
+
+fn is_elementary(ty: &str) -> bool {
+    match ty {
+        "address" | "bool" | "string" | "bytes" | "function" => true,
+        _ => {
+            if let Some(bytes) = ty.strip_prefix("bytes") {
+                return bytes.parse::<u8>().is_ok_and(|n| (1..=32).contains(&n));
+            }
+
+            let bits = ty.strip_prefix("uint").or_else(|| ty.strip_prefix("int"));
+            bits.and_then(|bits| bits.parse::<u16>().ok())
+                .is_some_and(|n| n % 8 == 0 && (8..=256).contains(&n))
+        }
+    }
+}
+
+/// Split `ty` into its base type and array suffix, e.g. `uint8[2][]` -> (`uint8`, `[2][]`)
+fn split_array(ty: &str) -> (&str, &str) {
+    ty.split_at(ty.find('[').unwrap_or(ty.len()))
+}
+
+fn unsupported(ty: &str) -> SchemaError {
+    SchemaError::UnsupportedType {
+        ty: ty.to_string(),
+        reason: "not a valid Solidity type",
+    }
+}
+
+/// Whether `param` is a plain tuple, rather than a struct or a single value
+fn is_tuple(param: &Param) -> bool {
+    param.ty == "tuple" && param.internal_type.is_none()
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Support code for the `#[job_schema]` macro. Not public API.
+
+    use super::{Param, SchemaError, is_tuple, struct_param, value_param};
+    use alloy_sol_types::{SolStruct, SolType, SolValue};
+    use blueprint_std::string::ToString;
+    use blueprint_std::vec::Vec;
+    use core::marker::PhantomData;
+
+    /// Resolves to [`StructSchema`] for `sol!` structs, and [`ValueSchema`] for everything else,
+    /// when called as `(&&SchemaOf::<T>::new()).schema()`
+    pub struct SchemaOf<T>(PhantomData<T>);
+
+    impl<T> SchemaOf<T> {
+        #[must_use]
+        pub const fn new() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    impl<T> Default for SchemaOf<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    pub trait StructSchema {
+        fn schema(&self) -> Result<Param, SchemaError>;
+    }
+
+    impl<T: SolStruct> StructSchema for &SchemaOf<T> {
+        fn schema(&self) -> Result<Param, SchemaError> {
+            struct_param(&T::eip712_encode_type())
+        }
+    }
+
+    pub trait ValueSchema {
+        fn schema(&self) -> Result<Param, SchemaError>;
+    }
+
+    impl<T: SolValue> ValueSchema for SchemaOf<T> {
+        fn schema(&self) -> Result<Param, SchemaError> {
+            value_param(<T::SolType as SolType>::SOL_NAME)
+        }
+    }
+
+    /// The params of a `TangleArg`, named after the bindings in its pattern
+    #[must_use]
+    pub fn params(param: Param, names: &[&str]) -> Vec<Param> {
+        let mut params = if is_tuple(&param) {
+            param.components
+        } else {
+            alloc::vec![param]
+        };
+
+        if names.len() == params.len() {
+            for (param, name) in params.iter_mut().zip(names) {
+                param.name = name.to_string();
+            }
+        }
+
+        params
+    }
+
+    /// The result of a `TangleResult`
+    #[must_use]
+    pub fn result(param: Param) -> Vec<Param> {
+        params(param, &[])
+    }
+}
+
+#[cfg(test)]
+// `(&&SchemaOf::<T>::new())` mirrors the `#[job_schema]` expansion, the borrows pick the impl
+#[allow(clippy::needless_borrow)]
+mod tests {
+    use super::__private::{SchemaOf, StructSchema as _, ValueSchema as _, params, result};
+    use super::*;
+    use alloy_primitives::{Address, U256};
+    use alloy_sol_types::sol;
+
+    sol! {
+        struct Inner {
+            address owner;
+            bool active;
+        }
+
+        struct Request {
+            string doc_id;
+            uint64[] sizes;
+            Inner inner;
+            Inner[] history;
+        }
+    }
+
+    fn schema(params: Vec<Param>) -> JobSchema {
+        JobSchema {
+            name: "job".into(),
+            description: String::new(),
+            params,
+            result: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn tuple_args_are_flattened_and_named() {
+        let param = (&&SchemaOf::<(u64, String, Vec<Address>)>::new())
+            .schema()
+            .unwrap();
+        let params = params(param, &["a", "b", "c"]);
+
+        let types = params.iter().map(|p| p.ty.as_str()).collect::<Vec<_>>();
+        let names = params.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(types, ["uint64", "string", "address[]"]);
+        assert_eq!(names, ["a", "b", "c"]);
+
+        let unit = (&&SchemaOf::<()>::new()).schema().unwrap();
+        assert!(result(unit).is_empty());
+    }
+
+    #[test]
+    fn structs_keep_field_names() {
+        let param = (&&SchemaOf::<Request>::new()).schema().unwrap();
+        let params = params(param, &["request"]);
+        assert_eq!(params.len(), 1);
+
+        let request = &params[0];
+        assert_eq!(request.name, "request");
+        assert_eq!(request.ty, "tuple");
+        let fields = request
+            .components
+            .iter()
+            .map(|p| (p.name.as_str(), p.ty.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("doc_id", "string"),
+                ("sizes", "uint64[]"),
+                ("inner", "tuple"),
+                ("history", "tuple[]"),
+            ]
+        );
+        assert_eq!(request.components[3].components.len(), 2);
+        assert_eq!(
+            request.components[3].internal_type,
+            Some(InternalType::Struct {
+                contract: None,
+                ty: "Inner[]".into()
+            })
+        );
+    }
+
+    #[test]
+    fn nested_structs_in_tuples_are_rejected() {
+        let err = (&&SchemaOf::<(U256, Inner)>::new()).schema().unwrap_err();
+        assert!(matches!(err, SchemaError::UnsupportedType { .. }));
+    }
+
+    #[test]
+    fn definitions_must_cover_routed_jobs() {
+        let definitions = JobDefinitions::new()
+            .job(0, schema(Vec::new()))
+            .job(1, schema(Vec::new()));
+
+        definitions
+            .check([JobId::from(0u8), JobId::from(1u8)])
+            .unwrap();
+        let err = definitions.check([JobId::from(2u8)]).unwrap_err();
+        assert!(matches!(err, SchemaError::MissingDefinition(id) if id == JobId::from(2u8)));
+    }
+
+    #[test]
+    fn definitions_serialize_as_job_specs() {
+        let param = (&&SchemaOf::<(u64, u64)>::new()).schema().unwrap();
+        let definitions = JobDefinitions::new().job(0, schema(params(param, &["x", "y"])));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&definitions.to_json().unwrap()).unwrap();
+        let params_schema = json[0]["params_schema"].as_str().unwrap();
+        let parsed: Vec<Param> = serde_json::from_str(params_schema).unwrap();
+        assert_eq!(parsed[1].name, "y");
+        assert_eq!(json[0]["result_schema"], "[]");
+
+        let gap = JobDefinitions::new().job(1, schema(vec![]));
+        assert!(matches!(
+            gap.to_json(),
+            Err(SchemaError::Gap {
+                last: 1,
+                missing: 0
+            })
+        ));
+    }
+}
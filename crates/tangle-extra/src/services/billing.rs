@@ -211,31 +211,24 @@ async fn check_and_bill(
     );
 
     // Bill in batches
-    let provider = config.get_provider().await?;
-    let tangle = ITangle::new(tangle_address, provider);
+    let tx = config.get_tx_manager().await?;
     let mut any_billed = false;
 
     for chunk in billable_ids.chunks(config.billing_max_batch_size) {
         let batch: Vec<u64> = chunk.to_vec();
-        match tangle.billSubscriptionBatch(batch.clone()).send().await {
-            Ok(pending) => match pending.get_receipt().await {
-                Ok(receipt) => {
-                    info!(
-                        "[{}] Billed {} services, tx: {:?}",
-                        SubscriptionBillingKeeper::NAME,
-                        batch.len(),
-                        receipt.transaction_hash
-                    );
-                    any_billed = true;
-                }
-                Err(e) => {
-                    warn!(
-                        "[{}] Failed to get billing receipt: {}",
-                        SubscriptionBillingKeeper::NAME,
-                        e
-                    );
-                }
-            },
+        let request = tangle_read
+            .billSubscriptionBatch(batch.clone())
+            .into_transaction_request();
+        match tx.send(request).await {
+            Ok(receipt) => {
+                info!(
+                    "[{}] Billed {} services, tx: {:?}",
+                    SubscriptionBillingKeeper::NAME,
+                    batch.len(),
+                    receipt.transaction_hash
+                );
+                any_billed = true;
+            }
             Err(e) => {
                 // Race condition: another operator may have billed first. This is harmless.
                 warn!(
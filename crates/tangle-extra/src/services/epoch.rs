@@ -124,20 +124,14 @@ impl BackgroundKeeper for EpochKeeper {
         );
 
         // Submit distribution transaction
-        let provider = config.get_provider().await?;
-        let pool = IInflationPool::new(inflation_pool, provider);
-
-        let receipt = pool
-            .distributeEpoch()
-            .send()
+        let request = pool.distributeEpoch().into_transaction_request();
+        let receipt = config
+            .get_tx_manager()
+            .await?
+            .send(request)
             .await
             .map_err(|e| {
                 KeeperError::Transaction(format!("Failed to send distributeEpoch: {}", e))
-            })?
-            .get_receipt()
-            .await
-            .map_err(|e| {
-                KeeperError::Transaction(format!("Failed to get distributeEpoch receipt: {}", e))
             })?;
 
         info!(
@@ -3,6 +3,7 @@
 //! Provides reusable components for building keepers that monitor and trigger
 //! lifecycle operations on Tangle v2 contracts.
 
+use crate::tx_manager::{TxManager, TxManagerConfig};
 use alloy::network::EthereumWallet;
 use alloy::primitives::Address;
 use alloy::providers::ProviderBuilder;
@@ -99,6 +100,10 @@ pub struct KeeperConfig {
 
     /// Maximum number of services to bill in a single batch (default: 50)
     pub billing_max_batch_size: usize,
+
+    /// Transaction manager to send transactions through (optional, shared with the consumers
+    /// sending from the same account if unset)
+    pub tx_manager: Option<TxManager>,
 }
 
 impl KeeperConfig {
@@ -118,6 +123,7 @@ impl KeeperConfig {
             billing_check_interval: Duration::from_secs(60), // 1 minute
             billing_rescan_interval: Duration::from_secs(300), // 5 minutes
             billing_max_batch_size: 50,
+            tx_manager: None,
         }
     }
 
@@ -187,6 +193,15 @@ impl KeeperConfig {
         self
     }
 
+    /// Send transactions through `tx`
+    ///
+    /// Share the [`TxManager`] of the consumer, so their nonces don't collide.
+    #[must_use]
+    pub fn with_tx_manager(mut self, tx: TxManager) -> Self {
+        self.tx_manager = Some(tx);
+        self
+    }
+
     /// Get the signer from the keystore
     pub fn get_signer(&self) -> KeeperResult<PrivateKeySigner> {
         use blueprint_crypto::BytesEncoding;
@@ -230,6 +245,24 @@ impl KeeperConfig {
             .map_err(|e| KeeperError::Provider(e.to_string()))
     }
 
+    /// Get the transaction manager
+    ///
+    /// Unless one was configured, this is the manager shared by everything sending from the
+    /// operator's account through `http_rpc_endpoint`, created on first use.
+    pub async fn get_tx_manager(&self) -> KeeperResult<TxManager> {
+        if let Some(tx) = &self.tx_manager {
+            return Ok(tx.clone());
+        }
+
+        let signer = self.get_signer()?;
+        TxManager::shared(&self.http_rpc_endpoint, signer.address(), || {
+            let wallet = EthereumWallet::from(signer);
+            TxManager::connect(&self.http_rpc_endpoint, wallet, TxManagerConfig::default())
+        })
+        .await
+        .map_err(|e| KeeperError::Provider(e.to_string()))
+    }
+
     /// Create a read-only provider (no wallet needed)
     pub async fn get_read_provider(&self) -> KeeperResult<impl alloy::providers::Provider + Clone> {
         ProviderBuilder::new()
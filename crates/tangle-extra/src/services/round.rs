@@ -137,19 +137,13 @@ impl BackgroundKeeper for RoundKeeper {
         );
 
         // Submit advance round transaction
-        let provider = config.get_provider().await?;
-        let contract = IMultiAssetDelegationRounds::new(mad_address, provider);
-
-        let receipt = contract
-            .advanceRound()
-            .send()
-            .await
-            .map_err(|e| KeeperError::Transaction(format!("Failed to send advanceRound: {}", e)))?
-            .get_receipt()
+        let request = contract.advanceRound().into_transaction_request();
+        let receipt = config
+            .get_tx_manager()
+            .await?
+            .send(request)
             .await
-            .map_err(|e| {
-                KeeperError::Transaction(format!("Failed to get advanceRound receipt: {}", e))
-            })?;
+            .map_err(|e| KeeperError::Transaction(format!("Failed to send advanceRound: {}", e)))?;
 
         info!(
             "[{}] Advanced to round {}, tx: {:?}",
@@ -109,6 +109,9 @@ pub struct HttpServiceConfig {
     pub threshold_timeout: Duration,
     /// Poll interval when waiting for threshold
     pub poll_interval: Duration,
+    /// Whether to wait for threshold on the service's task event stream, re-checking
+    /// every `poll_interval`
+    pub stream_events: bool,
 }
 
 #[cfg(feature = "aggregation")]
@@ -130,6 +133,7 @@ impl HttpServiceConfig {
             wait_for_threshold: false,
             threshold_timeout: Duration::from_secs(60),
             poll_interval: Duration::from_secs(1),
+            stream_events: false,
         }
     }
 
@@ -153,6 +157,27 @@ impl HttpServiceConfig {
         self.poll_interval = interval;
         self
     }
+
+    /// Set whether to wait for threshold on the service's task event stream
+    ///
+    /// The service must expose task events, see
+    /// [`AggregationServiceClient::wait_for_threshold_streaming()`].
+    ///
+    /// [`AggregationServiceClient::wait_for_threshold_streaming()`]: blueprint_tangle_aggregation_svc::AggregationServiceClient::wait_for_threshold_streaming
+    #[must_use]
+    pub fn with_event_stream(mut self, stream: bool) -> Self {
+        self.stream_events = stream;
+        self
+    }
+
+    /// Sign submissions with the operator's ECDSA key
+    ///
+    /// Services requiring authenticated submissions reject unsigned ones.
+    #[must_use]
+    pub fn with_envelope_signer(mut self, signer: alloy_signer_local::PrivateKeySigner) -> Self {
+        self.client = self.client.with_envelope_signer(signer);
+        self
+    }
 }
 
 /// Configuration for P2P gossip-based aggregation
@@ -466,16 +491,28 @@ async fn aggregate_via_http(
 
     // Wait for threshold if configured
     let result = if config.wait_for_threshold {
-        match config
-            .client
-            .wait_for_threshold_or_submitted(
-                service_id,
-                call_id,
-                config.poll_interval,
-                config.threshold_timeout,
-            )
-            .await?
-        {
+        let wait = if config.stream_events {
+            config
+                .client
+                .wait_for_threshold_streaming(
+                    service_id,
+                    call_id,
+                    config.poll_interval,
+                    config.threshold_timeout,
+                )
+                .await
+        } else {
+            config
+                .client
+                .wait_for_threshold_or_submitted(
+                    service_id,
+                    call_id,
+                    config.poll_interval,
+                    config.threshold_timeout,
+                )
+                .await
+        };
+        match wait? {
             ThresholdWaitResult::Aggregated(result) => result,
             ThresholdWaitResult::Submitted => return Err(StrategyError::AlreadySubmitted),
         }
@@ -0,0 +1,1126 @@
+//! Transaction Manager
+//!
+//! Sends transactions from the operator's account and sees them through to inclusion.
+//!
+//! The [`TangleConsumer`], the [`AggregatingConsumer`] and the keepers all send transactions from
+//! the same account. They should share a single [`TxManager`], so that their nonces don't collide:
+//!
+//! * **Nonces** are allocated locally. The account's pending nonce is only fetched on first use,
+//!   and again whenever the node reports that a nonce was already used.
+//! * **Fees** follow EIP-1559. A transaction still pending after the
+//!   [bump interval](TxManagerConfig::bump_interval) is replaced with one paying
+//!   [`bump_percent`](TxManagerConfig::bump_percent) more, up to an optional cap.
+//! * **Rebroadcasts**: once fees can't be raised any further, the last transaction is rebroadcast
+//!   on the same schedule, in case it was dropped from the mempool. Nonces below a pending
+//!   transaction that the node has forgotten about are filled with cancellations, so that a
+//!   dropped transaction can't hold back the ones after it.
+//! * **Cancellation**: a transaction still pending after
+//!   [`cancel_after`](TxManagerConfig::cancel_after) is replaced with an empty transfer from the
+//!   account to itself, which frees up its nonce.
+//!
+//! Unless one is provided, the consumers and keepers use the same manager for the same account
+//! and RPC endpoint, created on first use with the default [`TxManagerConfig`]. A manager with a
+//! custom configuration should be built once, and injected everywhere:
+//!
+//! ```rust,ignore
+//! use blueprint_tangle_extra::{TangleConsumer, TxManager, TxManagerConfig};
+//!
+//! let tx = TxManager::from_client(&client, TxManagerConfig::default().with_max_bumps(5))?;
+//! let consumer = TangleConsumer::new(client).with_tx_manager(tx.clone());
+//! let keepers = KeeperConfig::new(http_rpc, keystore).with_tx_manager(tx);
+//! ```
+//!
+//! [`TangleConsumer`]: crate::TangleConsumer
+//! [`AggregatingConsumer`]: crate::AggregatingConsumer
+
+use alloy_network::{Ethereum, EthereumWallet, Network, NetworkWallet, TransactionBuilder};
+use alloy_primitives::{Address, B256, Bytes, TxKind, U256};
+use alloy_provider::{DynProvider, Provider, ProviderBuilder};
+use alloy_rpc_types::{TransactionReceipt, TransactionRequest};
+use alloy_sol_types::SolCall;
+use blueprint_client_tangle::TangleClient;
+use blueprint_client_tangle::contracts::ITangle;
+use blueprint_std::collections::{BTreeMap, BTreeSet};
+use blueprint_std::string::{String, ToString};
+use blueprint_std::sync::PoisonError;
+use blueprint_std::sync::{Arc, LazyLock, Weak};
+use blueprint_std::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::time::Duration;
+use tokio::sync::{Mutex, OnceCell};
+use tokio::time::{Instant, sleep};
+
+/// Gas limit floor for result submissions, matching [`TangleClient::submit_result()`]
+const SUBMIT_RESULT_MIN_GAS_LIMIT: u64 = 8_000_000;
+const GAS_BUFFER_NUMERATOR: u64 = 13;
+const GAS_BUFFER_DENOMINATOR: u64 = 10;
+/// Gas limit of a cancellation, a plain transfer
+const CANCEL_GAS_LIMIT: u64 = 21_000;
+/// Nodes only accept a replacement if it raises both fees by at least this much
+const MIN_REPLACEMENT_BUMP_PERCENT: u64 = 10;
+/// How many times a new transaction is re-signed after the node rejects its nonce or fees
+const MAX_BROADCAST_ATTEMPTS: u32 = 5;
+
+/// Managers created on first use, by RPC endpoint and account, see [`TxManager::shared()`]
+static SHARED: LazyLock<blueprint_std::sync::Mutex<BTreeMap<(String, Address), Weak<Inner>>>> =
+    LazyLock::new(Default::default);
+
+/// Errors from the [`TxManager`]
+#[derive(Debug, thiserror::Error)]
+pub enum TxError {
+    /// Client error
+    #[error("Client error: {0}")]
+    Client(#[from] blueprint_client_tangle::Error),
+    /// RPC error
+    #[error("RPC error: {0}")]
+    Rpc(String),
+    /// Gas estimation failed, usually because the transaction would revert
+    #[error("Gas estimation failed: {0}")]
+    Estimate(String),
+    /// Signing error
+    #[error("Failed to sign transaction: {0}")]
+    Signing(String),
+    /// The transaction was included, but reverted
+    #[error("Transaction {tx_hash} reverted on-chain (estimate_gas reported: {reason:?})")]
+    Reverted {
+        /// Hash of the reverted transaction
+        tx_hash: B256,
+        /// Why gas estimation failed, if it did
+        reason: Option<String>,
+    },
+    /// The transaction was cancelled after being pending for too long
+    #[error("Transaction with nonce {nonce} was cancelled by {tx_hash}")]
+    Cancelled {
+        /// Nonce of the cancelled transaction
+        nonce: u64,
+        /// Hash of the cancellation
+        tx_hash: B256,
+    },
+    /// The nonce was used by a transaction that wasn't sent by this manager
+    #[error("Nonce {0} was used by another transaction")]
+    NonceConsumed(u64),
+}
+
+/// Configuration for a [`TxManager`]
+#[derive(Debug, Clone)]
+pub struct TxManagerConfig {
+    /// How often pending transactions are checked for inclusion (default: 1 second)
+    pub poll_interval: Duration,
+    /// How long a transaction may stay pending before it is replaced (default: 30 seconds)
+    pub bump_interval: Duration,
+    /// How much fees are raised by on every replacement, in percent (default: 20, minimum: 10)
+    pub bump_percent: u64,
+    /// The most a transaction may be replaced before it's only rebroadcast (default: 10)
+    pub max_bumps: u32,
+    /// Upper bound for `max_fee_per_gas`, in wei (default: none)
+    pub max_fee_per_gas: Option<u128>,
+    /// Cancel transactions still pending after this long (default: never)
+    pub cancel_after: Option<Duration>,
+}
+
+impl Default for TxManagerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            bump_interval: Duration::from_secs(30),
+            bump_percent: 20,
+            max_bumps: 10,
+            max_fee_per_gas: None,
+            cancel_after: None,
+        }
+    }
+}
+
+impl TxManagerConfig {
+    /// Set how often pending transactions are checked for inclusion
+    #[must_use]
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Set how long a transaction may stay pending before it is replaced
+    #[must_use]
+    pub fn with_bump_interval(mut self, interval: Duration) -> Self {
+        self.bump_interval = interval;
+        self
+    }
+
+    /// Set how much fees are raised by on every replacement, in percent
+    ///
+    /// Nodes reject replacements that raise fees by less than 10%, so lower values are raised
+    /// to that.
+    #[must_use]
+    pub fn with_bump_percent(mut self, percent: u64) -> Self {
+        self.bump_percent = percent.max(MIN_REPLACEMENT_BUMP_PERCENT);
+        self
+    }
+
+    /// Set the most a transaction may be replaced before it's only rebroadcast
+    #[must_use]
+    pub fn with_max_bumps(mut self, bumps: u32) -> Self {
+        self.max_bumps = bumps;
+        self
+    }
+
+    /// Never pay more than `max_fee_per_gas` wei per unit of gas
+    #[must_use]
+    pub fn with_max_fee_per_gas(mut self, max_fee_per_gas: u128) -> Self {
+        self.max_fee_per_gas = Some(max_fee_per_gas);
+        self
+    }
+
+    /// Cancel transactions still pending after `timeout`
+    #[must_use]
+    pub fn with_cancel_after(mut self, timeout: Duration) -> Self {
+        self.cancel_after = Some(timeout);
+        self
+    }
+
+    /// [`bump_percent`](Self::bump_percent), raised to the minimum nodes accept
+    fn replacement_bump_percent(&self) -> u64 {
+        self.bump_percent.max(MIN_REPLACEMENT_BUMP_PERCENT)
+    }
+}
+
+/// Sends transactions from a single account
+///
+/// Cloning a [`TxManager`] is cheap, and clones share their nonces. See the
+/// [module docs](self) for details.
+#[derive(Clone)]
+pub struct TxManager {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    provider: DynProvider,
+    wallet: EthereumWallet,
+    from: Address,
+    config: TxManagerConfig,
+    nonces: Mutex<NonceState>,
+}
+
+#[derive(Debug, Default)]
+struct NonceState {
+    chain_id: Option<u64>,
+    /// `None` until fetched, and whenever it has to be fetched again
+    next: Option<u64>,
+    /// Nonces of transactions sent and not yet included
+    in_flight: BTreeSet<u64>,
+}
+
+impl fmt::Debug for TxManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TxManager")
+            .field("from", &self.inner.from)
+            .field("config", &self.inner.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TxManager {
+    /// Create a manager sending from `wallet`'s default signer through `provider`
+    pub fn new(provider: DynProvider, wallet: EthereumWallet, config: TxManagerConfig) -> Self {
+        let from = NetworkWallet::<Ethereum>::default_signer_address(&wallet);
+        Self {
+            inner: Arc::new(Inner {
+                provider,
+                wallet,
+                from,
+                config,
+                nonces: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Create a manager sending from the client's operator account
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the operator's key is missing from the keystore.
+    pub fn from_client(client: &TangleClient, config: TxManagerConfig) -> Result<Self, TxError> {
+        let wallet = client.wallet()?;
+        Ok(Self::new(
+            DynProvider::clone(client.provider()),
+            wallet,
+            config,
+        ))
+    }
+
+    /// Create a manager sending from `wallet`'s default signer through `http_rpc_endpoint`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the endpoint is invalid.
+    pub async fn connect(
+        http_rpc_endpoint: &str,
+        wallet: EthereumWallet,
+        config: TxManagerConfig,
+    ) -> Result<Self, TxError> {
+        let provider = ProviderBuilder::new()
+            .connect(http_rpc_endpoint)
+            .await
+            .map_err(|e| TxError::Rpc(e.to_string()))?;
+        Ok(Self::new(DynProvider::new(provider), wallet, config))
+    }
+
+    /// The manager sending from `from` through `http_rpc_endpoint`, created with `create` unless
+    /// one is still in use
+    ///
+    /// Everything that isn't given a manager shares one this way, so that their nonces don't
+    /// collide.
+    pub(crate) async fn shared<F, Fut>(
+        http_rpc_endpoint: &str,
+        from: Address,
+        create: F,
+    ) -> Result<Self, TxError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Self, TxError>>,
+    {
+        let key = (http_rpc_endpoint.trim_end_matches('/').to_string(), from);
+        if let Some(inner) = SHARED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+            .and_then(Weak::upgrade)
+        {
+            return Ok(Self { inner });
+        }
+
+        let created = create().await?;
+        let mut shared = SHARED.lock().unwrap_or_else(PoisonError::into_inner);
+        shared.retain(|_, inner| inner.strong_count() > 0);
+        // Another task may have created one in the meantime
+        if let Some(inner) = shared.get(&key).and_then(Weak::upgrade) {
+            return Ok(Self { inner });
+        }
+        shared.insert(key, Arc::downgrade(&created.inner));
+        Ok(created)
+    }
+
+    /// The account transactions are sent from
+    #[must_use]
+    pub fn address(&self) -> Address {
+        self.inner.from
+    }
+
+    /// Forget the locally allocated nonce, fetching the account's pending nonce again
+    pub async fn reset_nonce(&self) {
+        self.inner.nonces.lock().await.next = None;
+    }
+
+    /// Send `request` and wait for it to be included
+    ///
+    /// The nonce, fees and chain ID are filled in, along with the gas limit if it isn't set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if gas estimation fails, if the transaction reverts, or if it was
+    /// cancelled or replaced before being included.
+    pub async fn send(&self, request: TransactionRequest) -> Result<TransactionReceipt, TxError> {
+        self.send_with_fallback_gas(request, None).await
+    }
+
+    /// Submit a job result to the Tangle contract at `tangle`
+    ///
+    /// # Errors
+    ///
+    /// See [`TxManager::send()`]. Unlike it, failing gas estimation doesn't stop the submission,
+    /// which is then sent with a conservative gas limit.
+    pub async fn submit_result(
+        &self,
+        tangle: Address,
+        service_id: u64,
+        call_id: u64,
+        output: Bytes,
+    ) -> Result<TransactionReceipt, TxError> {
+        let call = ITangle::submitResultCall {
+            serviceId: service_id,
+            callId: call_id,
+            result: output,
+        };
+        self.submit_call(tangle, call.abi_encode()).await
+    }
+
+    /// Submit several job results of a service in a single transaction
+    ///
+    /// The batch reverts as a whole if any of its results can't be submitted.
+    ///
+    /// # Errors
+    ///
+    /// See [`TxManager::submit_result()`].
+    pub async fn submit_results(
+        &self,
+        tangle: Address,
+        service_id: u64,
+        results: &[(u64, Bytes)],
+    ) -> Result<TransactionReceipt, TxError> {
+        let call = ITangle::submitResultsCall {
+            serviceId: service_id,
+            callIds: results.iter().map(|(call_id, _)| *call_id).collect(),
+            results: results.iter().map(|(_, output)| output.clone()).collect(),
+        };
+        self.submit_call(tangle, call.abi_encode()).await
+    }
+
+    /// Submit an aggregated BLS signature result to the Tangle contract
+    ///
+    /// # Errors
+    ///
+    /// See [`TxManager::submit_result()`].
+    pub async fn submit_aggregated_result(
+        &self,
+        tangle: Address,
+        service_id: u64,
+        call_id: u64,
+        output: Bytes,
+        signer_bitmap: U256,
+        aggregated_signature: [U256; 2],
+        aggregated_pubkey: [U256; 4],
+    ) -> Result<TransactionReceipt, TxError> {
+        let call = ITangle::submitAggregatedResultCall {
+            serviceId: service_id,
+            callId: call_id,
+            output,
+            signerBitmap: signer_bitmap,
+            aggregatedSignature: aggregated_signature,
+            aggregatedPubkey: aggregated_pubkey,
+        };
+        self.submit_call(tangle, call.abi_encode()).await
+    }
+
+    async fn submit_call(
+        &self,
+        tangle: Address,
+        calldata: Vec<u8>,
+    ) -> Result<TransactionReceipt, TxError> {
+        let request = TransactionRequest::default()
+            .to(tangle)
+            .input(calldata.into());
+        self.send_with_fallback_gas(request, Some(SUBMIT_RESULT_MIN_GAS_LIMIT))
+            .await
+    }
+
+    async fn send_with_fallback_gas(
+        &self,
+        mut request: TransactionRequest,
+        min_gas_limit: Option<u64>,
+    ) -> Result<TransactionReceipt, TxError> {
+        request.from = Some(self.inner.from);
+        request.nonce = None;
+
+        let mut estimate_error = None;
+        if request.gas.is_none() {
+            let estimate = match self.inner.provider.estimate_gas(request.clone()).await {
+                Ok(gas) => Some(gas),
+                Err(e) => {
+                    let Some(min_gas_limit) = min_gas_limit else {
+                        return Err(TxError::Estimate(e.to_string()));
+                    };
+                    blueprint_core::warn!(
+                        target: "tangle-tx-manager",
+                        "eth_estimateGas failed; falling back to min_gas_limit={min_gas_limit}: {e}"
+                    );
+                    estimate_error = Some(e.to_string());
+                    None
+                }
+            };
+            request.gas = Some(buffered_gas_limit(estimate, min_gas_limit.unwrap_or(0)));
+        }
+
+        let fees = self.current_fees().await?;
+        let mut pending = self.broadcast_new(request, fees).await?;
+        let result = self.confirm(&mut pending).await;
+        self.inner
+            .nonces
+            .lock()
+            .await
+            .in_flight
+            .remove(&pending.nonce);
+
+        let receipt = result?;
+        if !receipt.status() {
+            return Err(TxError::Reverted {
+                tx_hash: receipt.transaction_hash,
+                reason: estimate_error,
+            });
+        }
+        Ok(receipt)
+    }
+
+    /// Allocate a nonce for `request`, and broadcast it
+    async fn broadcast_new(
+        &self,
+        request: TransactionRequest,
+        mut fees: Fees,
+    ) -> Result<PendingTx, TxError> {
+        // Held until the transaction is broadcast, so a nonce can be handed out again if it wasn't
+        let mut nonces = self.inner.nonces.lock().await;
+        let chain_id = match nonces.chain_id {
+            Some(chain_id) => chain_id,
+            None => {
+                let chain_id = self.inner.provider.get_chain_id().await.map_err(rpc)?;
+                nonces.chain_id = Some(chain_id);
+                chain_id
+            }
+        };
+
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let nonce = match nonces.next {
+                Some(nonce) => nonce,
+                None => self.account_nonce(true).await.map_err(TxError::Rpc)?,
+            };
+
+            let mut request = request.clone();
+            request.chain_id = Some(chain_id);
+            request.nonce = Some(nonce);
+            let signed = self.sign(fees.apply(request.clone())).await?;
+
+            let err = match self.broadcast(signed.clone()).await {
+                Ok(hash) => {
+                    nonces.next = Some(nonce + 1);
+                    nonces.in_flight.insert(nonce);
+                    blueprint_core::debug!(
+                        target: "tangle-tx-manager",
+                        nonce,
+                        tx_hash = ?hash,
+                        "Sent transaction"
+                    );
+                    return Ok(PendingTx::new(nonce, request, fees, signed, hash));
+                }
+                Err(err) => err,
+            };
+
+            match Rejection::classify(&err) {
+                Some(Rejection::NonceTooLow) if attempts < MAX_BROADCAST_ATTEMPTS => {
+                    blueprint_core::debug!(
+                        target: "tangle-tx-manager",
+                        nonce,
+                        "Nonce already used, fetching the account's nonce again"
+                    );
+                    nonces.next = None;
+                }
+                Some(Rejection::Underpriced) if attempts < MAX_BROADCAST_ATTEMPTS => {
+                    // The nonce may be taken by a transaction stuck from a previous run
+                    let config = &self.inner.config;
+                    let Some(bumped) = fees.bumped(
+                        config.replacement_bump_percent(),
+                        None,
+                        config.max_fee_per_gas,
+                    ) else {
+                        nonces.next = None;
+                        return Err(TxError::Rpc(err));
+                    };
+                    fees = bumped;
+                }
+                _ => {
+                    nonces.next = None;
+                    return Err(TxError::Rpc(err));
+                }
+            }
+        }
+    }
+
+    /// Wait for `pending` to be included, replacing it as needed
+    async fn confirm(&self, pending: &mut PendingTx) -> Result<TransactionReceipt, TxError> {
+        let config = &self.inner.config;
+        loop {
+            sleep(config.poll_interval).await;
+
+            match self.status(pending).await {
+                Ok(Status::Included(receipt)) => {
+                    if pending.cancellations.contains(&receipt.transaction_hash) {
+                        return Err(TxError::Cancelled {
+                            nonce: pending.nonce,
+                            tx_hash: receipt.transaction_hash,
+                        });
+                    }
+                    return Ok(receipt);
+                }
+                Ok(Status::NonceConsumed) => return Err(TxError::NonceConsumed(pending.nonce)),
+                Ok(Status::Pending) => {}
+                Err(e) => {
+                    blueprint_core::warn!(
+                        target: "tangle-tx-manager",
+                        nonce = pending.nonce,
+                        "Failed to check pending transaction: {e}"
+                    );
+                    continue;
+                }
+            }
+
+            if pending.last_sent.elapsed() < config.bump_interval {
+                continue;
+            }
+
+            self.replace(pending).await;
+            self.fill_gaps(pending.nonce).await;
+        }
+    }
+
+    async fn status(&self, pending: &PendingTx) -> Result<Status, String> {
+        if let Some(receipt) = self.receipt(pending).await? {
+            return Ok(Status::Included(receipt));
+        }
+
+        if self.account_nonce(false).await? > pending.nonce {
+            // Either included since the receipts were checked, or replaced by someone else
+            return Ok(match self.receipt(pending).await? {
+                Some(receipt) => Status::Included(receipt),
+                None => Status::NonceConsumed,
+            });
+        }
+
+        Ok(Status::Pending)
+    }
+
+    async fn receipt(&self, pending: &PendingTx) -> Result<Option<TransactionReceipt>, String> {
+        for hash in pending.hashes.iter().rev() {
+            let receipt = self
+                .inner
+                .provider
+                .get_transaction_receipt(*hash)
+                .await
+                .map_err(|e| e.to_string())?;
+            if receipt.is_some() {
+                return Ok(receipt);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Replace `pending` with higher fees, or rebroadcast it if they can't be raised
+    ///
+    /// Once `pending` has been pending for [`cancel_after`](TxManagerConfig::cancel_after), it is
+    /// replaced with a cancellation instead, and so is every replacement after that.
+    async fn replace(&self, pending: &mut PendingTx) {
+        let config = &self.inner.config;
+        let cancel = pending.cancelling
+            || config
+                .cancel_after
+                .is_some_and(|timeout| pending.first_sent.elapsed() >= timeout);
+        pending.last_sent = Instant::now();
+
+        // The first cancellation has to outbid the call, however many times it was bumped
+        let bumped = if (cancel && !pending.cancelling) || pending.bumps < config.max_bumps {
+            let estimate = self.current_fees().await.ok();
+            pending.fees.bumped(
+                config.replacement_bump_percent(),
+                estimate,
+                config.max_fee_per_gas,
+            )
+        } else {
+            None
+        };
+
+        let Some(fees) = bumped else {
+            if let Err(e) = self.broadcast(pending.signed.clone()).await {
+                blueprint_core::debug!(
+                    target: "tangle-tx-manager",
+                    nonce = pending.nonce,
+                    "Failed to rebroadcast transaction: {e}"
+                );
+            }
+            return;
+        };
+
+        let request = if cancel {
+            pending.cancellation(self.inner.from)
+        } else {
+            pending.request.clone()
+        };
+        let signed = match self.sign(fees.apply(request)).await {
+            Ok(signed) => signed,
+            Err(e) => {
+                blueprint_core::warn!(
+                    target: "tangle-tx-manager",
+                    nonce = pending.nonce,
+                    "Failed to sign replacement transaction: {e}"
+                );
+                return;
+            }
+        };
+
+        pending.bumps += 1;
+        pending.fees = fees;
+        match self.broadcast(signed.clone()).await {
+            Ok(hash) => {
+                blueprint_core::info!(
+                    target: "tangle-tx-manager",
+                    nonce = pending.nonce,
+                    tx_hash = ?hash,
+                    max_fee_per_gas = fees.max_fee_per_gas,
+                    max_priority_fee_per_gas = fees.max_priority_fee_per_gas,
+                    cancel,
+                    "Replaced pending transaction"
+                );
+                if let Some(hash) = hash {
+                    pending.hashes.push(hash);
+                    if cancel {
+                        pending.cancellations.push(hash);
+                    }
+                }
+                pending.cancelling |= cancel;
+                pending.signed = signed;
+            }
+            // Either way, the next replacement goes from the fees tried here
+            Err(e) => blueprint_core::warn!(
+                target: "tangle-tx-manager",
+                nonce = pending.nonce,
+                "Failed to replace pending transaction: {e}"
+            ),
+        }
+    }
+
+    /// Cancel any nonce below `nonce` that the node doesn't know about, and that isn't in flight
+    ///
+    /// A gap is left behind when a transaction is dropped from the mempool, and holds back every
+    /// transaction after it.
+    async fn fill_gaps(&self, nonce: u64) {
+        let Ok(known) = self.account_nonce(true).await else {
+            return;
+        };
+        if known >= nonce {
+            return;
+        }
+
+        let (chain_id, gaps) = {
+            let nonces = self.inner.nonces.lock().await;
+            let gaps: Vec<u64> = (known..nonce)
+                .filter(|nonce| !nonces.in_flight.contains(nonce))
+                .collect();
+            (nonces.chain_id, gaps)
+        };
+        if gaps.is_empty() {
+            return;
+        }
+        let Ok(fees) = self.current_fees().await else {
+            return;
+        };
+
+        for gap in gaps {
+            blueprint_core::warn!(
+                target: "tangle-tx-manager",
+                nonce = gap,
+                "Filling nonce gap with a cancellation"
+            );
+            let mut request = cancellation(self.inner.from, gap);
+            request.chain_id = chain_id;
+            let result = match self.sign(fees.apply(request)).await {
+                Ok(signed) => self.broadcast(signed).await.map(drop),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = result {
+                blueprint_core::warn!(
+                    target: "tangle-tx-manager",
+                    nonce = gap,
+                    "Failed to fill nonce gap: {e}"
+                );
+            }
+        }
+    }
+
+    async fn current_fees(&self) -> Result<Fees, TxError> {
+        let estimate = self
+            .inner
+            .provider
+            .estimate_eip1559_fees()
+            .await
+            .map_err(rpc)?;
+        Ok(Fees {
+            max_fee_per_gas: estimate.max_fee_per_gas,
+            max_priority_fee_per_gas: estimate.max_priority_fee_per_gas,
+        }
+        .capped(self.inner.config.max_fee_per_gas))
+    }
+
+    /// The account's nonce, including transactions in the mempool if `pending`
+    async fn account_nonce(&self, pending: bool) -> Result<u64, String> {
+        let count = self.inner.provider.get_transaction_count(self.inner.from);
+        let count = if pending {
+            count.pending().await
+        } else {
+            count.latest().await
+        };
+        count.map_err(|e| e.to_string())
+    }
+
+    async fn sign(
+        &self,
+        request: TransactionRequest,
+    ) -> Result<<Ethereum as Network>::TxEnvelope, TxError> {
+        <TransactionRequest as TransactionBuilder<Ethereum>>::build(request, &self.inner.wallet)
+            .await
+            .map_err(|e| TxError::Signing(e.to_string()))
+    }
+
+    /// Broadcast a signed transaction, returning its hash unless the node already had it
+    async fn broadcast(
+        &self,
+        signed: <Ethereum as Network>::TxEnvelope,
+    ) -> Result<Option<B256>, String> {
+        match self.inner.provider.send_tx_envelope(signed).await {
+            Ok(pending) => Ok(Some(*pending.tx_hash())),
+            Err(e) => {
+                let err = e.to_string();
+                match Rejection::classify(&err) {
+                    Some(Rejection::AlreadyKnown) => Ok(None),
+                    _ => Err(err),
+                }
+            }
+        }
+    }
+}
+
+/// A [`TxManager`] that's created from the client on first use, unless one was provided
+#[derive(Clone, Default)]
+pub(crate) struct LazyTxManager(Arc<OnceCell<TxManager>>);
+
+impl LazyTxManager {
+    pub(crate) fn new(tx: TxManager) -> Self {
+        Self(Arc::new(OnceCell::new_with(Some(tx))))
+    }
+
+    pub(crate) async fn get(&self, client: &TangleClient) -> Result<&TxManager, TxError> {
+        self.0
+            .get_or_try_init(|| {
+                TxManager::shared(
+                    client.config.http_rpc_endpoint.as_str(),
+                    client.account(),
+                    || async { TxManager::from_client(client, TxManagerConfig::default()) },
+                )
+            })
+            .await
+    }
+}
+
+enum Status {
+    Pending,
+    Included(TransactionReceipt),
+    NonceConsumed,
+}
+
+/// A transaction sent and not yet included, along with its replacements
+struct PendingTx {
+    nonce: u64,
+    /// The transaction as requested, without fees
+    request: TransactionRequest,
+    fees: Fees,
+    /// The last transaction signed, to rebroadcast
+    signed: <Ethereum as Network>::TxEnvelope,
+    /// Every transaction broadcast for this nonce, oldest first
+    hashes: Vec<B256>,
+    /// Which of `hashes` are cancellations
+    cancellations: Vec<B256>,
+    /// Whether a cancellation was broadcast, so that replacements don't resend the call
+    cancelling: bool,
+    bumps: u32,
+    first_sent: Instant,
+    last_sent: Instant,
+}
+
+impl PendingTx {
+    fn new(
+        nonce: u64,
+        request: TransactionRequest,
+        fees: Fees,
+        signed: <Ethereum as Network>::TxEnvelope,
+        hash: Option<B256>,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            nonce,
+            request,
+            fees,
+            signed,
+            hashes: hash.into_iter().collect(),
+            cancellations: Vec::new(),
+            cancelling: false,
+            bumps: 0,
+            first_sent: now,
+            last_sent: now,
+        }
+    }
+
+    fn cancellation(&self, from: Address) -> TransactionRequest {
+        let mut request = cancellation(from, self.nonce);
+        request.chain_id = self.request.chain_id;
+        request
+    }
+}
+
+/// An empty transfer from `from` to itself
+fn cancellation(from: Address, nonce: u64) -> TransactionRequest {
+    let mut request = TransactionRequest::default();
+    request.from = Some(from);
+    request.to = Some(TxKind::Call(from));
+    request.value = Some(U256::ZERO);
+    request.gas = Some(CANCEL_GAS_LIMIT);
+    request.nonce = Some(nonce);
+    request
+}
+
+/// EIP-1559 fees
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fees {
+    max_fee_per_gas: u128,
+    max_priority_fee_per_gas: u128,
+}
+
+impl Fees {
+    fn apply(self, mut request: TransactionRequest) -> TransactionRequest {
+        request.gas_price = None;
+        request.max_fee_per_gas = Some(self.max_fee_per_gas);
+        request.max_priority_fee_per_gas = Some(self.max_priority_fee_per_gas);
+        request
+    }
+
+    fn capped(self, cap: Option<u128>) -> Self {
+        let max_fee_per_gas = cap.map_or(self.max_fee_per_gas, |cap| self.max_fee_per_gas.min(cap));
+        Self {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas.min(max_fee_per_gas),
+        }
+    }
+
+    /// The fees of a replacement, raised by `percent` or to the current `estimate`
+    ///
+    /// Returns `None` if the replacement would be rejected, because `cap` doesn't leave room
+    /// for raising fees enough.
+    fn bumped(self, percent: u64, estimate: Option<Fees>, cap: Option<u128>) -> Option<Self> {
+        let raise =
+            |fee: u128, percent: u64| fee.saturating_mul(u128::from(100 + percent)).div_ceil(100);
+
+        let mut bumped = Self {
+            max_fee_per_gas: raise(self.max_fee_per_gas, percent),
+            max_priority_fee_per_gas: raise(self.max_priority_fee_per_gas, percent),
+        };
+        if let Some(estimate) = estimate {
+            bumped.max_fee_per_gas = bumped.max_fee_per_gas.max(estimate.max_fee_per_gas);
+            bumped.max_priority_fee_per_gas = bumped
+                .max_priority_fee_per_gas
+                .max(estimate.max_priority_fee_per_gas);
+        }
+        bumped.max_fee_per_gas = bumped.max_fee_per_gas.max(bumped.max_priority_fee_per_gas);
+        let bumped = bumped.capped(cap);
+
+        let accepted = bumped.max_fee_per_gas
+            >= raise(self.max_fee_per_gas, MIN_REPLACEMENT_BUMP_PERCENT)
+            && bumped.max_priority_fee_per_gas
+                >= raise(self.max_priority_fee_per_gas, MIN_REPLACEMENT_BUMP_PERCENT);
+        accepted.then_some(bumped)
+    }
+}
+
+/// Why a node rejected a transaction, for the rejections that can be recovered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+    /// The transaction is already in the mempool
+    AlreadyKnown,
+    /// The nonce was already used
+    NonceTooLow,
+    /// The fees are too low, either for the mempool or to replace a transaction
+    Underpriced,
+}
+
+impl Rejection {
+    fn classify(message: &str) -> Option<Self> {
+        let message = message.to_ascii_lowercase();
+        if message.contains("already known") || message.contains("known transaction") {
+            Some(Self::AlreadyKnown)
+        } else if message.contains("nonce too low")
+            || message.contains("nonce has already been used")
+        {
+            Some(Self::NonceTooLow)
+        } else if message.contains("underpriced") || message.contains("fee too low") {
+            Some(Self::Underpriced)
+        } else {
+            None
+        }
+    }
+}
+
+/// The gas limit to send with: the estimate plus a buffer, and at least `min_gas_limit`
+fn buffered_gas_limit(estimate: Option<u64>, min_gas_limit: u64) -> u64 {
+    estimate
+        .map(|gas| gas.saturating_mul(GAS_BUFFER_NUMERATOR) / GAS_BUFFER_DENOMINATOR)
+        .unwrap_or(min_gas_limit)
+        .max(min_gas_limit)
+}
+
+fn rpc(err: impl fmt::Display) -> TxError {
+    TxError::Rpc(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_transport::mock::Asserter;
+
+    const GWEI: u128 = 1_000_000_000;
+
+    fn fees(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> Fees {
+        Fees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+
+    #[test]
+    fn bumped_fees_are_raised_by_the_percentage() {
+        let bumped = fees(100 * GWEI, 2 * GWEI).bumped(20, None, None);
+        assert_eq!(bumped, Some(fees(120 * GWEI, 2_400_000_000)));
+    }
+
+    #[test]
+    fn bumped_fees_follow_a_higher_estimate() {
+        let bumped = fees(100 * GWEI, 2 * GWEI).bumped(20, Some(fees(300 * GWEI, GWEI)), None);
+        assert_eq!(bumped, Some(fees(300 * GWEI, 2_400_000_000)));
+    }
+
+    #[test]
+    fn bumped_fees_stop_at_the_cap() {
+        let current = fees(100 * GWEI, 2 * GWEI);
+        assert_eq!(
+            current.bumped(20, None, Some(115 * GWEI)),
+            Some(fees(115 * GWEI, 2_400_000_000))
+        );
+        // Less than the 10% nodes require to accept a replacement
+        assert_eq!(current.bumped(20, None, Some(105 * GWEI)), None);
+    }
+
+    #[test]
+    fn bumped_fees_round_up() {
+        assert_eq!(fees(1, 1).bumped(10, None, None), Some(fees(2, 2)));
+        assert_eq!(fees(0, 0).bumped(10, None, None), Some(fees(0, 0)));
+    }
+
+    #[test]
+    fn capped_fees_keep_the_priority_fee_below_the_max_fee() {
+        assert_eq!(
+            fees(100 * GWEI, 60 * GWEI).capped(Some(50 * GWEI)),
+            fees(50 * GWEI, 50 * GWEI)
+        );
+        assert_eq!(fees(100 * GWEI, GWEI).capped(None), fees(100 * GWEI, GWEI));
+    }
+
+    #[test]
+    fn node_rejections_are_classified() {
+        let cases = [
+            ("already known", Some(Rejection::AlreadyKnown)),
+            ("Known transaction: 0xabc", Some(Rejection::AlreadyKnown)),
+            (
+                "nonce too low: next nonce 5, tx nonce 4",
+                Some(Rejection::NonceTooLow),
+            ),
+            (
+                "replacement transaction underpriced",
+                Some(Rejection::Underpriced),
+            ),
+            ("transaction underpriced", Some(Rejection::Underpriced)),
+            ("execution reverted", None),
+        ];
+        for (message, expected) in cases {
+            assert_eq!(Rejection::classify(message), expected, "{message}");
+        }
+    }
+
+    #[tokio::test]
+    async fn managers_are_shared_per_account_and_endpoint() {
+        let signer = alloy_signer_local::PrivateKeySigner::random();
+        let create = |signer: &alloy_signer_local::PrivateKeySigner| {
+            let provider =
+                ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+            let wallet = EthereumWallet::from(signer.clone());
+            async move {
+                Ok(TxManager::new(
+                    DynProvider::new(provider),
+                    wallet,
+                    TxManagerConfig::default(),
+                ))
+            }
+        };
+        let unused = || async { Err(TxError::Rpc("created a second manager".into())) };
+
+        let from = signer.address();
+        let first = TxManager::shared("http://127.0.0.1:1", from, || create(&signer))
+            .await
+            .unwrap();
+        let second = TxManager::shared("http://127.0.0.1:1/", from, unused)
+            .await
+            .unwrap();
+        assert!(Arc::ptr_eq(&first.inner, &second.inner));
+
+        let other_endpoint = TxManager::shared("http://127.0.0.1:2", from, || create(&signer))
+            .await
+            .unwrap();
+        assert!(!Arc::ptr_eq(&first.inner, &other_endpoint.inner));
+    }
+
+    #[test]
+    fn bump_percent_set_directly_is_still_clamped() {
+        let config = TxManagerConfig {
+            bump_percent: 1,
+            ..TxManagerConfig::default()
+        };
+        assert_eq!(
+            config.replacement_bump_percent(),
+            MIN_REPLACEMENT_BUMP_PERCENT
+        );
+    }
+
+    #[tokio::test]
+    async fn replacements_after_a_cancellation_are_cancellations() {
+        let asserter = Asserter::new();
+        let signer = alloy_signer_local::PrivateKeySigner::random();
+        let from = signer.address();
+        let tx = TxManager::new(
+            DynProvider::new(ProviderBuilder::new().connect_mocked_client(asserter.clone())),
+            EthereumWallet::from(signer),
+            TxManagerConfig::default().with_cancel_after(Duration::ZERO),
+        );
+
+        let mut request = TransactionRequest::default();
+        request.from = Some(from);
+        request.to = Some(TxKind::Call(Address::repeat_byte(0x11)));
+        request.input = Bytes::from_static(b"submitResult").into();
+        request.gas = Some(100_000);
+        request.chain_id = Some(1);
+        request.nonce = Some(0);
+        let initial = fees(GWEI, GWEI);
+        let signed = tx.sign(initial.apply(request.clone())).await.unwrap();
+        let mut pending = PendingTx::new(0, request, initial, signed, Some(B256::ZERO));
+
+        // The cancellation, then two bumps of it
+        for i in 1..=3 {
+            // Without a fee estimate, fees are bumped from the last ones
+            asserter.push_failure_msg("fee history unavailable");
+            asserter.push_success(&B256::repeat_byte(i));
+            tx.replace(&mut pending).await;
+        }
+
+        assert_eq!(pending.bumps, 3);
+        assert_eq!(pending.hashes.len(), 4);
+        assert_eq!(
+            pending.cancellations,
+            [1, 2, 3].map(B256::repeat_byte).to_vec()
+        );
+        let cancellation = tx
+            .sign(pending.fees.apply(pending.cancellation(from)))
+            .await
+            .unwrap();
+        assert_eq!(pending.signed, cancellation);
+        assert!(pending.fees.max_fee_per_gas > initial.max_fee_per_gas);
+    }
+
+    #[test]
+    fn gas_limit_is_buffered_and_floored() {
+        assert_eq!(buffered_gas_limit(Some(100_000), 0), 130_000);
+        assert_eq!(buffered_gas_limit(Some(100_000), 200_000), 200_000);
+        assert_eq!(buffered_gas_limit(None, 200_000), 200_000);
+    }
+}
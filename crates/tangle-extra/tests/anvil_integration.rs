@@ -5,23 +5,38 @@
 //! is missing. This keeps the tests deterministic in CI without forcing contributors
 //! to run setup scripts manually.
 
+use alloy_network::EthereumWallet;
 use alloy_primitives::{Address, Bytes, U256};
-use anyhow::{Context, Result};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::TransactionRequest;
+use alloy_signer_local::PrivateKeySigner;
+use alloy_sol_types::{SolCall, SolValue};
+use anyhow::{Context, Result, ensure};
 use blueprint_anvil_testing_utils::{
     LOCAL_BLUEPRINT_ID, LOCAL_SERVICE_ID, SeededTangleTestnet, harness_builder_from_env,
     missing_tnt_core_artifacts,
 };
+use blueprint_client_tangle::contracts::ITangle::addPermittedCallerCall;
 use blueprint_client_tangle::{TangleClient, TangleClientConfig, TangleSettings};
+use blueprint_core::JobResult;
 use blueprint_crypto::BytesEncoding;
 use blueprint_crypto::k256::{K256Ecdsa, K256SigningKey};
 use blueprint_keystore::backends::Backend;
 use blueprint_keystore::{Keystore, KeystoreConfig};
+use blueprint_store_local_database::LocalDatabase;
+use blueprint_tangle_extra::extract::{CallId, ServiceId};
+use blueprint_tangle_extra::outbox::{OutboxEntry, OutboxStore};
+use blueprint_tangle_extra::{TangleConsumer, TxManager, TxManagerConfig};
+use futures_util::SinkExt;
+use std::str::FromStr;
 use std::sync::Arc;
 use tokio::time::{Duration, timeout};
 
 const OPERATOR1_PRIVATE_KEY: &str =
     "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
 const OPERATOR1_ADDRESS: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+const SERVICE_OWNER_PRIVATE_KEY: &str =
+    "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
 const BLUEPRINT_ID: u64 = LOCAL_BLUEPRINT_ID;
 const SERVICE_ID: u64 = LOCAL_SERVICE_ID;
 const TEST_TIMEOUT: Duration = Duration::from_secs(1_800);
@@ -50,6 +65,124 @@ async fn submit_result() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn tx_manager_allocates_nonces_for_concurrent_sends() -> Result<()> {
+    run_anvil_test("tx_manager_allocates_nonces_for_concurrent_sends", async {
+        let Some(deployment) =
+            boot_testnet("tx_manager_allocates_nonces_for_concurrent_sends").await?
+        else {
+            return Ok(());
+        };
+        let client = create_test_client(&deployment).await?;
+        let tx = TxManager::from_client(
+            &client,
+            TxManagerConfig::default().with_poll_interval(Duration::from_millis(100)),
+        )?;
+
+        let nonce_before = client
+            .provider()
+            .get_transaction_count(client.account())
+            .await?;
+        let transfer = || {
+            TransactionRequest::default()
+                .to(client.account())
+                .value(U256::from(1))
+        };
+        let (first, second) = tokio::join!(tx.send(transfer()), tx.send(transfer()));
+        let (first, second) = (first?, second?);
+        assert!(first.status() && second.status());
+        assert_ne!(first.transaction_hash, second.transaction_hash);
+
+        let nonce_after = client
+            .provider()
+            .get_transaction_count(client.account())
+            .await?;
+        assert_eq!(nonce_after, nonce_before + 2);
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn consumer_replays_the_outbox_after_a_crash() -> Result<()> {
+    run_anvil_test("consumer_replays_the_outbox_after_a_crash", async {
+        let Some(deployment) = boot_testnet("consumer_replays_the_outbox_after_a_crash").await?
+        else {
+            return Ok(());
+        };
+        let client = create_test_client(&deployment).await?;
+        permit_caller(&deployment, client.account()).await?;
+        let call_id = client
+            .submit_job(SERVICE_ID, 0, Bytes::from(50u64.abi_encode()))
+            .await?
+            .call_id;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("tangle-outbox.json");
+        let output = Bytes::from(100u64.abi_encode());
+
+        // The operator goes down before submitting the result
+        let mut consumer =
+            TangleConsumer::new((*client).clone())
+                .with_outbox(LocalDatabase::<OutboxEntry>::open_strict(&path)?)?;
+        consumer
+            .feed(job_result(call_id, output.clone()))
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        drop(consumer);
+        ensure!(
+            !client.get_job_call(SERVICE_ID, call_id).await?.completed,
+            "the result should not be submitted yet"
+        );
+
+        // On the next boot, the result left in the outbox is submitted
+        let mut consumer =
+            TangleConsumer::new((*client).clone())
+                .with_outbox(LocalDatabase::<OutboxEntry>::open_strict(&path)?)?;
+        consumer.flush().await.map_err(|e| anyhow::anyhow!(e))?;
+        ensure!(
+            client.get_job_call(SERVICE_ID, call_id).await?.completed,
+            "the replayed result should complete the job"
+        );
+        ensure!(
+            LocalDatabase::<OutboxEntry>::open_strict(&path)?
+                .pending()?
+                .is_empty()
+        );
+
+        // A result confirmed right before going down isn't submitted again
+        LocalDatabase::<OutboxEntry>::open_strict(&path)?.insert(OutboxEntry {
+            service_id: SERVICE_ID,
+            call_id,
+            output,
+        })?;
+        let nonce = client
+            .provider()
+            .get_transaction_count(client.account())
+            .await?;
+        let mut consumer =
+            TangleConsumer::new((*client).clone())
+                .with_outbox(LocalDatabase::<OutboxEntry>::open_strict(&path)?)?;
+        consumer.flush().await.map_err(|e| anyhow::anyhow!(e))?;
+        ensure!(
+            LocalDatabase::<OutboxEntry>::open_strict(&path)?
+                .pending()?
+                .is_empty()
+        );
+        assert_eq!(
+            client
+                .provider()
+                .get_transaction_count(client.account())
+                .await?,
+            nonce
+        );
+
+        Ok(())
+    })
+    .await
+}
+
 #[tokio::test]
 async fn get_operator_weights() -> Result<()> {
     run_anvil_test("get_operator_weights", async {
@@ -181,6 +314,33 @@ async fn create_test_client(deployment: &SeededTangleTestnet) -> Result<Arc<Tang
     ))
 }
 
+async fn permit_caller(deployment: &SeededTangleTestnet, caller: Address) -> Result<()> {
+    let signer = PrivateKeySigner::from_str(SERVICE_OWNER_PRIVATE_KEY)?;
+    let provider = ProviderBuilder::new()
+        .wallet(EthereumWallet::from(signer))
+        .connect(deployment.http_endpoint().as_str())
+        .await?;
+
+    let call = addPermittedCallerCall {
+        serviceId: SERVICE_ID,
+        caller,
+    };
+    let tx = TransactionRequest::default()
+        .to(deployment.tangle_contract)
+        .input(call.abi_encode().into());
+    let receipt = provider.send_transaction(tx).await?.get_receipt().await?;
+    ensure!(receipt.status(), "add permitted caller transaction failed");
+    Ok(())
+}
+
+fn job_result(call_id: u64, output: Bytes) -> JobResult {
+    let mut result = JobResult::new(bytes::Bytes::copy_from_slice(&output));
+    let metadata = result.metadata_mut().expect("result is ok");
+    metadata.insert(ServiceId::METADATA_KEY, SERVICE_ID);
+    metadata.insert(CallId::METADATA_KEY, call_id);
+    result
+}
+
 async fn run_anvil_test<F>(name: &str, fut: F) -> Result<()>
 where
     F: std::future::Future<Output = Result<()>>,
@@ -20,8 +20,8 @@ use blueprint_crypto::BytesEncoding;
 use blueprint_crypto::k256::{K256Ecdsa, K256SigningKey};
 use blueprint_keystore::backends::Backend;
 use blueprint_keystore::{Keystore, KeystoreConfig};
-use blueprint_tangle_extra::TangleProducer;
 use blueprint_tangle_extra::extract::{CallId, ServiceId, TangleArg};
+use blueprint_tangle_extra::{MultiServiceProducer, TangleProducer};
 use futures_util::{StreamExt, pin_mut};
 use std::str::FromStr;
 use tokio::time::{Duration, timeout};
@@ -97,6 +97,103 @@ async fn tangle_producer_streams_real_job_submissions() -> Result<()> {
     .await
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn multi_service_producer_tags_jobs_with_their_service() -> Result<()> {
+    let _ = tracing_subscriber::fmt::try_init();
+    run_anvil_test(
+        "multi_service_producer_tags_jobs_with_their_service",
+        async {
+            let Some(deployment) =
+                boot_testnet("multi_service_producer_tags_jobs_with_their_service").await?
+            else {
+                return Ok(());
+            };
+
+            let client = create_client(&deployment).await?;
+            let start_block = client.block_number().await.unwrap_or_default();
+            let producer = MultiServiceProducer::from_block(client.clone(), start_block)
+                .with_services([LOCAL_SERVICE_ID])
+                .with_poll_interval(Duration::from_millis(50));
+            assert_eq!(producer.services(), vec![LOCAL_SERVICE_ID]);
+
+            submit_job(&client).await?;
+
+            pin_mut!(producer);
+            let job_call = timeout(PRODUCER_STREAM_TIMEOUT, async {
+                loop {
+                    match producer.next().await {
+                        Some(Ok(job)) => break Ok(job),
+                        Some(Err(err)) => tracing::warn!("producer error: {err:?}"),
+                        None => break Err(anyhow::anyhow!("producer ended unexpectedly")),
+                    }
+                }
+            })
+            .await
+            .context("timed out waiting for job event")??;
+
+            let (mut parts, _) = job_call.into_parts();
+            let service_id = ServiceId::try_from(&mut parts).expect("service id missing");
+            assert_eq!(service_id.0, LOCAL_SERVICE_ID);
+
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn tangle_producer_wakes_up_on_log_subscription() -> Result<()> {
+    let _ = tracing_subscriber::fmt::try_init();
+    run_anvil_test("tangle_producer_wakes_up_on_log_subscription", async {
+        let Some(deployment) = boot_testnet("tangle_producer_wakes_up_on_log_subscription").await?
+        else {
+            return Ok(());
+        };
+        log_testnet_endpoints(&deployment);
+
+        let client = create_client(&deployment).await?;
+        let start_block = client.block_number().await.unwrap_or_default();
+        // Far longer than the test, so only a log notification can wake the producer up
+        let producer = TangleProducer::from_block(client.clone(), LOCAL_SERVICE_ID, start_block)
+            .with_poll_interval(Duration::from_secs(3_600))
+            .with_log_subscription();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let driver = tokio::spawn(async move {
+            pin_mut!(producer);
+            while let Some(result) = producer.next().await {
+                if tx.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Let the producer subscribe and read up to the head before the job is submitted
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        submit_job(&client).await?;
+
+        let job_call = timeout(Duration::from_secs(60), async {
+            loop {
+                match rx.recv().await {
+                    Some(Ok(job)) => break Ok(job),
+                    Some(Err(err)) => tracing::warn!("producer error: {err:?}"),
+                    None => break Err(anyhow::anyhow!("producer ended unexpectedly")),
+                }
+            }
+        })
+        .await
+        .context("timed out waiting for a job through the log subscription")??;
+        driver.abort();
+
+        let (mut parts, _) = job_call.into_parts();
+        let service_id = ServiceId::try_from(&mut parts).expect("service id missing");
+        assert_eq!(service_id.0, LOCAL_SERVICE_ID);
+
+        Ok(())
+    })
+    .await
+}
+
 async fn create_client(deployment: &SeededTangleTestnet) -> Result<TangleClient> {
     let keystore = Keystore::new(KeystoreConfig::new().in_memory(true))?;
     let secret_bytes = hex::decode(SERVICE_OWNER_PRIVATE_KEY)?;